@@ -0,0 +1,45 @@
+// examples/equivalence.rs
+//
+// Comparing languages: equivalence with a shortest distinguishing word,
+// inclusion, and the simplified expression rebuilt from a minimal DFA.
+//
+//     cargo run --example equivalence -- "(a+b)*" "(a*b*)*"
+use kleeners::decisions::{inclusion, Inclusion};
+use kleeners::pipeline::language::LanguageSet;
+use kleeners::regex::equivalence::equivalence_witness;
+
+fn compare(a: &str, b: &str) -> Result<(), String> {
+    match equivalence_witness(a, b)? {
+        None => println!("{}  ≡  {}", a, b),
+        Some((word, in_a)) => {
+            let (yes, no) = if in_a { (a, b) } else { (b, a) };
+            println!("{}  ≢  {}: {:?} is in {} only", a, b, word, yes);
+            if let Inclusion::Included = inclusion(no, yes)? {
+                println!("    but {} ⊂ {}", no, yes);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [a, b] = args.as_slice() {
+        return compare(a, b);
+    }
+
+    for (a, b) in [
+        ("(a+b)*", "(a*b*)*"),
+        ("(ab)*a", "a(ba)*"),
+        ("a*b*", "(a+b)*"),
+        ("(a+b)*abb", "(a+b)*bb"),
+    ] {
+        compare(a, b)?;
+    }
+
+    // The same language, spelled more simply
+    let mut langs = LanguageSet::new();
+    let lang = langs.define("messy", "(a*b*)*(ε+a)(aa*)?")?;
+    println!("{} simplifies to {}", lang.source, lang.to_regex());
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// examples/grading.rs
+//
+// Grading regex exercises: every submission is compared against a
+// reference solution, and a wrong answer comes with the shortest word it
+// gets wrong, which is usually all the feedback a student needs.
+//
+//     cargo run --example grading
+use kleeners::regex::equivalence::equivalence_witness;
+
+#[derive(Debug, PartialEq)]
+enum Grade {
+    Correct,
+    // The shortest word the submission misclassifies
+    Wrong { word: String, accepted: bool },
+    Invalid(String),
+}
+
+fn grade(reference: &str, submission: &str) -> Grade {
+    match equivalence_witness(submission, reference) {
+        Ok(None) => Grade::Correct,
+        Ok(Some((word, accepted))) => Grade::Wrong { word, accepted },
+        Err(e) => Grade::Invalid(e),
+    }
+}
+
+fn main() {
+    // Binary strings with an even number of 1s
+    let reference = "0*(10*10*)*";
+    let submissions = [
+        ("ada", "(0+10*1)*"),
+        ("brook", "(0*10*1)*0*"),
+        ("cyd", "(0+11)*"),
+        ("dana", "0*(10*1)*"),
+        ("eli", "(0+1(0*1"),
+    ];
+
+    println!("reference: {}", reference);
+    for (student, answer) in submissions {
+        let feedback = match grade(reference, answer) {
+            Grade::Correct => "correct".to_string(),
+            Grade::Wrong { word, accepted } => format!(
+                "wrong: {:?} should be {}",
+                word,
+                if accepted { "rejected" } else { "accepted" }
+            ),
+            Grade::Invalid(e) => format!("does not parse: {}", e),
+        };
+        println!("{:<6} {:<14} {}", student, answer, feedback);
+    }
+}
@@ -0,0 +1,59 @@
+// examples/lexer.rs
+//
+// A maximal-munch lexer from a table of token rules. Every rule is
+// compiled to a longest-prefix matcher; at each position the longest
+// match wins, and the earlier rule wins a tie, so keywords go first.
+//
+//     cargo run --example lexer -- "if x1 = 42 then y"
+use kleeners::dfa::compiled::Acceptance;
+use kleeners::pipeline::language::LanguageSet;
+use kleeners::pipeline::preprocess::Matcher;
+
+const RULES: [(&str, &str); 6] = [
+    ("if", "if"),
+    ("then", "then"),
+    ("ident", "[a-z][a-z0-9]*"),
+    ("number", "[0-9][0-9]*"),
+    // `=`, `<` and `>` have no escape of their own
+    ("op", "[\\u{3d}\\u{3c}\\u{3e}\\+\\-]\\u{3d}?"),
+    ("space", "[\\u{20}\\t\\n][\\u{20}\\t\\n]*"),
+];
+
+fn lex<'a>(rules: &[(&str, Matcher)], input: &'a str) -> Result<Vec<(String, &'a str)>, String> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < input.len() {
+        let mut best: Option<(&str, usize)> = None;
+        for (name, matcher) in rules {
+            if let Some(len) = matcher.find(&input[pos..]) {
+                if len > 0 && best.is_none_or(|(_, l)| len > l) {
+                    best = Some((name, len));
+                }
+            }
+        }
+
+        let (name, len) = best.ok_or_else(|| format!("no token matches at byte {}", pos))?;
+        if name != "space" {
+            tokens.push((name.to_string(), &input[pos..pos + len]));
+        }
+        pos += len;
+    }
+    Ok(tokens)
+}
+
+fn main() -> Result<(), String> {
+    let mut langs = LanguageSet::new();
+    let mut rules = Vec::new();
+    for (name, pattern) in RULES {
+        let matcher = langs.define(name, pattern)?.matcher();
+        rules.push((name, matcher.with_acceptance(Acceptance::LongestPrefix)));
+    }
+
+    let input = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "if count >= 10 then iffy = count - 1".to_string());
+    for (name, text) in lex(&rules, &input)? {
+        println!("{:<8} {:?}", name, text);
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+// examples/scan.rs
+//
+// Streaming search: a compiled DFA driven one char at a time over a text,
+// reporting the line and column where each match ends.
+//
+//     cargo run --example scan -- "[0-9][0-9]*\\.[0-9][0-9]" prices.txt
+use kleeners::dfa::compiled::CompiledDFA;
+use kleeners::dfa::scan::Scanner;
+use kleeners::pipeline::tests::full_pipeline;
+
+const SAMPLE: &str = "coffee 3.50\ncroissant 2.25 (was 2.75)\ntotal: 5.75\n";
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let pattern = args
+        .next()
+        .unwrap_or_else(|| "[0-9]\\.[0-9][0-9]".to_string());
+    let text = match args.next() {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("IO error: {}", e))?,
+        None => SAMPLE.to_string(),
+    };
+
+    let dfa = CompiledDFA::from_dfa(&full_pipeline(&pattern)?.mindfa);
+    let mut scanner = Scanner::new(&dfa);
+
+    for (row, line) in text.lines().enumerate() {
+        // Matches don't span lines
+        scanner.reset();
+        for (col, c) in line.chars().enumerate() {
+            if scanner.step(c) {
+                println!("{}:{}: match ends", row + 1, col + 1);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+// examples/visualize.rs
+//
+// Every stage of the pipeline as a Graphviz file: the Thompson ε-NFA, the
+// ε-free NFA, the subset construction linking NFA states to the DFA states
+// built from them, and the minimal DFA.
+//
+//     cargo run --example visualize -- "(a+b)*abb" out/
+//     dot -Tsvg out/subsets.dot > subsets.svg
+use kleeners::dfa::dfa::{nfa_to_dfa_traced, DeterminizeOptions};
+use kleeners::nfa::renumber::StateOrder;
+use kleeners::pipeline::tests::full_pipeline;
+use kleeners::utils::dot::{dfa_to_dot, nfa_to_dot, nfa_to_dot_ordered, subsets_to_dot};
+use std::path::PathBuf;
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let pattern = args.next().unwrap_or_else(|| "(a+b)*abb".to_string());
+    let dir = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("kleeners-visualize"));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("IO error: {}", e))?;
+
+    let out = full_pipeline(&pattern)?;
+    let (dfa, subsets) = nfa_to_dfa_traced(&out.nfa, &DeterminizeOptions::default());
+
+    for (name, dot) in [
+        ("enfa", nfa_to_dot(&out.enfa)),
+        // Original ids, so the NFA matches the subsets drawn next
+        ("nfa", nfa_to_dot_ordered(&out.nfa, StateOrder::Original)),
+        ("subsets", subsets_to_dot(&out.nfa, &dfa, &subsets)),
+        ("mindfa", dfa_to_dot(&out.mindfa)),
+    ] {
+        let path = dir.join(format!("{}.dot", name));
+        std::fs::write(&path, dot).map_err(|e| format!("IO error: {}", e))?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,505 @@
+// decisions.rs
+//
+// The classic decision problems for regular languages, answered on
+// minimized DFAs. Every "no" (and most "yes") answers carry a witness.
+use crate::dfa::dfa::{nfa_to_dfa, DFA};
+use crate::dfa::equivalence::equivalent;
+use crate::dfa::minimize::minimize_dfa;
+use crate::dfa::ops::{co_reachable, reachable};
+use crate::nfa::epsilon_elimination::remove_epsilon;
+use crate::nfa::nfa::NFA;
+use crate::pipeline::language::Language;
+use crate::pipeline::tests::full_pipeline;
+use crate::types::StateId;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Anything the decision procedures can compile to a DFA.
+pub trait LanguageInput {
+    fn to_dfa(&self) -> Result<DFA, String>;
+}
+
+impl LanguageInput for str {
+    fn to_dfa(&self) -> Result<DFA, String> {
+        Ok(full_pipeline(self)?.mindfa)
+    }
+}
+
+impl LanguageInput for String {
+    fn to_dfa(&self) -> Result<DFA, String> {
+        self.as_str().to_dfa()
+    }
+}
+
+impl LanguageInput for Language {
+    fn to_dfa(&self) -> Result<DFA, String> {
+        Ok(minimize_dfa(&nfa_to_dfa(&remove_epsilon(&self.enfa))))
+    }
+}
+
+/// Any NFA, with or without ε-edges or several start states.
+impl LanguageInput for NFA {
+    fn to_dfa(&self) -> Result<DFA, String> {
+        Ok(minimize_dfa(&nfa_to_dfa(&remove_epsilon(self))))
+    }
+}
+
+impl LanguageInput for DFA {
+    fn to_dfa(&self) -> Result<DFA, String> {
+        Ok(minimize_dfa(self))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Membership {
+    pub accepted: bool,
+    // DFA states visited while reading the word; stops early if the run dies
+    pub run: Vec<StateId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Emptiness {
+    Empty,
+    // Shortest accepted word (lexicographically smallest among those)
+    NonEmpty { witness: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finiteness {
+    // Number of accepted words, saturating at u128::MAX
+    Finite {
+        count: u128,
+    },
+    // prefix · cycle^i · suffix is accepted for every i
+    Infinite {
+        prefix: String,
+        cycle: String,
+        suffix: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent,
+    // A shortest word in exactly one of the languages
+    Distinguished { witness: String, in_left: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inclusion {
+    Included,
+    // A shortest word in the left language but not the right one
+    NotIncluded { witness: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Universality {
+    Universal,
+    // A shortest word over the alphabet that is rejected
+    NotUniversal { witness: String },
+}
+
+/// Is `word` in the language?
+pub fn membership<L: LanguageInput + ?Sized>(lang: &L, word: &str) -> Result<Membership, String> {
+    let dfa = lang.to_dfa()?;
+    let mut run = vec![dfa.start];
+    let mut state = dfa.start;
+
+    for ch in word.chars() {
+        match dfa.transitions.get(&state).and_then(|m| m.get(&ch)) {
+            Some(&next) => {
+                state = next;
+                run.push(next);
+            }
+            None => {
+                return Ok(Membership {
+                    accepted: false,
+                    run,
+                })
+            }
+        }
+    }
+
+    Ok(Membership {
+        accepted: dfa.accepts.contains(&state),
+        run,
+    })
+}
+
+/// Is the language empty?
+pub fn emptiness<L: LanguageInput + ?Sized>(lang: &L) -> Result<Emptiness, String> {
+    let dfa = lang.to_dfa()?;
+    Ok(match shortest_from(&dfa, dfa.start) {
+        Some(witness) => Emptiness::NonEmpty { witness },
+        None => Emptiness::Empty,
+    })
+}
+
+/// Is the language finite? Infinite languages come with a pumpable word.
+pub fn finiteness<L: LanguageInput + ?Sized>(lang: &L) -> Result<Finiteness, String> {
+    let dfa = lang.to_dfa()?;
+    let live: HashSet<StateId> = reachable(&dfa)
+        .intersection(&co_reachable(&dfa))
+        .copied()
+        .collect();
+
+    if !live.contains(&dfa.start) {
+        return Ok(Finiteness::Finite { count: 0 });
+    }
+
+    let mut stack = vec![(dfa.start, None)];
+    let mut done = HashSet::new();
+    if let Some((prefix, cycle, entry)) = find_cycle(&dfa, &live, &mut stack, &mut done) {
+        let suffix = shortest_from(&dfa, entry).expect("cycle states are live");
+        return Ok(Finiteness::Infinite {
+            prefix,
+            cycle,
+            suffix,
+        });
+    }
+
+    // No cycle among live states: count paths through the DAG
+    let mut memo = HashMap::new();
+    Ok(Finiteness::Finite {
+        count: count_words(&dfa, &live, dfa.start, &mut memo),
+    })
+}
+
+/// Do the two languages contain exactly the same words?
+pub fn equivalence<A, B>(left: &A, right: &B) -> Result<Equivalence, String>
+where
+    A: LanguageInput + ?Sized,
+    B: LanguageInput + ?Sized,
+{
+    let (a, b) = (left.to_dfa()?, right.to_dfa()?);
+    // The union-find check decides; the product search only runs to find
+    // a shortest witness
+    if equivalent(&a, &b) {
+        return Ok(Equivalence::Equivalent);
+    }
+    Ok(match distinguish(&a, &b, |x, y| x != y) {
+        Some((witness, in_left)) => Equivalence::Distinguished { witness, in_left },
+        None => Equivalence::Equivalent,
+    })
+}
+
+/// Is every word of `left` also in `right`?
+pub fn inclusion<A, B>(left: &A, right: &B) -> Result<Inclusion, String>
+where
+    A: LanguageInput + ?Sized,
+    B: LanguageInput + ?Sized,
+{
+    let (a, b) = (left.to_dfa()?, right.to_dfa()?);
+    Ok(match distinguish(&a, &b, |x, y| x && !y) {
+        Some((witness, _)) => Inclusion::NotIncluded { witness },
+        None => Inclusion::Included,
+    })
+}
+
+/// Does the language contain every word over `alphabet`?
+pub fn universality<L: LanguageInput + ?Sized>(
+    lang: &L,
+    alphabet: &BTreeSet<char>,
+) -> Result<Universality, String> {
+    let dfa = lang.to_dfa()?;
+
+    // BFS for the shortest word leading to a rejecting (or missing) state
+    let mut parent: HashMap<Option<StateId>, (Option<StateId>, char)> = HashMap::new();
+    let mut queue = VecDeque::from([Some(dfa.start)]);
+    let mut seen = HashSet::from([Some(dfa.start)]);
+
+    while let Some(state) = queue.pop_front() {
+        if !state.is_some_and(|s| dfa.accepts.contains(&s)) {
+            return Ok(Universality::NotUniversal {
+                witness: spell(&parent, state),
+            });
+        }
+        for &c in alphabet {
+            let next = state.and_then(|s| step(&dfa, s, c));
+            if seen.insert(next) {
+                parent.insert(next, (state, c));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    Ok(Universality::Universal)
+}
+
+/*
+* =====================
+*   HELPER FUNCTIONS
+* =====================
+*/
+
+fn step(dfa: &DFA, state: StateId, c: char) -> Option<StateId> {
+    dfa.transitions.get(&state).and_then(|m| m.get(&c)).copied()
+}
+
+// Rebuild the word that led to `node` from BFS parent pointers.
+fn spell<K: Copy + Eq + std::hash::Hash>(parent: &HashMap<K, (K, char)>, mut node: K) -> String {
+    let mut word = Vec::new();
+    while let Some(&(prev, c)) = parent.get(&node) {
+        word.push(c);
+        node = prev;
+    }
+    word.iter().rev().collect()
+}
+
+// Shortest, then lexicographically smallest, word accepted from `from`.
+fn shortest_from(dfa: &DFA, from: StateId) -> Option<String> {
+    let mut parent: HashMap<StateId, (StateId, char)> = HashMap::new();
+    let mut seen = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(s) = queue.pop_front() {
+        if dfa.accepts.contains(&s) {
+            return Some(spell(&parent, s));
+        }
+        let mut edges: Vec<(char, StateId)> = dfa
+            .transitions
+            .get(&s)
+            .into_iter()
+            .flatten()
+            .map(|(&c, &t)| (c, t))
+            .collect();
+        edges.sort_unstable();
+        for (c, t) in edges {
+            if seen.insert(t) {
+                parent.insert(t, (s, c));
+                queue.push_back(t);
+            }
+        }
+    }
+
+    None
+}
+
+// BFS over the product of `a` and `b` (None standing for a dead state) for
+// the shortest word whose acceptance pair satisfies `differs`. Returns the
+// word and whether `a` accepts it.
+fn distinguish(a: &DFA, b: &DFA, differs: impl Fn(bool, bool) -> bool) -> Option<(String, bool)> {
+    type Pair = (Option<StateId>, Option<StateId>);
+    let alphabet: BTreeSet<char> = a.alphabet().union(&b.alphabet()).copied().collect();
+    let accepts = |d: &DFA, s: Option<StateId>| s.is_some_and(|s| d.accepts.contains(&s));
+
+    let start: Pair = (Some(a.start), Some(b.start));
+    let mut parent: HashMap<Pair, (Pair, char)> = HashMap::new();
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(pair) = queue.pop_front() {
+        let (in_a, in_b) = (accepts(a, pair.0), accepts(b, pair.1));
+        if differs(in_a, in_b) {
+            return Some((spell(&parent, pair), in_a));
+        }
+        for &c in &alphabet {
+            let next = (
+                pair.0.and_then(|s| step(a, s, c)),
+                pair.1.and_then(|s| step(b, s, c)),
+            );
+            if next != (None, None) && seen.insert(next) {
+                parent.insert(next, (pair, c));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+// DFS over live states. On a back edge, returns (word to the cycle entry,
+// word around the cycle, cycle entry state). `stack` holds the current path
+// with the symbol used to enter each state.
+fn find_cycle(
+    dfa: &DFA,
+    live: &HashSet<StateId>,
+    stack: &mut Vec<(StateId, Option<char>)>,
+    done: &mut HashSet<StateId>,
+) -> Option<(String, String, StateId)> {
+    let (state, _) = *stack.last().unwrap();
+    let mut edges: Vec<(char, StateId)> = dfa
+        .transitions
+        .get(&state)
+        .into_iter()
+        .flatten()
+        .filter(|(_, t)| live.contains(t))
+        .map(|(&c, &t)| (c, t))
+        .collect();
+    edges.sort_unstable();
+
+    for (c, t) in edges {
+        if let Some(pos) = stack.iter().position(|(s, _)| *s == t) {
+            let prefix = stack[1..=pos].iter().filter_map(|(_, c)| *c).collect();
+            let mut cycle: String = stack[pos + 1..].iter().filter_map(|(_, c)| *c).collect();
+            cycle.push(c);
+            return Some((prefix, cycle, t));
+        }
+        if done.contains(&t) {
+            continue;
+        }
+        stack.push((t, Some(c)));
+        if let Some(found) = find_cycle(dfa, live, stack, done) {
+            return Some(found);
+        }
+        stack.pop();
+    }
+
+    done.insert(state);
+    None
+}
+
+// Number of accepted words from `state`, assuming the live part is acyclic.
+fn count_words(
+    dfa: &DFA,
+    live: &HashSet<StateId>,
+    state: StateId,
+    memo: &mut HashMap<StateId, u128>,
+) -> u128 {
+    if let Some(&n) = memo.get(&state) {
+        return n;
+    }
+
+    let mut n = u128::from(dfa.accepts.contains(&state));
+    for &t in dfa
+        .transitions
+        .get(&state)
+        .into_iter()
+        .flat_map(|m| m.values())
+    {
+        if live.contains(&t) {
+            n = n.saturating_add(count_words(dfa, live, t, memo));
+        }
+    }
+
+    memo.insert(state, n);
+    n
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::language::LanguageSet;
+
+    #[test]
+    fn membership_with_run() {
+        let m = membership("ab*", "abb").unwrap();
+        assert!(m.accepted);
+        assert_eq!(m.run.len(), 4);
+
+        let m = membership("ab*", "ba").unwrap();
+        assert!(!m.accepted);
+        assert_eq!(m.run.len(), 1);
+    }
+
+    #[test]
+    fn emptiness_witness() {
+        assert_eq!(emptiness("∅").unwrap(), Emptiness::Empty);
+        assert_eq!(emptiness("a∅").unwrap(), Emptiness::Empty);
+        assert_eq!(
+            emptiness("bb+a(a+b)").unwrap(),
+            Emptiness::NonEmpty {
+                witness: "aa".into()
+            }
+        );
+    }
+
+    #[test]
+    fn finiteness_counts_and_pumps() {
+        assert_eq!(
+            finiteness("(a+b)(a+b)?").unwrap(),
+            Finiteness::Finite { count: 6 }
+        );
+        assert_eq!(finiteness("∅").unwrap(), Finiteness::Finite { count: 0 });
+
+        let Finiteness::Infinite {
+            prefix,
+            cycle,
+            suffix,
+        } = finiteness("ab(cd)*e").unwrap()
+        else {
+            panic!("expected an infinite language");
+        };
+        assert!(!cycle.is_empty());
+        for i in 0..4 {
+            let word = format!("{}{}{}", prefix, cycle.repeat(i), suffix);
+            assert!(membership("ab(cd)*e", &word).unwrap().accepted, "{}", word);
+        }
+    }
+
+    #[test]
+    fn equivalence_and_witness() {
+        assert_eq!(
+            equivalence("(a+b)*", "(a*b*)*").unwrap(),
+            Equivalence::Equivalent
+        );
+        assert_eq!(
+            equivalence("a*", "aa*").unwrap(),
+            Equivalence::Distinguished {
+                witness: "".into(),
+                in_left: true
+            }
+        );
+        assert_eq!(
+            equivalence("ab", "ab+ba").unwrap(),
+            Equivalence::Distinguished {
+                witness: "ba".into(),
+                in_left: false
+            }
+        );
+    }
+
+    #[test]
+    fn inclusion_and_witness() {
+        assert_eq!(inclusion("ab*", "a(a+b)*").unwrap(), Inclusion::Included);
+        assert_eq!(
+            inclusion("a(a+b)*", "ab*").unwrap(),
+            Inclusion::NotIncluded {
+                witness: "aa".into()
+            }
+        );
+    }
+
+    #[test]
+    fn universality_over_alphabet() {
+        let ab: BTreeSet<char> = "ab".chars().collect();
+        assert_eq!(
+            universality("(a+b)*", &ab).unwrap(),
+            Universality::Universal
+        );
+        assert_eq!(
+            universality("a*", &ab).unwrap(),
+            Universality::NotUniversal {
+                witness: "b".into()
+            }
+        );
+        assert_eq!(
+            universality("(a+b)(a+b)*", &ab).unwrap(),
+            Universality::NotUniversal { witness: "".into() }
+        );
+    }
+
+    #[test]
+    fn languages_as_inputs() {
+        let mut langs = LanguageSet::new();
+        langs.define("bit", "0+1").unwrap();
+        langs.define("word", "{bit}*").unwrap();
+
+        let word = langs.get("word").unwrap();
+        assert_eq!(
+            equivalence(word, "(0+1)*").unwrap(),
+            Equivalence::Equivalent
+        );
+        assert!(membership(word, "0110").unwrap().accepted);
+    }
+
+    #[test]
+    fn parse_errors_propagate() {
+        assert!(emptiness("(a").is_err());
+    }
+}
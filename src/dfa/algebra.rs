@@ -0,0 +1,349 @@
+// dfa/algebra.rs
+use super::dfa::DFA;
+use super::minimize::minimize_dfa;
+use super::ops::trim;
+use crate::types::StateId;
+use std::collections::{HashMap, VecDeque};
+
+/// Upper bound on the number of elements `transition_monoid` will enumerate.
+pub const MAX_MONOID_SIZE: usize = 1 << 14;
+
+/// A transformation of the DFA's states: `t[i]` is where state `states[i]`
+/// ends up. The last index is an implicit sink for missing transitions.
+pub type Transformation = Vec<usize>;
+
+/// The transition monoid of a DFA: every transformation of its states
+/// induced by some word, each with a shortest word that induces it.
+#[derive(Debug, Clone)]
+pub struct TransitionMonoid {
+    // DFA states in index order; the sink is not listed
+    pub states: Vec<StateId>,
+    pub generators: Vec<char>,
+    // elements[0] is the identity, induced by the empty word
+    pub elements: Vec<Transformation>,
+    pub words: Vec<String>,
+}
+
+impl TransitionMonoid {
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Index of a transformation in `elements`, if it belongs to the monoid.
+    pub fn index_of(&self, t: &Transformation) -> Option<usize> {
+        self.elements.iter().position(|e| e == t)
+    }
+
+    /// Product `elements[x] · elements[y]`: first apply x, then y.
+    pub fn multiply(&self, x: usize, y: usize) -> usize {
+        let product = compose(&self.elements[x], &self.elements[y]);
+        self.index_of(&product)
+            .expect("the monoid is closed under composition")
+    }
+
+    /// Period of the cycle the powers of `elements[x]` fall into: the
+    /// smallest k with x^(n+k) = x^n for all large n.
+    pub fn period(&self, x: usize) -> usize {
+        let x = &self.elements[x];
+        let mut seen: HashMap<Transformation, usize> = HashMap::new();
+        let mut power = x.clone();
+        for k in 0.. {
+            if let Some(&j) = seen.get(&power) {
+                return k - j;
+            }
+            seen.insert(power.clone(), k);
+            power = compose(&power, x);
+        }
+        unreachable!()
+    }
+
+    /// True if the monoid has no nontrivial subgroup, i.e. every element
+    /// satisfies x^n = x^(n+1) for some n.
+    pub fn is_aperiodic(&self) -> bool {
+        (0..self.len()).all(|x| self.period(x) == 1)
+    }
+}
+
+/// Enumerate the transition monoid of `dfa` by breadth-first closure of
+/// the identity under the letter transformations. Fails once more than
+/// `MAX_MONOID_SIZE` elements have been found.
+pub fn transition_monoid(dfa: &DFA) -> Result<TransitionMonoid, String> {
+    let states: Vec<StateId> = dfa.states().into_iter().collect();
+    let generators: Vec<char> = dfa.alphabet().into_iter().collect();
+    let index: HashMap<StateId, usize> = states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let sink = states.len();
+
+    // The transformation each letter induces
+    let letters: Vec<Transformation> = generators
+        .iter()
+        .map(|c| {
+            let mut t: Transformation = states
+                .iter()
+                .map(|s| {
+                    dfa.transitions
+                        .get(s)
+                        .and_then(|m| m.get(c))
+                        .map_or(sink, |t| index[t])
+                })
+                .collect();
+            t.push(sink);
+            t
+        })
+        .collect();
+
+    let identity: Transformation = (0..=sink).collect();
+    let mut found: HashMap<Transformation, usize> = HashMap::from([(identity.clone(), 0)]);
+    let mut elements = vec![identity];
+    let mut words = vec![String::new()];
+    let mut queue = VecDeque::from([0]);
+
+    while let Some(i) = queue.pop_front() {
+        for (c, letter) in generators.iter().zip(&letters) {
+            let next = compose(&elements[i], letter);
+            if found.contains_key(&next) {
+                continue;
+            }
+            if elements.len() >= MAX_MONOID_SIZE {
+                return Err(format!(
+                    "Transition monoid exceeds {} elements",
+                    MAX_MONOID_SIZE
+                ));
+            }
+
+            found.insert(next.clone(), elements.len());
+            queue.push_back(elements.len());
+            words.push(format!("{}{}", words[i], c));
+            elements.push(next);
+        }
+    }
+
+    Ok(TransitionMonoid {
+        states,
+        generators,
+        elements,
+        words,
+    })
+}
+
+/// The syntactic monoid of L(dfa): the transition monoid of its trimmed
+/// minimal DFA. Two words are syntactically congruent (interchangeable in
+/// every context) exactly when they induce the same element.
+#[derive(Debug, Clone)]
+pub struct SyntacticMonoid {
+    pub minimal: DFA,
+    pub monoid: TransitionMonoid,
+}
+
+impl SyntacticMonoid {
+    pub fn new(dfa: &DFA) -> Result<Self, String> {
+        let minimal = trim(&minimize_dfa(dfa));
+        let monoid = transition_monoid(&minimal)?;
+        Ok(Self { minimal, monoid })
+    }
+
+    /// Index of the congruence class of `word`, or None when `word` has a
+    /// symbol outside the alphabet and no word over the alphabet is dead,
+    /// so the monoid has no element sending every state to the sink.
+    pub fn class_of(&self, word: &str) -> Option<usize> {
+        let m = &self.monoid;
+        let sink = m.states.len();
+        let index: HashMap<StateId, usize> =
+            m.states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+
+        let mut t: Transformation = (0..=sink).collect();
+        for c in word.chars() {
+            let letter: Transformation = m
+                .states
+                .iter()
+                .map(|s| {
+                    self.minimal
+                        .transitions
+                        .get(s)
+                        .and_then(|map| map.get(&c))
+                        .map_or(sink, |t| index[t])
+                })
+                .chain([sink])
+                .collect();
+            t = compose(&t, &letter);
+        }
+
+        // Only symbols outside the alphabet leave the monoid, and they
+        // send everything to the sink
+        m.index_of(&t).or_else(|| self.zero())
+    }
+
+    /// The class of the dead words, which no context makes accepted, if
+    /// the alphabet has any.
+    pub fn zero(&self) -> Option<usize> {
+        // In the empty language every word is dead, and the monoid is
+        // trivial (the trimmed DFA is a single state, itself dead)
+        if self.minimal.accepts.is_empty() {
+            return Some(0);
+        }
+        let sink = self.monoid.states.len();
+        self.monoid.index_of(&vec![sink; sink + 1])
+    }
+
+    /// Shortest representative of each congruence class, by class index.
+    pub fn representatives(&self) -> &[String] {
+        &self.monoid.words
+    }
+
+    /// Whether `u` and `v` are syntactically congruent. Words with symbols
+    /// outside the alphabet are dead: congruent to each other and to the
+    /// dead words over the alphabet.
+    pub fn congruent(&self, u: &str, v: &str) -> bool {
+        self.class_of(u) == self.class_of(v)
+    }
+
+    /// Classes whose words belong to the language. The language is the
+    /// union of these classes.
+    pub fn accepting_classes(&self) -> Vec<usize> {
+        let start = self
+            .monoid
+            .states
+            .iter()
+            .position(|&s| s == self.minimal.start);
+        (0..self.monoid.len())
+            .filter(|&i| {
+                start.is_some_and(|st| {
+                    let end = self.monoid.elements[i][st];
+                    end < self.monoid.states.len()
+                        && self.minimal.accepts.contains(&self.monoid.states[end])
+                })
+            })
+            .collect()
+    }
+}
+
+// Apply `first`, then `then`.
+fn compose(first: &Transformation, then: &Transformation) -> Transformation {
+    first.iter().map(|&i| then[i]).collect()
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn monoid(lang: &str) -> TransitionMonoid {
+        transition_monoid(&full_pipeline(lang).unwrap().mindfa).unwrap()
+    }
+
+    #[test]
+    fn parity_is_a_group() {
+        // Even number of a's: the monoid is Z/2
+        let m = monoid("(b*ab*a)*b*");
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.words, vec!["", "a"]);
+        assert!(!m.is_aperiodic());
+        assert_eq!(m.multiply(1, 1), 0);
+        assert_eq!(m.period(1), 2);
+    }
+
+    #[test]
+    fn elements_match_their_words() {
+        let dfa = full_pipeline("(a+b)*abb").unwrap().mindfa;
+        let m = transition_monoid(&dfa).unwrap();
+
+        for (t, w) in m.elements.iter().zip(&m.words) {
+            for (i, &s) in m.states.iter().enumerate() {
+                // Run w from s by hand
+                let mut state = Some(s);
+                for c in w.chars() {
+                    state = state.and_then(|q| dfa.transitions.get(&q)?.get(&c).copied());
+                }
+                let expected = state.map_or(m.states.len(), |q| {
+                    m.states.iter().position(|&x| x == q).unwrap()
+                });
+                assert_eq!(t[i], expected, "word {:?} from {}", w, s);
+            }
+        }
+    }
+
+    #[test]
+    fn syntactic_classes() {
+        // Words ending in "ab": classes are ε, a, b, ab, ... (a and ba agree)
+        let dfa = full_pipeline("(a+b)*ab").unwrap().mindfa;
+        let syn = SyntacticMonoid::new(&dfa).unwrap();
+
+        assert!(syn.congruent("a", "ba"));
+        assert!(syn.congruent("ab", "bab"));
+        assert!(!syn.congruent("a", "b"));
+        assert!(!syn.congruent("", "b"));
+
+        // Every representative lands in its own class
+        for (i, w) in syn.representatives().iter().enumerate() {
+            assert_eq!(syn.class_of(w), Some(i));
+        }
+
+        // Membership is decided by the class
+        let accepting = syn.accepting_classes();
+        for w in ["ab", "aab", "b", "aba", ""] {
+            assert_eq!(
+                accepting.contains(&syn.class_of(w).unwrap()),
+                dfa.accepts(w),
+                "{:?}",
+                w
+            );
+        }
+    }
+
+    #[test]
+    fn syntactic_monoid_ignores_presentation() {
+        // A redundant DFA for a* and the minimal one share a syntactic monoid
+        let redundant = DFA {
+            start: StateId(0),
+            accepts: [StateId(0), StateId(1)].into(),
+            transitions: HashMap::from([
+                (StateId(0), HashMap::from([('a', StateId(1))])),
+                (StateId(1), HashMap::from([('a', StateId(0))])),
+            ]),
+        };
+        assert_eq!(transition_monoid(&redundant).unwrap().len(), 2);
+        assert_eq!(SyntacticMonoid::new(&redundant).unwrap().monoid.len(), 1);
+
+        // Words with a symbol outside the alphabet fall in the zero class
+        let syn = SyntacticMonoid::new(&full_pipeline("ab").unwrap().mindfa).unwrap();
+        assert!(syn.congruent("z", "ba"));
+        assert_eq!(syn.class_of("z"), syn.zero());
+
+        // Without dead words over the alphabet there is no such class
+        for lang in ["a*", "(a+b)*"] {
+            let syn = SyntacticMonoid::new(&full_pipeline(lang).unwrap().mindfa).unwrap();
+            assert_eq!((syn.class_of("z"), syn.zero()), (None, None), "{}", lang);
+            assert!(syn.congruent("z", "zy") && !syn.congruent("z", ""));
+        }
+        // In the empty language, all words are dead
+        let syn = SyntacticMonoid::new(&full_pipeline("∅").unwrap().mindfa).unwrap();
+        assert_eq!((syn.class_of("z"), syn.zero()), (Some(0), Some(0)));
+        assert!(syn.congruent("z", ""));
+    }
+
+    #[test]
+    fn size_cap() {
+        // The full transformation monoid on 7 states has 7^7 elements
+        let mut dfa = DFA {
+            start: StateId(0),
+            accepts: [StateId(0)].into(),
+            transitions: HashMap::new(),
+        };
+        for s in 0..7 {
+            let m = dfa.transitions.entry(StateId(s)).or_default();
+            m.insert('a', StateId((s + 1) % 7));
+            m.insert('b', StateId(if s < 2 { 1 - s } else { s }));
+            m.insert('c', StateId(if s == 0 { 1 } else { s }));
+        }
+        assert!(transition_monoid(&dfa).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+// dfa/analysis.rs
+use super::algebra::SyntacticMonoid;
+use super::dfa::DFA;
+use super::ops::{co_reachable, trim};
+use crate::decisions::{inclusion, Inclusion};
+use crate::types::StateId;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Upper bound on the number of rows `truth_table` will enumerate.
+pub const MAX_TRUTH_TABLE_ROWS: usize = 1 << 16;
+
+/// Enumerate every string of length `len` over the DFA's alphabet, in
+/// lexicographic order, as CSV rows of `input,accepted`.
+pub fn truth_table(dfa: &DFA, len: usize) -> Result<String, String> {
+    let alphabet: Vec<char> = dfa.alphabet().into_iter().collect();
+
+    let rows = u32::try_from(len)
+        .ok()
+        .and_then(|l| alphabet.len().checked_pow(l))
+        .filter(|&rows| rows <= MAX_TRUTH_TABLE_ROWS)
+        .ok_or_else(|| {
+            format!(
+                "Truth table too large: |Σ|^{} exceeds {} rows",
+                len, MAX_TRUTH_TABLE_ROWS
+            )
+        })?;
+
+    let mut out = String::from("input,accepted\n");
+    // Odometer over alphabet indices, last position varies fastest
+    let mut digits = vec![0usize; len];
+
+    for _ in 0..rows {
+        let input: String = digits.iter().map(|&d| alphabet[d]).collect();
+        out.push_str(&format!("{},{}\n", input, dfa.accepts(&input)));
+
+        for d in digits.iter_mut().rev() {
+            *d += 1;
+            if *d < alphabet.len() {
+                break;
+            }
+            *d = 0;
+        }
+    }
+
+    Ok(out)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StarFreeness {
+    StarFree,
+    // u·word^n·v membership cycles with this period (> 1) for some u, v
+    NotStarFree { word: String, period: usize },
+}
+
+/// Star-freeness via Schützenberger's theorem: the syntactic monoid must
+/// be aperiodic. A failing answer names a word whose powers cycle.
+pub fn is_star_free(dfa: &DFA) -> Result<StarFreeness, String> {
+    let monoid = SyntacticMonoid::new(dfa)?.monoid;
+
+    Ok(match (0..monoid.len()).find(|&x| monoid.period(x) > 1) {
+        Some(x) => StarFreeness::NotStarFree {
+            word: monoid.words[x].clone(),
+            period: monoid.period(x),
+        },
+        None => StarFreeness::StarFree,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Locality {
+    Local,
+    // Allowed by the language's first letters, last letters and two-letter
+    // factors, yet not in the language
+    NotLocal { witness: String },
+}
+
+/// Whether the language is local (strictly 2-testable): membership of a
+/// nonempty word only depends on its first letter, last letter and the
+/// set of its two-letter factors.
+pub fn is_local(dfa: &DFA) -> Locality {
+    let live = trim(dfa);
+    let edges = || {
+        live.transitions
+            .iter()
+            .flat_map(|(&p, m)| m.iter().map(move |(&c, &q)| (p, c, q)))
+    };
+
+    let firsts: BTreeSet<char> = edges().filter(|e| e.0 == live.start).map(|e| e.1).collect();
+    let lasts: BTreeSet<char> = edges()
+        .filter(|e| live.accepts.contains(&e.2))
+        .map(|e| e.1)
+        .collect();
+    let mut factors: BTreeSet<(char, char)> = BTreeSet::new();
+    for (_, a, q) in edges() {
+        for &b in live.transitions.get(&q).into_iter().flat_map(|m| m.keys()) {
+            factors.insert((a, b));
+        }
+    }
+
+    // The local approximation: state 0 before any input, then one state
+    // per letter remembering the last one read
+    let alphabet: Vec<char> = live.alphabet().into_iter().collect();
+    let state = |c: char| StateId::new(alphabet.binary_search(&c).unwrap() + 1);
+    let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+    for &c in &firsts {
+        transitions
+            .entry(StateId(0))
+            .or_default()
+            .insert(c, state(c));
+    }
+    for &(a, b) in &factors {
+        transitions.entry(state(a)).or_default().insert(b, state(b));
+    }
+    let mut accepts: HashSet<StateId> = lasts.iter().map(|&c| state(c)).collect();
+    if live.accepts.contains(&live.start) {
+        accepts.insert(StateId(0));
+    }
+    let local = DFA {
+        start: StateId(0),
+        accepts,
+        transitions,
+    };
+
+    // The approximation always contains the language; check the converse
+    match inclusion(&local, dfa).expect("DFA inputs don't fail") {
+        Inclusion::Included => Locality::Local,
+        Inclusion::NotIncluded { witness } => Locality::NotLocal { witness },
+    }
+}
+
+impl DFA {
+    /// The lexicographically smallest (`Ordering::Less`) or largest
+    /// (`Ordering::Greater`) accepted word of exactly `len` symbols.
+    /// `Ordering::Equal` is treated as smallest.
+    pub fn extreme_word(&self, len: usize, order: Ordering) -> Option<String> {
+        // live[k] = states from which some accepted word of length k starts
+        let mut live: Vec<HashSet<StateId>> = vec![self.accepts.clone()];
+        for k in 1..=len {
+            let next: HashSet<StateId> = self
+                .transitions
+                .iter()
+                .filter(|(_, map)| map.values().any(|t| live[k - 1].contains(t)))
+                .map(|(&s, _)| s)
+                .collect();
+            live.push(next);
+        }
+
+        if !live[len].contains(&self.start) {
+            return None;
+        }
+
+        // Guided walk: always take the extreme symbol that stays live
+        let mut word = String::with_capacity(len);
+        let mut state = self.start;
+        for remaining in (0..len).rev() {
+            let options = self.transitions[&state]
+                .iter()
+                .filter(|(_, t)| live[remaining].contains(t));
+            let (&c, &t) = match order {
+                Ordering::Greater => options.max_by_key(|(c, _)| **c),
+                _ => options.min_by_key(|(c, _)| **c),
+            }?;
+            word.push(c);
+            state = t;
+        }
+
+        Some(word)
+    }
+
+    /// Whether infinitely many accepted words start with `prefix`, i.e.
+    /// the state after `prefix` reaches a cycle that can still reach an
+    /// accepting state.
+    pub fn accepts_arbitrarily_long_extensions(&self, prefix: &str) -> bool {
+        let mut state = self.start;
+        for ch in prefix.chars() {
+            match self.transitions.get(&state).and_then(|m| m.get(&ch)) {
+                Some(&next) => state = next,
+                None => return false,
+            }
+        }
+
+        // Live states reachable from `state`
+        let co = co_reachable(self);
+        if !co.contains(&state) {
+            return false;
+        }
+        let mut live = HashSet::from([state]);
+        let mut queue = VecDeque::from([state]);
+        while let Some(s) = queue.pop_front() {
+            for &t in self
+                .transitions
+                .get(&s)
+                .into_iter()
+                .flat_map(|m| m.values())
+            {
+                if co.contains(&t) && live.insert(t) {
+                    queue.push_back(t);
+                }
+            }
+        }
+
+        // Kahn's algorithm: a cycle exists iff some live state is never peeled off
+        let successors = |s: &StateId| {
+            self.transitions
+                .get(s)
+                .into_iter()
+                .flat_map(|m| m.values())
+                .filter(|t| live.contains(t))
+        };
+        let mut in_degree: HashMap<StateId, usize> = live.iter().map(|&s| (s, 0)).collect();
+        for s in &live {
+            for t in successors(s) {
+                *in_degree.get_mut(t).unwrap() += 1;
+            }
+        }
+
+        let mut ready: Vec<StateId> = in_degree
+            .iter()
+            .filter(|(_, &d)| d == 0)
+            .map(|(&s, _)| s)
+            .collect();
+        let mut peeled = 0;
+        while let Some(s) = ready.pop() {
+            peeled += 1;
+            for t in successors(&s) {
+                let d = in_degree.get_mut(t).unwrap();
+                *d -= 1;
+                if *d == 0 {
+                    ready.push(*t);
+                }
+            }
+        }
+
+        peeled < live.len()
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn truth_table_over_binary() {
+        let dfa = full_pipeline("(0+1)*1").unwrap().mindfa;
+        let csv = truth_table(&dfa, 2).unwrap();
+
+        assert_eq!(
+            csv,
+            "input,accepted\n00,false\n01,true\n10,false\n11,true\n"
+        );
+    }
+
+    #[test]
+    fn truth_table_empty_input() {
+        let dfa = full_pipeline("a*").unwrap().mindfa;
+        assert_eq!(truth_table(&dfa, 0).unwrap(), "input,accepted\n,true\n");
+    }
+
+    #[test]
+    fn truth_table_size_guard() {
+        let dfa = full_pipeline("(0+1)*").unwrap().mindfa;
+        assert!(truth_table(&dfa, 16).is_ok());
+        assert!(truth_table(&dfa, 17).is_err());
+    }
+
+    #[test]
+    fn extreme_words() {
+        let dfa = full_pipeline("(a+b)*b(a+b)").unwrap().mindfa;
+
+        assert_eq!(dfa.extreme_word(3, Ordering::Less).as_deref(), Some("aba"));
+        assert_eq!(
+            dfa.extreme_word(3, Ordering::Greater).as_deref(),
+            Some("bbb")
+        );
+        assert_eq!(dfa.extreme_word(1, Ordering::Less), None);
+    }
+
+    #[test]
+    fn arbitrarily_long_extensions() {
+        let dfa = full_pipeline("ab*+cd").unwrap().mindfa;
+
+        assert!(dfa.accepts_arbitrarily_long_extensions(""));
+        assert!(dfa.accepts_arbitrarily_long_extensions("abb"));
+        // Only "cd" extends "c"
+        assert!(!dfa.accepts_arbitrarily_long_extensions("c"));
+        assert!(!dfa.accepts_arbitrarily_long_extensions("cd"));
+        // Not a prefix of anything accepted
+        assert!(!dfa.accepts_arbitrarily_long_extensions("d"));
+    }
+
+    #[test]
+    fn cycle_must_reach_accept() {
+        let mut dfa = full_pipeline("ab").unwrap().mindfa;
+        // A self-loop on a dead branch doesn't count
+        let dead = dfa.states().last().unwrap().next();
+        dfa.transitions
+            .get_mut(&dfa.start)
+            .unwrap()
+            .insert('z', dead);
+        dfa.transitions.entry(dead).or_default().insert('z', dead);
+
+        assert!(!dfa.accepts_arbitrarily_long_extensions(""));
+        assert!(!dfa.accepts_arbitrarily_long_extensions("z"));
+    }
+
+    #[test]
+    fn star_free_languages() {
+        for lang in ["(a+b)*ab(a+b)*", "a*b*", "ab", "∅", "(ab)*"] {
+            let dfa = full_pipeline(lang).unwrap().mindfa;
+            assert_eq!(is_star_free(&dfa), Ok(StarFreeness::StarFree), "{}", lang);
+        }
+    }
+
+    #[test]
+    fn not_star_free_witness() {
+        let dfa = full_pipeline("(a+b)*b(aa)*").unwrap().mindfa;
+        assert_eq!(
+            is_star_free(&dfa),
+            Ok(StarFreeness::NotStarFree {
+                word: "a".into(),
+                period: 2
+            })
+        );
+
+        let dfa = full_pipeline("(abc)*").unwrap().mindfa;
+        assert!(matches!(is_star_free(&dfa), Ok(StarFreeness::StarFree)));
+        let dfa = full_pipeline("((a+b)(a+b)(a+b))*").unwrap().mindfa;
+        assert!(matches!(
+            is_star_free(&dfa),
+            Ok(StarFreeness::NotStarFree { period: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn local_languages() {
+        for lang in ["ab*c", "(ab)*", "a(b+c)*", "ε+a*", "∅"] {
+            let dfa = full_pipeline(lang).unwrap().mindfa;
+            assert_eq!(is_local(&dfa), Locality::Local, "{}", lang);
+        }
+    }
+
+    #[test]
+    fn not_local_witness() {
+        // Words may start with a ("aab") and end with a ("ba")
+        let dfa = full_pipeline("aab+ba").unwrap().mindfa;
+        let Locality::NotLocal { witness } = is_local(&dfa) else {
+            panic!("expected a non-local language");
+        };
+        assert!(!dfa.accepts(&witness));
+        assert_eq!(witness, "a");
+
+        // a*ba* cannot count its b's with 2-letter windows
+        let dfa = full_pipeline("a*ba*").unwrap().mindfa;
+        assert!(matches!(is_local(&dfa), Locality::NotLocal { .. }));
+    }
+
+    #[test]
+    fn extreme_word_empty() {
+        let dfa = full_pipeline("a*").unwrap().mindfa;
+        assert_eq!(dfa.extreme_word(0, Ordering::Less).as_deref(), Some(""));
+        assert_eq!(dfa.extreme_word(2, Ordering::Less).as_deref(), Some("aa"));
+    }
+}
@@ -0,0 +1,144 @@
+// dfa/batch.rs
+//
+// Batch execution: several inputs advance through the same `CompiledDFA`
+// in lockstep, and each step looks up all their next states at once. On
+// x86_64 with AVX2 the lookup is a single gather instruction; elsewhere
+// it falls back to a scalar loop over the lanes.
+use super::compiled::CompiledDFA;
+use std::str::Chars;
+
+/// Number of inputs advanced together per step.
+pub const LANES: usize = 8;
+
+impl CompiledDFA {
+    /// Whether each input is accepted, in order. Same answers as calling
+    /// `accepts` on each input; faster for many inputs of similar length.
+    pub fn accepts_batch(&self, inputs: &[&str]) -> Vec<bool> {
+        let mut out = Vec::with_capacity(inputs.len());
+        for chunk in inputs.chunks(LANES) {
+            out.extend_from_slice(&self.run_lanes(chunk)[..chunk.len()]);
+        }
+        out
+    }
+
+    // Run up to LANES inputs to completion.
+    fn run_lanes(&self, inputs: &[&str]) -> [bool; LANES] {
+        let mut chars: Vec<Chars> = inputs.iter().map(|s| s.chars()).collect();
+        let mut states = [self.start(); LANES];
+        let mut active = [false; LANES];
+        active[..inputs.len()].fill(true);
+
+        loop {
+            // Column of each lane's next symbol; finished lanes read column 0
+            // and have the result discarded
+            let mut classes = [0u32; LANES];
+            let mut any = false;
+            for lane in 0..inputs.len() {
+                if !active[lane] {
+                    continue;
+                }
+                match chars[lane].next() {
+                    Some(c) => {
+                        classes[lane] = self.class(c);
+                        any = true;
+                    }
+                    None => active[lane] = false,
+                }
+            }
+            if !any {
+                break;
+            }
+
+            let next = self.gather(&states, &classes);
+            for lane in 0..LANES {
+                if active[lane] {
+                    states[lane] = next[lane];
+                    // Nothing leaves the dead state
+                    active[lane] = next[lane] != self.dead();
+                }
+            }
+        }
+
+        states.map(|s| self.is_accepting(s))
+    }
+
+    // table[state * stride + class] for every lane.
+    fn gather(&self, states: &[u32; LANES], classes: &[u32; LANES]) -> [u32; LANES] {
+        let (table, stride) = (self.table(), self.stride());
+
+        #[cfg(target_arch = "x86_64")]
+        if self.is_validated()
+            && table.len() <= i32::MAX as usize
+            && std::arch::is_x86_feature_detected!("avx2")
+        {
+            // SAFETY: AVX2 is available, and every index is below
+            // `table.len()` (states are rows, classes are below `stride`,
+            // both checked by `validate`), which fits in an i32.
+            return unsafe { gather_avx2(table, stride, states, classes) };
+        }
+
+        let mut out = [0u32; LANES];
+        for lane in 0..LANES {
+            out[lane] = table[(states[lane] * stride + classes[lane]) as usize];
+        }
+        out
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn gather_avx2(
+    table: &[u32],
+    stride: u32,
+    states: &[u32; LANES],
+    classes: &[u32; LANES],
+) -> [u32; LANES] {
+    use std::arch::x86_64::*;
+
+    let s = _mm256_loadu_si256(states.as_ptr() as *const __m256i);
+    let c = _mm256_loadu_si256(classes.as_ptr() as *const __m256i);
+    let idx = _mm256_add_epi32(_mm256_mullo_epi32(s, _mm256_set1_epi32(stride as i32)), c);
+    let next = _mm256_i32gather_epi32::<4>(table.as_ptr() as *const i32, idx);
+
+    let mut out = [0u32; LANES];
+    _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, next);
+    out
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn batch_matches_single() {
+        let dfa = full_pipeline("(a+b)*abb+é[0-9]*").unwrap().mindfa;
+        let compiled = CompiledDFA::from_dfa(&dfa);
+
+        // More than one chunk, with uneven lengths and early dead lanes
+        let inputs = [
+            "abb",
+            "",
+            "babababb",
+            "é1234567890",
+            "x",
+            "abbb",
+            "é",
+            "aaaaaaaaaabb",
+            "ab",
+            "é9",
+            "zzzzzzzzzzzzzz",
+            "bbabb",
+        ];
+        let expected: Vec<bool> = inputs.iter().map(|w| dfa.accepts(w)).collect();
+
+        assert_eq!(compiled.accepts_batch(&inputs), expected);
+        assert!(compiled.accepts_batch(&[]).is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+// dfa/bits.rs
+use super::dfa::DFA;
+use crate::types::StateId;
+use std::collections::{HashMap, HashSet};
+
+/// Largest input width accepted by `DFA::from_bool_fn`.
+pub const MAX_BOOL_FN_BITS: u32 = 20;
+
+/*
+* Bitstring API for DFAs over the alphabet {0,1}.
+* A `len`-bit number is read most significant bit first, so
+* `accepts_bits(0b011, 3)` is the same query as `accepts("011")`.
+*/
+impl DFA {
+    /// Errors when `len` is more bits than a `u64` holds.
+    pub fn accepts_bits(&self, bits: u64, len: u32) -> Result<bool, String> {
+        if len > u64::BITS {
+            return Err(format!(
+                "Bitstring too long: {} bits exceeds {}",
+                len,
+                u64::BITS
+            ));
+        }
+        Ok((0..len)
+            .rev()
+            .try_fold(self.start, |state, i| {
+                self.step_bit(state, (bits >> i) & 1 == 1)
+            })
+            .is_some_and(|state| self.accepts.contains(&state)))
+    }
+
+    pub fn accepts_bitslice(&self, bits: &[bool]) -> bool {
+        bits.iter()
+            .try_fold(self.start, |state, &bit| self.step_bit(state, bit))
+            .is_some_and(|state| self.accepts.contains(&state))
+    }
+
+    #[inline]
+    fn step_bit(&self, state: StateId, bit: bool) -> Option<StateId> {
+        let c = if bit { '1' } else { '0' };
+        self.transitions.get(&state)?.get(&c).copied()
+    }
+
+    /// Build the DFA accepting exactly the `len`-bit strings `x` with `f(x)`.
+    /// States are shared bottom-up (like a reduced decision diagram), so the
+    /// result has no two states with the same residual function.
+    pub fn from_bool_fn(len: u32, f: impl Fn(u64) -> bool) -> Result<DFA, String> {
+        if len > MAX_BOOL_FN_BITS {
+            return Err(format!(
+                "Boolean function too wide: {} bits exceeds {}",
+                len, MAX_BOOL_FN_BITS
+            ));
+        }
+
+        let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+        let mut shared: HashMap<(Option<StateId>, Option<StateId>), StateId> = HashMap::new();
+
+        // Leaves: one accepting state, `None` for rejection
+        let accept = StateId(0);
+        let mut level: Vec<Option<StateId>> = (0..1u64 << len)
+            .map(|x| if f(x) { Some(accept) } else { None })
+            .collect();
+        let mut next_id = 1;
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match (pair[0], pair[1]) {
+                    (None, None) => None,
+                    key => Some(*shared.entry(key).or_insert_with(|| {
+                        let id = StateId::new(next_id);
+                        next_id += 1;
+                        let edges = transitions.entry(id).or_default();
+                        if let Some(t) = key.0 {
+                            edges.insert('0', t);
+                        }
+                        if let Some(t) = key.1 {
+                            edges.insert('1', t);
+                        }
+                        id
+                    })),
+                })
+                .collect();
+        }
+
+        Ok(match level[0] {
+            Some(start) => DFA {
+                start,
+                accepts: HashSet::from([accept]),
+                transitions,
+            },
+            // Nothing accepted: a lone non-accepting start state
+            None => DFA {
+                start: StateId(0),
+                accepts: HashSet::new(),
+                transitions: HashMap::new(),
+            },
+        })
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn bits_match_string_queries() {
+        let dfa = full_pipeline("(0+1)*01").unwrap().mindfa;
+
+        for x in 0..16u64 {
+            let s = format!("{:04b}", x);
+            let slice: Vec<bool> = s.chars().map(|c| c == '1').collect();
+
+            assert_eq!(dfa.accepts_bits(x, 4), Ok(dfa.accepts(&s)), "{}", s);
+            assert_eq!(dfa.accepts_bitslice(&slice), dfa.accepts(&s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn from_bool_fn_parity() {
+        let dfa = DFA::from_bool_fn(5, |x| x.count_ones() % 2 == 1).unwrap();
+
+        for x in 0..32u64 {
+            assert_eq!(dfa.accepts_bits(x, 5), Ok(x.count_ones() % 2 == 1));
+        }
+        assert_eq!(dfa.accepts_bits(1, 4), Ok(false));
+        assert_eq!(dfa.accepts_bits(1, 6), Ok(false));
+        assert_eq!(dfa.accepts_bits(1, 64), Ok(false));
+        assert!(dfa.accepts_bits(1, 65).is_err());
+
+        // Parity needs two states per level below the root, plus the leaf
+        assert_eq!(dfa.transitions.len(), 1 + 2 * 4);
+    }
+
+    #[test]
+    fn from_bool_fn_constant_false() {
+        let dfa = DFA::from_bool_fn(3, |_| false).unwrap();
+        assert!((0..8).all(|x| dfa.accepts_bits(x, 3) == Ok(false)));
+        assert!(DFA::from_bool_fn(MAX_BOOL_FN_BITS + 1, |_| true).is_err());
+    }
+}
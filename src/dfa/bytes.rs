@@ -0,0 +1,163 @@
+// dfa/bytes.rs
+//
+// Char DFAs compiled to byte DFAs, for matching byte buffers (network
+// packets, mmapped files, parser input) without decoding them first. Each
+// char transition becomes the path of its encoding's bytes; a buffer is
+// accepted exactly when it is the encoding of an accepted word, so invalid
+// UTF-8 is rejected rather than an error.
+use super::dense::DenseDFA;
+use super::dfa::DFA;
+use crate::types::StateId;
+use std::collections::{HashMap, HashSet};
+
+/// How chars become bytes in `DFA::to_byte_dfa`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    // One to four bytes per char
+    #[default]
+    Utf8,
+    // One byte per char; chars above U+00FF have no encoding, so their
+    // transitions are dropped
+    Latin1,
+}
+
+impl DFA {
+    /// The byte DFA accepting the encodings of the words `self` accepts.
+    /// States of `self` keep their sorted order as 0..n; the states inside
+    /// multi-byte sequences come after, shared between chars with a common
+    /// prefix. Run `minimize_dfa` on the result for the smallest machine.
+    /// For ASCII case-insensitive matching, compile `self` with the
+    /// pipeline's `case_insensitive` flag: both cases of every letter then
+    /// have their own bytes in the table, and nothing is folded per byte.
+    pub fn to_byte_dfa(&self, encoding: Encoding) -> DFA<u8> {
+        let states: Vec<StateId> = self.states().into_iter().collect();
+        let id: HashMap<StateId, StateId> = states
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| (s, StateId::new(i)))
+            .collect();
+        let mut transitions: HashMap<StateId, HashMap<u8, StateId>> = HashMap::new();
+        let mut next = states.len();
+        let mut buf = [0; 4];
+
+        for s in &states {
+            let Some(map) = self.transitions.get(s) else {
+                continue;
+            };
+            // Sorted so intermediate states are numbered deterministically
+            let mut edges: Vec<(char, StateId)> = map.iter().map(|(&c, &t)| (c, t)).collect();
+            edges.sort_unstable();
+
+            for (c, t) in edges {
+                let bytes: &[u8] = match encoding {
+                    Encoding::Utf8 => c.encode_utf8(&mut buf).as_bytes(),
+                    Encoding::Latin1 => match u8::try_from(c) {
+                        Ok(b) => {
+                            buf[0] = b;
+                            &buf[..1]
+                        }
+                        Err(_) => continue,
+                    },
+                };
+                let (last, prefix) = bytes.split_last().unwrap();
+                let mut at = id[s];
+                for &b in prefix {
+                    at = *transitions
+                        .entry(at)
+                        .or_default()
+                        .entry(b)
+                        .or_insert_with(|| {
+                            next += 1;
+                            StateId::new(next - 1)
+                        });
+                }
+                transitions.entry(at).or_default().insert(*last, id[&t]);
+            }
+        }
+
+        DFA {
+            start: id[&self.start],
+            accepts: self.accepts.iter().map(|s| id[s]).collect::<HashSet<_>>(),
+            transitions,
+        }
+    }
+}
+
+impl DFA<u8> {
+    pub fn accepts_bytes(&self, input: &[u8]) -> bool {
+        self.accepts_letters(input.iter().copied())
+    }
+}
+
+impl DenseDFA<u8> {
+    pub fn accepts_bytes(&self, input: &[u8]) -> bool {
+        self.accepts_letters(input.iter().copied())
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::minimize::minimize_dfa;
+    use crate::pipeline::tests::{full_pipeline, full_pipeline_with, PipelineOptions};
+
+    #[test]
+    fn utf8() {
+        // é is C3 A9 and è is C3 A8, sharing their first byte
+        let dfa = full_pipeline("(é+è+a)*あ").unwrap().mindfa;
+        let bytes = dfa.to_byte_dfa(Encoding::Utf8);
+        for word in ["あ", "éèあ", "aéあ", "", "é", "ああ", "xあ"] {
+            assert_eq!(
+                bytes.accepts_bytes(word.as_bytes()),
+                dfa.accepts(word),
+                "{:?}",
+                word
+            );
+        }
+        // Half a char, and an é cut short before the next one
+        assert!(!bytes.accepts_bytes(&[0xC3]));
+        assert!(!bytes.accepts_bytes(&[0xC3, 0xE3, 0x81, 0x82]));
+
+        // One state inside é/è, shared, and two inside あ
+        assert_eq!(dfa.states().len(), 2);
+        assert_eq!(bytes.states().len(), 2 + 1 + 2);
+        assert_eq!(minimize_dfa(&bytes).states().len(), 5);
+
+        let dense = DenseDFA::from(&bytes);
+        assert!(dense.accepts_bytes("aéèあ".as_bytes()));
+    }
+
+    #[test]
+    fn latin1() {
+        let dfa = full_pipeline("ça+あ").unwrap().mindfa;
+        let bytes = dfa.to_byte_dfa(Encoding::Latin1);
+        assert!(bytes.accepts_bytes(&[0xE7, b'a']));
+        assert!(!bytes.accepts_bytes("ça".as_bytes()));
+        // あ is past U+00FF: no Latin-1 buffer spells it
+        assert_eq!(bytes.alphabet().len(), 2);
+        assert!(!bytes.accepts_bytes(&[]));
+    }
+
+    #[test]
+    fn case_insensitive_tables() {
+        let opts = PipelineOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        let dfa = full_pipeline_with("[^x]bb*é", &opts).unwrap().mindfa;
+        let bytes = DenseDFA::from(&dfa.to_byte_dfa(Encoding::Utf8));
+        for word in ["bbé", "BBé", "éBbé"] {
+            assert!(bytes.accepts_bytes(word.as_bytes()), "{:?}", word);
+        }
+        // The negated class leaves out both cases; é doesn't fold
+        for word in ["xbé", "Xbé", "bbÉ"] {
+            assert!(!bytes.accepts_bytes(word.as_bytes()), "{:?}", word);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// dfa/canonical.rs
+use super::dfa::DFA;
+use super::minimize::minimize_dfa;
+use crate::types::{Letter, StateId};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Renumber the reachable part of a DFA in BFS order from the start state,
+/// following symbols in sorted order. Two isomorphic DFAs produce identical
+/// output, so on minimized DFAs this is a canonical form of the language.
+pub fn canonicalize<S: Letter>(dfa: &DFA<S>) -> DFA<S> {
+    let mut old_to_new: HashMap<StateId, StateId> = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    old_to_new.insert(dfa.start, StateId(0));
+    queue.push_back(dfa.start);
+
+    let mut transitions: HashMap<StateId, HashMap<S, StateId>> = HashMap::new();
+
+    while let Some(old) = queue.pop_front() {
+        let Some(map) = dfa.transitions.get(&old) else {
+            continue;
+        };
+
+        let mut symbols: Vec<S> = map.keys().copied().collect();
+        symbols.sort_unstable();
+
+        let mut new_map = HashMap::new();
+        for c in symbols {
+            let target = map[&c];
+            let next_id = StateId::new(old_to_new.len());
+            let new_target = *old_to_new.entry(target).or_insert_with(|| {
+                queue.push_back(target);
+                next_id
+            });
+            new_map.insert(c, new_target);
+        }
+        if !new_map.is_empty() {
+            transitions.insert(old_to_new[&old], new_map);
+        }
+    }
+
+    let accepts: HashSet<StateId> = dfa
+        .accepts
+        .iter()
+        .filter_map(|a| old_to_new.get(a).copied())
+        .collect();
+
+    DFA {
+        start: StateId(0),
+        accepts,
+        transitions,
+    }
+}
+
+/// The renaming of states that turns `a` into `b` exactly, if there is
+/// one: same acceptance, same symbols out of each state, and matching
+/// targets, with every state of either machine paired. Found by walking
+/// both machines in lockstep from their starts, which leaves at most one
+/// candidate, so this is linear. Meant for minimized DFAs, where it is
+/// unique and states the same machine up to renaming; a dead state or an
+/// unreachable one on one side only makes the machines differ.
+pub fn isomorphism<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> Option<HashMap<StateId, StateId>> {
+    let empty: HashMap<S, StateId> = HashMap::new();
+    let mut forward: HashMap<StateId, StateId> = HashMap::from([(a.start, b.start)]);
+    let mut backward: HashMap<StateId, StateId> = HashMap::from([(b.start, a.start)]);
+    let mut queue = VecDeque::from([(a.start, b.start)]);
+
+    while let Some((p, q)) = queue.pop_front() {
+        if a.accepts.contains(&p) != b.accepts.contains(&q) {
+            return None;
+        }
+        let from_p = a.transitions.get(&p).unwrap_or(&empty);
+        let from_q = b.transitions.get(&q).unwrap_or(&empty);
+        if from_p.len() != from_q.len() {
+            return None;
+        }
+        for (c, &pt) in from_p {
+            let qt = *from_q.get(c)?;
+            match (forward.get(&pt), backward.get(&qt)) {
+                (None, None) => {
+                    forward.insert(pt, qt);
+                    backward.insert(qt, pt);
+                    queue.push_back((pt, qt));
+                }
+                (Some(&x), Some(&y)) if x == qt && y == pt => {}
+                _ => return None,
+            }
+        }
+    }
+
+    let complete = forward.len() == a.states().len() && backward.len() == b.states().len();
+    complete.then_some(forward)
+}
+
+/// Are `a` and `b` the same machine up to renaming states? See
+/// `isomorphism`. On minimized DFAs this is equivalence; on others it is
+/// stricter.
+pub fn isomorphic<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> bool {
+    isomorphism(a, b).is_some()
+}
+
+/// A 128-bit fingerprint of the language recognized by `dfa`, computed from
+/// its canonical minimal form. Equal languages get equal fingerprints, and
+/// the value is stable across runs and platforms.
+pub fn fingerprint(dfa: &DFA) -> u128 {
+    fingerprint_canonical(&canonicalize(&minimize_dfa(dfa)))
+}
+
+// `fingerprint` of a DFA already minimized and canonicalized.
+pub(crate) fn fingerprint_canonical(canon: &DFA) -> u128 {
+    let mut h = Fnv128::new();
+    write_dfa(&mut h, canon);
+    h.finish()
+}
+
+/// A hash of `dfa` exactly as numbered, stable like `fingerprint` but
+/// without minimizing: equal for identical DFAs only. Cheap enough to key
+/// caches of the more expensive `fingerprint`.
+pub fn structural_hash(dfa: &DFA) -> u128 {
+    let mut h = Fnv128::new();
+    h.write_u64(dfa.start.0 as u64);
+    write_dfa(&mut h, dfa);
+    h.finish()
+}
+
+// Accept states and edges, in sorted order.
+fn write_dfa(h: &mut Fnv128, canon: &DFA) {
+    let mut accepts: Vec<StateId> = canon.accepts.iter().copied().collect();
+    accepts.sort_unstable();
+    h.write_u64(accepts.len() as u64);
+    for a in accepts {
+        h.write_u64(a.0 as u64);
+    }
+
+    let mut edges: Vec<(StateId, char, StateId)> = canon
+        .transitions
+        .iter()
+        .flat_map(|(&s, m)| m.iter().map(move |(&c, &t)| (s, c, t)))
+        .collect();
+    edges.sort_unstable();
+    h.write_u64(edges.len() as u64);
+    for (s, c, t) in edges {
+        h.write_u64(s.0 as u64);
+        h.write_u64(c as u64);
+        h.write_u64(t.0 as u64);
+    }
+}
+
+// FNV-1a, 128-bit variant. Unlike `DefaultHasher` its output is fixed.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    fn new() -> Self {
+        Fnv128(Self::OFFSET)
+    }
+
+    fn write_u64(&mut self, v: u64) {
+        for b in v.to_le_bytes() {
+            self.0 ^= b as u128;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u128 {
+        self.0
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn fp(lang: &str) -> u128 {
+        fingerprint(&full_pipeline(lang).unwrap().dfa)
+    }
+
+    #[test]
+    fn canonicalize_is_bfs_numbered() {
+        let dfa = canonicalize(&full_pipeline("ab+c").unwrap().mindfa);
+
+        assert_eq!(dfa.start, StateId(0));
+        assert_eq!(dfa.transitions[&StateId(0)][&'a'], StateId(1));
+        assert_eq!(dfa.transitions[&StateId(0)][&'c'], StateId(2));
+        assert_eq!(dfa.transitions[&StateId(1)][&'b'], StateId(2));
+    }
+
+    #[test]
+    fn isomorphic_minimal_machines() {
+        let reference = full_pipeline("(a+b)*abb").unwrap().mindfa;
+        let student = canonicalize(&full_pipeline("(b+a)*a(bb)").unwrap().mindfa);
+        let renaming = isomorphism(&reference, &student).unwrap();
+        assert_eq!(renaming.len(), 4);
+        assert_eq!(renaming[&reference.start], student.start);
+        for (&p, &q) in &renaming {
+            assert_eq!(reference.accepts.contains(&p), student.accepts.contains(&q));
+        }
+        assert!(isomorphic(&student, &reference));
+
+        // Same language, but not minimal, or completed with a dead state
+        let unminimized = full_pipeline("(a+b)*abb").unwrap().dfa;
+        assert!(!isomorphic(&reference, &unminimized));
+        let completed = reference.complete_with(Default::default());
+        assert!(!isomorphic(&reference, &completed));
+        assert!(!isomorphic(&completed, &reference));
+
+        // One edge relabelled or redirected
+        let mut relabelled = student.clone();
+        let map = relabelled.transitions.get_mut(&StateId(0)).unwrap();
+        let to = map.remove(&'a').unwrap();
+        map.insert('c', to);
+        assert!(!isomorphic(&reference, &relabelled));
+        let mut redirected = student.clone();
+        redirected
+            .transitions
+            .get_mut(&StateId(0))
+            .unwrap()
+            .insert('b', StateId(1));
+        assert!(!isomorphic(&reference, &redirected));
+    }
+
+    #[test]
+    fn equal_languages_equal_fingerprints() {
+        assert_eq!(fp("(a+b)*"), fp("(a*b*)*"));
+        assert_eq!(fp("a(ba)*"), fp("(ab)*a"));
+        assert_eq!(fp("a{2,3}"), fp("aa+aaa"));
+    }
+
+    #[test]
+    fn structural_hash_follows_numbering() {
+        let dfa = full_pipeline("(a+b)*abb").unwrap().mindfa;
+        assert_eq!(structural_hash(&dfa), structural_hash(&dfa.clone()));
+
+        // The same machine with every state shifted by 100
+        let shift = |s: StateId| StateId(s.0 + 100);
+        let shifted = DFA {
+            start: shift(dfa.start),
+            accepts: dfa.accepts.iter().map(|&a| shift(a)).collect(),
+            transitions: dfa
+                .transitions
+                .iter()
+                .map(|(&s, m)| (shift(s), m.iter().map(|(&c, &t)| (c, shift(t))).collect()))
+                .collect(),
+        };
+        assert_ne!(structural_hash(&shifted), structural_hash(&dfa));
+        assert_eq!(fingerprint(&shifted), fingerprint(&dfa));
+    }
+
+    #[test]
+    fn different_languages_different_fingerprints() {
+        assert_ne!(fp("a*"), fp("a"));
+        assert_ne!(fp("ab"), fp("ba"));
+        assert_ne!(fp("a+b"), fp("a+c"));
+    }
+}
@@ -0,0 +1,146 @@
+// dfa/codegen.rs
+//
+// Byte DFAs written out as Rust source: one self-contained function with
+// the transition table as statics, for firmware and build scripts that
+// want a matcher without linking this crate.
+use super::dense::DenseDFA;
+
+impl DenseDFA<u8> {
+    /// Source for `pub fn <name>(input: &[u8]) -> bool`, accepting what
+    /// `accepts_bytes` does. Bytes outside the alphabet get a column of
+    /// their own leading to the dead state, so the table has one column
+    /// per letter plus one, and lookups need no bounds checks of their own.
+    pub fn to_rust(&self, name: &str) -> Result<String, String> {
+        let valid = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("Codegen error: '{}' is not a function name", name));
+        }
+
+        let letters = self.alphabet().len();
+        let columns = letters + 1;
+        let mut column = [letters; 256];
+        for (j, &b) in self.alphabet().iter().enumerate() {
+            column[b as usize] = j;
+        }
+        let mut next = Vec::with_capacity(self.num_states() * columns);
+        for s in 0..self.num_states() {
+            next.extend_from_slice(&self.table()[s * letters..(s + 1) * letters]);
+            next.push(self.dead());
+        }
+        let accepting: Vec<bool> = (0..self.num_states())
+            .map(|s| self.is_accepting(s))
+            .collect();
+
+        let list = |items: Vec<String>| items.join(", ");
+        Ok(format!(
+            "pub fn {name}(input: &[u8]) -> bool {{\n\
+             \x20   const COLUMNS: usize = {columns};\n\
+             \x20   const DEAD: usize = {dead};\n\
+             \x20   static COLUMN: [u16; 256] = [{column}];\n\
+             \x20   static NEXT: [u32; {len}] = [{next}];\n\
+             \x20   static ACCEPTING: [bool; {states}] = [{accepting}];\n\
+             \n\
+             \x20   let mut state = {start};\n\
+             \x20   for &b in input {{\n\
+             \x20       state = NEXT[state * COLUMNS + COLUMN[b as usize] as usize] as usize;\n\
+             \x20       if state == DEAD {{\n\
+             \x20           return false;\n\
+             \x20       }}\n\
+             \x20   }}\n\
+             \x20   ACCEPTING[state]\n\
+             }}\n",
+            dead = self.dead(),
+            column = list(column.iter().map(|c| c.to_string()).collect()),
+            len = next.len(),
+            next = list(next.iter().map(|t| t.to_string()).collect()),
+            states = accepting.len(),
+            accepting = list(accepting.iter().map(|a| a.to_string()).collect()),
+            start = self.start(),
+        ))
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::bytes::Encoding;
+    use crate::dfa::minimize::minimize_dfa;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn byte_table(pattern: &str) -> DenseDFA<u8> {
+        let dfa = full_pipeline(pattern).unwrap().mindfa;
+        DenseDFA::from(&minimize_dfa(&dfa.to_byte_dfa(Encoding::Utf8)))
+    }
+
+    // Run the emitted statics the way the emitted loop does
+    fn run(source: &str, input: &[u8]) -> bool {
+        let numbers = |name: &str| -> Vec<String> {
+            let line = source.lines().find(|l| l.contains(name)).unwrap();
+            let body = &line[line.rfind('[').unwrap() + 1..line.rfind(']').unwrap()];
+            body.split(", ").map(str::to_string).collect()
+        };
+        let constant = |name: &str| -> usize {
+            let line = source.lines().find(|l| l.contains(name)).unwrap();
+            line.trim_end_matches(';')
+                .rsplit(' ')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        let column: Vec<usize> = numbers("COLUMN:")
+            .iter()
+            .map(|c| c.parse().unwrap())
+            .collect();
+        let next: Vec<usize> = numbers("NEXT:")
+            .iter()
+            .map(|t| t.parse().unwrap())
+            .collect();
+        let accepting = numbers("ACCEPTING:");
+        let (columns, dead) = (constant("COLUMNS:"), constant("DEAD:"));
+
+        let mut state = constant("let mut state");
+        for &b in input {
+            state = next[state * columns + column[b as usize]];
+            if state == dead {
+                return false;
+            }
+        }
+        accepting[state] == "true"
+    }
+
+    #[test]
+    fn emitted_table_matches() {
+        let table = byte_table("(a+é)*b");
+        let source = table.to_rust("is_word").unwrap();
+        assert!(source.starts_with("pub fn is_word(input: &[u8]) -> bool {"));
+        for input in ["b", "aéab", "", "ab\u{0}", "é", "ee"] {
+            assert_eq!(
+                run(&source, input.as_bytes()),
+                table.accepts_bytes(input.as_bytes()),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn empty_alphabet_and_bad_names() {
+        let source = byte_table("ε").to_rust("empty").unwrap();
+        assert!(run(&source, b"") && !run(&source, b"a"));
+
+        assert!(byte_table("a").to_rust("9lives").is_err());
+        assert!(byte_table("a").to_rust("a-b").is_err());
+        assert!(byte_table("a").to_rust("").is_err());
+    }
+}
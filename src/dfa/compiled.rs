@@ -0,0 +1,620 @@
+// dfa/compiled.rs
+use super::dfa::DFA;
+use super::ops::ignore_ascii_case;
+use crate::types::StateId;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How much of the input a match must cover.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Acceptance {
+    // Membership: the whole input is in the language
+    #[default]
+    WholeInput,
+    // The first prefix in the language, found without reading further
+    ShortestPrefix,
+    // The longest prefix in the language, as a lexer's maximal munch
+    LongestPrefix,
+}
+
+/// A DFA flattened into a dense transition table for fast matching.
+///
+/// Symbols are mapped to column indices (the last column catches every
+/// char outside the alphabet), and states to rows. One extra row is the
+/// dead state, so every lookup succeeds and no branch is needed per step.
+///
+/// Everything lives in one buffer of little-endian `u32` words, which is
+/// also the file format, so a saved DFA can be memory-mapped and used in
+/// place (see `from_mmap`):
+///
+/// ```text
+/// header     HEADER_WORDS words, see the H_* indices
+/// ascii      128 words: column of each ASCII char
+/// alphabet   alphabet_len words: sorted chars, column i is alphabet[i]
+/// accepting  ceil(num_states / 32) words: bitset over rows
+/// table      num_states * stride words, row-major
+/// ```
+#[derive(Debug)]
+pub struct CompiledDFA {
+    words: Words,
+    // Copied out of the header
+    stride: u32,
+    start: u32,
+    dead: u32,
+    num_states: u32,
+    alphabet_at: usize,
+    alphabet_len: usize,
+    accepting_at: usize,
+    table_at: usize,
+    // Every ASCII column, alphabet entry and transition target checked;
+    // false only for mapped tables until `validate` runs
+    validated: bool,
+}
+
+/// First word of every compiled DFA: "KLDF" in little-endian.
+pub const MAGIC: u32 = u32::from_le_bytes(*b"KLDF");
+pub const FORMAT_VERSION: u32 = 1;
+
+// Header layout, in words
+const H_MAGIC: usize = 0;
+const H_VERSION: usize = 1;
+const H_NUM_STATES: usize = 2;
+const H_STRIDE: usize = 3;
+const H_START: usize = 4;
+const H_ALPHABET_LEN: usize = 5;
+const H_TOTAL: usize = 6;
+const HEADER_WORDS: usize = 8;
+const ASCII_WORDS: usize = 128;
+
+// Backing storage of the word buffer.
+#[derive(Debug)]
+enum Words {
+    Owned(Vec<u32>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Words {
+    #[inline]
+    fn as_slice(&self) -> &[u32] {
+        match self {
+            Words::Owned(v) => v,
+            #[cfg(feature = "mmap")]
+            Words::Mapped(m) => {
+                // SAFETY: checked in `from_mmap`: the mapping is 4-byte
+                // aligned (mmap is page aligned), its length is a multiple of
+                // 4, and the host is little-endian like the file. Its caller
+                // promised the file stays unmodified while mapped.
+                unsafe { std::slice::from_raw_parts(m.as_ptr() as *const u32, m.len() / 4) }
+            }
+        }
+    }
+}
+
+impl Clone for CompiledDFA {
+    // Always an owned copy, validated or not like the original
+    fn clone(&self) -> Self {
+        Self {
+            words: Words::Owned(self.words().to_vec()),
+            ..*self
+        }
+    }
+}
+
+impl PartialEq for CompiledDFA {
+    fn eq(&self, other: &Self) -> bool {
+        self.words() == other.words()
+    }
+}
+
+impl Eq for CompiledDFA {}
+
+impl CompiledDFA {
+    pub fn from_dfa(dfa: &DFA) -> Self {
+        Self::build(dfa, false)
+    }
+
+    /// Like `from_dfa`, but ASCII letters match in either case. The folding
+    /// happens here, once: upper-case letters get the columns of their
+    /// lower-case forms in the ASCII table, so matching costs exactly what
+    /// it does without folding. Other chars are matched as they are, and
+    /// `alphabet()` lists only the lower-case letters.
+    pub fn from_dfa_ignore_ascii_case(dfa: &DFA) -> Self {
+        Self::build(&ignore_ascii_case(dfa), true)
+    }
+
+    // With `fold`, `dfa` must treat both cases of a letter alike.
+    fn build(dfa: &DFA, fold: bool) -> Self {
+        let alphabet: Vec<char> = dfa
+            .alphabet()
+            .into_iter()
+            .filter(|c| !(fold && c.is_ascii_uppercase()))
+            .collect();
+        let states: Vec<StateId> = dfa.states().into_iter().collect();
+        let row: HashMap<StateId, u32> = states
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| (s, i as u32))
+            .collect();
+
+        let stride = alphabet.len() as u32 + 1;
+        let dead = states.len() as u32;
+        let num_states = dead as usize + 1;
+
+        let mut words = vec![0u32; HEADER_WORDS];
+        words[H_MAGIC] = MAGIC;
+        words[H_VERSION] = FORMAT_VERSION;
+        words[H_NUM_STATES] = num_states as u32;
+        words[H_STRIDE] = stride;
+        words[H_START] = row[&dfa.start];
+        words[H_ALPHABET_LEN] = alphabet.len() as u32;
+
+        let mut ascii = [stride - 1; ASCII_WORDS];
+        for (col, &c) in alphabet.iter().enumerate() {
+            if c.is_ascii() {
+                ascii[c as usize] = col as u32;
+            }
+        }
+        if fold {
+            for c in b'A'..=b'Z' {
+                ascii[c as usize] = ascii[c.to_ascii_lowercase() as usize];
+            }
+        }
+        words.extend(ascii);
+        words.extend(alphabet.iter().map(|&c| c as u32));
+
+        let mut accepting = vec![0u32; num_states.div_ceil(32)];
+        for (i, s) in states.iter().enumerate() {
+            if dfa.accepts.contains(s) {
+                accepting[i / 32] |= 1 << (i % 32);
+            }
+        }
+        words.extend(accepting);
+
+        let table_at = words.len();
+        words.resize(table_at + num_states * stride as usize, dead);
+        for (&s, map) in &dfa.transitions {
+            for (&c, &t) in map {
+                // Folded upper-case letters share their lower-case column
+                let Ok(col) = alphabet.binary_search(&c) else {
+                    continue;
+                };
+                words[table_at + (row[&s] * stride) as usize + col] = row[&t];
+            }
+        }
+
+        words[H_TOTAL] = words.len() as u32;
+        Self::from_words(words).expect("freshly compiled tables are valid")
+    }
+
+    /// The compiled tables in file form (little-endian words).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.words().iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Write error: {}", e))
+    }
+
+    /// Load tables produced by `to_bytes`, copying them.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err("Compiled DFA length is not a multiple of 4".into());
+        }
+        let words = bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+            .collect();
+        Self::from_words(words)
+    }
+
+    /// Map a file written by `save` into memory and match against it in
+    /// place. Only the header is checked, so loading takes the same time
+    /// for any size of table; call `validate` to check the entries too.
+    /// Until then, a corrupt entry makes matching panic (never read out of
+    /// bounds), and batch matching skips its unchecked SIMD path.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the returned DFA
+    /// (or anything borrowed from it) is alive. The mapping reads the file
+    /// as it is at each access, and a change under it is undefined behavior.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_mmap(path: impl AsRef<Path>) -> Result<Self, String> {
+        if cfg!(target_endian = "big") {
+            return Err("Memory-mapped DFAs need a little-endian host".into());
+        }
+
+        let file = std::fs::File::open(path).map_err(|e| format!("Open error: {}", e))?;
+        // SAFETY: passed on to our caller, see above.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| format!("Map error: {}", e))?;
+        if !map.len().is_multiple_of(4) || !(map.as_ptr() as usize).is_multiple_of(4) {
+            return Err("Compiled DFA length is not a multiple of 4".into());
+        }
+
+        Self::from_storage(Words::Mapped(map))
+    }
+
+    fn from_words(words: Vec<u32>) -> Result<Self, String> {
+        let mut dfa = Self::from_storage(Words::Owned(words))?;
+        dfa.validate()?;
+        Ok(dfa)
+    }
+
+    // Check the header and the layout it describes, in constant time.
+    fn from_storage(words: Words) -> Result<Self, String> {
+        let w = words.as_slice();
+        if w.len() < HEADER_WORDS + ASCII_WORDS || w[H_MAGIC] != MAGIC {
+            return Err("Not a compiled DFA".into());
+        }
+        if w[H_VERSION] != FORMAT_VERSION {
+            return Err(format!("Unsupported format version {}", w[H_VERSION]));
+        }
+
+        let num_states = w[H_NUM_STATES];
+        let stride = w[H_STRIDE];
+        let alphabet_len = w[H_ALPHABET_LEN] as usize;
+        let alphabet_at = HEADER_WORDS + ASCII_WORDS;
+        let accepting_at = alphabet_at + alphabet_len;
+        let table_at = accepting_at + (num_states as usize).div_ceil(32);
+        let total = (num_states as u64)
+            .checked_mul(stride as u64)
+            .map(|t| t + table_at as u64);
+
+        if num_states == 0 || stride as usize != alphabet_len + 1 {
+            return Err("Inconsistent compiled DFA header".into());
+        }
+        if total != Some(w.len() as u64) || w[H_TOTAL] as usize != w.len() {
+            return Err("Compiled DFA has the wrong length".into());
+        }
+        if w[H_START] >= num_states {
+            return Err("Start state out of range".into());
+        }
+
+        Ok(Self {
+            stride,
+            start: w[H_START],
+            dead: num_states - 1,
+            num_states,
+            alphabet_at,
+            alphabet_len,
+            accepting_at,
+            table_at,
+            validated: false,
+            words,
+        })
+    }
+
+    /// Check every ASCII column, alphabet entry and transition target, so
+    /// no lookup can go out of bounds. Done on every load except
+    /// `from_mmap`, where it is up to the caller; linear in the table size.
+    pub fn validate(&mut self) -> Result<(), String> {
+        let w = self.words();
+        if w[HEADER_WORDS..self.alphabet_at]
+            .iter()
+            .any(|&c| c >= self.stride)
+        {
+            return Err("ASCII column out of range".into());
+        }
+        let alphabet = &w[self.alphabet_at..self.accepting_at];
+        if alphabet.iter().any(|&c| char::from_u32(c).is_none())
+            || alphabet.windows(2).any(|p| p[0] >= p[1])
+        {
+            return Err("Alphabet is not a sorted list of chars".into());
+        }
+        if w[self.table_at..].iter().any(|&t| t >= self.num_states) {
+            return Err("Transition target out of range".into());
+        }
+        self.validated = true;
+        Ok(())
+    }
+
+    /// Whether `validate` has passed; always true except after `from_mmap`.
+    pub fn is_validated(&self) -> bool {
+        self.validated
+    }
+
+    #[inline]
+    fn words(&self) -> &[u32] {
+        self.words.as_slice()
+    }
+
+    #[inline]
+    pub(crate) fn table(&self) -> &[u32] {
+        &self.words()[self.table_at..]
+    }
+
+    #[inline]
+    pub(crate) fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    #[inline]
+    pub(crate) fn dead(&self) -> u32 {
+        self.dead
+    }
+
+    /// Column index of a symbol.
+    #[inline]
+    pub fn class(&self, c: char) -> u32 {
+        if c.is_ascii() {
+            return self.words()[HEADER_WORDS + c as usize];
+        }
+        let alphabet = &self.words()[self.alphabet_at..self.alphabet_at + self.alphabet_len];
+        match alphabet.binary_search(&(c as u32)) {
+            Ok(col) => col as u32,
+            Err(_) => self.stride - 1,
+        }
+    }
+
+    #[inline]
+    pub fn next_state(&self, state: u32, c: char) -> u32 {
+        self.table()[(state * self.stride() + self.class(c)) as usize]
+    }
+
+    pub fn accepts(&self, input: &str) -> bool {
+        let accepted = self.run(input);
+        #[cfg(feature = "testing")]
+        crate::testing::corpus::record_compiled(self, None, input, accepted);
+        accepted
+    }
+
+    fn run(&self, input: &str) -> bool {
+        let mut state = self.start;
+        for c in input.chars() {
+            state = self.next_state(state, c);
+            if state == self.dead() {
+                return false;
+            }
+        }
+        self.is_accepting(state)
+    }
+
+    /// Byte offset just past the match `mode` selects, or None. With
+    /// `WholeInput` that's `input.len()` exactly when `accepts(input)`.
+    pub fn find(&self, input: &str, mode: Acceptance) -> Option<usize> {
+        let steps = input.char_indices().map(|(i, c)| (i + c.len_utf8(), c));
+        self.find_steps(steps, input.len(), mode)
+    }
+
+    // `find` over chars paired with the offset just past each; `len` is the
+    // end of input. Lets callers skip or rewrite chars but report offsets
+    // into the original text.
+    pub(crate) fn find_steps(
+        &self,
+        steps: impl Iterator<Item = (usize, char)>,
+        len: usize,
+        mode: Acceptance,
+    ) -> Option<usize> {
+        let mut state = self.start;
+        let mut last = self.is_accepting(state).then_some(0);
+        if mode == Acceptance::ShortestPrefix && last.is_some() {
+            return last;
+        }
+
+        for (end, c) in steps {
+            state = self.next_state(state, c);
+            if state == self.dead() {
+                if mode == Acceptance::WholeInput {
+                    return None;
+                }
+                break;
+            }
+            if self.is_accepting(state) {
+                if mode == Acceptance::ShortestPrefix {
+                    return Some(end);
+                }
+                last = Some(end);
+            }
+        }
+
+        match mode {
+            Acceptance::WholeInput => self.is_accepting(state).then_some(len),
+            Acceptance::ShortestPrefix => None,
+            Acceptance::LongestPrefix => last,
+        }
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    #[inline]
+    pub fn is_accepting(&self, state: u32) -> bool {
+        let word = self.words()[self.accepting_at + state as usize / 32];
+        word & (1 << (state % 32)) != 0
+    }
+
+    /// Number of states, including the added dead state.
+    pub fn num_states(&self) -> usize {
+        self.num_states as usize
+    }
+
+    pub fn alphabet(&self) -> Vec<char> {
+        self.words()[self.alphabet_at..self.alphabet_at + self.alphabet_len]
+            .iter()
+            .map(|&c| char::from_u32(c).expect("alphabet entry is not a char"))
+            .collect()
+    }
+
+    /// Back to a map-based DFA numbered by row, without the dead state.
+    /// ASCII letters folded by `from_dfa_ignore_ascii_case` get transitions
+    /// in both cases.
+    pub fn to_dfa(&self) -> DFA {
+        let mut chars = self.alphabet();
+        chars.extend((0..128u8).map(char::from));
+        chars.sort_unstable();
+        chars.dedup();
+
+        let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+        for s in (0..self.num_states).filter(|&s| s != self.dead()) {
+            for &c in &chars {
+                let t = self.next_state(s, c);
+                if t != self.dead() {
+                    transitions
+                        .entry(StateId(s))
+                        .or_default()
+                        .insert(c, StateId(t));
+                }
+            }
+        }
+        DFA {
+            start: StateId(self.start),
+            accepts: (0..self.num_states)
+                .filter(|&s| s != self.dead() && self.is_accepting(s))
+                .map(StateId)
+                .collect(),
+            transitions,
+        }
+    }
+}
+
+impl From<&DFA> for CompiledDFA {
+    fn from(dfa: &DFA) -> Self {
+        CompiledDFA::from_dfa(dfa)
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::canonical::isomorphic;
+    use crate::pipeline::tests::full_pipeline;
+
+    const WORDS: [&str; 9] = ["abb", "babb", "é", "é42", "ab", "", "x", "éa", "abbé"];
+
+    fn compiled() -> (DFA, CompiledDFA) {
+        let dfa = full_pipeline("(a+b)*abb+é[0-9]*").unwrap().mindfa;
+        let compiled = CompiledDFA::from_dfa(&dfa);
+        (dfa, compiled)
+    }
+
+    #[test]
+    fn agrees_with_dfa() {
+        let (dfa, compiled) = compiled();
+
+        for w in WORDS {
+            assert_eq!(compiled.accepts(w), dfa.accepts(w), "{:?}", w);
+        }
+        assert_eq!(
+            compiled.alphabet(),
+            dfa.alphabet().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn dead_state_absorbs() {
+        let compiled = CompiledDFA::from_dfa(&full_pipeline("ab").unwrap().mindfa);
+        let dead = compiled.num_states() as u32 - 1;
+
+        assert_eq!(compiled.next_state(compiled.start(), 'z'), dead);
+        assert_eq!(compiled.next_state(dead, 'a'), dead);
+        assert!(!compiled.is_accepting(dead));
+    }
+
+    #[test]
+    fn acceptance_modes() {
+        let compiled = CompiledDFA::from_dfa(&full_pipeline("a(bc)*").unwrap().mindfa);
+        let find = |input, mode| compiled.find(input, mode);
+
+        assert_eq!(find("abcbc", Acceptance::WholeInput), Some(5));
+        assert_eq!(find("abcb", Acceptance::WholeInput), None);
+        assert_eq!(find("abcbx", Acceptance::ShortestPrefix), Some(1));
+        assert_eq!(find("abcbx", Acceptance::LongestPrefix), Some(3));
+        assert_eq!(find("xa", Acceptance::LongestPrefix), None);
+
+        // Offsets are in bytes; an empty match is at 0
+        let compiled = CompiledDFA::from_dfa(&full_pipeline("é*").unwrap().mindfa);
+        assert_eq!(compiled.find("ééa", Acceptance::LongestPrefix), Some(4));
+        assert_eq!(compiled.find("ééa", Acceptance::ShortestPrefix), Some(0));
+    }
+
+    #[test]
+    fn ascii_case_folded_at_compile_time() {
+        let dfa = full_pipeline("(ab+Cd)*é").unwrap().mindfa;
+        let compiled = CompiledDFA::from_dfa_ignore_ascii_case(&dfa);
+
+        for w in ["abé", "ABé", "aBcDé", "cdé", "é"] {
+            assert!(compiled.accepts(w), "{:?}", w);
+        }
+        assert!(!compiled.accepts("abÉ") && !compiled.accepts("ab"));
+        assert_eq!(compiled.class('A'), compiled.class('a'));
+        assert_eq!(compiled.alphabet(), ['a', 'b', 'c', 'd', 'é']);
+
+        // The folding is in the saved tables
+        let loaded = CompiledDFA::from_bytes(&compiled.to_bytes()).unwrap();
+        assert!(loaded.accepts("CDé"));
+    }
+
+    #[test]
+    fn back_to_a_dfa() {
+        let (dfa, compiled) = compiled();
+        assert!(isomorphic(&compiled.to_dfa(), &dfa));
+
+        // Folded letters come back in both cases
+        let folded = CompiledDFA::from_dfa_ignore_ascii_case(&full_pipeline("ab").unwrap().mindfa);
+        let dfa = folded.to_dfa();
+        assert!(["ab", "aB", "Ab", "AB"].iter().all(|w| dfa.accepts(w)));
+        assert_eq!(dfa.states().len(), 3);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let (_, compiled) = compiled();
+        let loaded = CompiledDFA::from_bytes(&compiled.to_bytes()).unwrap();
+        assert_eq!(loaded, compiled);
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected() {
+        let (_, compiled) = compiled();
+        let bytes = compiled.to_bytes();
+
+        assert!(CompiledDFA::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+        assert!(CompiledDFA::from_bytes(&bytes[1..]).is_err());
+        assert!(CompiledDFA::from_bytes(b"nope").is_err());
+
+        // Point the last transition past the last state
+        let mut bad = bytes.clone();
+        let n = bad.len();
+        bad[n - 4..].copy_from_slice(&1000u32.to_le_bytes());
+        assert!(CompiledDFA::from_bytes(&bad).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_round_trip() {
+        let (dfa, compiled) = compiled();
+        let path = std::env::temp_dir().join(format!("kleeners-{}.kldf", std::process::id()));
+        compiled.save(&path).unwrap();
+
+        // SAFETY: nothing else touches the file while it is mapped
+        let mut mapped = unsafe { CompiledDFA::from_mmap(&path) }.unwrap();
+        assert!(!mapped.is_validated());
+        for w in WORDS {
+            assert_eq!(mapped.accepts(w), dfa.accepts(w), "{:?}", w);
+        }
+        assert_eq!(mapped, compiled);
+        mapped.validate().unwrap();
+        assert!(mapped.is_validated() && mapped.clone().is_validated());
+        drop(mapped);
+
+        // A bad transition is only caught on request
+        let mut bad = compiled.to_bytes();
+        let n = bad.len();
+        bad[n - 4..].copy_from_slice(&1000u32.to_le_bytes());
+        std::fs::write(&path, bad).unwrap();
+        // SAFETY: as above
+        let mut mapped = unsafe { CompiledDFA::from_mmap(&path) }.unwrap();
+        assert!(mapped.validate().is_err() && !mapped.is_validated());
+        drop(mapped);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+// dfa/dense.rs
+//
+// A DFA with its transitions in one flat table instead of a map per state,
+// for matching without hashing. Unlike `CompiledDFA` it works for any
+// letter type and is a plain `Vec`, with no file format or ASCII fast path.
+use super::dfa::DFA;
+use crate::types::{Letter, StateId};
+use std::collections::{HashMap, HashSet};
+
+/// Transitions at `table[state * alphabet.len() + symbol index]`, symbols
+/// indexed by their position in the sorted `alphabet`. States are rows
+/// 0..n; one extra row is a dead state, the target of every missing
+/// transition, so every lookup succeeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenseDFA<S = char> {
+    alphabet: Vec<S>,
+    table: Vec<usize>,
+    accepting: Vec<bool>,
+    start: usize,
+}
+
+impl<S: Letter> DenseDFA<S> {
+    /// Rows follow the sorted state ids of `dfa`.
+    pub fn from_dfa(dfa: &DFA<S>) -> Self {
+        let alphabet: Vec<S> = dfa.alphabet().into_iter().collect();
+        let states: Vec<StateId> = dfa.states().into_iter().collect();
+        let row: HashMap<StateId, usize> =
+            states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+        let dead = states.len();
+
+        let mut table = vec![dead; (states.len() + 1) * alphabet.len()];
+        for (i, s) in states.iter().enumerate() {
+            for (j, c) in alphabet.iter().enumerate() {
+                if let Some(t) = dfa.transitions.get(s).and_then(|m| m.get(c)) {
+                    table[i * alphabet.len() + j] = row[t];
+                }
+            }
+        }
+
+        let mut accepting: Vec<bool> = states.iter().map(|s| dfa.accepts.contains(s)).collect();
+        accepting.push(false);
+        DenseDFA {
+            alphabet,
+            table,
+            accepting,
+            start: row[&dfa.start],
+        }
+    }
+
+    /// Back to a map-based DFA numbered by row, without the dead state.
+    pub fn to_dfa(&self) -> DFA<S> {
+        let mut transitions: HashMap<StateId, HashMap<S, StateId>> = HashMap::new();
+        for s in 0..self.dead() {
+            for (j, &c) in self.alphabet.iter().enumerate() {
+                let t = self.table[s * self.alphabet.len() + j];
+                if t != self.dead() {
+                    transitions
+                        .entry(StateId::new(s))
+                        .or_default()
+                        .insert(c, StateId::new(t));
+                }
+            }
+        }
+        DFA {
+            start: StateId::new(self.start),
+            accepts: (0..self.dead())
+                .filter(|&s| self.accepting[s])
+                .map(StateId::new)
+                .collect::<HashSet<_>>(),
+            transitions,
+        }
+    }
+
+    /// Index of `c` in the alphabet, or None when the DFA never reads it.
+    #[inline]
+    pub fn symbol_index(&self, c: &S) -> Option<usize> {
+        self.alphabet.binary_search(c).ok()
+    }
+
+    #[inline]
+    pub fn next_state(&self, state: usize, c: &S) -> usize {
+        match self.symbol_index(c) {
+            Some(j) => self.table[state * self.alphabet.len() + j],
+            None => self.dead(),
+        }
+    }
+
+    pub fn accepts_letters(&self, input: impl IntoIterator<Item = S>) -> bool {
+        let mut state = self.start;
+        for c in input {
+            state = self.next_state(state, &c);
+            if state == self.dead() {
+                return false;
+            }
+        }
+        self.accepting[state]
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    #[inline]
+    pub fn dead(&self) -> usize {
+        self.accepting.len() - 1
+    }
+
+    #[inline]
+    pub fn is_accepting(&self, state: usize) -> bool {
+        self.accepting[state]
+    }
+
+    /// Number of states, including the added dead state.
+    pub fn num_states(&self) -> usize {
+        self.accepting.len()
+    }
+
+    pub fn alphabet(&self) -> &[S] {
+        &self.alphabet
+    }
+
+    /// The whole table, row-major.
+    pub fn table(&self) -> &[usize] {
+        &self.table
+    }
+}
+
+impl DenseDFA {
+    pub fn accepts(&self, input: &str) -> bool {
+        self.accepts_letters(input.chars())
+    }
+}
+
+impl<S: Letter> From<&DFA<S>> for DenseDFA<S> {
+    fn from(dfa: &DFA<S>) -> Self {
+        DenseDFA::from_dfa(dfa)
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::canonical::isomorphic;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn matches_like_the_dfa() {
+        let dfa = full_pipeline("(a+b)*abb+é[0-9]*").unwrap().mindfa;
+        let dense = DenseDFA::from(&dfa);
+        for word in ["abb", "babb", "é", "é42", "ab", "", "x", "éa", "abbé"] {
+            assert_eq!(dense.accepts(word), dfa.accepts(word), "{:?}", word);
+        }
+
+        assert_eq!(dense.num_states(), dfa.states().len() + 1);
+        assert_eq!(
+            dense.table().len(),
+            dense.num_states() * dense.alphabet().len()
+        );
+        assert_eq!(dense.next_state(dense.start(), &'x'), dense.dead());
+        assert!(isomorphic(&dense.to_dfa(), &dfa));
+    }
+
+    #[test]
+    fn byte_letters() {
+        let dfa: DFA<u8> = DFA {
+            start: StateId(7),
+            accepts: HashSet::from([StateId(9)]),
+            transitions: HashMap::from([
+                (StateId(7), HashMap::from([(b'o', StateId(8))])),
+                (StateId(8), HashMap::from([(b'k', StateId(9))])),
+            ]),
+        };
+        let dense = DenseDFA::from_dfa(&dfa);
+        assert_eq!(dense.alphabet(), b"ko");
+        assert!(dense.accepts_letters(*b"ok"));
+        assert!(!dense.accepts_letters(*b"oko") && !dense.accepts_letters(*b"k"));
+        assert_eq!(dense.to_dfa().start, StateId(0));
+    }
+}
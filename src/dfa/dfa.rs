@@ -1,21 +1,86 @@
 // dfa/dfa.rs
-use crate::nfa::nfa::{StateID, TransitionLabel, NFA};
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
-
-#[derive(Debug)]
-pub struct DFA {
-    pub start: usize,
-    pub accepts: HashSet<usize>,
-    pub transitions: HashMap<usize, HashMap<char, usize>>,
+use super::ops::co_reachable;
+use crate::nfa::nfa::{StateId, TransitionLabel, NFA};
+use crate::types::{AutomatonStats, Letter};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "S: Letter + Deserialize<'de>"))
+)]
+pub struct DFA<S = char> {
+    pub start: StateId,
+    pub accepts: HashSet<StateId>,
+    pub transitions: HashMap<StateId, HashMap<S, StateId>>,
 }
 
 impl DFA {
     // Run the DFA on an input string.
     pub fn accepts(&self, input: &str) -> bool {
+        let accepted = self.accepts_letters(input.chars());
+        #[cfg(feature = "testing")]
+        crate::testing::corpus::record(self, None, input, accepted);
+        accepted
+    }
+
+    /// Byte span of the leftmost-longest match anywhere in `haystack`: the
+    /// earliest start where some word of the language begins, extended as
+    /// far as it goes. Empty matches count, so a DFA accepting ε matches at
+    /// 0. Tries each start in turn, so quadratic at worst; each attempt
+    /// stops as soon as no accepting state is reachable any more.
+    pub fn find(&self, haystack: &str) -> Option<(usize, usize)> {
+        let live = co_reachable(self);
+        let starts = haystack.char_indices().map(|(i, _)| i);
+        starts
+            .chain([haystack.len()])
+            .find_map(|start| Some((start, self.longest_match_at(haystack, start, &live)?)))
+    }
+
+    // Byte offset just past the longest match starting at `start`. `live`
+    // holds the states that can still reach an accepting state
+    fn longest_match_at(
+        &self,
+        haystack: &str,
+        start: usize,
+        live: &HashSet<StateId>,
+    ) -> Option<usize> {
         let mut state = self.start;
+        let mut last = self.accepts.contains(&state).then_some(start);
+        for (i, c) in haystack[start..].char_indices() {
+            match self.transitions.get(&state).and_then(|m| m.get(&c)) {
+                Some(&next) if live.contains(&next) => state = next,
+                _ => break,
+            }
+            if self.accepts.contains(&state) {
+                last = Some(start + i + c.len_utf8());
+            }
+        }
+        last
+    }
 
-        for ch in input.chars() {
-            match self.transitions.get(&state).and_then(|m| m.get(&ch)) {
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("JSON error: {}", e))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("JSON error: {}", e))
+    }
+}
+
+impl<S: Letter> DFA<S> {
+    /// Run the DFA on a sequence of letters: bytes, token ids, ...
+    pub fn accepts_letters(&self, input: impl IntoIterator<Item = S>) -> bool {
+        let mut state = self.start;
+
+        for letter in input {
+            match self.transitions.get(&state).and_then(|m| m.get(&letter)) {
                 Some(&next) => state = next,
                 None => return false,
             }
@@ -23,9 +88,132 @@ impl DFA {
 
         self.accepts.contains(&state)
     }
+
+    // All states mentioned anywhere in the DFA, in sorted order.
+    pub fn states(&self) -> BTreeSet<StateId> {
+        let mut states: BTreeSet<StateId> = self.accepts.iter().copied().collect();
+        states.insert(self.start);
+        for (src, map) in &self.transitions {
+            states.insert(*src);
+            states.extend(map.values().copied());
+        }
+        states
+    }
+
+    // All symbols used on some transition, in sorted order.
+    pub fn alphabet(&self) -> BTreeSet<S> {
+        self.transitions
+            .values()
+            .flat_map(|m| m.keys().copied())
+            .collect()
+    }
+
+    /// Size counts, as `NFA::stats`; a DFA has no ε-transitions.
+    pub fn stats(&self) -> AutomatonStats {
+        AutomatonStats {
+            states: self.states().len(),
+            transitions: self.transitions.values().map(HashMap::len).sum(),
+            epsilons: 0,
+            alphabet: self.alphabet().len(),
+            max_out_degree: self
+                .transitions
+                .values()
+                .map(HashMap::len)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Order in which subset construction explores newly discovered subsets.
+/// This decides the numbering of the resulting DFA states.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrontierOrder {
+    #[default]
+    Bfs,
+    Dfs,
+    // Smallest subset first, ties broken by discovery order
+    SmallestSubset,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeterminizeOptions {
+    pub order: FrontierOrder,
+}
+
+// Work list of DFA state ids waiting to be expanded.
+enum Frontier {
+    Queue(VecDeque<StateId>),
+    Stack(Vec<StateId>),
+    Heap(BinaryHeap<Reverse<(usize, StateId)>>),
+}
+
+impl Frontier {
+    fn new(order: FrontierOrder) -> Self {
+        match order {
+            FrontierOrder::Bfs => Frontier::Queue(VecDeque::new()),
+            FrontierOrder::Dfs => Frontier::Stack(Vec::new()),
+            FrontierOrder::SmallestSubset => Frontier::Heap(BinaryHeap::new()),
+        }
+    }
+
+    fn push(&mut self, id: StateId, subset_size: usize) {
+        match self {
+            Frontier::Queue(q) => q.push_back(id),
+            Frontier::Stack(s) => s.push(id),
+            Frontier::Heap(h) => h.push(Reverse((subset_size, id))),
+        }
+    }
+
+    fn pop(&mut self) -> Option<StateId> {
+        match self {
+            Frontier::Queue(q) => q.pop_front(),
+            Frontier::Stack(s) => s.pop(),
+            Frontier::Heap(h) => h.pop().map(|Reverse((_, id))| id),
+        }
+    }
 }
 
-pub fn nfa_to_dfa(nfa: &NFA) -> DFA {
+/// Every DFA is an NFA: the same states and edges, edges in symbol order.
+impl<S: Letter> From<&DFA<S>> for NFA<S> {
+    fn from(dfa: &DFA<S>) -> Self {
+        let mut nfa = NFA::default();
+        for s in dfa.states() {
+            let mut edges: Vec<(S, StateId)> = dfa
+                .transitions
+                .get(&s)
+                .into_iter()
+                .flatten()
+                .map(|(&c, &t)| (c, t))
+                .collect();
+            edges.sort_unstable();
+            let edges = edges
+                .into_iter()
+                .map(|(c, t)| (TransitionLabel::Char(c), t))
+                .collect();
+            nfa.transitions.insert(s, edges);
+        }
+        nfa.start = dfa.start;
+        nfa.accept = dfa.accepts.iter().copied().collect();
+        nfa.accept.sort_unstable();
+        nfa
+    }
+}
+
+pub fn nfa_to_dfa<S: Letter>(nfa: &NFA<S>) -> DFA<S> {
+    nfa_to_dfa_with(nfa, &DeterminizeOptions::default())
+}
+
+pub fn nfa_to_dfa_with<S: Letter>(nfa: &NFA<S>, opts: &DeterminizeOptions) -> DFA<S> {
+    nfa_to_dfa_traced(nfa, opts).0
+}
+
+/// Subset construction that also returns its provenance: the set of NFA
+/// states behind each DFA state, indexed by DFA state id.
+pub fn nfa_to_dfa_traced<S: Letter>(
+    nfa: &NFA<S>,
+    opts: &DeterminizeOptions,
+) -> (DFA<S>, Vec<BTreeSet<StateId>>) {
     // Collect all symbols in the NFA w/ BTreeSet
     let mut symbols = BTreeSet::new();
     for edges in nfa.transitions.values() {
@@ -38,27 +226,27 @@ pub fn nfa_to_dfa(nfa: &NFA) -> DFA {
 
     // --- Subset construction state machinery ---
     // Use BTreeSet as the key type since HashSet doesn't implement Hash
-    let mut subset_to_id: HashMap<BTreeSet<usize>, usize> = HashMap::new();
-    let mut id_to_subset: Vec<BTreeSet<StateID>> = Vec::new();
-    let mut queue = VecDeque::new();
-    let mut transitions: HashMap<usize, HashMap<char, usize>> = HashMap::new();
+    let mut subset_to_id: HashMap<BTreeSet<StateId>, StateId> = HashMap::new();
+    let mut id_to_subset: Vec<BTreeSet<StateId>> = Vec::new();
+    let mut queue = Frontier::new(opts.order);
+    let mut transitions: HashMap<StateId, HashMap<S, StateId>> = HashMap::new();
     let mut accepts = HashSet::new();
 
-    // Start subset = { nfa.start }
-    let start_subset: BTreeSet<StateID> = [nfa.start].into_iter().collect();
+    // Start subset = every NFA start state
+    let start_subset: BTreeSet<StateId> = nfa.starts().into_iter().collect();
 
-    subset_to_id.insert(start_subset.clone(), 0);
+    subset_to_id.insert(start_subset.clone(), StateId(0));
     id_to_subset.push(start_subset.clone());
-    queue.push_back(0);
+    queue.push(StateId(0), start_subset.len());
 
     // Check if start subset contains accept state
     if nfa.accept.iter().any(|a| start_subset.contains(a)) {
-        accepts.insert(0);
+        accepts.insert(StateId(0));
     }
 
-    // --- Main BFS over subsets ---
-    while let Some(dfa_state) = queue.pop_front() {
-        let subset = id_to_subset[dfa_state].clone();
+    // --- Main loop over subsets ---
+    while let Some(dfa_state) = queue.pop() {
+        let subset = id_to_subset[dfa_state.index()].clone();
 
         for &c in &symbols {
             // Compute target subset
@@ -85,10 +273,10 @@ pub fn nfa_to_dfa(nfa: &NFA) -> DFA {
             let target_id = match subset_to_id.get(&target) {
                 Some(id) => *id,
                 None => {
-                    let new_id = id_to_subset.len();
+                    let new_id = StateId::new(id_to_subset.len());
                     id_to_subset.push(target.clone());
                     subset_to_id.insert(target.clone(), new_id);
-                    queue.push_back(new_id);
+                    queue.push(new_id, target.len());
 
                     // If this new subset contains the NFA accept → record as accepting DFA state
                     if nfa.accept.iter().any(|a| target.contains(a)) {
@@ -106,11 +294,12 @@ pub fn nfa_to_dfa(nfa: &NFA) -> DFA {
         }
     }
 
-    DFA {
-        start: 0,
+    let dfa = DFA {
+        start: StateId(0),
         accepts,
         transitions,
-    }
+    };
+    (dfa, id_to_subset)
 }
 
 /*
@@ -121,22 +310,96 @@ pub fn nfa_to_dfa(nfa: &NFA) -> DFA {
 
 #[cfg(test)]
 mod tests {
-    use crate::dfa::dfa::nfa_to_dfa;
-    use crate::nfa::nfa::{TransitionLabel, NFA};
+    use crate::dfa::dfa::{nfa_to_dfa, nfa_to_dfa_with, DeterminizeOptions, FrontierOrder};
+    use crate::nfa::nfa::{StateId, TransitionLabel, NFA};
     use std::collections::HashMap;
 
+    #[test]
+    fn other_letter_types() {
+        use crate::dfa::minimize::minimize_dfa;
+        use crate::nfa::epsilon_elimination::remove_epsilon;
+        use crate::types::TokenId;
+
+        // "é" or "e" as UTF-8 bytes, then any number of '!'
+        let mut bytes = NFA::<u8>::default();
+        for (from, label, to) in [
+            (0, Some(0xC3), 1),
+            (1, Some(0xA9), 2),
+            (0, Some(b'e'), 2),
+            (2, None, 3),
+            (3, Some(b'!'), 3),
+        ] {
+            let label = label.map_or(TransitionLabel::Epsilon, TransitionLabel::Char);
+            bytes.add_edge_auto(StateId(from), label, StateId(to));
+        }
+        bytes.accept = vec![StateId(3)];
+
+        let dfa = minimize_dfa(&nfa_to_dfa(&remove_epsilon(&bytes)));
+        assert!(dfa.accepts_letters("é!!".bytes()) && dfa.accepts_letters("e".bytes()));
+        assert!(!dfa.accepts_letters([0xC3]) && !dfa.accepts_letters("!".bytes()));
+        assert_eq!(dfa.alphabet().len(), 4);
+
+        // Token ids: IDENT (ASSIGN NUMBER)?
+        let (ident, assign, number): (TokenId, TokenId, TokenId) = (7, 1, 2);
+        let mut tokens = NFA::<TokenId>::default();
+        tokens.add_edge_auto(StateId(0), TransitionLabel::Char(ident), StateId(1));
+        tokens.add_edge_auto(StateId(1), TransitionLabel::Char(assign), StateId(2));
+        tokens.add_edge_auto(StateId(2), TransitionLabel::Char(number), StateId(3));
+        tokens.accept = vec![StateId(1), StateId(3)];
+
+        let dfa = nfa_to_dfa(&tokens);
+        assert!(dfa.accepts_letters([ident]) && dfa.accepts_letters([ident, assign, number]));
+        assert!(!dfa.accepts_letters([ident, assign]));
+        assert_eq!(minimize_dfa(&dfa).states().len(), 4);
+    }
+
+    #[test]
+    fn leftmost_longest_find() {
+        use crate::pipeline::tests::full_pipeline;
+
+        let find =
+            |pattern: &str, haystack: &str| full_pipeline(pattern).unwrap().mindfa.find(haystack);
+        // Leftmost wins over longer matches further right, then longest
+        assert_eq!(find("ab*", "xxabbab"), Some((2, 5)));
+        assert_eq!(find("b+abbb", "aabbb"), Some((1, 5)));
+        assert_eq!(find("a(bc)*", "abcbx"), Some((0, 3)));
+        // Offsets are in bytes
+        assert_eq!(find("é[0-9]*", "ça é42!"), Some((4, 8)));
+        // Empty matches, and none at all
+        assert_eq!(find("a*", "bbb"), Some((0, 0)));
+        assert_eq!(find("ε", ""), Some((0, 0)));
+        assert_eq!(find("ab", "ba"), None);
+        assert_eq!(find("∅", "abc"), None);
+    }
+
+    #[test]
+    fn find_through_a_dead_state() {
+        use crate::dfa::ops::Completion;
+        use crate::pipeline::tests::full_pipeline;
+
+        // Completing adds a sink every mismatch runs into; matches are the
+        // same as without it
+        let dfa = full_pipeline("ab*c").unwrap().mindfa;
+        let complete = dfa.complete_over(&"abcx".chars().collect(), Completion::DeadState);
+        for haystack in ["xxabbbc", "aac", "abx", "c", "xabc"] {
+            assert_eq!(complete.find(haystack), dfa.find(haystack), "{}", haystack);
+        }
+        assert_eq!(complete.find("xxabbbc"), Some((2, 7)));
+    }
+
     #[test]
     fn dfa_from_single_char() {
         // NFA for "a"
         //
         // 0 --a--> 1
         let mut transitions = HashMap::new();
-        transitions.insert(0, vec![(TransitionLabel::Char('a'), 1)]);
-        transitions.insert(1, vec![]);
+        transitions.insert(StateId(0), vec![(TransitionLabel::Char('a'), StateId(1))]);
+        transitions.insert(StateId(1), vec![]);
 
         let nfa = NFA {
-            start: 0,
-            accept: vec![1],
+            start: StateId(0),
+            extra_starts: vec![],
+            accept: vec![StateId(1)],
             transitions,
         };
 
@@ -144,13 +407,16 @@ mod tests {
 
         // DFA should have exactly two states
         assert_eq!(dfa.accepts.len(), 1);
-        assert!(dfa.accepts.contains(&1));
+        assert!(dfa.accepts.contains(&StateId(1)));
 
         // Start state is 0
-        assert_eq!(dfa.start, 0);
+        assert_eq!(dfa.start, StateId(0));
 
         // Transition: 0 -a-> 1
-        assert_eq!(dfa.transitions.get(&0).unwrap().get(&'a'), Some(&1));
+        assert_eq!(
+            dfa.transitions.get(&StateId(0)).unwrap().get(&'a'),
+            Some(&StateId(1))
+        );
     }
 
     #[test]
@@ -161,29 +427,36 @@ mod tests {
         // 0 ->(b)-> 3
         let mut transitions = HashMap::new();
         transitions.insert(
-            0,
+            StateId(0),
             vec![
-                (TransitionLabel::Char('a'), 1),
-                (TransitionLabel::Char('b'), 2),
+                (TransitionLabel::Char('a'), StateId(1)),
+                (TransitionLabel::Char('b'), StateId(2)),
             ],
         );
-        transitions.insert(1, vec![]);
-        transitions.insert(2, vec![]);
+        transitions.insert(StateId(1), vec![]);
+        transitions.insert(StateId(2), vec![]);
 
         let nfa = NFA {
-            start: 0,
-            accept: vec![1],
+            start: StateId(0),
+            extra_starts: vec![],
+            accept: vec![StateId(1)],
             transitions,
         };
 
         let dfa = nfa_to_dfa(&nfa);
 
         // Accepts should include the DFA state that corresponds to subset {1}
-        assert!(dfa.accepts.contains(&1));
+        assert!(dfa.accepts.contains(&StateId(1)));
 
         // Transitions preserve union behavior deterministically.
-        assert_eq!(dfa.transitions.get(&0).unwrap().get(&'a'), Some(&1));
-        assert_eq!(dfa.transitions.get(&0).unwrap().get(&'b'), Some(&2));
+        assert_eq!(
+            dfa.transitions.get(&StateId(0)).unwrap().get(&'a'),
+            Some(&StateId(1))
+        );
+        assert_eq!(
+            dfa.transitions.get(&StateId(0)).unwrap().get(&'b'),
+            Some(&StateId(2))
+        );
     }
 
     #[test]
@@ -192,19 +465,20 @@ mod tests {
         //
         // 0 --a--> 1 --b--> 2
         let mut transitions = HashMap::new();
-        transitions.insert(0, vec![(TransitionLabel::Char('a'), 1)]);
-        transitions.insert(1, vec![(TransitionLabel::Char('b'), 2)]);
-        transitions.insert(2, vec![]);
+        transitions.insert(StateId(0), vec![(TransitionLabel::Char('a'), StateId(1))]);
+        transitions.insert(StateId(1), vec![(TransitionLabel::Char('b'), StateId(2))]);
+        transitions.insert(StateId(2), vec![]);
 
         let nfa = NFA {
-            start: 0,
-            accept: vec![2],
+            start: StateId(0),
+            extra_starts: vec![],
+            accept: vec![StateId(2)],
             transitions,
         };
 
         let dfa = nfa_to_dfa(&nfa);
 
-        assert!(dfa.accepts.contains(&2)); // final state should accept
+        assert!(dfa.accepts.contains(&StateId(2))); // final state should accept
 
         // Deterministic verification:
         let s0 = dfa.start;
@@ -221,11 +495,12 @@ mod tests {
         // 0 (accept) --a--> 0
         //
         let mut transitions = HashMap::new();
-        transitions.insert(0, vec![(TransitionLabel::Char('a'), 0)]);
+        transitions.insert(StateId(0), vec![(TransitionLabel::Char('a'), StateId(0))]);
 
         let nfa = NFA {
-            start: 0,
-            accept: vec![0],
+            start: StateId(0),
+            extra_starts: vec![],
+            accept: vec![StateId(0)],
             transitions,
         };
 
@@ -248,25 +523,26 @@ mod tests {
         //
         let mut transitions = HashMap::new();
         transitions.insert(
-            0,
+            StateId(0),
             vec![
-                (TransitionLabel::Char('a'), 0),
-                (TransitionLabel::Char('b'), 0),
-                (TransitionLabel::Char('c'), 1),
+                (TransitionLabel::Char('a'), StateId(0)),
+                (TransitionLabel::Char('b'), StateId(0)),
+                (TransitionLabel::Char('c'), StateId(1)),
             ],
         );
-        transitions.insert(1, vec![]);
+        transitions.insert(StateId(1), vec![]);
 
         let nfa = NFA {
-            start: 0,
-            accept: vec![1],
+            start: StateId(0),
+            extra_starts: vec![],
+            accept: vec![StateId(1)],
             transitions,
         };
 
         let dfa = nfa_to_dfa(&nfa);
 
         // DFA should accept after reading exactly one c after any a/b* prefix
-        assert!(dfa.accepts.contains(&1));
+        assert!(dfa.accepts.contains(&StateId(1)));
 
         let s0 = dfa.start;
         let s_loop_a = dfa.transitions[&s0].get(&'a').copied().unwrap();
@@ -277,4 +553,55 @@ mod tests {
         assert_eq!(s_loop_b, s0);
         assert!(dfa.accepts.contains(&s_accept));
     }
+
+    #[test]
+    fn frontier_order_changes_numbering_not_language() {
+        // NFA for aa + bab:
+        //
+        // 0 --a--> 1 --a--> 3
+        // 0 --b--> 2 --a--> 4 --b--> 3
+        let mut transitions = HashMap::new();
+        transitions.insert(
+            StateId(0),
+            vec![
+                (TransitionLabel::Char('a'), StateId(1)),
+                (TransitionLabel::Char('b'), StateId(2)),
+            ],
+        );
+        transitions.insert(StateId(1), vec![(TransitionLabel::Char('a'), StateId(3))]);
+        transitions.insert(StateId(2), vec![(TransitionLabel::Char('a'), StateId(4))]);
+        transitions.insert(StateId(3), vec![]);
+        transitions.insert(StateId(4), vec![(TransitionLabel::Char('b'), StateId(3))]);
+
+        let nfa = NFA {
+            start: StateId(0),
+            extra_starts: vec![],
+            accept: vec![StateId(3)],
+            transitions,
+        };
+
+        let bfs = nfa_to_dfa(&nfa);
+        let dfs = nfa_to_dfa_with(
+            &nfa,
+            &DeterminizeOptions {
+                order: FrontierOrder::Dfs,
+            },
+        );
+        let smallest = nfa_to_dfa_with(
+            &nfa,
+            &DeterminizeOptions {
+                order: FrontierOrder::SmallestSubset,
+            },
+        );
+
+        for dfa in [&bfs, &dfs, &smallest] {
+            assert!(dfa.accepts("aa"));
+            assert!(dfa.accepts("bab"));
+            assert!(!dfa.accepts("ba"));
+        }
+
+        // BFS expands {1} before {2}; DFS expands {2} first.
+        assert_eq!(bfs.transitions[&StateId(1)][&'a'], StateId(3));
+        assert_eq!(dfs.transitions[&StateId(2)][&'a'], StateId(3));
+    }
 }
@@ -0,0 +1,554 @@
+// dfa/edit.rs
+use super::dfa::DFA;
+use crate::types::StateId;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    UnknownState(StateId),
+    // Replaying a patch that adds a state this machine already has
+    StateExists(StateId),
+    // The start state can't be removed; move the start first
+    RemoveStart(StateId),
+    // Found by `validate`: an edge or accept refers to a missing state
+    DanglingEdge {
+        from: StateId,
+        symbol: char,
+        to: StateId,
+    },
+    DanglingAccept(StateId),
+}
+
+/// A DFA behind edit operations that keep it consistent: every edge and
+/// accepting state refers to an existing state, and each state has at most
+/// one edge per symbol. States are tracked explicitly, so isolated states
+/// survive edits.
+#[derive(Debug, Clone)]
+pub struct MutableDfa {
+    dfa: DFA,
+    states: BTreeSet<StateId>,
+}
+
+impl From<DFA> for MutableDfa {
+    fn from(dfa: DFA) -> Self {
+        Self::new(dfa)
+    }
+}
+
+impl MutableDfa {
+    pub fn new(dfa: DFA) -> Self {
+        let states = dfa.states();
+        Self { dfa, states }
+    }
+
+    pub fn dfa(&self) -> &DFA {
+        &self.dfa
+    }
+
+    pub fn into_dfa(self) -> DFA {
+        self.dfa
+    }
+
+    pub fn states(&self) -> &BTreeSet<StateId> {
+        &self.states
+    }
+
+    /// Add a fresh non-accepting state without edges.
+    pub fn add_state(&mut self) -> StateId {
+        let id = self.states.last().map_or(StateId(0), |m| m.next());
+        self.states.insert(id);
+        id
+    }
+
+    /// Remove `state` with all edges into and out of it.
+    pub fn remove_state(&mut self, state: StateId) -> Result<(), EditError> {
+        self.check(state)?;
+        if state == self.dfa.start {
+            return Err(EditError::RemoveStart(state));
+        }
+
+        self.states.remove(&state);
+        self.dfa.accepts.remove(&state);
+        self.dfa.transitions.remove(&state);
+        for map in self.dfa.transitions.values_mut() {
+            map.retain(|_, t| *t != state);
+        }
+        self.dfa.transitions.retain(|_, map| !map.is_empty());
+        Ok(())
+    }
+
+    /// Point the `symbol` edge of `from` at `to`, adding it if missing.
+    /// Returns the previous target.
+    pub fn redirect(
+        &mut self,
+        from: StateId,
+        symbol: char,
+        to: StateId,
+    ) -> Result<Option<StateId>, EditError> {
+        self.check(from)?;
+        self.check(to)?;
+        Ok(self
+            .dfa
+            .transitions
+            .entry(from)
+            .or_default()
+            .insert(symbol, to))
+    }
+
+    /// Remove the `symbol` edge of `from`, returning its target.
+    pub fn remove_edge(
+        &mut self,
+        from: StateId,
+        symbol: char,
+    ) -> Result<Option<StateId>, EditError> {
+        self.check(from)?;
+        let Some(map) = self.dfa.transitions.get_mut(&from) else {
+            return Ok(None);
+        };
+        let old = map.remove(&symbol);
+        if map.is_empty() {
+            self.dfa.transitions.remove(&from);
+        }
+        Ok(old)
+    }
+
+    /// Flip whether `state` is accepting; returns the new status.
+    pub fn toggle_accept(&mut self, state: StateId) -> Result<bool, EditError> {
+        self.check(state)?;
+        if self.dfa.accepts.remove(&state) {
+            Ok(false)
+        } else {
+            self.dfa.accepts.insert(state);
+            Ok(true)
+        }
+    }
+
+    pub fn set_start(&mut self, state: StateId) -> Result<(), EditError> {
+        self.check(state)?;
+        self.dfa.start = state;
+        Ok(())
+    }
+
+    /// Re-check the invariants from scratch; useful after loading a
+    /// machine or as a debug assertion in editors.
+    pub fn validate(&self) -> Result<(), EditError> {
+        self.check(self.dfa.start)?;
+        let mut accepts: Vec<StateId> = self.dfa.accepts.iter().copied().collect();
+        accepts.sort_unstable();
+        if let Some(&a) = accepts.iter().find(|a| !self.states.contains(a)) {
+            return Err(EditError::DanglingAccept(a));
+        }
+
+        let mut edges: Vec<(StateId, char, StateId)> = self
+            .dfa
+            .transitions
+            .iter()
+            .flat_map(|(&s, m)| m.iter().map(move |(&c, &t)| (s, c, t)))
+            .collect();
+        edges.sort_unstable();
+        for (from, symbol, to) in edges {
+            if !self.states.contains(&from) || !self.states.contains(&to) {
+                return Err(EditError::DanglingEdge { from, symbol, to });
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, state: StateId) -> Result<(), EditError> {
+        if self.states.contains(&state) {
+            Ok(())
+        } else {
+            Err(EditError::UnknownState(state))
+        }
+    }
+}
+
+/// One recorded edit, with what it replaced so it can be reverted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Edit {
+    AddState(StateId),
+    // Edges are every edge into or out of `state` at removal time
+    RemoveState {
+        state: StateId,
+        accepting: bool,
+        edges: Vec<(StateId, char, StateId)>,
+    },
+    Redirect {
+        from: StateId,
+        symbol: char,
+        old: Option<StateId>,
+        new: StateId,
+    },
+    RemoveEdge {
+        from: StateId,
+        symbol: char,
+        old: StateId,
+    },
+    ToggleAccept(StateId),
+    SetStart {
+        old: StateId,
+        new: StateId,
+    },
+}
+
+/// The edits of a session in order; replaying them with
+/// `EditSession::apply` on the original machine gives the edited one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Patch {
+    pub edits: Vec<Edit>,
+}
+
+impl Patch {
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("JSON error: {}", e))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("JSON error: {}", e))
+    }
+}
+
+/// A `MutableDfa` with undo/redo history. Every successful edit is
+/// recorded; a new edit after an undo drops the redo history.
+#[derive(Debug, Clone)]
+pub struct EditSession {
+    current: MutableDfa,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl EditSession {
+    pub fn new(dfa: impl Into<MutableDfa>) -> Self {
+        Self {
+            current: dfa.into(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn current(&self) -> &MutableDfa {
+        &self.current
+    }
+
+    pub fn into_current(self) -> MutableDfa {
+        self.current
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn add_state(&mut self) -> StateId {
+        let state = self.current.add_state();
+        self.record(Edit::AddState(state));
+        state
+    }
+
+    pub fn remove_state(&mut self, state: StateId) -> Result<(), EditError> {
+        let accepting = self.current.dfa.accepts.contains(&state);
+        let mut edges: Vec<(StateId, char, StateId)> = self
+            .current
+            .dfa
+            .transitions
+            .iter()
+            .flat_map(|(&s, m)| m.iter().map(move |(&c, &t)| (s, c, t)))
+            .filter(|&(s, _, t)| s == state || t == state)
+            .collect();
+        edges.sort_unstable();
+
+        self.current.remove_state(state)?;
+        self.record(Edit::RemoveState {
+            state,
+            accepting,
+            edges,
+        });
+        Ok(())
+    }
+
+    pub fn redirect(
+        &mut self,
+        from: StateId,
+        symbol: char,
+        to: StateId,
+    ) -> Result<Option<StateId>, EditError> {
+        let old = self.current.redirect(from, symbol, to)?;
+        if old != Some(to) {
+            self.record(Edit::Redirect {
+                from,
+                symbol,
+                old,
+                new: to,
+            });
+        }
+        Ok(old)
+    }
+
+    pub fn remove_edge(
+        &mut self,
+        from: StateId,
+        symbol: char,
+    ) -> Result<Option<StateId>, EditError> {
+        let old = self.current.remove_edge(from, symbol)?;
+        if let Some(old) = old {
+            self.record(Edit::RemoveEdge { from, symbol, old });
+        }
+        Ok(old)
+    }
+
+    pub fn toggle_accept(&mut self, state: StateId) -> Result<bool, EditError> {
+        let accepting = self.current.toggle_accept(state)?;
+        self.record(Edit::ToggleAccept(state));
+        Ok(accepting)
+    }
+
+    pub fn set_start(&mut self, state: StateId) -> Result<(), EditError> {
+        let old = self.current.dfa.start;
+        self.current.set_start(state)?;
+        if old != state {
+            self.record(Edit::SetStart { old, new: state });
+        }
+        Ok(())
+    }
+
+    /// Revert the last edit; false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        self.revert(&edit);
+        self.redo.push(edit);
+        true
+    }
+
+    /// Reapply the last undone edit; false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        let redo = std::mem::take(&mut self.redo);
+        self.perform(&edit)
+            .expect("redo replays an edit that was valid in this state");
+        self.redo = redo;
+        true
+    }
+
+    /// The edits currently applied, oldest first.
+    pub fn patch(&self) -> Patch {
+        Patch {
+            edits: self.undo.clone(),
+        }
+    }
+
+    /// Replay `patch` as new edits, stopping at the first one that doesn't
+    /// apply. The recorded `old` values are recomputed against this machine.
+    pub fn apply(&mut self, patch: &Patch) -> Result<(), EditError> {
+        patch.edits.iter().try_for_each(|edit| self.perform(edit))
+    }
+
+    fn perform(&mut self, edit: &Edit) -> Result<(), EditError> {
+        match *edit {
+            Edit::AddState(state) => {
+                if self.current.states.contains(&state) {
+                    return Err(EditError::StateExists(state));
+                }
+                self.current.states.insert(state);
+                self.record(Edit::AddState(state));
+            }
+            Edit::RemoveState { state, .. } => self.remove_state(state)?,
+            Edit::Redirect {
+                from, symbol, new, ..
+            } => {
+                self.redirect(from, symbol, new)?;
+            }
+            Edit::RemoveEdge { from, symbol, .. } => {
+                self.remove_edge(from, symbol)?;
+            }
+            Edit::ToggleAccept(state) => {
+                self.toggle_accept(state)?;
+            }
+            Edit::SetStart { new, .. } => self.set_start(new)?,
+        }
+        Ok(())
+    }
+
+    // Undo `edit`, which is the latest applied one, so its states exist.
+    fn revert(&mut self, edit: &Edit) {
+        let m = &mut self.current;
+        let reverted = match *edit {
+            Edit::AddState(state) => m.remove_state(state),
+            Edit::RemoveState {
+                state,
+                accepting,
+                ref edges,
+            } => {
+                m.states.insert(state);
+                if accepting {
+                    m.dfa.accepts.insert(state);
+                }
+                for &(from, symbol, to) in edges {
+                    m.dfa
+                        .transitions
+                        .entry(from)
+                        .or_default()
+                        .insert(symbol, to);
+                }
+                Ok(())
+            }
+            Edit::Redirect {
+                from,
+                symbol,
+                old: Some(old),
+                ..
+            } => m.redirect(from, symbol, old).map(drop),
+            Edit::Redirect {
+                from,
+                symbol,
+                old: None,
+                ..
+            } => m.remove_edge(from, symbol).map(drop),
+            Edit::RemoveEdge { from, symbol, old } => m.redirect(from, symbol, old).map(drop),
+            Edit::ToggleAccept(state) => m.toggle_accept(state).map(drop),
+            Edit::SetStart { old, .. } => m.set_start(old),
+        };
+        reverted.expect("undo reverts the latest edit");
+    }
+
+    fn record(&mut self, edit: Edit) {
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::canonical::canonicalize;
+    use crate::pipeline::tests::full_pipeline;
+
+    // 0 -a-> 1 -b-> 2, accepting 2
+    fn ab() -> MutableDfa {
+        MutableDfa::new(canonicalize(&full_pipeline("ab").unwrap().mindfa))
+    }
+
+    #[test]
+    fn edits_change_the_language() {
+        let mut m = ab();
+        let s = m.add_state();
+        assert_eq!(s, StateId(3));
+
+        assert_eq!(m.redirect(StateId(1), 'b', s), Ok(Some(StateId(2))));
+        assert_eq!(m.toggle_accept(s), Ok(true));
+        assert!(m.dfa().accepts("ab"));
+
+        m.remove_state(StateId(2)).unwrap();
+        assert_eq!(m.redirect(s, 'c', s), Ok(None));
+        assert!(m.dfa().accepts("abcc"));
+        assert_eq!(m.remove_edge(StateId(0), 'a'), Ok(Some(StateId(1))));
+        assert!(!m.dfa().accepts("ab"));
+        assert_eq!(m.validate(), Ok(()));
+    }
+
+    #[test]
+    fn removing_a_state_drops_its_edges() {
+        let mut m = ab();
+        m.remove_state(StateId(1)).unwrap();
+
+        assert_eq!(m.validate(), Ok(()));
+        assert!(m.dfa().transitions.is_empty());
+        // State 2 is isolated but still there
+        assert!(m.states().contains(&StateId(2)));
+        assert!(m.dfa().accepts.contains(&StateId(2)));
+    }
+
+    #[test]
+    fn invalid_edits_are_rejected() {
+        let mut m = ab();
+        assert_eq!(
+            m.remove_state(StateId(0)),
+            Err(EditError::RemoveStart(StateId(0)))
+        );
+        assert_eq!(
+            m.redirect(StateId(0), 'a', StateId(9)),
+            Err(EditError::UnknownState(StateId(9)))
+        );
+        assert_eq!(
+            m.toggle_accept(StateId(9)),
+            Err(EditError::UnknownState(StateId(9)))
+        );
+
+        m.set_start(StateId(2)).unwrap();
+        m.remove_state(StateId(0)).unwrap();
+        assert!(m.dfa().accepts(""));
+        assert_eq!(m.validate(), Ok(()));
+    }
+
+    // Identical states and edges, not just the same language
+    fn same(a: &DFA, b: &DFA) -> bool {
+        a.start == b.start && a.accepts == b.accepts && a.transitions == b.transitions
+    }
+
+    #[test]
+    fn undo_and_redo_restore_each_step() {
+        let original = ab().into_dfa();
+        let mut session = EditSession::new(original.clone());
+        let s = session.add_state();
+        session.redirect(StateId(1), 'b', s).unwrap();
+        session.toggle_accept(s).unwrap();
+        session.remove_state(StateId(2)).unwrap();
+        session.set_start(s).unwrap();
+        let edited = session.current().dfa().clone();
+
+        while session.undo() {}
+        assert!(same(session.current().dfa(), &original));
+        assert_eq!(
+            session.current().states(),
+            &BTreeSet::from([StateId(0), StateId(1), StateId(2)])
+        );
+
+        while session.redo() {}
+        assert!(same(session.current().dfa(), &edited));
+        assert!(!session.can_redo());
+
+        // A fresh edit after an undo drops the redo history
+        session.undo();
+        session.remove_edge(StateId(0), 'a').unwrap();
+        assert!(!session.can_redo());
+    }
+
+    #[test]
+    fn patch_replays_on_the_original() {
+        let mut session = EditSession::new(ab());
+        let s = session.add_state();
+        session.redirect(StateId(2), 'c', s).unwrap();
+        session.toggle_accept(s).unwrap();
+        assert_eq!(session.remove_edge(StateId(0), 'z'), Ok(None));
+
+        let patch = session.patch();
+        assert_eq!(patch.edits.len(), 3);
+        #[cfg(feature = "serde")]
+        let patch = Patch::from_json(&patch.to_json().unwrap()).unwrap();
+
+        let mut replay = EditSession::new(ab());
+        replay.apply(&patch).unwrap();
+        assert!(same(replay.current().dfa(), session.current().dfa()));
+        assert!(replay.current().dfa().accepts("abc"));
+
+        assert_eq!(replay.apply(&patch), Err(EditError::StateExists(s)));
+    }
+}
@@ -0,0 +1,249 @@
+// dfa/equivalence.rs
+//
+// Hopcroft and Karp's equivalence check: merge the start states of the two
+// machines, then keep merging the states the merged pairs lead to on each
+// symbol, with a union-find. The languages differ exactly when some merged
+// pair disagrees on acceptance. Nothing is minimized, and each state is
+// merged at most once, so this is near-linear in the size of the machines
+// times the alphabet.
+use super::dfa::DFA;
+use crate::types::{Letter, StateId};
+use std::collections::{BTreeSet, HashMap};
+
+/// Do `a` and `b` accept the same words? Partial machines are fine: a
+/// missing transition goes to a shared dead state. For a word telling the
+/// languages apart, use `decisions::equivalence`.
+pub fn equivalent<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> bool {
+    let nodes = Nodes::new(a, b);
+    let symbols: BTreeSet<S> = &a.alphabet() | &b.alphabet();
+    let mut sets = UnionFind::new(nodes.len());
+
+    let start = (nodes.of_a(a.start), nodes.of_b(b.start));
+    sets.union(start.0, start.1);
+    let mut pending = vec![start];
+
+    while let Some((p, q)) = pending.pop() {
+        if nodes.accepting(p) != nodes.accepting(q) {
+            return false;
+        }
+        for c in &symbols {
+            let (p, q) = (nodes.step(p, c), nodes.step(q, c));
+            if sets.union(p, q) {
+                pending.push((p, q));
+            }
+        }
+    }
+    true
+}
+
+// The states of both machines and one dead state, as indices: `a`'s states
+// first, then `b`'s, then the dead state.
+struct Nodes<'a, S> {
+    a: &'a DFA<S>,
+    b: &'a DFA<S>,
+    states: Vec<(bool, StateId)>,
+    index: [HashMap<StateId, usize>; 2],
+}
+
+impl<'a, S: Letter> Nodes<'a, S> {
+    fn new(a: &'a DFA<S>, b: &'a DFA<S>) -> Self {
+        let mut states = Vec::new();
+        let mut index = [HashMap::new(), HashMap::new()];
+        for (side, dfa) in [(false, a), (true, b)] {
+            for s in dfa.states() {
+                index[side as usize].insert(s, states.len());
+                states.push((side, s));
+            }
+        }
+        Nodes {
+            a,
+            b,
+            states,
+            index,
+        }
+    }
+
+    // Including the dead state
+    fn len(&self) -> usize {
+        self.states.len() + 1
+    }
+
+    fn dead(&self) -> usize {
+        self.states.len()
+    }
+
+    fn of_a(&self, s: StateId) -> usize {
+        self.index[0][&s]
+    }
+
+    fn of_b(&self, s: StateId) -> usize {
+        self.index[1][&s]
+    }
+
+    fn dfa(&self, on_b: bool) -> &'a DFA<S> {
+        if on_b {
+            self.b
+        } else {
+            self.a
+        }
+    }
+
+    fn accepting(&self, node: usize) -> bool {
+        self.states
+            .get(node)
+            .is_some_and(|&(on_b, s)| self.dfa(on_b).accepts.contains(&s))
+    }
+
+    fn step(&self, node: usize, c: &S) -> usize {
+        let Some(&(on_b, s)) = self.states.get(node) else {
+            return self.dead();
+        };
+        match self.dfa(on_b).transitions.get(&s).and_then(|m| m.get(c)) {
+            Some(t) => self.index[on_b as usize][t],
+            None => self.dead(),
+        }
+    }
+}
+
+// Disjoint sets with union by size and path halving.
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    // Merge the sets of `x` and `y`; false if they were already one.
+    fn union(&mut self, x: usize, y: usize) -> bool {
+        let (mut x, mut y) = (self.find(x), self.find(y));
+        if x == y {
+            return false;
+        }
+        if self.size[x] < self.size[y] {
+            std::mem::swap(&mut x, &mut y);
+        }
+        self.parent[y] = x;
+        self.size[x] += self.size[y];
+        true
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decisions::{equivalence, Equivalence};
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn agrees_with_minimize_and_compare() {
+        let patterns = [
+            "(a+b)*abb",
+            "(a+b)*bb",
+            "(a*b*)*",
+            "(a+b)*",
+            "a*",
+            "aa*",
+            "∅",
+            "ε",
+            "(ab)*a",
+            "a(ba)*",
+        ];
+        for x in patterns {
+            for y in patterns {
+                // Unminimized subset machines against minimal ones
+                let (a, b) = (
+                    full_pipeline(x).unwrap().dfa,
+                    full_pipeline(y).unwrap().mindfa,
+                );
+                assert_eq!(
+                    equivalent(&a, &b),
+                    equivalence(&a, &b) == Ok(Equivalence::Equivalent),
+                    "{} vs {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    // "The symbol `from_end` places before the last is an a", remembering
+    // the last `n` symbols as bits, with state ids scrambled by `key`
+    fn from_end(n: u32, from_end: u32, key: u32) -> DFA {
+        let mask = (1 << n) - 1;
+        let mut transitions = HashMap::new();
+        for s in 0..=mask {
+            let next = |bit: u32| StateId((((s << 1) | bit) & mask) ^ key);
+            transitions.insert(
+                StateId(s ^ key),
+                HashMap::from([('a', next(1)), ('b', next(0))]),
+            );
+        }
+        DFA {
+            start: StateId(key),
+            accepts: (0..=mask)
+                .filter(|s| s >> from_end & 1 == 1)
+                .map(|s| StateId(s ^ key))
+                .collect(),
+            transitions,
+        }
+    }
+
+    #[test]
+    fn large_machines() {
+        let n = 14;
+        let left = from_end(n, n - 1, 0);
+        let right = from_end(n, n - 1, 0b10_0110_1100_0101);
+        assert_eq!(left.states().len(), 1 << n);
+        assert!(equivalent(&left, &right));
+        assert!(!equivalent(&left, &from_end(n, n - 2, 0)));
+        assert!(left.accepts(&format!("ab{}", "b".repeat(n as usize - 2))));
+    }
+
+    #[test]
+    fn other_letters_and_partial_machines() {
+        // "ab" over bytes, partial and completed with a dead state
+        let partial: DFA<u8> = DFA {
+            start: StateId(0),
+            accepts: [StateId(2)].into(),
+            transitions: HashMap::from([
+                (StateId(0), HashMap::from([(b'a', StateId(1))])),
+                (StateId(1), HashMap::from([(b'b', StateId(2))])),
+            ]),
+        };
+        let mut complete = partial.clone();
+        for s in 0..4 {
+            for c in [b'a', b'b'] {
+                complete
+                    .transitions
+                    .entry(StateId(s))
+                    .or_default()
+                    .entry(c)
+                    .or_insert(StateId(3));
+            }
+        }
+        assert!(equivalent(&partial, &complete));
+
+        complete.accepts.insert(StateId(3));
+        assert!(!equivalent(&partial, &complete));
+    }
+}
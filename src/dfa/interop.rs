@@ -0,0 +1,231 @@
+// dfa/interop.rs
+//
+// Conversions to and from regex-automata's dense DFAs. Ours run over chars
+// and accept whole words; theirs run over UTF-8 bytes and search. Exports
+// go through an expression (state elimination) compiled as an anchored
+// full match; imports read a dense DFA as a word acceptor over a given
+// alphabet, stepping through each char's UTF-8 encoding.
+use super::dfa::DFA;
+use super::to_regex::dfa_to_regex_bounded;
+use crate::regex::ast::RegexAST;
+use crate::types::StateId;
+use regex_automata::dfa::{dense, Automaton, StartKind};
+use regex_automata::util::primitives::StateID as DenseStateID;
+use regex_automata::util::start;
+use regex_automata::{Anchored, MatchKind};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Largest expression, in AST nodes, that `to_regex_automata` goes
+/// through before giving up.
+pub const MAX_EXPORT_NODES: usize = 100_000;
+
+/// A dense DFA matching exactly the words `dfa` accepts, when searched
+/// anchored (`Input::anchored(Anchored::Yes)`). Fails when the expression
+/// in between would have more than `MAX_EXPORT_NODES` nodes.
+pub fn to_regex_automata(dfa: &DFA) -> Result<dense::DFA<Vec<u32>>, String> {
+    to_regex_automata_bounded(dfa, MAX_EXPORT_NODES)
+}
+
+/// `to_regex_automata` with its own node limit. State elimination can
+/// produce expressions exponential in the number of states, so some limit
+/// is always applied.
+pub fn to_regex_automata_bounded(
+    dfa: &DFA,
+    max_nodes: usize,
+) -> Result<dense::DFA<Vec<u32>>, String> {
+    let ast = dfa_to_regex_bounded(dfa, max_nodes)
+        .ok_or_else(|| format!("Regex for export too large: over {} nodes", max_nodes))?;
+    let mut pattern = String::from("^(?:");
+    write_syntax(&ast, &mut pattern);
+    pattern.push_str(")$");
+
+    dense::Builder::new()
+        .configure(
+            dense::Config::new()
+                .start_kind(StartKind::Anchored)
+                .match_kind(MatchKind::All),
+        )
+        .build(&pattern)
+        .map_err(|e| format!("regex-automata error: {}", e))
+}
+
+/// Read `dense` as an acceptor over `alphabet`: a word is accepted when a
+/// match ends at its last byte, starting from the anchored start state.
+/// Build it with `MatchKind::All` for this to be its full language;
+/// leftmost-first DFAs drop branches once a match is found.
+pub fn from_regex_automata<T: AsRef<[u32]>>(
+    dense: &dense::DFA<T>,
+    alphabet: &BTreeSet<char>,
+) -> Result<DFA, String> {
+    let start = dense
+        .start_state(&start::Config::new().anchored(Anchored::Yes))
+        .map_err(|e| format!("regex-automata error: {}", e))?;
+
+    let mut ids: HashMap<DenseStateID, StateId> = HashMap::from([(start, StateId(0))]);
+    let mut queue = VecDeque::from([start]);
+    let mut accepts = HashSet::new();
+    let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+    let mut buf = [0; 4];
+
+    while let Some(s) = queue.pop_front() {
+        let id = ids[&s];
+        // Matches are reported one byte late, here at end of input
+        if dense.is_match_state(dense.next_eoi_state(s)) {
+            accepts.insert(id);
+        }
+
+        for &c in alphabet {
+            let t = c
+                .encode_utf8(&mut buf)
+                .bytes()
+                .fold(s, |t, b| dense.next_state(t, b));
+            if dense.is_dead_state(t) {
+                continue;
+            }
+            if dense.is_quit_state(t) {
+                return Err(format!("regex-automata DFA gives up on {:?}", c));
+            }
+
+            let next = StateId::new(ids.len());
+            let t = *ids.entry(t).or_insert_with(|| {
+                queue.push_back(t);
+                next
+            });
+            transitions.entry(id).or_default().insert(c, t);
+        }
+    }
+
+    Ok(DFA {
+        start: StateId(0),
+        accepts,
+        transitions,
+    })
+}
+
+// Write in regex-syntax's dialect. Only what state elimination produces
+// is needed; every compound operand gets a non-capturing group.
+fn write_syntax(ast: &RegexAST, out: &mut String) {
+    match ast {
+        RegexAST::Epsilon => out.push_str("(?:)"),
+        RegexAST::Empty => out.push_str("[a&&b]"),
+        RegexAST::Char(c) => push_literal(out, *c),
+        RegexAST::Class(ranges) => {
+            out.push('[');
+            for r in ranges {
+                push_literal(out, *r.start());
+                if r.start() != r.end() {
+                    out.push('-');
+                    push_literal(out, *r.end());
+                }
+            }
+            out.push(']');
+        }
+        RegexAST::Concat(a, b) => {
+            write_syntax(a, out);
+            write_syntax(b, out);
+        }
+        RegexAST::Union(a, b) => {
+            out.push_str("(?:");
+            write_syntax(a, out);
+            out.push('|');
+            write_syntax(b, out);
+            out.push(')');
+        }
+        RegexAST::Star(e) | RegexAST::Optional(e) => {
+            out.push_str("(?:");
+            write_syntax(e, out);
+            out.push(')');
+            out.push(if matches!(ast, RegexAST::Star(_)) {
+                '*'
+            } else {
+                '?'
+            });
+        }
+        _ => unreachable!("not produced by state elimination: {:?}", ast),
+    }
+}
+
+// ASCII punctuation may always be escaped; other non-word chars are
+// written by code point.
+fn push_literal(out: &mut String, c: char) {
+    if c.is_ascii_punctuation() {
+        out.push('\\');
+        out.push(c);
+    } else if c.is_alphanumeric() || (!c.is_ascii() && !c.is_control() && !c.is_whitespace()) {
+        out.push(c);
+    } else {
+        out.push_str(&format!("\\x{{{:x}}}", c as u32));
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decisions::{equivalence, Equivalence};
+    use crate::pipeline::tests::full_pipeline;
+    use regex_automata::Input;
+
+    fn dense_accepts(dense: &dense::DFA<Vec<u32>>, word: &str) -> bool {
+        let input = Input::new(word).anchored(Anchored::Yes);
+        dense.try_search_fwd(&input).unwrap().is_some()
+    }
+
+    #[test]
+    fn export_matches_whole_words() {
+        for (pattern, words) in [
+            ("(a+b)*abb", vec!["abb", "babb", "ab", "abba", ""]),
+            ("é[0-9]*", vec!["é", "é42", "e4", "é4x"]),
+            ("(\\.\\.*\\-)?\\*", vec!["*", ".-*", "..-*", ".*"]),
+            ("∅", vec!["", "a"]),
+            ("ε", vec!["", "a"]),
+        ] {
+            let dfa = full_pipeline(pattern).unwrap().mindfa;
+            let dense = to_regex_automata(&dfa).unwrap();
+            for w in words {
+                assert_eq!(
+                    dense_accepts(&dense, w),
+                    dfa.accepts(w),
+                    "{} on {:?}",
+                    pattern,
+                    w
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn export_is_bounded() {
+        let dfa = full_pipeline("(a+b)*a(a+b)(a+b)(a+b)").unwrap().mindfa;
+        assert!(to_regex_automata_bounded(&dfa, 20)
+            .unwrap_err()
+            .contains("over 20 nodes"));
+        assert!(to_regex_automata(&dfa).is_ok());
+    }
+
+    #[test]
+    fn import_keeps_the_language() {
+        let dense = dense::Builder::new()
+            .configure(dense::Config::new().match_kind(MatchKind::All))
+            .build("^(?:(a|b)*abb|é+)$")
+            .unwrap();
+        let alphabet: BTreeSet<char> = "abé".chars().collect();
+        let imported = from_regex_automata(&dense, &alphabet).unwrap();
+
+        let ours = full_pipeline("(a+b)*abb+éé*").unwrap().mindfa;
+        assert_eq!(equivalence(&imported, &ours), Ok(Equivalence::Equivalent));
+    }
+
+    #[test]
+    fn round_trip() {
+        let dfa = full_pipeline("a(b+c)*d?").unwrap().mindfa;
+        let alphabet: BTreeSet<char> = "abcd".chars().collect();
+        let back = from_regex_automata(&to_regex_automata(&dfa).unwrap(), &alphabet).unwrap();
+        assert_eq!(equivalence(&back, &dfa), Ok(Equivalence::Equivalent));
+    }
+}
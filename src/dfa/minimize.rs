@@ -1,120 +1,461 @@
 // dfa/minimize.rs
-use super::dfa::DFA;
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use super::dfa::{nfa_to_dfa, DFA};
+use super::ops::co_reachable;
+use crate::nfa::epsilon_elimination::remove_epsilon;
+use crate::nfa::nfa::NFA;
+use crate::types::{Letter, StateId};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// Result of a budgeted minimization.
+#[derive(Debug, Clone)]
+pub struct Minimized<S = char> {
+    pub dfa: DFA<S>,
+    // False when the budget ran out and `dfa` is only partially reduced
+    pub minimal: bool,
+}
+
+/// Which algorithm minimizes; see `minimize_with`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MinimizeAlgorithm {
+    // Partition refinement, `minimize_dfa`
+    #[default]
+    Hopcroft,
+    // Double reversal, `minimize_brzozowski`: exponential in the worst
+    // case, but short enough to serve as an oracle for the others
+    Brzozowski,
+    // Round-by-round refinement, `minimize_moore`: O(n²), the textbook
+    // table of partitions
+    Moore,
+}
+
+/// Minimize `dfa` with `algorithm`. The results accept the same language
+/// with the fewest states, except that Hopcroft keeps a dead state that
+/// Brzozowski drops; after `ops::trim` they are isomorphic.
+pub fn minimize_with<S: Letter>(dfa: &DFA<S>, algorithm: MinimizeAlgorithm) -> DFA<S> {
+    match algorithm {
+        MinimizeAlgorithm::Hopcroft => minimize_dfa(dfa),
+        MinimizeAlgorithm::Brzozowski => minimize_brzozowski(dfa),
+        MinimizeAlgorithm::Moore => minimize_moore(dfa),
+    }
+}
+
+/// Brzozowski's algorithm: reverse, determinize, reverse, determinize.
+/// Determinizing the reversal of a DFA whose states are all reachable
+/// yields the minimal DFA of the reversed language, so doing it twice
+/// minimizes without any partition. States are numbered in the order the
+/// last subset construction found them; there is no dead state.
+pub fn minimize_brzozowski<S: Letter>(dfa: &DFA<S>) -> DFA<S> {
+    brzozowski(&NFA::from(dfa))
+}
+
+/// `minimize_brzozowski` straight from an NFA, ε-edges and several start
+/// states included. The first determinization is of the reversed NFA,
+/// which is sometimes far smaller than determinizing the NFA itself (as
+/// for "the n-th symbol from the end").
+pub fn brzozowski<S: Letter>(nfa: &NFA<S>) -> DFA<S> {
+    let reversed = nfa_to_dfa(&remove_epsilon(nfa).reverse());
+    nfa_to_dfa(&NFA::from(&reversed).reverse())
+}
 
 /// Minimizes a DFA using Hopcroft's algorithm.
 /// Returns a new minimized DFA with renumbered states starting from 0.
-pub fn minimize_dfa(dfa: &DFA) -> DFA {
-    // Collect all states that appear in the DFA
-    let mut all_states: HashSet<usize> = HashSet::new();
-    all_states.insert(dfa.start);
-    all_states.extend(dfa.accepts.iter().copied());
-    
-    for (src, trans) in &dfa.transitions {
-        all_states.insert(*src);
-        all_states.extend(trans.values().copied());
-    }
-
-    // Collect all symbols
-    let mut symbols: HashSet<char> = HashSet::new();
-    for trans_map in dfa.transitions.values() {
-        symbols.extend(trans_map.keys().copied());
-    }
-
-    // Initial partition: accepting vs non-accepting states
-    let accepting: BTreeSet<usize> = dfa.accepts.iter().copied().collect();
-    let non_accepting: BTreeSet<usize> = all_states
+/// O(n·|Σ|·log n): splitters only visit predecessor lists built up front.
+/// A missing transition goes to an implicit dead state, so a state lacking
+/// one merges with a state that has it but goes to a real dead state.
+pub fn minimize_dfa<S: Letter>(dfa: &DFA<S>) -> DFA<S> {
+    minimize_dfa_traced(dfa).0
+}
+
+/// `minimize_dfa` that also returns the partition map: the states of
+/// `dfa` merged into each minimized state, indexed by minimized state id.
+pub fn minimize_dfa_traced<S: Letter>(dfa: &DFA<S>) -> (DFA<S>, Vec<BTreeSet<StateId>>) {
+    hopcroft(dfa, usize::MAX).expect("no budget")
+}
+
+/// The partition of the states after each round of Moore's algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MooreTrace {
+    // Round 0 splits accepting from non-accepting states; each later round
+    // splits states whose targets on some symbol lie in different blocks.
+    // The last round changed nothing. Blocks are ordered by their smallest
+    // state. On a partial machine a round may also split off the virtual
+    // dead state, which isn't shown, so it can repeat the one before
+    pub rounds: Vec<Vec<BTreeSet<StateId>>>,
+}
+
+impl fmt::Display for MooreTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, partition) in self.rounds.iter().enumerate() {
+            let blocks: Vec<String> = partition
+                .iter()
+                .map(|block| {
+                    let states: Vec<String> = block.iter().map(|s| s.to_string()).collect();
+                    format!("{{{}}}", states.join(","))
+                })
+                .collect();
+            writeln!(f, "round {}: {}", i, blocks.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Moore's algorithm: start from accepting vs non-accepting states, and
+/// in each round split the states that some symbol takes to different
+/// blocks, until a round splits nothing. A missing transition goes to a
+/// virtual dead state, so states with no way to accept merge whether their
+/// transitions are missing or lead to real dead states. Minimized state
+/// ids are block indices of the last round.
+pub fn minimize_moore<S: Letter>(dfa: &DFA<S>) -> DFA<S> {
+    minimize_moore_traced(dfa).0
+}
+
+/// `minimize_moore` with the partition after every round.
+pub fn minimize_moore_traced<S: Letter>(dfa: &DFA<S>) -> (DFA<S>, MooreTrace) {
+    let states = dfa.states();
+    let symbols = dfa.alphabet();
+
+    // The virtual dead state is refined with the others and left out of
+    // the trace; complete machines don't need one
+    let dead = states.last().map_or(StateId(0), |s| s.next());
+    let mut all = states.clone();
+    let partial = states.iter().any(|s| {
+        let out = dfa.transitions.get(s).map_or(0, HashMap::len);
+        out < symbols.len()
+    });
+    if partial {
+        all.insert(dead);
+    }
+
+    let mut block: HashMap<StateId, usize> = all
         .iter()
-        .copied()
-        .filter(|s| !dfa.accepts.contains(s))
+        .map(|&s| (s, dfa.accepts.contains(&s) as usize))
         .collect();
+    let mut partitions = vec![blocks_of(&all, &block)];
+    renumber_blocks(&partitions[0], &mut block);
+
+    loop {
+        // A state's signature: its block, then the block of each target
+        let mut ids: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+        let mut next = HashMap::new();
+        for &s in &all {
+            let targets = symbols
+                .iter()
+                .map(|c| {
+                    let t = dfa.transitions.get(&s).and_then(|m| m.get(c));
+                    block[t.unwrap_or(&dead)]
+                })
+                .collect();
+            let fresh = ids.len();
+            next.insert(s, *ids.entry((block[&s], targets)).or_insert(fresh));
+        }
 
-    let mut partitions: Vec<BTreeSet<usize>> = Vec::new();
-    if !non_accepting.is_empty() {
-        partitions.push(non_accepting.clone());
+        let partition = blocks_of(&all, &next);
+        let done = partition.len() == partitions.last().map_or(0, Vec::len);
+        renumber_blocks(&partition, &mut next);
+        block = next;
+        partitions.push(partition);
+        if done {
+            break;
+        }
     }
-    if !accepting.is_empty() {
-        partitions.push(accepting.clone());
+
+    let rounds: Vec<Vec<BTreeSet<StateId>>> = partitions
+        .into_iter()
+        .map(|partition| {
+            partition
+                .into_iter()
+                .map(|mut b| {
+                    b.remove(&dead);
+                    b
+                })
+                .filter(|b| !b.is_empty())
+                .collect()
+        })
+        .collect();
+    let partition = rounds.last().expect("at least two rounds");
+    let minimized = build_minimized_dfa(dfa, partition, &HashSet::new());
+    (minimized, MooreTrace { rounds })
+}
+
+// The blocks of `block`, ordered by smallest state.
+fn blocks_of(
+    states: &BTreeSet<StateId>,
+    block: &HashMap<StateId, usize>,
+) -> Vec<BTreeSet<StateId>> {
+    let mut by_id: HashMap<usize, BTreeSet<StateId>> = HashMap::new();
+    for &s in states {
+        by_id.entry(block[&s]).or_default().insert(s);
     }
+    let mut blocks: Vec<BTreeSet<StateId>> = by_id.into_values().collect();
+    blocks.sort_unstable();
+    blocks
+}
 
-    // Work queue for refinement
-    let mut work_queue: VecDeque<BTreeSet<usize>> = VecDeque::new();
-    if !non_accepting.is_empty() {
-        work_queue.push_back(non_accepting);
+// Make `block` map each state to its block's index in `partition`.
+fn renumber_blocks(partition: &[BTreeSet<StateId>], block: &mut HashMap<StateId, usize>) {
+    for (i, states) in partition.iter().enumerate() {
+        for &s in states {
+            block.insert(s, i);
+        }
     }
-    if !accepting.is_empty() {
-        work_queue.push_back(accepting);
+}
+
+/// Hopcroft's algorithm, giving up once refinement has examined more than
+/// `budget` states in total; then only `approximate_minimize` is applied
+/// and the result is flagged as not minimal. Still the same language.
+pub fn minimize_dfa_within<S: Letter>(dfa: &DFA<S>, budget: usize) -> Minimized<S> {
+    match hopcroft(dfa, budget) {
+        Some((dfa, _)) => Minimized { dfa, minimal: true },
+        None => Minimized {
+            dfa: approximate_minimize(dfa),
+            minimal: false,
+        },
     }
+}
 
-    // Hopcroft's refinement loop
-    while let Some(splitter) = work_queue.pop_front() {
-        for &symbol in &symbols {
-            // Find all states that transition to the splitter on this symbol
-            let mut predecessors: BTreeSet<usize> = BTreeSet::new();
-            for &state in &all_states {
-                if let Some(trans_map) = dfa.transitions.get(&state) {
-                    if let Some(&target) = trans_map.get(&symbol) {
-                        if splitter.contains(&target) {
-                            predecessors.insert(state);
-                        }
-                    }
-                }
-            }
+/// A cheap reduction for machines too large to minimize: all dead states
+/// (that can't reach an accepting one) become one, and states with the
+/// same acceptance and the same targets on every symbol are merged until
+/// nothing changes. Language-preserving, rarely minimal.
+pub fn approximate_minimize<S: Letter>(dfa: &DFA<S>) -> DFA<S> {
+    let states = dfa.states();
+    let live = co_reachable(dfa);
+
+    // Start from singletons and merge equal signatures; dead states share
+    // the `None` signature from the first round on
+    let mut class: HashMap<StateId, usize> =
+        states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let mut count = states.len();
+    loop {
+        let mut ids: HashMap<Signature<S>, usize> = HashMap::new();
+        let mut next = HashMap::new();
+        for &s in &states {
+            let signature = live.contains(&s).then(|| {
+                let mut targets: Vec<(S, usize)> = dfa
+                    .transitions
+                    .get(&s)
+                    .into_iter()
+                    .flatten()
+                    .map(|(&c, t)| (c, class[t]))
+                    .collect();
+                targets.sort_unstable();
+                (dfa.accepts.contains(&s), targets)
+            });
+            let fresh = ids.len();
+            next.insert(s, *ids.entry(signature).or_insert(fresh));
+        }
+        class = next;
+        if ids.len() == count {
+            break;
+        }
+        count = ids.len();
+    }
 
-            if predecessors.is_empty() {
-                continue;
-            }
+    let mut partitions = vec![BTreeSet::new(); count];
+    for (&s, &c) in &class {
+        partitions[c].insert(s);
+    }
+    build_minimized_dfa(dfa, &partitions, &HashSet::new())
+}
 
-            // Refine each partition
-            let mut new_partitions: Vec<BTreeSet<usize>> = Vec::new();
-            
-            for partition in &partitions {
-                let intersection: BTreeSet<usize> = partition
-                    .intersection(&predecessors)
-                    .copied()
-                    .collect();
-                
-                let difference: BTreeSet<usize> = partition
-                    .difference(&predecessors)
-                    .copied()
-                    .collect();
+// Acceptance and the class reached on each symbol; None for dead states.
+type Signature<S> = Option<(bool, Vec<(S, usize)>)>;
+
+// Hopcroft's refinement and its partitions, or None once more than
+// `budget` states have been examined. Missing transitions go to a virtual
+// dead state, which is left out of the partitions returned.
+fn hopcroft<S: Letter>(dfa: &DFA<S>, budget: usize) -> Option<(DFA<S>, Vec<BTreeSet<StateId>>)> {
+    let all_states: HashSet<StateId> = dfa.states().into_iter().collect();
+    let ids: Vec<StateId> = dfa.states().into_iter().collect();
+    let index: HashMap<StateId, usize> = ids.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let symbols: Vec<S> = dfa.alphabet().into_iter().collect();
+    let (n, dead) = (ids.len() + 1, ids.len());
+
+    // Predecessors of each state on each symbol, at [symbol * n + state]
+    let mut inverse: Vec<Vec<usize>> = vec![Vec::new(); symbols.len() * n];
+    for (k, c) in symbols.iter().enumerate() {
+        for p in 0..n {
+            let target = ids
+                .get(p)
+                .and_then(|s| dfa.transitions.get(s)?.get(c))
+                .map_or(dead, |t| index[t]);
+            inverse[k * n + target].push(p);
+        }
+    }
 
-                if !intersection.is_empty() && !difference.is_empty() {
-                    // Partition was split
-                    new_partitions.push(intersection.clone());
-                    new_partitions.push(difference.clone());
+    let accepting: Vec<bool> = (0..n)
+        .map(|p| ids.get(p).is_some_and(|s| dfa.accepts.contains(s)))
+        .collect();
+    let mut blocks = Partition::new(&accepting);
+
+    // Splitters as (block, symbol index). Every block but the largest
+    // starts out queued, which suffices for a complete DFA
+    let k = symbols.len();
+    let mut queued = vec![false; blocks.len() * k];
+    let mut work = Vec::new();
+    let largest = (0..blocks.len()).max_by_key(|&b| blocks.size(b));
+    for b in (0..blocks.len()).filter(|&b| Some(b) != largest) {
+        for c in 0..k {
+            queued[b * k + c] = true;
+            work.push((b, c));
+        }
+    }
 
-                    // Add smaller partition to work queue
-                    if intersection.len() <= difference.len() {
-                        work_queue.push_back(intersection);
-                    } else {
-                        work_queue.push_back(difference);
-                    }
+    let mut spent = 0usize;
+    while let Some((b, c)) = work.pop() {
+        queued[b * k + c] = false;
+        for t in blocks.members(b).to_vec() {
+            let predecessors = &inverse[c * n + t];
+            spent = spent.saturating_add(1 + predecessors.len());
+            for &p in predecessors {
+                blocks.mark(p);
+            }
+        }
+        if spent > budget {
+            return None;
+        }
+
+        for (x, y) in blocks.split_marked() {
+            queued.resize(blocks.len() * k, false);
+            for d in 0..k {
+                // With (x, d) queued both halves will split; otherwise
+                // the smaller half is enough
+                let half = if queued[x * k + d] || blocks.size(y) <= blocks.size(x) {
+                    y
                 } else {
-                    // Partition wasn't split
-                    new_partitions.push(partition.clone());
-                }
+                    x
+                };
+                queued[half * k + d] = true;
+                work.push((half, d));
             }
+        }
+    }
+
+    let mut partitions: Vec<BTreeSet<StateId>> = (0..blocks.len())
+        .map(|b| {
+            blocks
+                .members(b)
+                .iter()
+                .filter(|&&p| p != dead)
+                .map(|&p| ids[p])
+                .collect()
+        })
+        .filter(|block: &BTreeSet<StateId>| !block.is_empty())
+        .collect();
+    partitions.sort_unstable();
+
+    let minimized = build_minimized_dfa(dfa, &partitions, &all_states);
+    Some((minimized, partitions))
+}
+
+// Refinable partition of 0..n: every block is a contiguous range of
+// `elements`, with its marked states moved to the front of the range.
+struct Partition {
+    elements: Vec<usize>,
+    // Position of each state in `elements`
+    position: Vec<usize>,
+    block: Vec<usize>,
+    // Range of each block in `elements`, and how many are marked
+    first: Vec<usize>,
+    end: Vec<usize>,
+    marked: Vec<usize>,
+    // Blocks with a marked state since the last `split_marked`
+    touched: Vec<usize>,
+}
 
-            partitions = new_partitions;
+impl Partition {
+    // Two blocks, by `accepting`; a single one when either would be empty.
+    fn new(accepting: &[bool]) -> Self {
+        let mut elements: Vec<usize> = (0..accepting.len()).collect();
+        elements.sort_by_key(|&p| accepting[p]);
+        let split = elements.partition_point(|&p| !accepting[p]);
+        let ranges: Vec<(usize, usize)> = [(0, split), (split, elements.len())]
+            .into_iter()
+            .filter(|(a, b)| a < b)
+            .collect();
+
+        let mut position = vec![0; elements.len()];
+        let mut block = vec![0; elements.len()];
+        for (i, &p) in elements.iter().enumerate() {
+            position[p] = i;
+            block[p] = ranges.iter().position(|&(a, b)| a <= i && i < b).unwrap();
         }
+        Partition {
+            elements,
+            position,
+            block,
+            first: ranges.iter().map(|r| r.0).collect(),
+            end: ranges.iter().map(|r| r.1).collect(),
+            marked: vec![0; ranges.len()],
+            touched: Vec::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.first.len()
+    }
+
+    fn size(&self, b: usize) -> usize {
+        self.end[b] - self.first[b]
     }
 
-    // Build the minimized DFA
-    build_minimized_dfa(dfa, &partitions, &all_states)
+    fn members(&self, b: usize) -> &[usize] {
+        &self.elements[self.first[b]..self.end[b]]
+    }
+
+    // Move `p` into the marked front of its block, once.
+    fn mark(&mut self, p: usize) {
+        let b = self.block[p];
+        let boundary = self.first[b] + self.marked[b];
+        if self.position[p] < boundary {
+            return;
+        }
+        let other = self.elements[boundary];
+        self.elements.swap(self.position[p], boundary);
+        self.position[other] = self.position[p];
+        self.position[p] = boundary;
+        if self.marked[b] == 0 {
+            self.touched.push(b);
+        }
+        self.marked[b] += 1;
+    }
+
+    // Split every touched block into its marked and unmarked states, and
+    // return (old block, new block) for each real split. The new block
+    // takes the marked states.
+    fn split_marked(&mut self) -> Vec<(usize, usize)> {
+        let mut splits = Vec::new();
+        for b in std::mem::take(&mut self.touched) {
+            let marked = std::mem::take(&mut self.marked[b]);
+            if marked == self.size(b) {
+                continue;
+            }
+            let new = self.len();
+            self.first.push(self.first[b]);
+            self.end.push(self.first[b] + marked);
+            self.marked.push(0);
+            self.first[b] += marked;
+            for i in self.first[new]..self.end[new] {
+                self.block[self.elements[i]] = new;
+            }
+            splits.push((b, new));
+        }
+        splits
+    }
 }
 
 /// Constructs a new DFA from the partition structure.
-fn build_minimized_dfa(
-    original: &DFA,
-    partitions: &[BTreeSet<usize>],
-    _all_states: &HashSet<usize>,
-) -> DFA {
+fn build_minimized_dfa<S: Letter>(
+    original: &DFA<S>,
+    partitions: &[BTreeSet<StateId>],
+    _all_states: &HashSet<StateId>,
+) -> DFA<S> {
     // Map each state to its partition index
-    let mut state_to_partition: HashMap<usize, usize> = HashMap::new();
+    let mut state_to_partition: HashMap<StateId, StateId> = HashMap::new();
     for (idx, partition) in partitions.iter().enumerate() {
         for &state in partition {
-            state_to_partition.insert(state, idx);
+            state_to_partition.insert(state, StateId::new(idx));
         }
     }
 
@@ -124,7 +465,7 @@ fn build_minimized_dfa(
         .expect("Start state must be in a partition");
 
     // Find accepting partitions
-    let mut accepts: HashSet<usize> = HashSet::new();
+    let mut accepts: HashSet<StateId> = HashSet::new();
     for &accept_state in &original.accepts {
         if let Some(&partition_id) = state_to_partition.get(&accept_state) {
             accepts.insert(partition_id);
@@ -132,14 +473,14 @@ fn build_minimized_dfa(
     }
 
     // Build transitions for minimized DFA
-    let mut transitions: HashMap<usize, HashMap<char, usize>> = HashMap::new();
+    let mut transitions: HashMap<StateId, HashMap<S, StateId>> = HashMap::new();
 
     for (partition_idx, partition) in partitions.iter().enumerate() {
         // Pick any representative from the partition
         if let Some(&representative) = partition.iter().next() {
             if let Some(trans_map) = original.transitions.get(&representative) {
-                let mut new_trans: HashMap<char, usize> = HashMap::new();
-                
+                let mut new_trans: HashMap<S, StateId> = HashMap::new();
+
                 for (&symbol, &target) in trans_map {
                     if let Some(&target_partition) = state_to_partition.get(&target) {
                         new_trans.insert(symbol, target_partition);
@@ -147,7 +488,7 @@ fn build_minimized_dfa(
                 }
 
                 if !new_trans.is_empty() {
-                    transitions.insert(partition_idx, new_trans);
+                    transitions.insert(StateId::new(partition_idx), new_trans);
                 }
             }
         }
@@ -171,23 +512,23 @@ mod tests {
         // DFA with just a start/accept state with self-loop
         let mut transitions = HashMap::new();
         let mut trans_map = HashMap::new();
-        trans_map.insert('a', 0);
-        transitions.insert(0, trans_map);
+        trans_map.insert('a', StateId(0));
+        transitions.insert(StateId(0), trans_map);
 
         let mut accepts = HashSet::new();
-        accepts.insert(0);
+        accepts.insert(StateId(0));
 
         let dfa = DFA {
-            start: 0,
+            start: StateId(0),
             accepts,
             transitions,
         };
 
         let minimized = minimize_dfa(&dfa);
         
-        assert_eq!(minimized.start, 0);
-        assert!(minimized.accepts.contains(&0));
-        assert_eq!(minimized.transitions[&0][&'a'], 0);
+        assert_eq!(minimized.start, StateId(0));
+        assert!(minimized.accepts.contains(&StateId(0)));
+        assert_eq!(minimized.transitions[&StateId(0)][&'a'], StateId(0));
     }
 
     #[test]
@@ -195,15 +536,15 @@ mod tests {
         // DFA: 0 --a--> 1 (accept)
         let mut transitions = HashMap::new();
         let mut trans0 = HashMap::new();
-        trans0.insert('a', 1);
-        transitions.insert(0, trans0);
-        transitions.insert(1, HashMap::new());
+        trans0.insert('a', StateId(1));
+        transitions.insert(StateId(0), trans0);
+        transitions.insert(StateId(1), HashMap::new());
 
         let mut accepts = HashSet::new();
-        accepts.insert(1);
+        accepts.insert(StateId(1));
 
         let dfa = DFA {
-            start: 0,
+            start: StateId(0),
             accepts,
             transitions,
         };
@@ -223,18 +564,18 @@ mod tests {
         // States 1 and 2 are equivalent (both accepting, no outgoing transitions)
         let mut transitions = HashMap::new();
         let mut trans0 = HashMap::new();
-        trans0.insert('a', 1);
-        trans0.insert('b', 2);
-        transitions.insert(0, trans0);
-        transitions.insert(1, HashMap::new());
-        transitions.insert(2, HashMap::new());
+        trans0.insert('a', StateId(1));
+        trans0.insert('b', StateId(2));
+        transitions.insert(StateId(0), trans0);
+        transitions.insert(StateId(1), HashMap::new());
+        transitions.insert(StateId(2), HashMap::new());
 
         let mut accepts = HashSet::new();
-        accepts.insert(1);
-        accepts.insert(2);
+        accepts.insert(StateId(1));
+        accepts.insert(StateId(2));
 
         let dfa = DFA {
-            start: 0,
+            start: StateId(0),
             accepts: accepts.clone(),
             transitions,
         };
@@ -254,36 +595,37 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn minimize_complex_example() {
         // DFA for (a|b)*abb with redundant states
         // This should reduce several equivalent states
         let mut transitions = HashMap::new();
         
         let mut trans0 = HashMap::new();
-        trans0.insert('a', 1);
-        trans0.insert('b', 0);
-        transitions.insert(0, trans0);
+        trans0.insert('a', StateId(1));
+        trans0.insert('b', StateId(0));
+        transitions.insert(StateId(0), trans0);
 
         let mut trans1 = HashMap::new();
-        trans1.insert('a', 1);
-        trans1.insert('b', 2);
-        transitions.insert(1, trans1);
+        trans1.insert('a', StateId(1));
+        trans1.insert('b', StateId(2));
+        transitions.insert(StateId(1), trans1);
 
         let mut trans2 = HashMap::new();
-        trans2.insert('a', 1);
-        trans2.insert('b', 3);
-        transitions.insert(2, trans2);
+        trans2.insert('a', StateId(1));
+        trans2.insert('b', StateId(3));
+        transitions.insert(StateId(2), trans2);
 
         let mut trans3 = HashMap::new();
-        trans3.insert('a', 1);
-        trans3.insert('b', 0);
-        transitions.insert(3, trans3);
+        trans3.insert('a', StateId(1));
+        trans3.insert('b', StateId(0));
+        transitions.insert(StateId(3), trans3);
 
         let mut accepts = HashSet::new();
-        accepts.insert(3);
+        accepts.insert(StateId(3));
 
         let dfa = DFA {
-            start: 0,
+            start: StateId(0),
             accepts,
             transitions,
         };
@@ -294,4 +636,240 @@ mod tests {
         assert!(minimized.accepts.len() >= 1);
         assert_eq!(minimized.start, minimized.start); // Start exists
     }
+
+    #[test]
+    fn approximation_keeps_the_language() {
+        use crate::decisions::{equivalence, Equivalence};
+        use crate::pipeline::tests::full_pipeline;
+
+        for pattern in ["(a+b)*abb", "ab+cb+(a+c)d", "(aa)*+(aaa)*", "∅"] {
+            let dfa = full_pipeline(pattern).unwrap().dfa;
+            let approx = approximate_minimize(&dfa);
+            assert_eq!(
+                equivalence(&approx, &dfa),
+                Ok(Equivalence::Equivalent),
+                "{}",
+                pattern
+            );
+            assert!(approx.states().len() <= dfa.states().len());
+            assert!(approx.states().len() >= minimize_dfa(&dfa).states().len());
+        }
+    }
+
+    #[test]
+    fn approximation_merges_sinks_and_equal_signatures() {
+        // 0 -a-> 1, 0 -b-> 2, 1 and 2 both accept and go to dead states 3, 4
+        let dfa = DFA {
+            start: StateId(0),
+            accepts: HashSet::from([StateId(1), StateId(2)]),
+            transitions: HashMap::from([
+                (
+                    StateId(0),
+                    HashMap::from([('a', StateId(1)), ('b', StateId(2))]),
+                ),
+                (StateId(1), HashMap::from([('a', StateId(3))])),
+                (StateId(2), HashMap::from([('a', StateId(4))])),
+                (StateId(3), HashMap::from([('a', StateId(3))])),
+                (StateId(4), HashMap::from([('b', StateId(4))])),
+            ]),
+        };
+        assert_eq!(approximate_minimize(&dfa).states().len(), 3);
+    }
+
+    #[test]
+    fn budget_falls_back_to_approximation() {
+        use crate::pipeline::tests::{full_pipeline, full_pipeline_with, PipelineOptions};
+
+        let dfa = full_pipeline("(a+b)*a(a+b){3}").unwrap().dfa;
+        let full = minimize_dfa_within(&dfa, usize::MAX);
+        assert!(full.minimal);
+        assert_eq!(full.dfa.states().len(), minimize_dfa(&dfa).states().len());
+
+        let cut = minimize_dfa_within(&dfa, 10);
+        assert!(!cut.minimal);
+        assert!(cut.dfa.accepts("abbb") && !cut.dfa.accepts("bbbb"));
+
+        let opts = PipelineOptions {
+            minimize_budget: Some(10),
+            ..Default::default()
+        };
+        assert!(
+            !full_pipeline_with("(a+b)*a(a+b){3}", &opts)
+                .unwrap()
+                .minimal
+        );
+        assert!(full_pipeline("ab").unwrap().minimal);
+
+        // Only Hopcroft has a budget
+        let opts = PipelineOptions {
+            minimize: MinimizeAlgorithm::Moore,
+            ..opts
+        };
+        assert!(full_pipeline_with("ab", &opts).is_err());
+    }
+
+    #[test]
+    fn brzozowski_cross_checks_hopcroft() {
+        use crate::dfa::canonical::isomorphic;
+        use crate::dfa::ops::trim;
+        use crate::pipeline::tests::{full_pipeline, full_pipeline_with, PipelineOptions};
+
+        let patterns = [
+            "(a+b)*abb",
+            "ab+cb+(a+c)d",
+            "(aa)*+(aaa)*",
+            "a{2,4}b?",
+            "(a+b)*a(a+b){3}",
+            "∅",
+            "ε",
+        ];
+        for pattern in patterns {
+            let out = full_pipeline(pattern).unwrap();
+            let hopcroft = trim(&minimize_with(&out.dfa, MinimizeAlgorithm::Hopcroft));
+            let double_reversal = minimize_with(&out.dfa, MinimizeAlgorithm::Brzozowski);
+            assert!(isomorphic(&hopcroft, &double_reversal), "{}", pattern);
+            // Straight from the ε-NFA, and from the pipeline
+            assert!(isomorphic(&hopcroft, &brzozowski(&out.enfa)), "{}", pattern);
+            let opts = PipelineOptions {
+                minimize: MinimizeAlgorithm::Brzozowski,
+                ..Default::default()
+            };
+            let mindfa = full_pipeline_with(pattern, &opts).unwrap().mindfa;
+            assert!(isomorphic(&hopcroft, &mindfa), "{}", pattern);
+
+            // Any letter type, bytes included
+            let bytes = out.dfa.to_byte_dfa(crate::dfa::bytes::Encoding::Utf8);
+            let hopcroft = trim(&minimize_with(&bytes, MinimizeAlgorithm::Hopcroft));
+            let double_reversal = minimize_with(&bytes, MinimizeAlgorithm::Brzozowski);
+            assert!(isomorphic(&hopcroft, &double_reversal), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn moore_rounds() {
+        use crate::dfa::canonical::isomorphic;
+        use crate::pipeline::tests::full_pipeline;
+
+        // The subset DFA of (a+b)*abb has 5 states: accepting or not, then
+        // one b from accepting, then two; the fourth round changes nothing
+        let dfa = full_pipeline("(a+b)*abb").unwrap().dfa;
+        let (minimized, trace) = minimize_moore_traced(&dfa);
+        assert!(isomorphic(&minimized, &minimize_dfa(&dfa)));
+        assert_eq!(trace.rounds.len(), 4);
+        assert_eq!(trace.rounds[0].len(), 2);
+        assert_eq!(trace.rounds.last(), trace.rounds.get(2));
+        assert_eq!(trace.rounds[3].len(), minimized.states().len());
+        for partition in &trace.rounds {
+            let covered: usize = partition.iter().map(BTreeSet::len).sum();
+            assert_eq!(covered, dfa.states().len());
+        }
+        assert!(trace.to_string().starts_with("round 0: {"));
+        assert_eq!(trace.to_string().lines().count(), 4);
+
+        for pattern in ["ab+cb+(a+c)d", "(aa)*+(aaa)*", "∅", "ε"] {
+            let dfa = full_pipeline(pattern).unwrap().dfa;
+            let moore = minimize_with(&dfa, MinimizeAlgorithm::Moore);
+            assert!(isomorphic(&moore, &minimize_dfa(&dfa)), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn moore_merges_dead_states_with_missing_transitions() {
+        use crate::dfa::canonical::isomorphic;
+
+        // 0 -a-> 1 (accepting), 0 -b-> 2, 1 -a-> 3; 2 and 3 are dead sinks
+        // and 1 has no b-transition
+        let dfa = DFA {
+            start: StateId(0),
+            accepts: HashSet::from([StateId(1)]),
+            transitions: HashMap::from([
+                (
+                    StateId(0),
+                    HashMap::from([('a', StateId(1)), ('b', StateId(2))]),
+                ),
+                (StateId(1), HashMap::from([('a', StateId(3))])),
+                (
+                    StateId(2),
+                    HashMap::from([('a', StateId(2)), ('b', StateId(2))]),
+                ),
+                (StateId(3), HashMap::from([('a', StateId(3))])),
+            ]),
+        };
+        let moore = minimize_moore(&dfa);
+        let hopcroft = minimize_dfa(&dfa);
+        assert_eq!(moore.states().len(), 3);
+        assert!(isomorphic(&moore, &hopcroft));
+        assert_eq!(minimize_brzozowski(&dfa).states().len(), 2);
+
+        let (_, trace) = minimize_moore_traced(&dfa);
+        let last = trace.rounds.last().unwrap();
+        assert!(last.contains(&BTreeSet::from([StateId(2), StateId(3)])));
+    }
+
+    #[test]
+    fn hopcroft_agrees_with_moore_on_random_machines() {
+        use crate::dfa::canonical::isomorphic;
+        use crate::dfa::ops::trim;
+
+        // Partial machines with some dead and unreachable states
+        let mut seed = 0x2545_f491_u64;
+        let mut next = |bound: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % bound
+        };
+        for _ in 0..200 {
+            let n = 1 + next(12) as usize;
+            let mut dfa = DFA {
+                start: StateId(0),
+                accepts: HashSet::new(),
+                transitions: HashMap::new(),
+            };
+            for s in (0..n).map(StateId::new) {
+                if next(3) == 0 {
+                    dfa.accepts.insert(s);
+                }
+                for c in ['a', 'b', 'c'] {
+                    if next(4) != 0 {
+                        let t = StateId::new(next(n as u64) as usize);
+                        dfa.transitions.entry(s).or_default().insert(c, t);
+                    }
+                }
+            }
+            let hopcroft = minimize_dfa(&dfa);
+            assert!(
+                isomorphic(&trim(&hopcroft), &trim(&minimize_moore(&dfa))),
+                "{:?}",
+                dfa
+            );
+            assert!(
+                isomorphic(&trim(&hopcroft), &minimize_brzozowski(&dfa)),
+                "{:?}",
+                dfa
+            );
+        }
+    }
+
+    #[test]
+    fn hopcroft_scales() {
+        // a's counted mod m twice over: state i and i + m agree
+        let m = 10_000;
+        let transitions = (0..2 * m)
+            .map(|s| {
+                let twin = if s < m { s + m } else { s - m };
+                let step = (s + 1) % m + if s < m { m } else { 0 };
+                (
+                    StateId::new(s),
+                    HashMap::from([('a', StateId::new(step)), ('b', StateId::new(twin))]),
+                )
+            })
+            .collect();
+        let dfa = DFA {
+            start: StateId(0),
+            accepts: HashSet::from([StateId(0), StateId::new(m)]),
+            transitions,
+        };
+        let minimized = minimize_dfa(&dfa);
+        assert_eq!(minimized.states().len(), m);
+        assert!(minimized.accepts_letters(std::iter::repeat_n('a', m)));
+    }
 }
@@ -1,2 +1,30 @@
+pub mod algebra;
+pub mod analysis;
+#[cfg(feature = "simd")]
+pub mod batch;
+pub mod bits;
+pub mod bytes;
+pub mod canonical;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod compiled;
+pub mod dense;
+#[allow(clippy::module_inception)]
 pub mod dfa;
+pub mod edit;
+pub mod equivalence;
+#[cfg(feature = "regex-automata")]
+pub mod interop;
 pub mod minimize;
+pub mod ops;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod profile;
+pub mod queries;
+pub mod runner;
+pub mod scan;
+pub mod to_regex;
+
+pub use canonical::isomorphic;
+pub use equivalence::equivalent;
+pub use ops::{difference, intersect, symmetric_difference, union};
@@ -0,0 +1,713 @@
+// dfa/ops.rs
+use super::canonical::{canonicalize, fingerprint_canonical, isomorphic, structural_hash};
+use super::dfa::{nfa_to_dfa, DFA};
+use super::minimize::minimize_dfa;
+use crate::nfa::epsilon_elimination::remove_epsilon;
+use crate::nfa::nfa::{TransitionLabel, NFA};
+use crate::types::{Letter, StateId};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// States reachable from the start state.
+pub fn reachable<S: Letter>(dfa: &DFA<S>) -> HashSet<StateId> {
+    let mut seen = HashSet::from([dfa.start]);
+    let mut queue = VecDeque::from([dfa.start]);
+
+    while let Some(s) = queue.pop_front() {
+        for &t in dfa.transitions.get(&s).into_iter().flat_map(|m| m.values()) {
+            if seen.insert(t) {
+                queue.push_back(t);
+            }
+        }
+    }
+
+    seen
+}
+
+/// States from which some accepting state can be reached.
+pub fn co_reachable<S: Letter>(dfa: &DFA<S>) -> HashSet<StateId> {
+    let mut reverse: HashMap<StateId, Vec<StateId>> = HashMap::new();
+    for (&s, map) in &dfa.transitions {
+        for &t in map.values() {
+            reverse.entry(t).or_default().push(s);
+        }
+    }
+
+    let mut seen: HashSet<StateId> = dfa.accepts.clone();
+    let mut queue: VecDeque<StateId> = dfa.accepts.iter().copied().collect();
+
+    while let Some(t) = queue.pop_front() {
+        for &s in reverse.get(&t).into_iter().flatten() {
+            if seen.insert(s) {
+                queue.push_back(s);
+            }
+        }
+    }
+
+    seen
+}
+
+/// Drop states that are unreachable or can't reach an accepting state.
+/// The start state is always kept.
+pub fn trim<S: Letter>(dfa: &DFA<S>) -> DFA<S> {
+    let live: HashSet<StateId> = reachable(dfa)
+        .intersection(&co_reachable(dfa))
+        .copied()
+        .collect();
+
+    let transitions = dfa
+        .transitions
+        .iter()
+        .filter(|(s, _)| live.contains(s))
+        .map(|(&s, map)| {
+            let kept: HashMap<S, StateId> = map
+                .iter()
+                .filter(|(_, t)| live.contains(t))
+                .map(|(&c, &t)| (c, t))
+                .collect();
+            (s, kept)
+        })
+        .filter(|(_, map)| !map.is_empty())
+        .collect();
+
+    DFA {
+        start: dfa.start,
+        accepts: dfa.accepts.intersection(&live).copied().collect(),
+        transitions,
+    }
+}
+
+/// Pref(L): every prefix of a word in L. Trim, then accept everywhere.
+pub fn prefix_closure(dfa: &DFA) -> DFA {
+    let mut out = trim(dfa);
+    if co_reachable(dfa).contains(&dfa.start) {
+        out.accepts = out.states().into_iter().collect();
+    }
+    out
+}
+
+/// Suff(L): every suffix of a word in L. Every live state becomes a
+/// possible start, which needs a round through the NFA pipeline.
+pub fn suffix_closure(dfa: &DFA) -> DFA {
+    let trimmed = trim(dfa);
+    let mut nfa = NFA::new();
+
+    for (&s, map) in &trimmed.transitions {
+        for (&c, &t) in map {
+            nfa.add_edge_auto(s, TransitionLabel::Char(c), t);
+        }
+    }
+    for &a in &trimmed.accepts {
+        nfa.transitions.entry(a).or_default();
+    }
+
+    // Fresh start with epsilon edges to every live state
+    let start = nfa
+        .transitions
+        .keys()
+        .max()
+        .map_or(StateId(0), |m| m.next());
+    let live: Vec<StateId> = nfa.transitions.keys().copied().collect();
+    nfa.transitions.insert(start, Vec::new());
+    if !trimmed.accepts.is_empty() {
+        for s in live {
+            nfa.add_edge(start, TransitionLabel::Epsilon, s)
+                .expect("states were created above");
+        }
+    }
+    nfa.start = start;
+    nfa.accept = trimmed.accepts.iter().copied().collect();
+
+    nfa_to_dfa(&remove_epsilon(&nfa))
+}
+
+/// The words that equal a word of L up to ASCII case. Letters end up with
+/// the same transitions in both cases; see `NFA::ignore_ascii_case` for
+/// why this differs from compiling the pattern case-insensitively.
+pub fn ignore_ascii_case(dfa: &DFA) -> DFA {
+    let mut nfa = NFA::new();
+    for (&s, map) in &dfa.transitions {
+        for (&c, &t) in map {
+            nfa.add_edge_auto(s, TransitionLabel::Char(c), t);
+        }
+    }
+    for &s in dfa.accepts.iter().chain([&dfa.start]) {
+        nfa.transitions.entry(s).or_default();
+    }
+    nfa.start = dfa.start;
+    nfa.accept = dfa.accepts.iter().copied().collect();
+
+    nfa_to_dfa(&nfa.ignore_ascii_case())
+}
+
+/// How `DFA::complete_with` fills in missing transitions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Completion {
+    // To a fresh non-accepting sink; the language is unchanged
+    #[default]
+    DeadState,
+    // Back to the state itself
+    SelfLoop,
+    // To the given state. If it isn't a state yet, it is added as a
+    // non-accepting sink.
+    Redirect(StateId),
+}
+
+impl DFA {
+    /// Give every state a transition on every symbol of the DFA's alphabet.
+    pub fn complete_with(&self, strategy: Completion) -> DFA {
+        self.complete_over(&BTreeSet::new(), strategy)
+    }
+
+    /// Like `complete_with`, over `alphabet` together with the DFA's own
+    /// symbols.
+    pub fn complete_over(&self, alphabet: &BTreeSet<char>, strategy: Completion) -> DFA {
+        let sigma: BTreeSet<char> = alphabet.union(&self.alphabet()).copied().collect();
+        let mut states = self.states();
+        let target = match strategy {
+            Completion::DeadState => Some(states.last().map_or(StateId(0), |m| m.next())),
+            Completion::SelfLoop => None,
+            Completion::Redirect(t) => Some(t),
+        };
+        states.extend(target);
+
+        let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+        for &s in &states {
+            let map = transitions.entry(s).or_default();
+            for &c in &sigma {
+                let t = self
+                    .transitions
+                    .get(&s)
+                    .and_then(|m| m.get(&c))
+                    .copied()
+                    .unwrap_or(target.unwrap_or(s));
+                map.insert(c, t);
+            }
+        }
+        transitions.retain(|_, map| !map.is_empty());
+
+        DFA {
+            start: self.start,
+            accepts: self.accepts.clone(),
+            transitions,
+        }
+    }
+
+    /// The words over `alphabet` (together with the DFA's own symbols) that
+    /// this DFA rejects; see `complement`. A reject list compiled once
+    /// becomes an allow list.
+    pub fn complement(&self, alphabet: &BTreeSet<char>) -> DFA {
+        complement(self, alphabet)
+    }
+}
+
+/// Complement over `alphabet` (together with the DFA's own symbols):
+/// complete the DFA with a dead state, then flip acceptance.
+pub fn complement(dfa: &DFA, alphabet: &BTreeSet<char>) -> DFA {
+    let mut out = dfa.complete_over(alphabet, Completion::DeadState);
+    out.accepts = out
+        .states()
+        .into_iter()
+        .filter(|s| !dfa.accepts.contains(s))
+        .collect();
+    out
+}
+
+/// Product construction for L(a) ∩ L(b): the words valid under both
+/// machines. Only state pairs reachable from the start pair are built,
+/// numbered in breadth-first order with symbols taken in order, so the
+/// start is 0. Partial DFAs are fine: a symbol missing on either side is
+/// missing in the product.
+pub fn intersect<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> DFA<S> {
+    let mut pair_to_id: HashMap<(StateId, StateId), StateId> = HashMap::new();
+    let mut queue = VecDeque::new();
+    let mut transitions: HashMap<StateId, HashMap<S, StateId>> = HashMap::new();
+    let mut accepts = HashSet::new();
+
+    pair_to_id.insert((a.start, b.start), StateId(0));
+    queue.push_back((a.start, b.start));
+
+    while let Some((p, q)) = queue.pop_front() {
+        let id = pair_to_id[&(p, q)];
+        if a.accepts.contains(&p) && b.accepts.contains(&q) {
+            accepts.insert(id);
+        }
+
+        let (Some(pm), Some(qm)) = (a.transitions.get(&p), b.transitions.get(&q)) else {
+            continue;
+        };
+
+        let mut symbols: Vec<S> = pm.keys().filter(|c| qm.contains_key(c)).copied().collect();
+        symbols.sort_unstable();
+
+        for c in symbols {
+            let next = (pm[&c], qm[&c]);
+            let next_id = StateId::new(pair_to_id.len());
+            let target = *pair_to_id.entry(next).or_insert_with(|| {
+                queue.push_back(next);
+                next_id
+            });
+            transitions.entry(id).or_default().insert(c, target);
+        }
+    }
+
+    DFA {
+        start: StateId(0),
+        accepts,
+        transitions,
+    }
+}
+
+/// L(a) ∪ L(b).
+pub fn union<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> DFA<S> {
+    product(a, b, |p, q| p || q)
+}
+
+/// L(a) \ L(b): the words of `a` that `b` rejects.
+pub fn difference<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> DFA<S> {
+    product(a, b, |p, q| p && !q)
+}
+
+/// The words in exactly one of L(a) and L(b); empty exactly when the two
+/// are equivalent, and any word it accepts tells them apart.
+pub fn symmetric_difference<S: Letter>(a: &DFA<S>, b: &DFA<S>) -> DFA<S> {
+    product(a, b, |p, q| p != q)
+}
+
+// Product of `a` and `b`, both completed over the union of their
+// alphabets, accepting pairs whose acceptance `accept` combines to true.
+// A side without a transition is in its dead state, None here; pairs are
+// built as `intersect` builds them, except that the pair of two dead
+// states is left out, since no combination accepts from it.
+fn product<S: Letter>(a: &DFA<S>, b: &DFA<S>, accept: impl Fn(bool, bool) -> bool) -> DFA<S> {
+    type Side = Option<StateId>;
+    let step =
+        |dfa: &DFA<S>, s: Side, c: &S| s.and_then(|s| dfa.transitions.get(&s)?.get(c).copied());
+    let accepting = |dfa: &DFA<S>, s: Side| s.is_some_and(|s| dfa.accepts.contains(&s));
+    let symbols: BTreeSet<S> = &a.alphabet() | &b.alphabet();
+
+    let start: (Side, Side) = (Some(a.start), Some(b.start));
+    let mut pair_to_id: HashMap<(Side, Side), StateId> = HashMap::from([(start, StateId(0))]);
+    let mut queue = VecDeque::from([start]);
+    let mut transitions: HashMap<StateId, HashMap<S, StateId>> = HashMap::new();
+    let mut accepts = HashSet::new();
+
+    while let Some((p, q)) = queue.pop_front() {
+        let id = pair_to_id[&(p, q)];
+        if accept(accepting(a, p), accepting(b, q)) {
+            accepts.insert(id);
+        }
+
+        for c in &symbols {
+            let next = (step(a, p, c), step(b, q, c));
+            if next == (None, None) {
+                continue;
+            }
+            let next_id = StateId::new(pair_to_id.len());
+            let target = *pair_to_id.entry(next).or_insert_with(|| {
+                queue.push_back(next);
+                next_id
+            });
+            transitions.entry(id).or_default().insert(*c, target);
+        }
+    }
+
+    DFA {
+        start: StateId(0),
+        accepts,
+        transitions,
+    }
+}
+
+// What a cached result was computed from. Operands are language
+// fingerprints, so equivalent DFAs share entries
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    // Smaller fingerprint first: intersection is commutative
+    Intersection(u128, u128),
+    // The operand and the full alphabet it was completed over
+    Complement(u128, BTreeSet<char>),
+}
+
+// A result and the canonical minimal DFAs of the operands it was computed
+// from. Fingerprints can collide, so a hit also compares the operands.
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    operands: Vec<DFA>,
+    result: DFA,
+}
+
+/// Memoizes `intersect` and `complement` by the languages of their
+/// operands, for many operations on the same few automata (grading
+/// submissions against one reference). A hit returns a clone of the first
+/// result computed for those languages: the same language, though not
+/// necessarily the same numbering as recomputing would give.
+///
+/// Operands are looked up by language fingerprint, and a hit is only
+/// taken once the canonical minimal DFAs of the operands match too.
+/// Minimizing is the costly part, so each operand is first looked up by
+/// its `structural_hash`; passing the same DFA again costs one pass over it.
+#[derive(Debug, Default)]
+pub struct OpCache {
+    // Structural hash → the DFAs with that hash, each with its fingerprint
+    // and canonical minimal DFA
+    languages: HashMap<u128, Vec<(DFA, u128, DFA)>>,
+    results: HashMap<CacheKey, Vec<CacheEntry>>,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl OpCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `canonical::fingerprint` of `dfa`, memoized by its structure.
+    pub fn fingerprint(&mut self, dfa: &DFA) -> u128 {
+        self.language(dfa).0
+    }
+
+    pub fn intersect(&mut self, a: &DFA, b: &DFA) -> DFA {
+        let (fa, ca) = self.language(a);
+        let (fb, cb) = self.language(b);
+        let key = CacheKey::Intersection(fa.min(fb), fa.max(fb));
+        self.get_or_compute(key, vec![ca, cb], || intersect(a, b))
+    }
+
+    pub fn complement(&mut self, dfa: &DFA, alphabet: &BTreeSet<char>) -> DFA {
+        let sigma = alphabet | &dfa.alphabet();
+        let (f, canonical) = self.language(dfa);
+        let key = CacheKey::Complement(f, sigma);
+        self.get_or_compute(key, vec![canonical], || complement(dfa, alphabet))
+    }
+
+    /// Number of cached results.
+    pub fn len(&self) -> usize {
+        self.results.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Drop every cached fingerprint and result, keeping the counters.
+    pub fn clear(&mut self) {
+        self.languages.clear();
+        self.results.clear();
+    }
+
+    // The fingerprint and canonical minimal DFA of `dfa`'s language.
+    pub(crate) fn language(&mut self, dfa: &DFA) -> (u128, DFA) {
+        let seen = self.languages.entry(structural_hash(dfa)).or_default();
+        if let Some((_, f, canonical)) = seen.iter().find(|(d, _, _)| identical(d, dfa)) {
+            return (*f, canonical.clone());
+        }
+        let canonical = canonicalize(&minimize_dfa(dfa));
+        let f = fingerprint_canonical(&canonical);
+        seen.push((dfa.clone(), f, canonical.clone()));
+        (f, canonical)
+    }
+
+    fn get_or_compute(
+        &mut self,
+        key: CacheKey,
+        operands: Vec<DFA>,
+        compute: impl FnOnce() -> DFA,
+    ) -> DFA {
+        let entries = self.results.entry(key).or_default();
+        if let Some(entry) = entries
+            .iter()
+            .find(|e| same_operands(&e.operands, &operands))
+        {
+            self.hits += 1;
+            return entry.result.clone();
+        }
+        self.misses += 1;
+        let result = compute();
+        entries.push(CacheEntry {
+            operands,
+            result: result.clone(),
+        });
+        result
+    }
+}
+
+// The same DFA exactly as numbered.
+fn identical(a: &DFA, b: &DFA) -> bool {
+    a.start == b.start && a.accepts == b.accepts && a.transitions == b.transitions
+}
+
+// The same canonical DFAs in any order. Canonical minimal DFAs of equal
+// languages are equal, so isomorphism is cheap and exact here.
+fn same_operands(a: &[DFA], b: &[DFA]) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|x| b.iter().any(|y| isomorphic(x, y)))
+        && b.iter().all(|y| a.iter().any(|x| isomorphic(x, y)))
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn dfa(lang: &str) -> DFA {
+        full_pipeline(lang).unwrap().mindfa
+    }
+
+    #[test]
+    fn trim_removes_dead_states() {
+        let mut d = dfa("ab");
+        // Add a dead branch 0 --z--> 99
+        d.transitions
+            .get_mut(&d.start)
+            .unwrap()
+            .insert('z', StateId(99));
+
+        let t = trim(&d);
+        assert!(!t.states().contains(&StateId(99)));
+        assert!(t.accepts("ab"));
+    }
+
+    #[test]
+    fn prefix_closure_accepts_prefixes() {
+        let p = prefix_closure(&dfa("abc+bd"));
+
+        for w in ["", "a", "ab", "abc", "b", "bd"] {
+            assert!(p.accepts(w), "{:?}", w);
+        }
+        for w in ["c", "ac", "abcd", "d"] {
+            assert!(!p.accepts(w), "{:?}", w);
+        }
+    }
+
+    #[test]
+    fn suffix_closure_accepts_suffixes() {
+        let s = suffix_closure(&dfa("abc+bd"));
+
+        for w in ["", "c", "bc", "abc", "d", "bd"] {
+            assert!(s.accepts(w), "{:?}", w);
+        }
+        for w in ["a", "ab", "b", "cd"] {
+            assert!(!s.accepts(w), "{:?}", w);
+        }
+    }
+
+    #[test]
+    fn closures_of_empty_language() {
+        let empty = dfa("∅");
+        assert!(!prefix_closure(&empty).accepts(""));
+        assert!(!suffix_closure(&empty).accepts(""));
+    }
+
+    #[test]
+    fn complement_flips_membership() {
+        let alphabet: BTreeSet<char> = "abc".chars().collect();
+        let c = complement(&dfa("ab*"), &alphabet);
+
+        for w in ["", "b", "ba", "abc", "c"] {
+            assert!(c.accepts(w), "{:?}", w);
+        }
+        for w in ["a", "ab", "abbb"] {
+            assert!(!c.accepts(w), "{:?}", w);
+        }
+
+        // A reject list: anything over the alphabet but these commands
+        let blocked = dfa("rm+dd");
+        let allowed = blocked.complement(&"dmrs".chars().collect());
+        for w in ["", "rs", "r", "rmm", "ddd", "sd"] {
+            assert!(allowed.accepts(w), "{:?}", w);
+        }
+        assert!(!allowed.accepts("rm") && !allowed.accepts("dd"));
+        // Chars outside the alphabet are still rejected, as by `blocked`
+        assert!(!allowed.accepts("x"));
+        assert!(complement(&allowed, &BTreeSet::new()).accepts("dd"));
+    }
+
+    #[test]
+    fn cache_shares_results_between_equivalent_operands() {
+        let mut cache = OpCache::new();
+        let reference = dfa("(a+b)*abb");
+
+        let first = cache.intersect(&reference, &dfa("a*b*"));
+        // Same languages, spelled differently and swapped
+        let again = cache.intersect(&full_pipeline("a*+a*b*b*").unwrap().dfa, &reference);
+        assert_eq!((cache.hits, cache.misses, cache.len()), (1, 1, 1));
+        for w in ["abb", "aabb", "babb", "ab", ""] {
+            assert_eq!(first.accepts(w), again.accepts(w), "{:?}", w);
+        }
+        assert!(first.accepts("aabb") && !first.accepts("babb"));
+
+        let alphabet: BTreeSet<char> = "ab".chars().collect();
+        let c = cache.complement(&reference, &alphabet);
+        assert!(c.accepts("ab") && !c.accepts("abb"));
+        cache.complement(&reference, &"abc".chars().collect());
+        cache.complement(&reference, &alphabet);
+        assert_eq!((cache.hits, cache.misses, cache.len()), (2, 3, 3));
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn cache_checks_operands_on_a_fingerprint_hit() {
+        let mut cache = OpCache::new();
+        let (a, b) = (dfa("a*"), dfa("ab"));
+        let (x, y) = (dfa("b*"), dfa("bb"));
+        cache.intersect(&a, &b);
+
+        // Pretend (x, y) collides with (a, b): same key, other languages
+        let (fx, fy) = (cache.fingerprint(&x), cache.fingerprint(&y));
+        let entries = cache.results.drain().next().unwrap().1;
+        let key = CacheKey::Intersection(fx.min(fy), fx.max(fy));
+        cache.results.insert(key, entries);
+
+        let both = cache.intersect(&x, &y);
+        assert!(both.accepts("bb") && !both.accepts("ab"));
+        assert_eq!((cache.hits, cache.misses, cache.len()), (0, 2, 2));
+        cache.intersect(&y, &x);
+        assert_eq!((cache.hits, cache.misses), (1, 2));
+    }
+
+    #[test]
+    fn completion_strategies() {
+        // States 0 -a-> 1 -b-> 2, accepting 2
+        let partial = dfa("ab");
+        let n = partial.states().len();
+
+        let dead = partial.complete_with(Completion::DeadState);
+        assert_eq!(dead.states().len(), n + 1);
+        for w in ["ab", "a", "ba", "abb", ""] {
+            assert_eq!(dead.accepts(w), partial.accepts(w), "{:?}", w);
+        }
+
+        // Missing symbols are ignored, so "ab" can be padded anywhere
+        let looped = partial.complete_with(Completion::SelfLoop);
+        assert_eq!(looped.states().len(), n);
+        assert!(looped.accepts("bbaaab") && looped.accepts("abab"));
+        assert!(!looped.accepts("bba"));
+
+        // Any unexpected symbol goes back to the start
+        let restart = partial.complete_with(Completion::Redirect(partial.start));
+        assert!(restart.accepts("bab") && restart.accepts("abbab"));
+        assert!(!restart.accepts("abb"));
+
+        for c in [&dead, &looped, &restart] {
+            for s in c.states() {
+                assert_eq!(c.transitions[&s].len(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn redirect_to_new_sink() {
+        let partial = dfa("a");
+        let out = partial.complete_over(&BTreeSet::from(['b']), Completion::Redirect(StateId(99)));
+
+        assert!(out.states().contains(&StateId(99)));
+        assert_eq!(out.transitions[&StateId(99)][&'a'], StateId(99));
+        assert!(out.accepts("a") && !out.accepts("ab"));
+    }
+
+    #[test]
+    fn intersection_of_languages() {
+        // Even number of a's, and ends in b
+        let i = intersect(&dfa("(b*ab*a)*b*"), &dfa("(a+b)*b"));
+
+        for w in ["b", "aab", "abab", "bbaab"] {
+            assert!(i.accepts(w), "{:?}", w);
+        }
+        for w in ["", "ab", "aa", "aaba"] {
+            assert!(!i.accepts(w), "{:?}", w);
+        }
+
+        // Only reachable pairs: a machine with itself stays its own size
+        let abb = dfa("(a+b)*abb");
+        let square = intersect(&abb, &abb);
+        assert_eq!(square.start, StateId(0));
+        assert_eq!(square.states().len(), abb.states().len());
+
+        // Any letter type, and symbols on one side only drop out
+        let bytes = |edges: &[(StateId, u8, StateId)], accept: StateId| DFA {
+            start: StateId(0),
+            accepts: HashSet::from([accept]),
+            transitions: edges.iter().fold(HashMap::new(), |mut m, &(s, c, t)| {
+                m.entry(s).or_insert_with(HashMap::new).insert(c, t);
+                m
+            }),
+        };
+        let a = bytes(
+            &[
+                (StateId(0), b'x', StateId(1)),
+                (StateId(0), b'y', StateId(1)),
+            ],
+            StateId(1),
+        );
+        let b = bytes(
+            &[
+                (StateId(0), b'y', StateId(1)),
+                (StateId(0), b'z', StateId(1)),
+            ],
+            StateId(1),
+        );
+        let both = intersect(&a, &b);
+        assert_eq!(both.alphabet(), BTreeSet::from([b'y']));
+        assert!(both.accepts_letters(*b"y") && !both.accepts_letters(*b"x"));
+    }
+
+    #[test]
+    fn boolean_operations() {
+        // Even number of a's, and ends in b, over different alphabets
+        let (even, ends_b) = (dfa("(b*ab*a)*b*"), dfa("(a+b+c)*b"));
+        let or = union(&even, &ends_b);
+        let minus = difference(&even, &ends_b);
+        let xor = symmetric_difference(&even, &ends_b);
+
+        for w in ["", "aa", "b", "ab", "cb", "aab", "abab", "bbba"] {
+            let (p, q) = (even.accepts(w), ends_b.accepts(w));
+            assert_eq!(or.accepts(w), p || q, "{:?}", w);
+            assert_eq!(minus.accepts(w), p && !q, "{:?}", w);
+            assert_eq!(xor.accepts(w), p != q, "{:?}", w);
+        }
+        assert!(!or.accepts("c") && !or.accepts("d"));
+    }
+
+    #[test]
+    fn symmetric_difference_finds_witnesses() {
+        use crate::decisions::{emptiness, equivalence, Emptiness, Equivalence};
+
+        let (a, b) = (dfa("(a+b)*abb"), dfa("(a+b)*bb"));
+        let Ok(Emptiness::NonEmpty { witness }) = emptiness(&symmetric_difference(&a, &b)) else {
+            panic!("the languages differ");
+        };
+        assert_eq!(witness, "bb");
+        assert!(matches!(
+            equivalence(&a, &b),
+            Ok(Equivalence::Distinguished { witness, .. }) if witness == "bb"
+        ));
+
+        // Equivalent machines, differently built
+        let nfa_built = full_pipeline("(a+b)*abb").unwrap().dfa;
+        assert_eq!(
+            emptiness(&symmetric_difference(&a, &nfa_built)),
+            Ok(Emptiness::Empty)
+        );
+        assert_eq!(emptiness(&difference(&a, &a)), Ok(Emptiness::Empty));
+    }
+
+    #[test]
+    fn ignoring_ascii_case() {
+        let d = ignore_ascii_case(&dfa("(a+B)*c"));
+        for w in ["c", "C", "AbC", "bBac"] {
+            assert!(d.accepts(w), "{:?}", w);
+        }
+        assert!(!d.accepts("ab") && !d.accepts("d"));
+        for map in d.transitions.values() {
+            assert_eq!(map.get(&'a'), map.get(&'A'));
+        }
+    }
+}
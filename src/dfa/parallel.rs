@@ -0,0 +1,47 @@
+// dfa/parallel.rs
+//
+// Matching many inputs on all cores. The table is shared read-only, so
+// every worker runs whole inputs on its own with no coordination.
+use super::compiled::CompiledDFA;
+use rayon::prelude::*;
+
+impl CompiledDFA {
+    /// Whether each input is accepted, in order, spread over rayon's
+    /// thread pool. Same answers as calling `accepts` on each input; worth
+    /// it for many inputs or long ones. Verdicts are recorded only on the
+    /// recording thread, so `testing::corpus` sees none from the workers.
+    pub fn accepts_par(&self, inputs: &[&str]) -> Vec<bool> {
+        inputs.par_iter().map(|input| self.accepts(input)).collect()
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn parallel_matches_single() {
+        let dfa = full_pipeline("(a+b)*abb+é[0-9]*").unwrap().mindfa;
+        let compiled = CompiledDFA::from_dfa(&dfa);
+
+        let words: Vec<String> = (0..1000)
+            .map(|i| match i % 4 {
+                0 => "ab".repeat(i % 7) + "b",
+                1 => format!("é{}", i),
+                2 => "a".repeat(i % 5) + "abb",
+                _ => "x".repeat(i % 3),
+            })
+            .collect();
+        let inputs: Vec<&str> = words.iter().map(String::as_str).collect();
+        let expected: Vec<bool> = inputs.iter().map(|w| dfa.accepts(w)).collect();
+        assert_eq!(compiled.accepts_par(&inputs), expected);
+        assert!(compiled.accepts_par(&[]).is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+// dfa/profile.rs
+//
+// Instrumented matching over a compiled DFA: run a workload through it and
+// count how often each state is entered and each transition taken, to see
+// which paths dominate on real inputs. `utils::dot::profile_to_dot` draws
+// the counts as a heat map.
+use super::compiled::CompiledDFA;
+use std::fmt;
+
+/// Counts gathered by a `Profiler`, in the compiled DFA's numbering:
+/// states are rows, symbols are columns (the last one for chars outside
+/// the alphabet), and the dead state is the last row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    alphabet: Vec<char>,
+    stride: usize,
+    // Entries into each state, the start state once per input
+    visits: Vec<u64>,
+    // Times each table cell was taken, row-major like the table
+    taken: Vec<u64>,
+    inputs: u64,
+    accepted: u64,
+}
+
+impl Profile {
+    fn new(dfa: &CompiledDFA) -> Self {
+        let stride = dfa.stride() as usize;
+        Profile {
+            alphabet: dfa.alphabet(),
+            stride,
+            visits: vec![0; dfa.num_states()],
+            taken: vec![0; dfa.num_states() * stride],
+            inputs: 0,
+            accepted: 0,
+        }
+    }
+
+    pub fn inputs(&self) -> u64 {
+        self.inputs
+    }
+
+    pub fn accepted(&self) -> u64 {
+        self.accepted
+    }
+
+    /// Transitions taken in total, including those into the dead state.
+    pub fn steps(&self) -> u64 {
+        self.taken.iter().sum()
+    }
+
+    /// Times `state` was entered. For the dead state, the number of inputs
+    /// rejected before their end.
+    pub fn visits(&self, state: u32) -> u64 {
+        self.visits[state as usize]
+    }
+
+    /// Times the transition out of `state` on `column` was taken.
+    pub fn taken(&self, state: u32, column: u32) -> u64 {
+        self.taken[state as usize * self.stride + column as usize]
+    }
+
+    /// The symbol of a column; None for the column of chars outside the
+    /// alphabet.
+    pub fn symbol(&self, column: u32) -> Option<char> {
+        self.alphabet.get(column as usize).copied()
+    }
+
+    /// Transitions taken on each column, summed over states.
+    pub fn per_symbol(&self) -> Vec<u64> {
+        let mut counts = vec![0; self.stride];
+        for (cell, &n) in self.taken.iter().enumerate() {
+            counts[cell % self.stride] += n;
+        }
+        counts
+    }
+
+    /// The `n` most taken transitions as (state, column, count), most taken
+    /// first, ties by state and column. Transitions never taken are left out.
+    pub fn hottest(&self, n: usize) -> Vec<(u32, u32, u64)> {
+        let mut cells: Vec<(u32, u32, u64)> = self
+            .taken
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(cell, &count)| {
+                let (state, column) = (cell / self.stride, cell % self.stride);
+                (state as u32, column as u32, count)
+            })
+            .collect();
+        cells.sort_by_key(|&(state, column, count)| (std::cmp::Reverse(count), state, column));
+        cells.truncate(n);
+        cells
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} inputs, {} accepted, {} transitions taken",
+            self.inputs,
+            self.accepted,
+            self.steps()
+        )?;
+
+        let symbols: Vec<String> = self
+            .per_symbol()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(column, n)| match self.symbol(column as u32) {
+                Some(c) => format!("{:?} {}", c, n),
+                None => format!("other {}", n),
+            })
+            .collect();
+        writeln!(f, "by symbol: {}", symbols.join(", "))?;
+
+        let states: Vec<String> = self
+            .visits
+            .iter()
+            .enumerate()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(state, n)| format!("{} {}", state, n))
+            .collect();
+        write!(f, "by state: {}", states.join(", "))
+    }
+}
+
+/// A matcher over a compiled DFA that counts as it goes.
+pub struct Profiler<'a> {
+    dfa: &'a CompiledDFA,
+    profile: Profile,
+}
+
+impl<'a> Profiler<'a> {
+    pub fn new(dfa: &'a CompiledDFA) -> Self {
+        Profiler {
+            dfa,
+            profile: Profile::new(dfa),
+        }
+    }
+
+    /// `CompiledDFA::accepts`, counting every state entered and transition
+    /// taken. Stops at the dead state as the uninstrumented matcher does.
+    pub fn accepts(&mut self, input: &str) -> bool {
+        let (dfa, profile) = (self.dfa, &mut self.profile);
+        let stride = profile.stride;
+        let mut state = dfa.start();
+        profile.inputs += 1;
+        profile.visits[state as usize] += 1;
+
+        for c in input.chars() {
+            let cell = state as usize * stride + dfa.class(c) as usize;
+            profile.taken[cell] += 1;
+            state = dfa.table()[cell];
+            profile.visits[state as usize] += 1;
+            if state == dfa.dead() {
+                return false;
+            }
+        }
+
+        let accepted = dfa.is_accepting(state);
+        profile.accepted += accepted as u64;
+        accepted
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn into_profile(self) -> Profile {
+        self.profile
+    }
+}
+
+impl CompiledDFA {
+    /// Run every input of `workload` through a `Profiler`.
+    pub fn profile<'s>(&self, workload: impl IntoIterator<Item = &'s str>) -> Profile {
+        let mut profiler = Profiler::new(self);
+        for input in workload {
+            profiler.accepts(input);
+        }
+        profiler.into_profile()
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn counts_a_workload() {
+        let dfa = CompiledDFA::from_dfa(&full_pipeline("a*b").unwrap().mindfa);
+        let workload = ["aab", "b", "ab", "ba", "x"];
+        let profile = dfa.profile(workload);
+
+        assert_eq!((profile.inputs(), profile.accepted()), (5, 3));
+        // Every char read, except after "ba" died on its 'a'
+        assert_eq!(profile.steps(), 3 + 1 + 2 + 2 + 1);
+        let start = dfa.start();
+        assert_eq!(profile.visits(start), 5 + 3);
+        assert_eq!(profile.visits(dfa.dead()), 2);
+
+        let [a, b] = [dfa.class('a'), dfa.class('b')];
+        assert_eq!(profile.taken(start, a), 3);
+        assert_eq!(profile.taken(start, b), 4);
+        assert_eq!(profile.per_symbol(), [4, 4, 1]);
+        assert_eq!(profile.symbol(2), None);
+        assert_eq!(profile.hottest(1), [(start, b, 4)]);
+
+        // Instrumented matching agrees with the plain matcher
+        let mut profiler = Profiler::new(&dfa);
+        for word in workload {
+            assert_eq!(profiler.accepts(word), dfa.accepts(word));
+        }
+        assert_eq!(*profiler.profile(), profile);
+    }
+
+    #[test]
+    fn report() {
+        let dfa = CompiledDFA::from_dfa(&full_pipeline("ab").unwrap().mindfa);
+        let profile = dfa.profile(["ab", "ab", "b"]);
+        let report = profile.to_string();
+        assert!(report.starts_with("3 inputs, 2 accepted, 5 transitions taken\n"));
+        assert!(report.contains("by symbol: 'a' 2, 'b' 3\n"));
+    }
+}
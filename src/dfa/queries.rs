@@ -0,0 +1,150 @@
+// dfa/queries.rs
+//
+// Questions about the factors (contiguous substrings) of accepted words,
+// e.g. "does every message contain the handshake?" or "can a session ever
+// send two ACKs in a row?". Each is a product or inclusion check against
+// the factor closure Σ*·F·Σ*, where F is one word or a whole sub-language.
+use super::dfa::{nfa_to_dfa, DFA};
+use super::ops::{intersect, trim};
+use crate::decisions::{emptiness, inclusion, Emptiness, Inclusion};
+use crate::nfa::epsilon_elimination::remove_epsilon;
+use crate::nfa::nfa::{TransitionLabel, NFA};
+use crate::types::StateId;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Does every word `dfa` accepts contain `word` as a factor? True for the
+/// empty language, and for every language when `word` is empty.
+pub fn contains_factor(dfa: &DFA, word: &str) -> bool {
+    contains_factor_in(dfa, &word_dfa(word))
+}
+
+/// Does no word `dfa` accepts contain `word` as a factor?
+pub fn avoids_factor(dfa: &DFA, word: &str) -> bool {
+    avoids_factor_in(dfa, &word_dfa(word))
+}
+
+/// `contains_factor` for a language of factors: does every accepted word
+/// have some factor in L(`factors`)?
+pub fn contains_factor_in(dfa: &DFA, factors: &DFA) -> bool {
+    let closure = factor_closure(factors, &dfa.alphabet());
+    inclusion(dfa, &closure) == Ok(Inclusion::Included)
+}
+
+/// `avoids_factor` for a language of factors: is no factor of any accepted
+/// word in L(`factors`)?
+pub fn avoids_factor_in(dfa: &DFA, factors: &DFA) -> bool {
+    let closure = factor_closure(factors, &dfa.alphabet());
+    emptiness(&intersect(dfa, &closure)) == Ok(Emptiness::Empty)
+}
+
+/// Σ*·L(`factors`)·Σ*, the words with a factor in L(`factors`), where Σ is
+/// `alphabet` together with the symbols of `factors`. Pass it to
+/// `decisions::inclusion` or `emptiness` for a witness behind the answers
+/// above.
+pub fn factor_closure(factors: &DFA, alphabet: &BTreeSet<char>) -> DFA {
+    let factors = trim(factors);
+    let sigma: BTreeSet<char> = alphabet | &factors.alphabet();
+
+    // 0 reads anything before the factor, 1 anything after it; the
+    // factor's own states are shifted past them
+    let mut nfa = NFA::new();
+    let (before, after) = (nfa.add_state(), nfa.add_state());
+    for &c in &sigma {
+        nfa.add_edge_auto(before, TransitionLabel::Char(c), before);
+        nfa.add_edge_auto(after, TransitionLabel::Char(c), after);
+    }
+    let shift = |s: StateId| StateId(s.0 + 2);
+    nfa.transitions.entry(shift(factors.start)).or_default();
+    for (&s, map) in &factors.transitions {
+        for (&c, &t) in map {
+            nfa.add_edge_auto(shift(s), TransitionLabel::Char(c), shift(t));
+        }
+    }
+    if !factors.accepts.is_empty() {
+        nfa.add_edge_auto(before, TransitionLabel::Epsilon, shift(factors.start));
+    }
+    for &a in &factors.accepts {
+        nfa.add_edge_auto(shift(a), TransitionLabel::Epsilon, after);
+    }
+    nfa.start = before;
+    nfa.accept = vec![after];
+
+    nfa_to_dfa(&remove_epsilon(&nfa))
+}
+
+// The DFA accepting exactly `word`: a chain of states 0, 1, ...
+fn word_dfa(word: &str) -> DFA {
+    let mut transitions = HashMap::new();
+    let mut len = 0;
+    for (i, c) in word.chars().enumerate() {
+        transitions.insert(StateId::new(i), HashMap::from([(c, StateId::new(i + 1))]));
+        len = i + 1;
+    }
+    DFA {
+        start: StateId(0),
+        accepts: HashSet::from([StateId::new(len)]),
+        transitions,
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn dfa(pattern: &str) -> DFA {
+        full_pipeline(pattern).unwrap().mindfa
+    }
+
+    #[test]
+    fn single_factors() {
+        // Every word ends in abb
+        let abb = dfa("(a+b)*abb");
+        assert!(contains_factor(&abb, "abb"));
+        assert!(contains_factor(&abb, "bb"));
+        assert!(!contains_factor(&abb, "aa"));
+        assert!(!avoids_factor(&abb, "aa"));
+        assert!(avoids_factor(&abb, "c"));
+        assert!(contains_factor(&abb, ""));
+
+        // No two b's in a row
+        let no_bb = dfa("(a+ba)*(b+ε)");
+        assert!(avoids_factor(&no_bb, "bb"));
+        assert!(!avoids_factor(&no_bb, "ba"));
+        assert!(!contains_factor(&no_bb, "a"));
+
+        // The empty language contains and avoids everything
+        let empty = dfa("∅");
+        assert!(contains_factor(&empty, "x") && avoids_factor(&empty, "x"));
+        assert!(!avoids_factor(&dfa("ε"), ""));
+    }
+
+    #[test]
+    fn factors_from_a_language() {
+        // Sessions that open with SYN and end with FIN: every one has a
+        // factor in SA*+F, none has FF or FS
+        let sessions = dfa("S(A+D)*F");
+        assert!(contains_factor_in(&sessions, &dfa("S(A+D)*F")));
+        assert!(contains_factor_in(&sessions, &dfa("SA*+F")));
+        assert!(!contains_factor_in(&sessions, &dfa("AD")));
+        assert!(avoids_factor_in(&sessions, &dfa("FF+FS")));
+        assert!(!avoids_factor_in(&sessions, &dfa("D{2}")));
+
+        // The closure itself, for witnesses
+        let closure = factor_closure(&dfa("ab"), &BTreeSet::from(['c']));
+        assert!(closure.accepts("cabc") && closure.accepts("ab"));
+        assert!(!closure.accepts("acb"));
+        assert_eq!(
+            inclusion(&sessions, &factor_closure(&dfa("AD"), &sessions.alphabet())),
+            Ok(Inclusion::NotIncluded {
+                witness: "SF".to_string()
+            })
+        );
+    }
+}
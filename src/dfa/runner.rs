@@ -0,0 +1,149 @@
+// dfa/runner.rs
+//
+// Membership over chunked input: the runner keeps the current state
+// between chunks, so text read from a socket or reader can be matched as
+// it arrives, without concatenating buffers.
+use super::dfa::DFA;
+use super::ops::co_reachable;
+use crate::types::{Letter, StateId};
+use std::collections::HashSet;
+
+/// Where a `DfaRunner` stands after the input fed so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    // The input so far is accepted
+    Accepting,
+    // Not accepted, but some continuation is
+    Pending,
+    // No continuation is accepted; further input is skipped
+    Dead,
+}
+
+/// A matcher fed one chunk at a time. Feeding "ab" then "c" ends in the
+/// same status as feeding "abc".
+pub struct DfaRunner<'a, S = char> {
+    dfa: &'a DFA<S>,
+    // States that can still reach an accepting one
+    live: HashSet<StateId>,
+    // None once dead
+    state: Option<StateId>,
+}
+
+impl<'a, S: Letter> DfaRunner<'a, S> {
+    pub fn new(dfa: &'a DFA<S>) -> Self {
+        let live = co_reachable(dfa);
+        let state = Some(dfa.start).filter(|s| live.contains(s));
+        DfaRunner { dfa, live, state }
+    }
+
+    /// Back to the start state, as if nothing had been fed.
+    pub fn reset(&mut self) {
+        self.state = Some(self.dfa.start).filter(|s| self.live.contains(s));
+    }
+
+    /// Consume one letter.
+    pub fn step(&mut self, letter: &S) -> Status {
+        if let Some(s) = self.state {
+            self.state = self
+                .dfa
+                .transitions
+                .get(&s)
+                .and_then(|m| m.get(letter))
+                .copied()
+                .filter(|t| self.live.contains(t));
+        }
+        self.status()
+    }
+
+    /// Consume a chunk of letters, stopping early once dead.
+    pub fn feed_letters(&mut self, chunk: impl IntoIterator<Item = S>) -> Status {
+        for letter in chunk {
+            if self.step(&letter) == Status::Dead {
+                break;
+            }
+        }
+        self.status()
+    }
+
+    pub fn status(&self) -> Status {
+        match self.state {
+            None => Status::Dead,
+            Some(s) if self.dfa.accepts.contains(&s) => Status::Accepting,
+            Some(_) => Status::Pending,
+        }
+    }
+
+    pub fn is_accepting(&self) -> bool {
+        self.status() == Status::Accepting
+    }
+
+    /// The current state, or None once dead.
+    pub fn state(&self) -> Option<StateId> {
+        self.state
+    }
+}
+
+impl DfaRunner<'_> {
+    pub fn feed(&mut self, chunk: &str) -> Status {
+        self.feed_letters(chunk.chars())
+    }
+}
+
+impl DFA {
+    pub fn runner(&self) -> DfaRunner<'_> {
+        DfaRunner::new(self)
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn chunks_agree_with_whole_input() {
+        let dfa = full_pipeline("(a+b)*abb").unwrap().dfa;
+        let text = "babaabbab";
+        for split in 0..=text.len() {
+            let mut runner = dfa.runner();
+            runner.feed(&text[..split]);
+            runner.feed(&text[split..]);
+            assert_eq!(runner.is_accepting(), dfa.accepts(text));
+        }
+
+        let mut runner = dfa.runner();
+        assert_eq!(runner.feed("ba"), Status::Pending);
+        assert_eq!(runner.feed("bb"), Status::Accepting);
+        assert_eq!(runner.feed(""), Status::Accepting);
+        assert_eq!(runner.feed("a"), Status::Pending);
+    }
+
+    #[test]
+    fn dead_is_final_until_reset() {
+        let dfa = full_pipeline("ab*").unwrap().mindfa;
+        let mut runner = dfa.runner();
+        assert_eq!(runner.feed("ab"), Status::Accepting);
+        assert_eq!(runner.feed("ba"), Status::Dead);
+        assert_eq!(runner.feed("b"), Status::Dead);
+        assert_eq!(runner.state(), None);
+
+        runner.reset();
+        assert_eq!(runner.feed("a"), Status::Accepting);
+
+        // Dead from the start when nothing is accepted
+        let empty = full_pipeline("∅").unwrap().mindfa;
+        assert_eq!(empty.runner().status(), Status::Dead);
+
+        // Over bytes, one at a time
+        let bytes = dfa.to_byte_dfa(Default::default());
+        let mut runner = DfaRunner::new(&bytes);
+        assert_eq!(runner.step(&b'a'), Status::Accepting);
+        assert_eq!(runner.feed_letters(*b"bbc"), Status::Dead);
+    }
+}
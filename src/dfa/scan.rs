@@ -0,0 +1,166 @@
+// dfa/scan.rs
+use super::compiled::CompiledDFA;
+use super::dense::DenseDFA;
+
+// The live states of every match started so far, at most one per state.
+struct Frontier {
+    active: Vec<usize>,
+    next: Vec<usize>,
+    // Membership in `next`, indexed by state
+    seen: Vec<bool>,
+}
+
+impl Frontier {
+    fn new(num_states: usize) -> Self {
+        Self {
+            active: Vec::new(),
+            next: Vec::new(),
+            seen: vec![false; num_states],
+        }
+    }
+
+    // Start a match here, then move every live state along `delta`.
+    fn step(&mut self, start: usize, dead: usize, delta: impl Fn(usize) -> usize) {
+        self.active.push(start);
+
+        self.next.clear();
+        for &s in &self.active {
+            let t = delta(s);
+            if t != dead && !self.seen[t] {
+                self.seen[t] = true;
+                self.next.push(t);
+            }
+        }
+        for &t in &self.next {
+            self.seen[t] = false;
+        }
+        std::mem::swap(&mut self.active, &mut self.next);
+    }
+}
+
+/// Streaming search for matches anywhere in a text. Fed one char at a time,
+/// it reports whether some non-empty match ends at that char, keeping one
+/// DFA state per distinct match start still alive (at most one per state).
+pub struct Scanner<'a> {
+    dfa: &'a CompiledDFA,
+    frontier: Frontier,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(dfa: &'a CompiledDFA) -> Self {
+        Self {
+            dfa,
+            frontier: Frontier::new(dfa.num_states()),
+        }
+    }
+
+    /// Forget all partial matches.
+    pub fn reset(&mut self) {
+        self.frontier.active.clear();
+    }
+
+    /// Consume `c`; true if a match ends right after it.
+    pub fn step(&mut self, c: char) -> bool {
+        let dfa = self.dfa;
+        self.frontier
+            .step(dfa.start() as usize, dfa.dead() as usize, |s| {
+                dfa.next_state(s as u32, c) as usize
+            });
+        self.frontier
+            .active
+            .iter()
+            .any(|&s| dfa.is_accepting(s as u32))
+    }
+
+    /// Char offsets just past each match end in `text`.
+    pub fn match_ends(&mut self, text: &str) -> Vec<usize> {
+        text.chars()
+            .enumerate()
+            .filter_map(|(i, c)| self.step(c).then_some(i + 1))
+            .collect()
+    }
+}
+
+/// `Scanner` over bytes, for input that may not be UTF-8. Build the table
+/// with `DFA::to_byte_dfa`; every byte is fed as is, `\r` included.
+pub struct ByteScanner<'a> {
+    dfa: &'a DenseDFA<u8>,
+    frontier: Frontier,
+}
+
+impl<'a> ByteScanner<'a> {
+    pub fn new(dfa: &'a DenseDFA<u8>) -> Self {
+        Self {
+            dfa,
+            frontier: Frontier::new(dfa.num_states()),
+        }
+    }
+
+    /// Forget all partial matches.
+    pub fn reset(&mut self) {
+        self.frontier.active.clear();
+    }
+
+    /// Consume `b`; true if a match ends right after it.
+    pub fn step(&mut self, b: u8) -> bool {
+        let dfa = self.dfa;
+        self.frontier
+            .step(dfa.start(), dfa.dead(), |s| dfa.next_state(s, &b));
+        self.frontier.active.iter().any(|&s| dfa.is_accepting(s))
+    }
+
+    /// Byte offsets just past each match end in `bytes`.
+    pub fn match_ends(&mut self, bytes: &[u8]) -> Vec<usize> {
+        (0..bytes.len())
+            .filter(|&i| self.step(bytes[i]))
+            .map(|i| i + 1)
+            .collect()
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::bytes::Encoding;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn ends(lang: &str, text: &str) -> Vec<usize> {
+        let dfa = CompiledDFA::from_dfa(&full_pipeline(lang).unwrap().mindfa);
+        Scanner::new(&dfa).match_ends(text)
+    }
+
+    #[test]
+    fn overlapping_and_foreign_chars() {
+        assert_eq!(ends("aba", "xabababx"), vec![4, 6]);
+        assert_eq!(ends("ab*", "abbxa"), vec![1, 2, 3, 5]);
+        assert_eq!(ends("é[0-9][0-9]*", "é1 é23"), vec![2, 5, 6]);
+        assert!(ends("abc", "ab ab").is_empty());
+    }
+
+    #[test]
+    fn reset_drops_partial_matches() {
+        let dfa = CompiledDFA::from_dfa(&full_pipeline("ab").unwrap().mindfa);
+        let mut s = Scanner::new(&dfa);
+        assert!(!s.step('a'));
+        s.reset();
+        assert!(!s.step('b'));
+        assert!(!s.step('a'));
+        assert!(s.step('b'));
+    }
+
+    #[test]
+    fn bytes_past_invalid_utf8() {
+        let dfa = full_pipeline("é[0-9]*\\u{d}").unwrap().mindfa;
+        let bytes = DenseDFA::from(&dfa.to_byte_dfa(Encoding::Utf8));
+        let mut s = ByteScanner::new(&bytes);
+        let text = [&[0xFF, 0xC3][..], "é1\r\né\r".as_bytes()].concat();
+        // é is two bytes, and the stray 0xC3 doesn't start one
+        assert_eq!(s.match_ends(&text), vec![6, 10]);
+    }
+}
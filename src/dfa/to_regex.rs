@@ -0,0 +1,241 @@
+// dfa/to_regex.rs
+//
+// Automaton → regex by state elimination: turn the machine into a
+// generalized NFA (GNFA) with regex-labelled edges, a fresh start and a
+// fresh final state, then remove the original states one at a time,
+// replacing every path p → q → r with an edge labelled
+// R(p,q) R(q,q)* R(q,r). Labels are simplified as they are built, and
+// states with the fewest in × out edges go first, which keeps the result
+// far smaller than a fixed order.
+use super::dfa::DFA;
+use super::ops::trim;
+use crate::nfa::nfa::{TransitionLabel, NFA};
+use crate::regex::ast::RegexAST;
+use crate::regex::metrics::metrics;
+use crate::regex::simplify::{concat, simplify, star, union};
+use crate::types::StateId;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An expression for the language of `dfa`.
+pub fn dfa_to_regex(dfa: &DFA) -> RegexAST {
+    dfa_to_regex_bounded(dfa, usize::MAX).expect("no size limit")
+}
+
+/// Like `dfa_to_regex`, giving up with `None` as soon as an intermediate
+/// expression has more than `max_nodes` AST nodes.
+pub fn dfa_to_regex_bounded(dfa: &DFA, max_nodes: usize) -> Option<RegexAST> {
+    let dfa = trim(dfa);
+    let mut symbols: BTreeMap<(StateId, StateId), BTreeSet<char>> = BTreeMap::new();
+    for (&from, map) in &dfa.transitions {
+        for (&c, &to) in map {
+            symbols.entry((from, to)).or_default().insert(c);
+        }
+    }
+    let edges = symbols.into_iter().map(|(k, cs)| (k, class(cs))).collect();
+
+    let accepts: Vec<StateId> = dfa.accepts.iter().copied().collect();
+    eliminate(dfa.states(), edges, &[dfa.start], &accepts, max_nodes)
+}
+
+/// An expression for the language of `nfa`. ε-edges and several start
+/// states are fine.
+pub fn nfa_to_regex(nfa: &NFA) -> RegexAST {
+    nfa_to_regex_bounded(nfa, usize::MAX).expect("no size limit")
+}
+
+/// Like `nfa_to_regex`, with the size limit of `dfa_to_regex_bounded`.
+pub fn nfa_to_regex_bounded(nfa: &NFA, max_nodes: usize) -> Option<RegexAST> {
+    let mut states: BTreeSet<StateId> = nfa.transitions.keys().copied().collect();
+    states.extend(nfa.starts());
+    states.extend(nfa.accept.iter().copied());
+
+    // Symbols and whether there is an ε-edge, per pair of states
+    let mut labels: BTreeMap<(StateId, StateId), (BTreeSet<char>, bool)> = BTreeMap::new();
+    for (&from, edges) in &nfa.transitions {
+        for (label, to) in edges {
+            states.insert(*to);
+            let entry = labels.entry((from, *to)).or_default();
+            match label {
+                TransitionLabel::Char(c) => {
+                    entry.0.insert(*c);
+                }
+                TransitionLabel::Epsilon => entry.1 = true,
+            }
+        }
+    }
+    let edges = labels
+        .into_iter()
+        .map(|(k, (cs, epsilon))| {
+            let chars = if cs.is_empty() {
+                RegexAST::Empty
+            } else {
+                class(cs)
+            };
+            let label = if epsilon {
+                union(RegexAST::Epsilon, chars)
+            } else {
+                chars
+            };
+            (k, label)
+        })
+        .collect();
+
+    eliminate(states, edges, &nfa.starts(), &nfa.accept, max_nodes)
+}
+
+fn class(chars: BTreeSet<char>) -> RegexAST {
+    simplify(&RegexAST::Class(chars.into_iter().map(|c| c..=c).collect()))
+}
+
+// Run state elimination on the GNFA over `states` with `edges`, joined to
+// a fresh start and final state by ε-edges.
+fn eliminate(
+    mut remaining: BTreeSet<StateId>,
+    mut edges: BTreeMap<(StateId, StateId), RegexAST>,
+    starts: &[StateId],
+    accepts: &[StateId],
+    max_nodes: usize,
+) -> Option<RegexAST> {
+    let start = remaining.last().map_or(StateId(0), |m| m.next());
+    let end = start.next();
+    for &s in starts {
+        edges.insert((start, s), RegexAST::Epsilon);
+    }
+    for &a in accepts {
+        edges.insert((a, end), RegexAST::Epsilon);
+    }
+
+    while let Some(q) = cheapest(&remaining, &edges) {
+        remaining.remove(&q);
+        let self_loop = star(edges.remove(&(q, q)).unwrap_or(RegexAST::Empty));
+        let ins: Vec<(StateId, RegexAST)> = take(&mut edges, |&(_, to)| to == q)
+            .into_iter()
+            .map(|((from, _), e)| (from, e))
+            .collect();
+        let outs: Vec<(StateId, RegexAST)> = take(&mut edges, |&(from, _)| from == q)
+            .into_iter()
+            .map(|((_, to), e)| (to, e))
+            .collect();
+
+        for (p, into) in &ins {
+            for (r, out) in &outs {
+                let path = concat(concat(into.clone(), self_loop.clone()), out.clone());
+                let label = match edges.remove(&(*p, *r)) {
+                    Some(old) => union(old, path),
+                    None => path,
+                };
+                if metrics(&label).node_count > max_nodes {
+                    return None;
+                }
+                edges.insert((*p, *r), label);
+            }
+        }
+    }
+
+    Some(edges.remove(&(start, end)).unwrap_or(RegexAST::Empty))
+}
+
+// The state whose removal creates the fewest new edges; ties go to the
+// lowest id so the output is deterministic.
+fn cheapest(
+    remaining: &BTreeSet<StateId>,
+    edges: &BTreeMap<(StateId, StateId), RegexAST>,
+) -> Option<StateId> {
+    remaining.iter().copied().min_by_key(|&q| {
+        let ins = edges.keys().filter(|&&(f, t)| t == q && f != q).count();
+        let outs = edges.keys().filter(|&&(f, t)| f == q && t != q).count();
+        ins * outs
+    })
+}
+
+type Edges = Vec<((StateId, StateId), RegexAST)>;
+
+// Remove and return the edges whose endpoints satisfy `pred`.
+fn take(
+    edges: &mut BTreeMap<(StateId, StateId), RegexAST>,
+    pred: impl Fn(&(StateId, StateId)) -> bool,
+) -> Edges {
+    let keys: Vec<(StateId, StateId)> = edges.keys().filter(|k| pred(k)).copied().collect();
+    keys.into_iter()
+        .map(|k| (k, edges.remove(&k).unwrap()))
+        .collect()
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+    use crate::regex::equivalent;
+
+    fn round_trip(pattern: &str) -> String {
+        dfa_to_regex(&full_pipeline(pattern).unwrap().mindfa).to_pattern()
+    }
+
+    #[test]
+    fn small_machines_give_small_expressions() {
+        assert_eq!(round_trip("abc"), "abc");
+        assert_eq!(round_trip("a*"), "a*");
+        assert_eq!(round_trip("a+b"), "[ab]");
+        assert_eq!(round_trip("∅"), "∅");
+        assert_eq!(round_trip("ε"), "ε");
+    }
+
+    #[test]
+    fn same_language_back() {
+        for pattern in [
+            "(a+b)*abb",
+            "(ab+ba)*",
+            "a(b+c)*d?",
+            "(a+b)*a(a+b){2}",
+            "!(a*b)&(a+b)*",
+            "(0+1(01*0)*1)*",
+        ] {
+            let out = round_trip(pattern);
+            assert_eq!(
+                equivalent(pattern, &out),
+                Ok(true),
+                "{} -> {}",
+                pattern,
+                out
+            );
+        }
+    }
+
+    #[test]
+    fn from_nfas() {
+        let out = full_pipeline("(a+b)*abb").unwrap();
+        for nfa in [&out.enfa, &out.nfa, &out.nfa.reverse().reverse()] {
+            let pattern = nfa_to_regex(nfa).to_pattern();
+            assert_eq!(equivalent("(a+b)*abb", &pattern), Ok(true), "{}", pattern);
+        }
+
+        // ε-edges alongside symbols, and two start states
+        let mut nfa = NFA::new();
+        nfa.add_edge_auto(StateId(0), TransitionLabel::Epsilon, StateId(1));
+        nfa.add_edge_auto(StateId(0), TransitionLabel::Char('a'), StateId(1));
+        nfa.add_edge_auto(StateId(2), TransitionLabel::Char('b'), StateId(1));
+        nfa.accept = vec![StateId(1)];
+        let nfa = nfa.with_starts([StateId(0), StateId(2)]);
+        let pattern = nfa_to_regex(&nfa).to_pattern();
+        assert_eq!(equivalent("ε+a+b", &pattern), Ok(true), "{}", pattern);
+
+        assert_eq!(
+            nfa_to_regex(&full_pipeline("∅").unwrap().enfa).to_pattern(),
+            "∅"
+        );
+        assert!(nfa_to_regex_bounded(&out.enfa, 3).is_none());
+    }
+
+    #[test]
+    fn size_bound() {
+        let dfa = full_pipeline("(a+b)*a(a+b){3}").unwrap().mindfa;
+        assert!(dfa_to_regex_bounded(&dfa, 10).is_none());
+        assert!(dfa_to_regex_bounded(&dfa, 100_000).is_some());
+    }
+}
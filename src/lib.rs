@@ -0,0 +1,11 @@
+// lib.rs
+pub mod decisions;
+pub mod dfa;
+pub mod nfa;
+pub mod pipeline;
+pub mod regex;
+pub mod symbols;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod types;
+pub mod utils;
@@ -1,15 +1,381 @@
 // main.rs
-pub mod dfa;
-pub mod nfa;
-pub mod pipeline;
-pub mod regex;
-pub mod types;
-pub mod utils;
+use kleeners::dfa::bytes::Encoding;
+use kleeners::dfa::dense::DenseDFA;
+use kleeners::dfa::minimize::{minimize_dfa, minimize_dfa_traced};
+use kleeners::dfa::scan::ByteScanner;
+use kleeners::nfa::renumber::StateOrder;
+use kleeners::pipeline::builder::PipelineBuilder;
+use kleeners::pipeline::tests::full_pipeline;
+use kleeners::testing::corpus;
+use kleeners::types::Automaton;
+use kleeners::utils::doctor::diagnose;
+use kleeners::utils::dot::{
+    dfa_to_dot, dfa_to_dot_ordered, minimization_to_dot, nfa_to_dot, nfa_to_dot_ordered,
+    subsets_to_dot,
+};
+use kleeners::utils::edge_list::parse_edge_list;
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::pipeline::tests::full_pipeline;
-use crate::utils::dot::{dfa_to_dot, nfa_to_dot};
+// Line and byte column of each match end in a file, or why it couldn't be
+// read
+type ScanResult = Result<Vec<(usize, usize)>, String>;
 
-fn main() {
+const USAGE: &str =
+    "Usage: kleeners [doctor --in machine.kfa | scan <regex> <paths...> [--jobs N] \
+     | compile <regex> [--preset teaching|fast|embedded] [--ignore-case] [--out table.bin] \
+     [--rust matcher.rs] \
+     | replay corpus.jsonl]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("doctor") => doctor(&args[1..]),
+        Some("scan") => scan(&args[1..]),
+        Some("compile") => compile(&args[1..]),
+        Some("replay") => replay(&args[1..]),
+        Some(other) => {
+            eprintln!("Unknown command: {}", other);
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+        None => {
+            demo();
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+// `kleeners doctor --in machine.kfa`: run every validator on a machine
+// saved as `Automaton` JSON, or typed in as an edge list, and print a
+// health report.
+fn doctor(args: &[String]) -> ExitCode {
+    let path = match args {
+        [flag, path] if flag == "--in" => path,
+        _ => {
+            eprintln!("Usage: kleeners doctor --in machine.kfa");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let machine: Automaton = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| {
+            if s.trim_start().starts_with('{') {
+                serde_json::from_str(&s).map_err(|e| e.to_string())
+            } else {
+                parse_edge_list(&s)
+            }
+        }) {
+        Ok(machine) => machine,
+        Err(e) => {
+            eprintln!("Error: can't load {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let report = diagnose(&machine);
+    println!("{}", report);
+    if report.is_healthy() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// `kleeners scan <regex> <paths...> --jobs N`: report where matches of the
+// regex end in every file under `paths`, scanning files in parallel. Files
+// are matched as bytes, so they needn't be UTF-8. Fails if any file can't
+// be read.
+fn scan(args: &[String]) -> ExitCode {
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--jobs" {
+            match iter.next().and_then(|n| n.parse().ok()).filter(|&n| n > 0) {
+                Some(n) => jobs = n,
+                None => {
+                    eprintln!("--jobs needs a positive number");
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else {
+            positional.push(arg);
+        }
+    }
+    let [regex, paths @ ..] = positional.as_slice() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    let dfa = match full_pipeline(regex) {
+        Ok(out) => DenseDFA::from(&minimize_dfa(&out.mindfa.to_byte_dfa(Encoding::Utf8))),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    for path in paths {
+        collect_files(Path::new(path), &mut visited, &mut files);
+    }
+
+    // Workers take the next unscanned file; results keep the input order
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<ScanResult>> = vec![None; files.len()];
+    std::thread::scope(|s| {
+        let workers: Vec<_> = (0..jobs.min(files.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(file) = files.get(i) else {
+                            return done;
+                        };
+                        done.push((i, scan_file(&dfa, file)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (i, result) in worker.join().expect("scan worker panicked") {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    let (mut total, mut matched, mut failed) = (0, 0, 0);
+    for (file, result) in files.iter().zip(results) {
+        match result.expect("every file is scanned") {
+            Ok(ends) if ends.is_empty() => {}
+            Ok(ends) => {
+                let at: Vec<String> = ends.iter().map(|(l, c)| format!("{}:{}", l, c)).collect();
+                println!(
+                    "{}: {} matches, ending at {}",
+                    file.display(),
+                    ends.len(),
+                    at.join(" ")
+                );
+                total += ends.len();
+                matched += 1;
+            }
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "{} matches in {} of {} files ({} unreadable)",
+        total,
+        matched,
+        files.len(),
+        failed
+    );
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// `kleeners compile <regex> --preset P`: compile with a preset and print
+// what it keeps as DOT; `--out` saves the embedded preset's table and
+// `--rust` writes its byte matcher as a function named after the file.
+fn compile(args: &[String]) -> ExitCode {
+    let mut builder = PipelineBuilder::new();
+    let mut out_path = None;
+    let mut rust_path = None;
+    let mut regex = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--preset" => match iter.next().map(|p| p.parse()) {
+                Some(Ok(p)) => builder = builder.with_preset(p),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+                None => {
+                    eprintln!("--preset needs a name");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--ignore-case" => builder = builder.with_case_insensitive(true),
+            "--out" => match iter.next() {
+                Some(path) => out_path = Some(path),
+                None => {
+                    eprintln!("--out needs a path");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--rust" => match iter.next() {
+                Some(path) => rust_path = Some(path),
+                None => {
+                    eprintln!("--rust needs a path");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ if regex.is_none() => regex = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(regex) = regex else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let out = match builder.compile(regex) {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let (Some(stages), Some(subsets)) = (&out.stages, &out.subsets) {
+        println!("{}", nfa_to_dot(&stages.enfa));
+        // Original ids, so the NFA matches the subsets drawn next
+        println!("{}", nfa_to_dot_ordered(&stages.nfa, StateOrder::Original));
+        println!("{}", subsets_to_dot(&stages.nfa, &stages.dfa, subsets));
+        println!("{}", dfa_to_dot_ordered(&stages.dfa, StateOrder::Original));
+        let (minimized, partitions) = minimize_dfa_traced(&stages.dfa);
+        println!(
+            "{}",
+            minimization_to_dot(&stages.dfa, &minimized, &partitions)
+        );
+    }
+    println!("{}", dfa_to_dot(&out.mindfa));
+
+    match (&out.table, out_path) {
+        (Some(table), Some(path)) => {
+            if let Err(e) = table.save(path) {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+            eprintln!("{} states saved to {}", table.num_states(), path);
+        }
+        (None, Some(_)) => {
+            eprintln!("--out needs the embedded preset");
+            return ExitCode::FAILURE;
+        }
+        _ => {}
+    }
+
+    match (&out.bytes, rust_path) {
+        (Some(bytes), Some(path)) => {
+            let name = Path::new(path)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("");
+            let written = bytes.to_rust(name).and_then(|src| {
+                std::fs::write(path, src).map_err(|e| format!("Write error: {}", e))
+            });
+            if let Err(e) = written {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+            eprintln!("fn {} written to {}", name, path);
+        }
+        (None, Some(_)) => {
+            eprintln!("--rust needs the embedded preset");
+            return ExitCode::FAILURE;
+        }
+        _ => {}
+    }
+    ExitCode::SUCCESS
+}
+
+// `kleeners replay corpus.jsonl`: check verdicts recorded with
+// `testing::corpus::record_to` against the current algorithms.
+fn replay(args: &[String]) -> ExitCode {
+    let [path] = args else {
+        eprintln!("Usage: kleeners replay corpus.jsonl");
+        return ExitCode::FAILURE;
+    };
+
+    match corpus::replay(path) {
+        Ok(report) => {
+            println!("{}", report);
+            if report.passed() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: can't replay {}: {}", path, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Directories are walked once each by their canonical path, so symlinks
+// back up the tree don't loop.
+fn collect_files(path: &Path, visited: &mut HashSet<PathBuf>, out: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        out.push(path.to_path_buf());
+        return;
+    }
+    if let Ok(canonical) = path.canonicalize() {
+        if !visited.insert(canonical) {
+            return;
+        }
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        out.push(path.to_path_buf());
+        return;
+    };
+    let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    children.sort();
+    for child in children {
+        collect_files(&child, visited, out);
+    }
+}
+
+// 1-based line and byte column of the last byte of every match. Line ends
+// are fed as they are, `\r` included.
+fn scan_file(dfa: &DenseDFA<u8>, path: &Path) -> ScanResult {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut reader = std::io::BufReader::new(file);
+    let mut scanner = ByteScanner::new(dfa);
+    let mut ends = Vec::new();
+    let mut line = Vec::new();
+
+    for line_no in 1.. {
+        line.clear();
+        if reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            break;
+        }
+        for (col, &b) in line.iter().enumerate() {
+            if scanner.step(b) {
+                ends.push((line_no, col + 1));
+            }
+        }
+    }
+    Ok(ends)
+}
+
+fn demo() {
     let test_lang = "(b+a(a+ba*b))*a";
 
     println!("--- Pipeline Language: '{}' ---", test_lang);
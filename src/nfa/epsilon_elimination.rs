@@ -1,10 +1,11 @@
 // nfa/epsilon_elimination.rs
 use std::collections::{HashMap, HashSet, VecDeque};
 
-use super::nfa::{StateID, TransitionLabel, NFA};
+use super::nfa::{StateId, TransitionLabel, NFA};
+use crate::types::Letter;
 
 /// Compute epsilon-closure of a single state.
-pub fn epsilon_closure_of_state(nfa: &NFA, s: StateID) -> HashSet<StateID> {
+pub fn epsilon_closure_of_state<S: Letter>(nfa: &NFA<S>, s: StateId) -> HashSet<StateId> {
     let mut visited = HashSet::new();
     let mut dq = VecDeque::new();
 
@@ -26,7 +27,10 @@ pub fn epsilon_closure_of_state(nfa: &NFA, s: StateID) -> HashSet<StateID> {
 }
 
 /// Compute epsilon-closure of a set of states.
-pub fn epsilon_closure_of_set(nfa: &NFA, states: &HashSet<StateID>) -> HashSet<StateID> {
+pub fn epsilon_closure_of_set<S: Letter>(
+    nfa: &NFA<S>,
+    states: &HashSet<StateId>,
+) -> HashSet<StateId> {
     let mut res = HashSet::new();
     let mut dq = VecDeque::new();
 
@@ -50,7 +54,7 @@ pub fn epsilon_closure_of_set(nfa: &NFA, states: &HashSet<StateID>) -> HashSet<S
 }
 
 /// Move: from a set of states, follow `Char(c)` transitions (not epsilon) and return destination set.
-pub fn move_on_char(nfa: &NFA, states: &HashSet<StateID>, c: char) -> HashSet<StateID> {
+pub fn move_on_char<S: Letter>(nfa: &NFA<S>, states: &HashSet<StateId>, c: S) -> HashSet<StateId> {
     let mut res = HashSet::new();
 
     for &s in states {
@@ -68,11 +72,66 @@ pub fn move_on_char(nfa: &NFA, states: &HashSet<StateID>, c: char) -> HashSet<St
     res
 }
 
+/// Which ε-removal algorithm the pipeline runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EpsilonRemoval {
+    // `remove_epsilon`: states are ε-closures, discovered from the start
+    #[default]
+    Closures,
+    // `remove_epsilon_classical`: the original states, with edges rewritten
+    Classical,
+}
+
+/// Run the algorithm `method` names.
+pub fn remove_epsilon_with<S: Letter>(nfa: &NFA<S>, method: EpsilonRemoval) -> NFA<S> {
+    match method {
+        EpsilonRemoval::Closures => remove_epsilon(nfa),
+        EpsilonRemoval::Classical => remove_epsilon_classical(nfa),
+    }
+}
+
+/// The textbook construction: keep every state and shortcut ε-paths.
+/// State `p` gets an edge `p -c-> r` for each `q -c-> r` with `q` in the
+/// ε-closure of `p`, and accepts when its closure holds an accepting
+/// state. Starts and state ids are unchanged; states only reachable over
+/// ε-edges are left in place, unreachable.
+pub fn remove_epsilon_classical<S: Letter>(nfa: &NFA<S>) -> NFA<S> {
+    let mut out = NFA {
+        start: nfa.start,
+        extra_starts: nfa.extra_starts.clone(),
+        accept: Vec::new(),
+        transitions: HashMap::new(),
+    };
+
+    let mut states: Vec<StateId> = nfa.transitions.keys().copied().collect();
+    states.sort_unstable();
+    for p in states {
+        let mut closure: Vec<StateId> = epsilon_closure_of_state(nfa, p).into_iter().collect();
+        closure.sort_unstable();
+
+        let mut edges: Vec<(TransitionLabel<S>, StateId)> = Vec::new();
+        for q in &closure {
+            for (label, r) in nfa.transitions.get(q).into_iter().flatten() {
+                let edge = (label.clone(), *r);
+                if *label != TransitionLabel::Epsilon && !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+        out.transitions.insert(p, edges);
+
+        if closure.iter().any(|q| nfa.accept.contains(q)) {
+            out.accept.push(p);
+        }
+    }
+    out
+}
+
 /// Remove epsilon transitions using subset construction approach.
 /// Creates minimal NFA by treating epsilon-closures as state identities.
-pub fn remove_epsilon(nfa: &NFA) -> NFA {
+pub fn remove_epsilon<S: Letter>(nfa: &NFA<S>) -> NFA<S> {
     // Collect all character symbols present
-    let mut symbols: HashSet<char> = HashSet::new();
+    let mut symbols: HashSet<S> = HashSet::new();
     for edges in nfa.transitions.values() {
         for (label, _) in edges {
             if let TransitionLabel::Char(ch) = label {
@@ -82,33 +141,33 @@ pub fn remove_epsilon(nfa: &NFA) -> NFA {
     }
 
     // State mapping: epsilon-closure -> new state ID
-    let mut state_map: HashMap<Vec<StateID>, StateID> = HashMap::new();
-    let mut next_id = 0;
+    let mut state_map: HashMap<Vec<StateId>, StateId> = HashMap::new();
+    let mut next_id = StateId(0);
 
     // Queue for BFS through reachable closure-sets
-    let mut queue: VecDeque<HashSet<StateID>> = VecDeque::new();
-    let mut new_transitions: HashMap<StateID, Vec<(TransitionLabel, StateID)>> = HashMap::new();
+    let mut queue: VecDeque<HashSet<StateId>> = VecDeque::new();
+    let mut new_transitions: HashMap<StateId, Vec<(TransitionLabel<S>, StateId)>> = HashMap::new();
 
-    // Start with epsilon-closure of original start state
-    let start_closure = epsilon_closure_of_state(nfa, nfa.start);
-    let mut start_closure_sorted: Vec<StateID> = start_closure.iter().copied().collect();
+    // Start with epsilon-closure of the original start states
+    let start_closure = epsilon_closure_of_set(nfa, &nfa.starts().into_iter().collect());
+    let mut start_closure_sorted: Vec<StateId> = start_closure.iter().copied().collect();
     start_closure_sorted.sort_unstable();
 
     let new_start = next_id;
     state_map.insert(start_closure_sorted.clone(), new_start);
     new_transitions.insert(new_start, Vec::new());
-    next_id += 1;
+    next_id = next_id.next();
 
     queue.push_back(start_closure);
 
     // BFS to discover reachable states
     while let Some(current_closure) = queue.pop_front() {
-        let mut current_sorted: Vec<StateID> = current_closure.iter().copied().collect();
+        let mut current_sorted: Vec<StateId> = current_closure.iter().copied().collect();
         current_sorted.sort_unstable();
         let current_id = *state_map.get(&current_sorted).unwrap();
 
         // Track transitions by character to deduplicate
-        let mut transitions_by_char: HashMap<char, HashSet<StateID>> = HashMap::new();
+        let mut transitions_by_char: HashMap<S, HashSet<StateId>> = HashMap::new();
 
         for &c in &symbols {
             // Move on character c from current closure
@@ -119,7 +178,7 @@ pub fn remove_epsilon(nfa: &NFA) -> NFA {
 
             // Compute epsilon-closure of moved states
             let target_closure = epsilon_closure_of_set(nfa, &moved);
-            let mut target_sorted: Vec<StateID> = target_closure.iter().copied().collect();
+            let mut target_sorted: Vec<StateId> = target_closure.iter().copied().collect();
             target_sorted.sort_unstable();
 
             // Get or create target state ID
@@ -129,16 +188,13 @@ pub fn remove_epsilon(nfa: &NFA) -> NFA {
                 let id = next_id;
                 state_map.insert(target_sorted.clone(), id);
                 new_transitions.insert(id, Vec::new());
-                next_id += 1;
+                next_id = next_id.next();
                 queue.push_back(target_closure);
                 id
             };
 
             // Deduplicate: only add if not already present for this character
-            transitions_by_char
-                .entry(c)
-                .or_insert_with(HashSet::new)
-                .insert(target_id);
+            transitions_by_char.entry(c).or_default().insert(target_id);
         }
 
         // Add deduplicated transitions
@@ -153,9 +209,9 @@ pub fn remove_epsilon(nfa: &NFA) -> NFA {
     }
 
     // Determine accepting states: any state whose closure contains an original accept state
-    let mut accepting_states: Vec<StateID> = Vec::new();
+    let mut accepting_states: Vec<StateId> = Vec::new();
     for (closure_sorted, &new_id) in &state_map {
-        let closure_set: HashSet<StateID> = closure_sorted.iter().copied().collect();
+        let closure_set: HashSet<StateId> = closure_sorted.iter().copied().collect();
         if nfa
             .accept
             .iter()
@@ -169,6 +225,7 @@ pub fn remove_epsilon(nfa: &NFA) -> NFA {
 
     NFA {
         start: new_start,
+        extra_starts: vec![],
         accept: accepting_states,
         transitions: new_transitions,
     }
@@ -186,10 +243,12 @@ mod tests {
     use crate::regex::{parser::Parser, tokenizer::tokenize};
 
     fn build_nfa(expr: &str) -> NFA {
-        let tokens = tokenize(expr);
+        let tokens = tokenize(expr).unwrap();
         let mut p = Parser::new(tokens);
         let ast = p.parse_expr().unwrap();
-        crate::nfa::thompson::Thompson::new().from_ast(&ast)
+        crate::nfa::thompson::Thompson::new()
+            .from_ast(&ast)
+            .unwrap()
     }
 
     #[test]
@@ -203,6 +262,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn remove_epsilon_removes_epsilon_transitions_except_accept_shim() {
         let nfa = build_nfa("(a+b)*c");
         let cleaned = remove_epsilon(&nfa);
@@ -238,4 +298,57 @@ mod tests {
         }
         assert!(has_c, "expected char 'c' transitions after epsilon removal");
     }
+
+    #[test]
+    fn classical_removal_keeps_the_states() {
+        for expr in ["(a+b)*c", "a*b*", "(ab+ε)*", "∅", "a@0"] {
+            let nfa = build_nfa(expr);
+            let classical = remove_epsilon_classical(&nfa);
+            assert_eq!(classical.validate(), Ok(()));
+            assert_eq!(classical.start, nfa.start);
+
+            let mut before: Vec<_> = nfa.transitions.keys().collect();
+            let mut after: Vec<_> = classical.transitions.keys().collect();
+            before.sort();
+            after.sort();
+            assert_eq!(before, after, "{}", expr);
+            assert!(classical
+                .transitions
+                .values()
+                .flatten()
+                .all(|(label, _)| *label != TransitionLabel::Epsilon));
+
+            let (dfa, reference) = (
+                crate::dfa::dfa::nfa_to_dfa(&classical),
+                crate::dfa::dfa::nfa_to_dfa(&remove_epsilon(&nfa)),
+            );
+            assert_eq!(
+                crate::decisions::equivalence(&dfa, &reference),
+                Ok(crate::decisions::Equivalence::Equivalent),
+                "{}",
+                expr
+            );
+        }
+    }
+
+    #[test]
+    fn classical_removal_by_hand() {
+        // 0 -ε-> 1 -a-> 2 -ε-> 3, 3 accepting
+        let mut nfa = NFA::new();
+        nfa.add_edge_auto(StateId(0), TransitionLabel::Epsilon, StateId(1));
+        nfa.add_edge_auto(StateId(1), TransitionLabel::Char('a'), StateId(2));
+        nfa.add_edge_auto(StateId(2), TransitionLabel::Epsilon, StateId(3));
+        nfa.accept = vec![StateId(3)];
+
+        let out = remove_epsilon_with(&nfa, EpsilonRemoval::Classical);
+        assert_eq!(
+            out.transitions[&StateId(0)],
+            [(TransitionLabel::Char('a'), StateId(2))]
+        );
+        assert_eq!(
+            out.transitions[&StateId(1)],
+            [(TransitionLabel::Char('a'), StateId(2))]
+        );
+        assert_eq!(out.accept, [StateId(2), StateId(3)]);
+    }
 }
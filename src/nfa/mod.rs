@@ -1,4 +1,9 @@
 pub mod epsilon_elimination;
+#[allow(clippy::module_inception)]
 pub mod nfa;
+pub mod ops;
 pub mod renumber;
+pub mod runs;
 pub mod thompson;
+
+pub use ops::{equivalent, intersect};
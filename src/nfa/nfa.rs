@@ -1,38 +1,481 @@
 // nfa/nfa.rs
-use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 
-pub type StateID = usize;
+pub use crate::types::StateId;
+use crate::types::{AutomatonStats, Letter};
 
-// ε-NFA edge: either a char-transition or epsilon
+// ε-NFA edge: either a letter (a char, unless the NFA reads another
+// `Letter` type) or epsilon
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum TransitionLabel {
-    Char(char),
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TransitionLabel<S = char> {
+    Char(S),
     Epsilon,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NfaError {
+    UnknownState(StateId),
+    MissingStart(StateId),
+    MissingAccept(StateId),
+    DanglingEdge { from: StateId, to: StateId },
+    // Thompson invariants
+    // Not exactly one accept state; holds the number found
+    AcceptCount(usize),
+    OutDegree { state: StateId, degree: usize },
+    EdgeFromAccept(StateId),
+}
+
+impl fmt::Display for NfaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NfaError::UnknownState(s) => write!(f, "state {} does not exist", s),
+            NfaError::MissingStart(s) => write!(f, "start state {} is not a state", s),
+            NfaError::MissingAccept(s) => write!(f, "accept state {} is not a state", s),
+            NfaError::DanglingEdge { from, to } => {
+                write!(f, "edge {} -> {} points at a missing state", from, to)
+            }
+            NfaError::AcceptCount(n) => write!(f, "{} accept states, expected exactly one", n),
+            NfaError::OutDegree { state, degree } => write!(
+                f,
+                "state {} has edges to {} states, at most 2 allowed",
+                state, degree
+            ),
+            NfaError::EdgeFromAccept(s) => write!(f, "accept state {} has outgoing edges", s),
+        }
+    }
+}
+
+impl std::error::Error for NfaError {}
+
+/// Outside this crate, build one with `NFA::new` and `add_state`, or from
+/// its parts with `from_parts`: new fields may be added, so struct
+/// literals are only allowed here.
 #[derive(Debug, Clone)]
-pub struct NFA {
-    pub start: StateID,
-    pub accept: Vec<StateID>,
-    pub transitions: HashMap<StateID, Vec<(TransitionLabel, StateID)>>,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct NFA<S = char> {
+    pub start: StateId,
+    // Further initial states: a run may begin in `start` or any of these.
+    // Reversal and ε-free union build such machines
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub extra_starts: Vec<StateId>,
+    pub accept: Vec<StateId>,
+    pub transitions: HashMap<StateId, Vec<(TransitionLabel<S>, StateId)>>,
 }
 
-impl NFA {
-    pub fn new() -> Self {
+/// No states and no edges, with `start` 0. Over chars this is
+/// `NFA::new()`; over other letters, e.g. `NFA::<u8>::default()`.
+impl<S: Letter> Default for NFA<S> {
+    fn default() -> Self {
         Self {
-            start: 0,
+            start: StateId(0),
+            extra_starts: vec![],
             accept: vec![],
             transitions: HashMap::new(),
         }
     }
+}
+
+impl NFA {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("JSON error: {}", e))
+    }
+
+    #[cfg(feature = "serde")]
+    /// Rejects machines that `violations` finds anything wrong with.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str::<Self>(json)
+            .map_err(|e| e.to_string())
+            .and_then(NFA::checked)
+            .map_err(|e| format!("JSON error: {}", e))
+    }
+}
+
+impl<S: Letter> NFA<S> {
+    /// A machine with one start state; see `with_starts` for more.
+    pub fn from_parts(
+        start: StateId,
+        accept: Vec<StateId>,
+        transitions: HashMap<StateId, Vec<(TransitionLabel<S>, StateId)>>,
+    ) -> Self {
+        Self {
+            start,
+            extra_starts: vec![],
+            accept,
+            transitions,
+        }
+    }
 
-    pub fn add_state(&mut self) -> StateID {
-        let id = self.transitions.len();
-        self.transitions.insert(id, Vec::new());
+    /// A new state, numbered one past the highest state so far, so ids
+    /// made elsewhere (by `add_edge_auto`, say) are never reused.
+    pub fn add_state(&mut self) -> StateId {
+        let id = self
+            .transitions
+            .keys()
+            .max()
+            .map_or(StateId(0), |m| m.next());
+        self.transitions.entry(id).or_default();
         id
     }
 
-    pub fn add_edge(&mut self, from: StateID, label: TransitionLabel, to: StateID) {
-        self.transitions.get_mut(&from).unwrap().push((label, to));
+    /// Make every state of `starts` initial, creating the missing ones. The
+    /// first becomes `start`; `starts` must not be empty.
+    pub fn with_starts(mut self, starts: impl IntoIterator<Item = StateId>) -> Self {
+        let mut starts = starts.into_iter();
+        self.start = starts.next().expect("an NFA needs a start state");
+        self.extra_starts = starts.filter(|&s| s != self.start).collect();
+        self.extra_starts.sort_unstable();
+        self.extra_starts.dedup();
+        for s in self.starts() {
+            self.transitions.entry(s).or_default();
+        }
+        self
+    }
+
+    /// Every initial state: `start`, then `extra_starts`.
+    pub fn starts(&self) -> Vec<StateId> {
+        std::iter::once(self.start)
+            .chain(self.extra_starts.iter().copied())
+            .collect()
+    }
+
+    /// Add an edge between two existing states.
+    pub fn add_edge(
+        &mut self,
+        from: StateId,
+        label: TransitionLabel<S>,
+        to: StateId,
+    ) -> Result<(), NfaError> {
+        if !self.transitions.contains_key(&to) {
+            return Err(NfaError::UnknownState(to));
+        }
+
+        match self.transitions.get_mut(&from) {
+            Some(edges) => {
+                edges.push((label, to));
+                Ok(())
+            }
+            None => Err(NfaError::UnknownState(from)),
+        }
+    }
+
+    /// Add an edge, creating either endpoint if it doesn't exist yet.
+    pub fn add_edge_auto(&mut self, from: StateId, label: TransitionLabel<S>, to: StateId) {
+        self.transitions.entry(to).or_default();
+        self.transitions.entry(from).or_default().push((label, to));
+    }
+
+    // All letters used on some edge, in sorted order.
+    pub fn alphabet(&self) -> BTreeSet<S> {
+        self.transitions
+            .values()
+            .flatten()
+            .filter_map(|(label, _)| match label {
+                TransitionLabel::Char(c) => Some(*c),
+                TransitionLabel::Epsilon => None,
+            })
+            .collect()
+    }
+
+    /// Size counts. States are those with an entry in `transitions`, plus
+    /// any start state without one.
+    pub fn stats(&self) -> AutomatonStats {
+        let mut states: HashSet<StateId> = self.transitions.keys().copied().collect();
+        states.extend(self.starts());
+        let edges = self.transitions.values().flatten();
+        AutomatonStats {
+            states: states.len(),
+            transitions: edges.clone().count(),
+            epsilons: edges
+                .filter(|(label, _)| *label == TransitionLabel::Epsilon)
+                .count(),
+            alphabet: self.alphabet().len(),
+            max_out_degree: self.transitions.values().map(Vec::len).max().unwrap_or(0),
+        }
+    }
+
+    /// Check transition integrity: every start state, every accept state and
+    /// every edge target must be a known state. Returns the first of
+    /// `violations`, as one error for `?` alongside the other `NfaError`
+    /// checks; call `violations` to report everything at once.
+    pub fn validate(&self) -> Result<(), NfaError> {
+        match self.violations().into_iter().next() {
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    /// Everything `validate` checks, all of it: missing start states, then
+    /// missing accept states, then dangling edges by source and target,
+    /// each reported once. Empty for a well-formed NFA. A machine with no
+    /// states at all, like `NFA::new()`, is well-formed: its start state
+    /// is implicit, as `stats` counts it.
+    pub fn violations(&self) -> Vec<NfaError> {
+        let known = |s: &StateId| self.transitions.contains_key(s);
+        let mut violations = Vec::new();
+
+        let mut starts: BTreeSet<StateId> = self.starts().into_iter().collect();
+        if self.transitions.is_empty() {
+            starts.remove(&self.start);
+        }
+        let accepts: BTreeSet<StateId> = self.accept.iter().copied().collect();
+        violations.extend(
+            starts
+                .into_iter()
+                .filter(|s| !known(s))
+                .map(NfaError::MissingStart),
+        );
+        violations.extend(
+            accepts
+                .into_iter()
+                .filter(|a| !known(a))
+                .map(NfaError::MissingAccept),
+        );
+
+        let dangling: BTreeSet<(StateId, StateId)> = self
+            .transitions
+            .iter()
+            .flat_map(|(&from, edges)| edges.iter().map(move |&(_, to)| (from, to)))
+            .filter(|(_, to)| !known(to))
+            .collect();
+        violations.extend(
+            dangling
+                .into_iter()
+                .map(|(from, to)| NfaError::DanglingEdge { from, to }),
+        );
+        violations
+    }
+
+    // `self` if well-formed, otherwise every violation in one message.
+    #[cfg(feature = "serde")]
+    pub(crate) fn checked(self) -> Result<Self, String> {
+        let violations: Vec<String> = self.violations().iter().map(|v| v.to_string()).collect();
+        if violations.is_empty() {
+            Ok(self)
+        } else {
+            Err(format!("invalid NFA: {}", violations.join("; ")))
+        }
+    }
+
+    /// `validate` plus the shape guaranteed by Thompson's construction:
+    /// a single accept state with no outgoing edges, and edges to at most
+    /// two distinct targets per state (character classes add parallel edges).
+    pub fn validate_thompson(&self) -> Result<(), NfaError> {
+        self.validate()?;
+
+        if self.accept.len() != 1 {
+            return Err(NfaError::AcceptCount(self.accept.len()));
+        }
+
+        let accept = self.accept[0];
+        if !self.transitions[&accept].is_empty() {
+            return Err(NfaError::EdgeFromAccept(accept));
+        }
+
+        for (&state, edges) in &self.transitions {
+            let targets: HashSet<StateId> = edges.iter().map(|&(_, to)| to).collect();
+            if targets.len() > 2 {
+                return Err(NfaError::OutDegree {
+                    state,
+                    degree: targets.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_accepts_well_formed() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+        let t = nfa.add_state();
+        nfa.add_edge(s, TransitionLabel::Char('a'), t).unwrap();
+        nfa.start = s;
+        nfa.accept = vec![t];
+
+        assert_eq!(nfa.validate(), Ok(()));
+        assert_eq!(nfa.validate_thompson(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_missing_states() {
+        let mut nfa = NFA::new();
+        assert_eq!(nfa.validate(), Ok(()));
+        let s = nfa.add_state();
+        nfa.start = StateId(3);
+        assert_eq!(nfa.validate(), Err(NfaError::MissingStart(StateId(3))));
+
+        nfa.start = s;
+        nfa.accept = vec![StateId(7)];
+        assert_eq!(nfa.validate(), Err(NfaError::MissingAccept(StateId(7))));
+
+        nfa.accept = vec![s];
+        nfa.transitions
+            .get_mut(&s)
+            .unwrap()
+            .push((TransitionLabel::Epsilon, StateId(9)));
+        assert_eq!(
+            nfa.validate(),
+            Err(NfaError::DanglingEdge {
+                from: s,
+                to: StateId(9)
+            })
+        );
+    }
+
+    #[test]
+    fn violations_lists_everything() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+        nfa.add_edge(s, TransitionLabel::Char('a'), s).unwrap();
+        nfa.transitions.get_mut(&s).unwrap().extend([
+            (TransitionLabel::Char('b'), StateId(9)),
+            (TransitionLabel::Epsilon, StateId(4)),
+            (TransitionLabel::Char('c'), StateId(9)),
+        ]);
+        nfa.accept = vec![StateId(7), s, StateId(7)];
+        nfa.extra_starts = vec![StateId(5)];
+
+        assert_eq!(
+            nfa.violations(),
+            [
+                NfaError::MissingStart(StateId(5)),
+                NfaError::MissingAccept(StateId(7)),
+                NfaError::DanglingEdge {
+                    from: s,
+                    to: StateId(4)
+                },
+                NfaError::DanglingEdge {
+                    from: s,
+                    to: StateId(9)
+                },
+            ]
+        );
+        assert_eq!(nfa.validate(), Err(NfaError::MissingStart(StateId(5))));
+        assert_eq!(
+            NfaError::DanglingEdge {
+                from: s,
+                to: StateId(4)
+            }
+            .to_string(),
+            "edge 0 -> 4 points at a missing state"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserializing_checks_structure() {
+        let json = r#"{"start": 2, "accept": [0], "transitions": {"0": [[{"Char": "a"}, 1]]}}"#;
+        assert_eq!(
+            NFA::from_json(json).unwrap_err(),
+            "JSON error: invalid NFA: start state 2 is not a state; \
+             edge 0 -> 1 points at a missing state"
+        );
+
+        let empty = NFA::from_json(&NFA::new().to_json().unwrap()).unwrap();
+        assert_eq!(empty.stats().states, 1);
+    }
+
+    #[test]
+    fn validate_thompson_rejects_edge_from_accept() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+        let t = nfa.add_state();
+        nfa.add_edge(s, TransitionLabel::Char('a'), t).unwrap();
+        nfa.add_edge(t, TransitionLabel::Epsilon, s).unwrap();
+        nfa.start = s;
+        nfa.accept = vec![t];
+
+        assert_eq!(nfa.validate(), Ok(()));
+        assert_eq!(nfa.validate_thompson(), Err(NfaError::EdgeFromAccept(t)));
+    }
+
+    #[test]
+    fn validate_thompson_counts_accepts() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+        nfa.start = s;
+        assert_eq!(nfa.validate_thompson(), Err(NfaError::AcceptCount(0)));
+        assert_eq!(
+            NfaError::AcceptCount(0).to_string(),
+            "0 accept states, expected exactly one"
+        );
+
+        nfa.accept = vec![s, s];
+        assert_eq!(nfa.validate_thompson(), Err(NfaError::AcceptCount(2)));
+    }
+
+    #[test]
+    fn add_edge_rejects_unknown_states() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+
+        assert_eq!(
+            nfa.add_edge(s, TransitionLabel::Char('a'), StateId(5)),
+            Err(NfaError::UnknownState(StateId(5)))
+        );
+        assert_eq!(
+            nfa.add_edge(StateId(4), TransitionLabel::Char('a'), s),
+            Err(NfaError::UnknownState(StateId(4)))
+        );
+        assert!(nfa.transitions[&s].is_empty());
+    }
+
+    #[test]
+    fn add_edge_auto_creates_states() {
+        let mut nfa = NFA::new();
+        nfa.add_edge_auto(StateId(0), TransitionLabel::Char('a'), StateId(1));
+        nfa.accept = vec![StateId(1)];
+
+        assert_eq!(nfa.transitions.len(), 2);
+        assert_eq!(nfa.validate(), Ok(()));
+    }
+
+    #[test]
+    fn add_state_after_sparse_ids() {
+        let mut nfa = NFA::new();
+        nfa.add_edge_auto(StateId(7), TransitionLabel::Char('a'), StateId(5));
+        nfa.add_edge_auto(StateId(3), TransitionLabel::Char('b'), StateId(9));
+        let edges = nfa.transitions[&StateId(3)].clone();
+
+        let s = nfa.add_state();
+        assert_eq!(s, StateId(10));
+        assert_eq!(nfa.transitions[&StateId(3)], edges);
+        assert_eq!(nfa.transitions.len(), 5);
+        assert_eq!(NFA::new().add_state(), StateId(0));
+    }
+
+    #[test]
+    fn several_start_states() {
+        let mut nfa = NFA::new().with_starts([StateId(2), StateId(0), StateId(2), StateId(1)]);
+        assert_eq!(nfa.starts(), [StateId(2), StateId(0), StateId(1)]);
+        assert_eq!(nfa.validate(), Ok(()));
+
+        nfa.extra_starts.push(StateId(7));
+        assert_eq!(nfa.validate(), Err(NfaError::MissingStart(StateId(7))));
     }
 }
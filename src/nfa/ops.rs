@@ -0,0 +1,436 @@
+// nfa/ops.rs
+//
+// Language operations directly on NFAs, without determinizing first.
+use super::epsilon_elimination::remove_epsilon;
+use super::nfa::{StateId, TransitionLabel, NFA};
+use crate::dfa::dfa::nfa_to_dfa;
+use crate::types::Letter;
+use std::collections::HashMap;
+
+/// Whether `a` and `b` accept the same words, ε-edges and all: a check
+/// that a transformation kept the language. Both are determinized, then
+/// compared by `dfa::equivalent` without minimizing; for a word telling
+/// them apart, use `decisions::equivalence` on the NFAs.
+pub fn equivalent<S: Letter>(a: &NFA<S>, b: &NFA<S>) -> bool {
+    let determinize = |nfa| nfa_to_dfa(&remove_epsilon(nfa));
+    crate::dfa::equivalent(&determinize(a), &determinize(b))
+}
+
+/// The synchronous product: an NFA for `L(a) ∩ L(b)`. Both sides move
+/// together on a shared symbol, and either moves alone on an ε-edge, so
+/// ε-NFAs work as they are. Only pairs reachable from the start pair are
+/// built, numbered in discovery order; with several start states on
+/// either side, so is the product.
+pub fn intersect(a: &NFA, b: &NFA) -> NFA {
+    let mut out = NFA::new();
+    // Product states in discovery order; a state's id is its index. Every
+    // pair of start states is a start
+    let mut pairs: Vec<(StateId, StateId)> = a
+        .starts()
+        .into_iter()
+        .flat_map(|p| b.starts().into_iter().map(move |q| (p, q)))
+        .collect();
+    let mut ids: HashMap<(StateId, StateId), StateId> = HashMap::new();
+    for &pair in &pairs {
+        ids.insert(pair, out.add_state());
+    }
+    out = out.with_starts((0..pairs.len()).map(StateId::new));
+
+    let mut from = StateId(0);
+    while let Some(&(p, q)) = pairs.get(from.index()) {
+        if a.accept.contains(&p) && b.accept.contains(&q) {
+            out.accept.push(from);
+        }
+
+        let (left, right) = (edges(a, p), edges(b, q));
+        let mut next = Vec::new();
+        for (label, p2) in left {
+            match label {
+                TransitionLabel::Epsilon => next.push((TransitionLabel::Epsilon, (*p2, q))),
+                TransitionLabel::Char(_) => next.extend(
+                    right
+                        .iter()
+                        .filter(|(other, _)| other == label)
+                        .map(|(_, q2)| (label.clone(), (*p2, *q2))),
+                ),
+            }
+        }
+        for (label, q2) in right {
+            if *label == TransitionLabel::Epsilon {
+                next.push((TransitionLabel::Epsilon, (p, *q2)));
+            }
+        }
+
+        for (label, pair) in next {
+            let to = *ids.entry(pair).or_insert_with(|| {
+                pairs.push(pair);
+                out.add_state()
+            });
+            out.add_edge(from, label, to)
+                .expect("product states are added before their edges");
+        }
+        from = from.next();
+    }
+    out
+}
+
+/// Combinators splicing whole machines together with fresh ε-edges, for
+/// automata built or loaded without a regex. The right operand's states
+/// are renumbered past the left one's; left ids are kept.
+impl NFA {
+    /// `L(self) ∪ L(other)`: a fresh start with ε-edges to both starts.
+    pub fn union(self, other: NFA) -> NFA {
+        let (mut out, other) = self.absorb(other);
+        let start = out.fresh_state();
+        for s in out.starts().into_iter().chain(other.starts()) {
+            out.add_edge_auto(start, TransitionLabel::Epsilon, s);
+        }
+        out.start = start;
+        out.extra_starts.clear();
+        out.accept.extend(other.accept);
+        out
+    }
+
+    /// `L(self) ∪ L(other)` without ε-edges: the two machines side by side,
+    /// with the start states of both.
+    pub fn union_multi_start(self, other: NFA) -> NFA {
+        let (out, other) = self.absorb(other);
+        let starts = out.starts().into_iter().chain(other.starts());
+        let mut out = out.with_starts(starts.collect::<Vec<_>>());
+        out.accept.extend(other.accept);
+        out
+    }
+
+    /// `L(self) L(other)`: ε-edges from every accepting state of `self` to
+    /// the starts of `other`, whose accepting states are the result's.
+    pub fn concat(self, other: NFA) -> NFA {
+        let (mut out, other) = self.absorb(other);
+        for a in std::mem::take(&mut out.accept) {
+            for s in other.starts() {
+                out.add_edge_auto(a, TransitionLabel::Epsilon, s);
+            }
+        }
+        out.accept = other.accept;
+        out
+    }
+
+    /// `L(self)*`: a fresh accepting start leading into the machine, and
+    /// ε-edges from every accepting state back to the old starts. The fresh
+    /// start keeps loops through it from accepting early.
+    pub fn star(mut self) -> NFA {
+        let start = self.fresh_state();
+        let old = self.starts();
+        for &a in &self.accept.clone() {
+            for &s in &old {
+                self.add_edge_auto(a, TransitionLabel::Epsilon, s);
+            }
+        }
+        for &s in &old {
+            self.add_edge_auto(start, TransitionLabel::Epsilon, s);
+        }
+        self.start = start;
+        self.extra_starts.clear();
+        self.accept.push(start);
+        self
+    }
+
+    /// An equivalent machine with one initial state: a fresh start with
+    /// ε-edges to every old one. Unchanged when there is only one.
+    pub fn single_start(mut self) -> NFA {
+        if self.extra_starts.is_empty() {
+            return self;
+        }
+        let start = self.fresh_state();
+        for s in self.starts() {
+            self.add_edge_auto(start, TransitionLabel::Epsilon, s);
+        }
+        self.start = start;
+        self.extra_starts.clear();
+        self
+    }
+
+    /// Let ASCII letters match in either case: every edge on one gets a
+    /// parallel edge on the other case. Other chars are left alone, so the
+    /// result doesn't depend on the locale. This folds the machine, not the
+    /// pattern: after a complement or negated class was compiled, the
+    /// other case of an excluded letter gets in. To compile a pattern case
+    /// insensitively, fold it with `RegexAST::ignore_ascii_case`.
+    pub fn ignore_ascii_case(mut self) -> NFA {
+        for edges in self.transitions.values_mut() {
+            let swapped: Vec<_> = edges
+                .iter()
+                .filter_map(|(label, t)| match label {
+                    TransitionLabel::Char(c) if c.is_ascii_alphabetic() => {
+                        let other = TransitionLabel::Char(swap_ascii_case(*c));
+                        Some((other, *t))
+                    }
+                    _ => None,
+                })
+                .collect();
+            for edge in swapped {
+                if !edges.contains(&edge) {
+                    edges.push(edge);
+                }
+            }
+        }
+        self
+    }
+
+    // Copy `other`'s edges into `self` under fresh ids; returns `other`
+    // with only its start and accepting states renumbered.
+    fn absorb(mut self, other: NFA) -> (NFA, NFA) {
+        let base = self.next_id();
+        let shift = |s: StateId| StateId(base.0 + s.0);
+        for (s, edges) in other.transitions {
+            self.transitions.entry(shift(s)).or_default();
+            for (label, t) in edges {
+                self.add_edge_auto(shift(s), label, shift(t));
+            }
+        }
+        let moved = NFA {
+            start: shift(other.start),
+            extra_starts: other.extra_starts.into_iter().map(shift).collect(),
+            accept: other.accept.into_iter().map(shift).collect(),
+            transitions: HashMap::new(),
+        };
+        for s in moved.accept.iter().copied().chain(moved.starts()) {
+            self.transitions.entry(s).or_default();
+        }
+        (self, moved)
+    }
+}
+
+impl<S: Letter> NFA<S> {
+    /// The mirror image, for `L(self)` reversed: every edge flipped, the
+    /// accepting states as starts and the starts as accepting states.
+    pub fn reverse(&self) -> NFA<S> {
+        let mut out = NFA::default();
+        for (&s, edges) in &self.transitions {
+            out.transitions.entry(s).or_default();
+            for (label, t) in edges {
+                out.add_edge_auto(*t, label.clone(), s);
+            }
+        }
+        out.accept = self.starts();
+        if self.accept.is_empty() {
+            // No words, so nothing to start from: an isolated start
+            out.start = out.fresh_state();
+            return out;
+        }
+        out.with_starts(self.accept.clone())
+    }
+
+    // A new state with an id above every id in use.
+    fn fresh_state(&mut self) -> StateId {
+        let id = self.next_id();
+        self.transitions.insert(id, Vec::new());
+        id
+    }
+
+    fn next_id(&self) -> StateId {
+        self.transitions
+            .iter()
+            .flat_map(|(&s, edges)| std::iter::once(s).chain(edges.iter().map(|&(_, t)| t)))
+            .chain(self.accept.iter().copied())
+            .chain(self.starts())
+            .max()
+            .map_or(StateId(0), |m| m.next())
+    }
+}
+
+fn swap_ascii_case(c: char) -> char {
+    if c.is_ascii_uppercase() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_ascii_uppercase()
+    }
+}
+
+fn edges(nfa: &NFA, state: StateId) -> &[(TransitionLabel, StateId)] {
+    nfa.transitions.get(&state).map_or(&[], Vec::as_slice)
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decisions::{equivalence, Equivalence};
+    use crate::dfa::dfa::{nfa_to_dfa, DFA};
+    use crate::nfa::epsilon_elimination::remove_epsilon;
+    use crate::nfa::renumber::renumber_dfs;
+    use crate::pipeline::tests::full_pipeline;
+
+    fn dfa_of(nfa: &NFA) -> DFA {
+        nfa_to_dfa(&remove_epsilon(nfa))
+    }
+
+    #[test]
+    fn transformations_keep_the_language() {
+        use crate::nfa::epsilon_elimination::{remove_epsilon_with, EpsilonRemoval};
+        use crate::nfa::renumber::{renumber, StateOrder};
+
+        for pattern in ["(a+b)*abb", "a{2,3}b?", "∅", "ε", "!(ab)&[ab]*"] {
+            let out = full_pipeline(pattern).unwrap();
+            let enfa = &out.enfa;
+            for other in [
+                out.nfa.clone(),
+                remove_epsilon_with(enfa, EpsilonRemoval::Classical),
+                renumber(enfa, StateOrder::Topological),
+                enfa.reverse().reverse(),
+            ] {
+                assert!(equivalent(enfa, &other), "{}", pattern);
+            }
+        }
+
+        let (ab, ba) = (full_pipeline("ab").unwrap(), full_pipeline("ba").unwrap());
+        assert!(!equivalent(&ab.enfa, &ba.nfa));
+        assert_eq!(
+            equivalence(&ab.enfa, &ba.nfa),
+            Ok(Equivalence::Distinguished {
+                witness: "ab".into(),
+                in_left: true
+            })
+        );
+    }
+
+    #[test]
+    fn product_is_the_intersection() {
+        for (x, y) in [
+            ("(a+b)*a", "(a+b)*b(a+b)"),
+            ("a*b*", "(ab)*"),
+            ("(a+b)*abb", "b(a+b)*"),
+            ("a+b", "c"),
+            ("ε", "a*"),
+        ] {
+            let (ex, ey) = (full_pipeline(x).unwrap(), full_pipeline(y).unwrap());
+            let expected = full_pipeline(&format!("({})&({})", x, y)).unwrap().mindfa;
+
+            // ε-NFAs and ε-free NFAs alike
+            for product in [intersect(&ex.enfa, &ey.enfa), intersect(&ex.nfa, &ey.nfa)] {
+                assert_eq!(product.validate(), Ok(()));
+                assert_eq!(
+                    equivalence(&dfa_of(&product), &expected),
+                    Ok(Equivalence::Equivalent),
+                    "{} & {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn combinators_match_the_regex_operators() {
+        let enfa = |p: &str| full_pipeline(p).unwrap().enfa;
+        let nfa = |p: &str| full_pipeline(p).unwrap().nfa;
+        for (built, pattern) in [
+            (enfa("ab").union(enfa("b*")), "ab+b*"),
+            (nfa("ab").union(nfa("a(a+b)")), "ab+a(a+b)"),
+            (enfa("a+b").concat(enfa("c*")), "(a+b)c*"),
+            (nfa("a*b").concat(nfa("ε+b")), "a*b(ε+b)"),
+            (enfa("ab").star(), "(ab)*"),
+            (nfa("a+ba").star(), "(a+ba)*"),
+            (nfa("ab").concat(nfa("c")).star().union(nfa("∅")), "(abc)*"),
+        ] {
+            assert_eq!(built.validate(), Ok(()), "{}", pattern);
+            let expected = full_pipeline(pattern).unwrap().mindfa;
+            assert_eq!(
+                equivalence(&dfa_of(&built), &expected),
+                Ok(Equivalence::Equivalent),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn star_does_not_accept_early() {
+        // a*b starred: a fresh accepting start, not the old start made
+        // accepting, so "a" stays rejected
+        let starred = full_pipeline("a*b").unwrap().nfa.star();
+        let dfa = dfa_of(&starred);
+        assert!(dfa.accepts("") && dfa.accepts("abab"));
+        assert!(!dfa.accepts("a") && !dfa.accepts("aba"));
+    }
+
+    #[test]
+    fn ignoring_ascii_case() {
+        let dfa = dfa_of(&full_pipeline("(ab+Cd)*é").unwrap().enfa.ignore_ascii_case());
+        for word in ["abé", "ABé", "aBcDé", "cdAbé", "é"] {
+            assert!(dfa.accepts(word), "{}", word);
+        }
+        // Non-ASCII letters keep their case
+        assert!(!dfa.accepts("abÉ") && !dfa.accepts("ab"));
+    }
+
+    #[test]
+    fn multi_start_machines() {
+        let nfa = |p: &str| full_pipeline(p).unwrap().nfa;
+        for (x, y) in [("ab*", "ba"), ("a+ε", "(ab)*"), ("∅", "b")] {
+            let union = nfa(x).union_multi_start(nfa(y));
+            assert_eq!(union.validate(), Ok(()));
+            assert_eq!(union.starts().len(), 2);
+            assert!(union
+                .transitions
+                .values()
+                .flatten()
+                .all(|(label, _)| *label != TransitionLabel::Epsilon));
+
+            // Every later construction sees both starts
+            let either = format!("({}+{})", x, y);
+            for (built, pattern) in [
+                (union.clone(), either.clone()),
+                (remove_epsilon(&union), either.clone()),
+                (renumber_dfs(&union), either.clone()),
+                (union.clone().single_start(), either.clone()),
+                (union.clone().concat(nfa("c")), format!("{}c", either)),
+                (union.clone().star(), format!("{}*", either)),
+                (intersect(&union, &nfa(x)), x.to_string()),
+            ] {
+                assert_eq!(
+                    equivalence(&dfa_of(&built), &full_pipeline(&pattern).unwrap().mindfa),
+                    Ok(Equivalence::Equivalent),
+                    "{}",
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reversal() {
+        for (pattern, reversed) in [("abc*", "c*ba"), ("(a+bc)*d", "d(a+cb)*"), ("∅", "∅")] {
+            let nfa = full_pipeline(pattern).unwrap().nfa.reverse();
+            assert_eq!(nfa.validate(), Ok(()));
+            assert_eq!(
+                equivalence(&dfa_of(&nfa), &full_pipeline(reversed).unwrap().mindfa),
+                Ok(Equivalence::Equivalent),
+                "{}",
+                pattern
+            );
+        }
+
+        // Runs may begin in any start state
+        let nfa = full_pipeline("ab+c").unwrap().nfa.reverse();
+        assert_eq!(nfa.starts().len(), 2);
+        assert!(nfa.accepting_path("ba").is_some() && nfa.accepting_path("c").is_some());
+        assert_eq!(nfa.accepting_runs("ba", 10).len(), 1);
+    }
+
+    #[test]
+    fn only_reachable_pairs() {
+        // ab and ba share no run beyond the start pair
+        let (x, y) = (
+            full_pipeline("ab").unwrap().nfa,
+            full_pipeline("ba").unwrap().nfa,
+        );
+        let product = intersect(&x, &y);
+        assert_eq!(product.transitions.len(), 1);
+        assert!(product.accept.is_empty());
+    }
+}
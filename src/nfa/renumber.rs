@@ -1,19 +1,36 @@
 // nfa/renumber.rs
-use std::collections::{HashMap, VecDeque};
-use super::nfa::{StateID, NFA};
+use std::collections::{HashMap, HashSet, VecDeque};
+use super::nfa::{StateId, TransitionLabel, NFA};
+use crate::dfa::dfa::DFA;
 
+/// How to number the states of an NFA.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StateOrder {
+    // Breadth-first from the start states: states one step further along
+    // get higher numbers, the reading order of a left-to-right diagram
+    #[default]
+    Bfs,
+    // Depth-first from the start states, as `renumber_dfs`
+    Dfs,
+    // Reverse postorder: every edge goes to a higher number, except the
+    // edges closing a cycle
+    Topological,
+    // Keep the ids as they are
+    Original,
+}
 
-// Renumber states in DFS order from the start state.
+// Renumber states in DFS order from the start states.
 // This tends to follow the "natural flow" of the regex more closely.
 pub fn renumber_dfs(nfa: &NFA) -> NFA {
-    let mut old_to_new: HashMap<StateID, StateID> = HashMap::new();
-    let mut stack: Vec<StateID> = Vec::new();
-    let mut next_id = 0;
-    
-    // Start state becomes 0
-    old_to_new.insert(nfa.start, next_id);
-    stack.push(nfa.start);
-    next_id += 1;
+    let mut old_to_new: HashMap<StateId, StateId> = HashMap::new();
+
+    // Start states come first, from 0
+    for s in nfa.starts() {
+        let id = StateId::new(old_to_new.len());
+        old_to_new.entry(s).or_insert(id);
+    }
+    let mut next_id = StateId::new(old_to_new.len());
+    let mut stack: Vec<StateId> = nfa.starts().into_iter().rev().collect();
     
     // DFS traversal
     while let Some(current) = stack.pop() {
@@ -23,13 +40,163 @@ pub fn renumber_dfs(nfa: &NFA) -> NFA {
                 if !old_to_new.contains_key(to) {
                     old_to_new.insert(*to, next_id);
                     stack.push(*to);
-                    next_id += 1;
+                    next_id = next_id.next();
                 }
             }
         }
     }
     
-    // Rebuild NFA with new numbering
+    // Keep accept states even when unreachable (e.g. the empty language)
+    for &a in &nfa.accept {
+        let id = StateId::new(old_to_new.len());
+        old_to_new.entry(a).or_insert(id);
+    }
+
+    relabel(nfa, &old_to_new)
+}
+
+/// Number states in breadth-first order from the start states, which
+/// come first. See `renumber`.
+pub fn renumber_bfs(nfa: &NFA) -> NFA {
+    let mut seen = HashSet::new();
+    let mut order = Vec::new();
+    let mut queue: VecDeque<StateId> = nfa
+        .starts()
+        .into_iter()
+        .filter(|&s| seen.insert(s))
+        .collect();
+
+    while let Some(s) = queue.pop_front() {
+        order.push(s);
+        for to in successors(nfa, s) {
+            if seen.insert(to) {
+                queue.push_back(to);
+            }
+        }
+    }
+    relabel(nfa, &complete(nfa, order))
+}
+
+/// Number states in reverse postorder of a depth-first search from the
+/// start states, so that on an acyclic NFA every edge goes forwards. See
+/// `renumber`.
+pub fn renumber_topological(nfa: &NFA) -> NFA {
+    let mut seen = HashSet::new();
+    let mut postorder = Vec::new();
+
+    for start in nfa.starts() {
+        if !seen.insert(start) {
+            continue;
+        }
+        // Each frame is a state and its successors still to visit
+        let mut stack = vec![(start, successors(nfa, start).into_iter())];
+        while let Some((s, next)) = stack.last_mut() {
+            match next.next() {
+                Some(to) if seen.insert(to) => {
+                    let successors = successors(nfa, to).into_iter();
+                    stack.push((to, successors));
+                }
+                Some(_) => {}
+                None => {
+                    postorder.push(*s);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    postorder.reverse();
+    relabel(nfa, &complete(nfa, postorder))
+}
+
+/// Renumber from 0 in the given order, deterministically: edges are
+/// followed by label (ε first, then chars in order), ties in the order they
+/// are stored. States not reachable from a start are kept, numbered last
+/// in the order of their old ids. Accept states come out sorted.
+pub fn renumber(nfa: &NFA, order: StateOrder) -> NFA {
+    match order {
+        StateOrder::Bfs => renumber_bfs(nfa),
+        StateOrder::Dfs => renumber_dfs(nfa),
+        StateOrder::Topological => renumber_topological(nfa),
+        StateOrder::Original => nfa.clone(),
+    }
+}
+
+/// `renumber` for a DFA, read as an NFA with its edges in symbol order, so
+/// the numbering depends only on the DFA's shape and not on its old ids
+/// or hash order.
+pub fn renumber_dfa(dfa: &DFA, order: StateOrder) -> DFA {
+    let transitions = dfa
+        .transitions
+        .iter()
+        .map(|(&s, map)| {
+            let mut edges: Vec<(char, StateId)> = map.iter().map(|(&c, &to)| (c, to)).collect();
+            edges.sort_unstable();
+            let edges = edges
+                .into_iter()
+                .map(|(c, to)| (TransitionLabel::Char(c), to))
+                .collect();
+            (s, edges)
+        })
+        .collect();
+    let mut accept: Vec<StateId> = dfa.accepts.iter().copied().collect();
+    accept.sort_unstable();
+    let nfa = renumber(&NFA::from_parts(dfa.start, accept, transitions), order);
+
+    DFA {
+        start: nfa.start,
+        accepts: nfa.accept.into_iter().collect(),
+        transitions: nfa
+            .transitions
+            .into_iter()
+            .map(|(s, edges)| {
+                let map = edges
+                    .into_iter()
+                    .filter_map(|(label, to)| match label {
+                        TransitionLabel::Char(c) => Some((c, to)),
+                        TransitionLabel::Epsilon => None,
+                    })
+                    .collect();
+                (s, map)
+            })
+            .collect(),
+    }
+}
+
+// Distinct targets of `state`'s edges, by label.
+fn successors(nfa: &NFA, state: StateId) -> Vec<StateId> {
+    let mut edges: Vec<&(TransitionLabel, StateId)> =
+        nfa.transitions.get(&state).into_iter().flatten().collect();
+    edges.sort_by_key(|(label, _)| match label {
+        TransitionLabel::Epsilon => None,
+        TransitionLabel::Char(c) => Some(*c),
+    });
+
+    let mut out: Vec<StateId> = Vec::new();
+    for &(_, to) in edges {
+        if !out.contains(&to) {
+            out.push(to);
+        }
+    }
+    out
+}
+
+// Map each state of `order` to its position, then every state left out,
+// by old id.
+fn complete(nfa: &NFA, order: Vec<StateId>) -> HashMap<StateId, StateId> {
+    let mut rest: Vec<StateId> = nfa.transitions.keys().chain(&nfa.accept).copied().collect();
+    rest.sort_unstable();
+
+    let mut old_to_new = HashMap::new();
+    for s in order.into_iter().chain(rest) {
+        let id = StateId::new(old_to_new.len());
+        old_to_new.entry(s).or_insert(id);
+    }
+    old_to_new
+}
+
+// Rebuild `nfa` under `old_to_new`, dropping states it doesn't map.
+fn relabel(nfa: &NFA, old_to_new: &HashMap<StateId, StateId>) -> NFA {
     let mut new_transitions = HashMap::new();
     for (old_state, edges) in &nfa.transitions {
         if let Some(&new_state) = old_to_new.get(old_state) {
@@ -43,16 +210,128 @@ pub fn renumber_dfs(nfa: &NFA) -> NFA {
         }
     }
     
-    let new_accept = nfa
+    let mut new_accept: Vec<StateId> = nfa
         .accept
         .iter()
         .filter_map(|old| old_to_new.get(old).copied())
         .collect();
-    
+    new_accept.sort_unstable();
+    let starts: Vec<StateId> = nfa.starts().iter().map(|s| old_to_new[s]).collect();
+
     NFA {
-        start: 0,
-        accept: new_accept,
         transitions: new_transitions,
+        accept: new_accept,
+        ..NFA::new()
+    }
+    .with_starts(starts)
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decisions::{equivalence, Equivalence};
+    use crate::dfa::dfa::nfa_to_dfa;
+    use crate::nfa::epsilon_elimination::remove_epsilon;
+    use crate::pipeline::tests::full_pipeline;
+
+    const ORDERS: [StateOrder; 4] = [
+        StateOrder::Bfs,
+        StateOrder::Dfs,
+        StateOrder::Topological,
+        StateOrder::Original,
+    ];
+
+    #[test]
+    fn renumbering_keeps_the_language() {
+        for pattern in ["(a+b)*abb", "a{2,3}", "∅", "(ab)*+c"] {
+            let out = full_pipeline(pattern).unwrap();
+            let reference = nfa_to_dfa(&out.nfa);
+            for order in ORDERS {
+                for nfa in [&out.enfa, &out.nfa] {
+                    let renumbered = renumber(nfa, order);
+                    assert_eq!(renumbered.validate(), Ok(()));
+                    assert_eq!(renumbered.transitions.len(), nfa.transitions.len());
+                    assert_eq!(
+                        equivalence(&nfa_to_dfa(&remove_epsilon(&renumbered)), &reference),
+                        Ok(Equivalence::Equivalent),
+                        "{} {:?}",
+                        pattern,
+                        order
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bfs_numbers_by_distance() {
+        // 7 -a-> 3 -b-> 9, and 7 -c-> 5
+        let mut nfa = NFA::new();
+        nfa.add_edge_auto(StateId(7), TransitionLabel::Char('c'), StateId(5));
+        nfa.add_edge_auto(StateId(3), TransitionLabel::Char('b'), StateId(9));
+        nfa.add_edge_auto(StateId(7), TransitionLabel::Char('a'), StateId(3));
+        nfa.start = StateId(7);
+        nfa.accept = vec![StateId(9), StateId(5)];
+
+        let bfs = renumber_bfs(&nfa);
+        assert_eq!(bfs.start, StateId(0));
+        // Edges followed by label: a before c
+        assert_eq!(
+            bfs.transitions[&StateId(0)],
+            [
+                (TransitionLabel::Char('c'), StateId(2)),
+                (TransitionLabel::Char('a'), StateId(1))
+            ]
+        );
+        assert_eq!(
+            bfs.transitions[&StateId(1)],
+            [(TransitionLabel::Char('b'), StateId(3))]
+        );
+        assert_eq!(bfs.accept, [StateId(2), StateId(3)]);
+    }
+
+    #[test]
+    fn topological_edges_go_forwards() {
+        let nfa = remove_epsilon(&full_pipeline("a(b+cd)e").unwrap().enfa);
+        let topo = renumber_topological(&nfa);
+        for (&from, edges) in &topo.transitions {
+            assert!(edges.iter().all(|&(_, to)| to > from));
+        }
+
+        // Unreachable states are kept, last
+        let mut nfa = full_pipeline("ab").unwrap().nfa;
+        nfa.add_edge_auto(StateId(100), TransitionLabel::Char('z'), StateId(101));
+        let n = nfa.transitions.len();
+        let bfs = renumber_bfs(&nfa);
+        assert_eq!(bfs.transitions.len(), n);
+        assert_eq!(
+            bfs.transitions[&StateId::new(n - 2)],
+            [(TransitionLabel::Char('z'), StateId::new(n - 1))]
+        );
+    }
+
+    #[test]
+    fn independent_of_input_numbering() {
+        // The same machine under two numberings renumbers identically
+        let nfa = full_pipeline("(a+b)*abb").unwrap().nfa;
+        let shuffled = relabel(
+            &nfa,
+            &nfa.transitions
+                .keys()
+                .map(|&s| (s, StateId(1000 - s.0)))
+                .collect(),
+        );
+        for order in [StateOrder::Bfs, StateOrder::Topological] {
+            let (a, b) = (renumber(&nfa, order), renumber(&shuffled, order));
+            assert_eq!((a.start, &a.accept), (b.start, &b.accept));
+            assert_eq!(a.transitions, b.transitions);
+        }
     }
 }
 
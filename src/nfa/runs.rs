@@ -0,0 +1,175 @@
+// nfa/runs.rs
+use super::nfa::{StateId, TransitionLabel, NFA};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl NFA {
+    /// One accepting run on `input`, as the sequence of visited states
+    /// (epsilon moves included). Shortest runs are preferred.
+    pub fn accepting_path(&self, input: &str) -> Option<Vec<StateId>> {
+        let chars: Vec<char> = input.chars().collect();
+
+        // BFS over configurations (state, consumed chars)
+        let mut parent: HashMap<(StateId, usize), (StateId, usize)> = HashMap::new();
+        let mut queue = VecDeque::new();
+        // Start configurations are their own parents
+        for s in self.starts() {
+            parent.insert((s, 0), (s, 0));
+            queue.push_back((s, 0));
+        }
+
+        while let Some((state, pos)) = queue.pop_front() {
+            if pos == chars.len() && self.accept.contains(&state) {
+                // Walk parents back to the start configuration
+                let mut path = vec![state];
+                let mut cur = (state, pos);
+                while parent[&cur] != cur {
+                    cur = parent[&cur];
+                    path.push(cur.0);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for (label, to) in self.transitions.get(&state).into_iter().flatten() {
+                let next = match label {
+                    TransitionLabel::Epsilon => (*to, pos),
+                    TransitionLabel::Char(c) if chars.get(pos) == Some(c) => (*to, pos + 1),
+                    TransitionLabel::Char(_) => continue,
+                };
+                if let Entry::Vacant(e) = parent.entry(next) {
+                    e.insert((state, pos));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Up to `limit` distinct accepting runs on `input`. A run never visits
+    /// the same (state, position) twice, so epsilon cycles can't make the
+    /// enumeration infinite. More than one run means `input` is parsed
+    /// ambiguously.
+    pub fn accepting_runs(&self, input: &str, limit: usize) -> Vec<Vec<StateId>> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut runs = Vec::new();
+        for s in self.starts() {
+            let mut path = vec![s];
+            let mut on_path = HashSet::from([(s, 0)]);
+            self.collect_runs(&chars, 0, &mut path, &mut on_path, &mut runs, limit);
+        }
+        runs
+    }
+
+    // DFS over configurations; `path` holds the run so far.
+    fn collect_runs(
+        &self,
+        chars: &[char],
+        pos: usize,
+        path: &mut Vec<StateId>,
+        on_path: &mut HashSet<(StateId, usize)>,
+        runs: &mut Vec<Vec<StateId>>,
+        limit: usize,
+    ) {
+        if runs.len() >= limit {
+            return;
+        }
+
+        let state = *path.last().unwrap();
+        if pos == chars.len() && self.accept.contains(&state) {
+            runs.push(path.clone());
+        }
+
+        for (label, to) in self.transitions.get(&state).into_iter().flatten() {
+            let next_pos = match label {
+                TransitionLabel::Epsilon => pos,
+                TransitionLabel::Char(c) if chars.get(pos) == Some(c) => pos + 1,
+                TransitionLabel::Char(_) => continue,
+            };
+            if !on_path.insert((*to, next_pos)) {
+                continue;
+            }
+
+            path.push(*to);
+            self.collect_runs(chars, next_pos, path, on_path, runs, limit);
+            path.pop();
+            on_path.remove(&(*to, next_pos));
+        }
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfa::thompson::enfa_from_ast;
+    use crate::regex::parser::parse_language;
+
+    fn build(expr: &str) -> NFA {
+        enfa_from_ast(&parse_language(expr).unwrap()).unwrap()
+    }
+
+    // Check that consecutive states are linked by an edge
+    fn is_run(nfa: &NFA, path: &[StateId]) -> bool {
+        path.windows(2)
+            .all(|w| nfa.transitions[&w[0]].iter().any(|(_, to)| *to == w[1]))
+    }
+
+    #[test]
+    fn path_for_accepted_input() {
+        let nfa = build("(a+b)*c");
+        let path = nfa.accepting_path("abc").unwrap();
+
+        assert_eq!(path.first(), Some(&nfa.start));
+        assert!(nfa.accept.contains(path.last().unwrap()));
+        assert!(is_run(&nfa, &path));
+    }
+
+    #[test]
+    fn no_path_for_rejected_input() {
+        let nfa = build("(a+b)*c");
+        assert_eq!(nfa.accepting_path("ab"), None);
+        assert_eq!(nfa.accepting_path("cc"), None);
+    }
+
+    #[test]
+    fn path_on_epsilon_free_nfa() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+        let t = nfa.add_state();
+        nfa.add_edge(s, TransitionLabel::Char('a'), t).unwrap();
+        nfa.add_edge(t, TransitionLabel::Char('a'), t).unwrap();
+        nfa.accept = vec![t];
+
+        assert_eq!(nfa.accepting_path("aaa"), Some(vec![s, t, t, t]));
+    }
+
+    #[test]
+    fn runs_show_ambiguity() {
+        // "a" can come from either side of the union
+        let nfa = build("a+a");
+        let runs = nfa.accepting_runs("a", 10);
+
+        assert_eq!(runs.len(), 2);
+        assert_ne!(runs[0], runs[1]);
+        assert!(runs.iter().all(|r| is_run(&nfa, r)));
+
+        // Unambiguous input
+        assert_eq!(build("ab").accepting_runs("ab", 10).len(), 1);
+        assert!(build("ab").accepting_runs("ba", 10).is_empty());
+    }
+
+    #[test]
+    fn runs_respect_limit() {
+        // (a*)* has many ways to split "aaa", plus epsilon cycles
+        let nfa = build("(a*)*");
+        assert_eq!(nfa.accepting_runs("aaa", 3).len(), 3);
+        assert!(nfa.accepting_runs("aaa", 1000).len() > 3);
+    }
+}
@@ -1,45 +1,161 @@
 // nfa/thompson.rs
-use super::nfa::{TransitionLabel, NFA};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+use super::epsilon_elimination::remove_epsilon;
+use super::nfa::{StateId, TransitionLabel, NFA};
+use crate::dfa::dfa::{nfa_to_dfa, DFA};
+use crate::dfa::ops::{complement, intersect};
 use crate::nfa::renumber::renumber_dfs;
 use crate::regex::ast::RegexAST;
+use crate::regex::desugar::desugar;
+
+/// How counted repetition `e{n,m}` is turned into NFA states.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepeatStrategy {
+    // Desugar the AST first: e…e e?…e?, one subtree per copy
+    #[default]
+    Unroll,
+    // Build e's fragment once and stamp out copies of its states. The
+    // optional copies nest as (e(e(e)?)?)?, sharing one exit, so every
+    // ε-closure stays constant-size and ε-elimination stays linear.
+    Shared,
+}
 
-pub struct Thompson {
+pub struct Thompson<'a> {
     pub nfa: NFA,
+    // ε-NFAs that `{name}` references are spliced from
+    refs: Option<&'a HashMap<String, NFA>>,
+    // Symbols that `.` and negated classes range over
+    alphabet: Option<BTreeSet<char>>,
+    repeat: RepeatStrategy,
 }
 
+#[derive(Clone, Copy)]
 struct Fragment {
-    start: usize,
-    accept: usize,
+    start: StateId,
+    accept: StateId,
+}
+
+impl Default for Thompson<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl Thompson {
+impl<'a> Thompson<'a> {
     pub fn new() -> Self {
-        Self { nfa: NFA::new() }
+        Self {
+            nfa: NFA::new(),
+            refs: None,
+            alphabet: None,
+            repeat: RepeatStrategy::default(),
+        }
+    }
+
+    /// Builder that resolves `{name}` references against `refs`.
+    pub fn with_refs(refs: &'a HashMap<String, NFA>) -> Self {
+        Self {
+            nfa: NFA::new(),
+            refs: Some(refs),
+            alphabet: None,
+            repeat: RepeatStrategy::default(),
+        }
+    }
+
+    /// Expand `.` and negated classes against `alphabet`. Without this, the
+    /// alphabet is inferred from the characters written in the expression.
+    pub fn with_alphabet(mut self, alphabet: BTreeSet<char>) -> Self {
+        self.alphabet = Some(alphabet);
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: RepeatStrategy) -> Self {
+        self.repeat = repeat;
+        self
     }
 
-    fn new_state(&mut self) -> usize {
+    fn new_state(&mut self) -> StateId {
         self.nfa.add_state()
     }
 
-    fn add_epsilon(&mut self, from: usize, to: usize) {
-        self.nfa.add_edge(from, TransitionLabel::Epsilon, to);
+    fn add_epsilon(&mut self, from: StateId, to: StateId) {
+        self.nfa
+            .add_edge(from, TransitionLabel::Epsilon, to)
+            .expect("Thompson fragments only connect existing states");
     }
 
-    fn add_char(&mut self, from: usize, c: char, to: usize) {
-        self.nfa.add_edge(from, TransitionLabel::Char(c), to);
+    fn add_char(&mut self, from: StateId, c: char, to: StateId) {
+        self.nfa
+            .add_edge(from, TransitionLabel::Char(c), to)
+            .expect("Thompson fragments only connect existing states");
     }
 
-    pub fn from_ast(mut self, ast: &RegexAST) -> NFA {
-        let frag = self.build(ast);
+    /// The ε-NFA of `ast`. Fails when it references a language this
+    /// builder wasn't given.
+    pub fn from_ast(mut self, ast: &RegexAST) -> Result<NFA, String> {
+        if let Some(name) = ast
+            .references()
+            .into_iter()
+            .find(|name| !self.refs.is_some_and(|refs| refs.contains_key(*name)))
+        {
+            return Err(format!("Unknown language: {{{}}}", name));
+        }
+        if self.alphabet.is_none() {
+            self.alphabet = Some(ast.literals());
+        }
+        let frag = match self.repeat {
+            RepeatStrategy::Unroll => self.build(&desugar(ast)),
+            RepeatStrategy::Shared => self.build(ast),
+        };
         self.nfa.start = frag.start;
         self.nfa.accept = vec![frag.accept];
         // Renumber for cleaner output
-        renumber_dfs(&self.nfa)
+        let nfa = renumber_dfs(&self.nfa);
+        debug_assert_eq!(nfa.validate_thompson(), Ok(()));
+        Ok(nfa)
     }
 
     fn build(&mut self, ast: &RegexAST) -> Fragment {
         match ast {
+            RegexAST::Epsilon => {
+                let s = self.new_state();
+                let t = self.new_state();
+
+                self.add_epsilon(s, t);
+
+                Fragment {
+                    start: s,
+                    accept: t,
+                }
+            }
+            RegexAST::Empty => {
+                // No path from start to accept
+                let s = self.new_state();
+                let t = self.new_state();
+
+                Fragment {
+                    start: s,
+                    accept: t,
+                }
+            }
             RegexAST::Char(c) => self.char_frag(*c),
+            RegexAST::Class(ranges) => {
+                let members: BTreeSet<char> = ranges.iter().cloned().flatten().collect();
+                self.set_frag(&members)
+            }
+            RegexAST::Any => {
+                let alphabet = self.alphabet.clone().unwrap_or_default();
+                self.set_frag(&alphabet)
+            }
+            RegexAST::NegClass(ranges) => {
+                let alphabet = self.alphabet.clone().unwrap_or_default();
+                let members: BTreeSet<char> = alphabet
+                    .into_iter()
+                    .filter(|c| !ranges.iter().any(|r| r.contains(c)))
+                    .collect();
+                self.set_frag(&members)
+            }
             RegexAST::Concat(a, b) => {
                 let left = self.build(a);
                 let right = self.build(b);
@@ -73,7 +189,7 @@ impl Thompson {
                     accept: t,
                 }
             }
-            RegexAST::Star(expr) => {
+            RegexAST::Optional(expr) => {
                 let inner = self.build(expr);
 
                 let s = self.new_state();
@@ -81,9 +197,7 @@ impl Thompson {
 
                 // s -> inner.start
                 self.add_epsilon(s, inner.start);
-                // inner.accept -> inner.start
-                self.add_epsilon(inner.accept, inner.start);
-                // s -> t (skip)
+                // s -> t (bypass)
                 self.add_epsilon(s, t);
                 // inner.accept -> t
                 self.add_epsilon(inner.accept, t);
@@ -93,6 +207,255 @@ impl Thompson {
                     accept: t,
                 }
             }
+            RegexAST::Intersect(a, b) => {
+                let left = self.sub_dfa(a);
+                let right = self.sub_dfa(b);
+                self.dfa_frag(&intersect(&left, &right))
+            }
+            RegexAST::Complement(expr) => {
+                let inner = self.sub_dfa(expr);
+                let alphabet = self.alphabet.clone().unwrap_or_default();
+                self.dfa_frag(&complement(&inner, &alphabet))
+            }
+            RegexAST::Repeat(expr, min, max) => self.repeat_frag(expr, *min, *max),
+            RegexAST::Ref(name) => {
+                let sub = self
+                    .refs
+                    .and_then(|refs| refs.get(name))
+                    .expect("references are checked by from_ast");
+                self.splice(sub)
+            }
+            RegexAST::Star(expr) => {
+                let inner = self.build(expr);
+                self.star_frag(inner)
+            }
+        }
+    }
+
+    fn star_frag(&mut self, inner: Fragment) -> Fragment {
+        let s = self.new_state();
+        let t = self.new_state();
+
+        // s -> inner.start
+        self.add_epsilon(s, inner.start);
+        // inner.accept -> inner.start
+        self.add_epsilon(inner.accept, inner.start);
+        // s -> t (skip)
+        self.add_epsilon(s, t);
+        // inner.accept -> t
+        self.add_epsilon(inner.accept, t);
+
+        Fragment {
+            start: s,
+            accept: t,
+        }
+    }
+
+    // e{min,max} under `RepeatStrategy::Shared`. Only reached when the AST
+    // wasn't desugared.
+    fn repeat_frag(&mut self, expr: &RegexAST, min: u32, max: Option<u32>) -> Fragment {
+        // Like desugaring, a max below min means exactly min
+        let copies = match max {
+            Some(max) => max.max(min) as usize,
+            None => min as usize + 1,
+        };
+        if copies == 0 {
+            return self.build(&RegexAST::Epsilon);
+        }
+
+        let first_state = self.nfa.transitions.len();
+        let template = self.build(expr);
+        let states = first_state..self.nfa.transitions.len();
+        let mut frags = vec![template];
+        while frags.len() < copies {
+            frags.push(self.copy_frag(states.clone(), &template));
+        }
+
+        let mut tail = frags.split_off(min as usize);
+        let mut parts = frags;
+        if max.is_none() {
+            parts.push(self.star_frag(tail.pop().expect("one copy for the star")));
+        } else if !tail.is_empty() {
+            // s_i -> copy_i -> s_i+1, every s_i also skipping to the exit
+            let exit = self.new_state();
+            let mut next = exit;
+            for frag in tail.into_iter().rev() {
+                let s = self.new_state();
+                self.add_epsilon(s, frag.start);
+                self.add_epsilon(s, exit);
+                self.add_epsilon(frag.accept, next);
+                next = s;
+            }
+            parts.push(Fragment {
+                start: next,
+                accept: exit,
+            });
+        }
+
+        for pair in parts.windows(2) {
+            self.add_epsilon(pair[0].accept, pair[1].start);
+        }
+        Fragment {
+            start: parts[0].start,
+            accept: parts[parts.len() - 1].accept,
+        }
+    }
+
+    // Duplicate the states in `states`, which hold exactly `frag`.
+    fn copy_frag(&mut self, states: Range<usize>, frag: &Fragment) -> Fragment {
+        let offset = self.nfa.transitions.len() - states.start;
+        let shift = |s: StateId| StateId::new(s.index() + offset);
+        for _ in states.clone() {
+            self.new_state();
+        }
+        for old in states.map(StateId::new) {
+            for (label, to) in self.nfa.transitions[&old].clone() {
+                self.nfa
+                    .add_edge(shift(old), label, shift(to))
+                    .expect("copied states were created above");
+            }
+        }
+        Fragment {
+            start: shift(frag.start),
+            accept: shift(frag.accept),
+        }
+    }
+
+    // Compile a subexpression on its own, down to a DFA.
+    fn sub_dfa(&self, ast: &RegexAST) -> DFA {
+        let sub = Thompson {
+            nfa: NFA::new(),
+            refs: self.refs,
+            alphabet: self.alphabet.clone(),
+            repeat: self.repeat,
+        };
+        let nfa = sub
+            .from_ast(ast)
+            .expect("references are checked by from_ast");
+        nfa_to_dfa(&remove_epsilon(&nfa))
+    }
+
+    // Embed a DFA as a Thompson-shaped fragment: each DFA state fans out
+    // through a binary tree of epsilon edges, one branch per target (and
+    // one to the fragment's accept when the DFA state accepts).
+    fn dfa_frag(&mut self, dfa: &DFA) -> Fragment {
+        let mut ids: HashMap<StateId, StateId> = HashMap::new();
+        for q in dfa.states() {
+            ids.insert(q, self.new_state());
+        }
+        let accept = self.new_state();
+
+        for q in dfa.states() {
+            // Group symbols by target so each branch is one parallel-edge hop
+            let mut by_target: HashMap<StateId, Vec<char>> = HashMap::new();
+            for (&c, &t) in dfa.transitions.get(&q).into_iter().flatten() {
+                by_target.entry(t).or_default().push(c);
+            }
+            let mut targets: Vec<(StateId, Vec<char>)> = by_target.into_iter().collect();
+            targets.sort_unstable();
+
+            let mut branches = Vec::new();
+            for (t, chars) in targets {
+                let hop = self.new_state();
+                for c in chars {
+                    self.add_char(hop, c, ids[&t]);
+                }
+                branches.push(hop);
+            }
+            if dfa.accepts.contains(&q) {
+                branches.push(accept);
+            }
+
+            self.fan_out(ids[&q], &branches);
+        }
+
+        Fragment {
+            start: ids[&dfa.start],
+            accept,
+        }
+    }
+
+    // Connect `from` to every branch using at most two epsilon edges per state.
+    fn fan_out(&mut self, from: StateId, branches: &[StateId]) {
+        match branches {
+            [] => {}
+            [b] => self.add_epsilon(from, *b),
+            [a, b] => {
+                self.add_epsilon(from, *a);
+                self.add_epsilon(from, *b);
+            }
+            _ => {
+                let (left, right) = branches.split_at(branches.len() / 2);
+                for half in [left, right] {
+                    let mid = self.new_state();
+                    self.add_epsilon(from, mid);
+                    self.fan_out(mid, half);
+                }
+            }
+        }
+    }
+
+    // Copy a whole NFA into the one under construction. Start and accept
+    // states need not have a `transitions` entry (`NFA::new()` has none),
+    // so they are collected along with the keys and edge targets.
+    fn splice(&mut self, sub: &NFA) -> Fragment {
+        let old_states: BTreeSet<StateId> = sub
+            .transitions
+            .iter()
+            .flat_map(|(from, edges)| std::iter::once(*from).chain(edges.iter().map(|(_, to)| *to)))
+            .chain(sub.starts())
+            .chain(sub.accept.iter().copied())
+            .collect();
+
+        let mut old_to_new: HashMap<StateId, StateId> = HashMap::new();
+        for old in old_states {
+            old_to_new.insert(old, self.new_state());
+        }
+
+        for (old, edges) in &sub.transitions {
+            for (label, to) in edges {
+                self.nfa
+                    .add_edge(old_to_new[old], label.clone(), old_to_new[to])
+                    .expect("spliced states were created above");
+            }
+        }
+
+        // Several starts: enter all of them from one fresh state
+        let start = if sub.extra_starts.is_empty() {
+            old_to_new[&sub.start]
+        } else {
+            let s = self.new_state();
+            let starts: Vec<StateId> = sub.starts().iter().map(|q| old_to_new[q]).collect();
+            self.fan_out(s, &starts);
+            s
+        };
+        let accept = match sub.accept.as_slice() {
+            [a] if sub.transitions.get(a).is_none_or(|edges| edges.is_empty()) => old_to_new[a],
+            accepts => {
+                // Funnel multiple accepts into a fresh one to keep Thompson shape
+                let t = self.new_state();
+                for a in accepts {
+                    self.add_epsilon(old_to_new[a], t);
+                }
+                t
+            }
+        };
+
+        Fragment { start, accept }
+    }
+
+    // One parallel char edge per member of the set
+    fn set_frag(&mut self, members: &BTreeSet<char>) -> Fragment {
+        let s = self.new_state();
+        let t = self.new_state();
+
+        for &c in members {
+            self.add_char(s, c, t);
+        }
+
+        Fragment {
+            start: s,
+            accept: t,
         }
     }
 
@@ -115,7 +478,7 @@ impl Thompson {
 * =====================
 */
 
-pub fn enfa_from_ast(ast: &RegexAST) -> NFA {
+pub fn enfa_from_ast(ast: &RegexAST) -> Result<NFA, String> {
     Thompson::new().from_ast(ast)
 }
 
@@ -128,14 +491,17 @@ pub fn enfa_from_ast(ast: &RegexAST) -> NFA {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::regex::{parser::Parser, tokenizer::tokenize};
+    use crate::regex::{
+        parser::{parse_language, Parser},
+        tokenizer::tokenize,
+    };
 
     fn build(expr: &str) -> NFA {
-        let tokens = tokenize(expr);
+        let tokens = tokenize(expr).unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse_expr().unwrap();
 
-        Thompson::new().from_ast(&ast)
+        Thompson::new().from_ast(&ast).unwrap()
     }
 
     #[test]
@@ -207,10 +573,173 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_optional_nfa() {
+        let nfa = build("a?");
+
+        // New start with an epsilon into 'a' and an epsilon bypass
+        let start_edges = nfa.transitions.get(&nfa.start).unwrap();
+        assert_eq!(start_edges.len(), 2);
+        assert!(start_edges.iter().all(|e| e.0 == TransitionLabel::Epsilon));
+        assert!(start_edges.iter().any(|e| vec![e.1] == nfa.accept));
+    }
+
+    #[test]
+    fn test_class_nfa() {
+        let nfa = build("[a-c]");
+        assert_eq!(nfa.transitions.len(), 2);
+
+        let outgoing = nfa.transitions.get(&nfa.start).unwrap();
+        assert_eq!(outgoing.len(), 3);
+        assert!(outgoing.iter().all(|e| vec![e.1] == nfa.accept));
+    }
+
+    #[test]
+    fn test_negated_class_nfa() {
+        let ast = parse_language("[^a-c]").unwrap();
+        let alphabet: BTreeSet<char> = "abcxyz".chars().collect();
+        let nfa = Thompson::new()
+            .with_alphabet(alphabet)
+            .from_ast(&ast)
+            .unwrap();
+
+        let outgoing = nfa.transitions.get(&nfa.start).unwrap();
+        let mut labels: Vec<char> = outgoing
+            .iter()
+            .filter_map(|(l, _)| match l {
+                TransitionLabel::Char(c) => Some(*c),
+                TransitionLabel::Epsilon => None,
+            })
+            .collect();
+        labels.sort_unstable();
+        assert_eq!(labels, vec!['x', 'y', 'z']);
+    }
+
     #[test]
     fn test_complex_nfa() {
         let nfa = build("(a+b)*c");
 
         assert!(nfa.transitions.len() >= 8);
     }
+
+    #[test]
+    fn shared_repeat_matches_unrolled() {
+        use crate::decisions::{equivalence, Equivalence};
+
+        for pattern in [
+            "a{3}",
+            "a{2,5}b",
+            "(ab){0,3}",
+            "(a+b){2,}c",
+            "(a{1,2}b){2,3}",
+            "a{0}b",
+            "(a{2}&.*a)c{1,}",
+        ] {
+            let ast = parse_language(pattern).unwrap();
+            let shared = Thompson::new()
+                .with_repeat(RepeatStrategy::Shared)
+                .from_ast(&ast)
+                .unwrap();
+            assert_eq!(shared.validate_thompson(), Ok(()), "{}", pattern);
+
+            let unrolled = nfa_to_dfa(&remove_epsilon(&Thompson::new().from_ast(&ast).unwrap()));
+            let shared = nfa_to_dfa(&remove_epsilon(&shared));
+            assert_eq!(
+                equivalence(&shared, &unrolled),
+                Ok(Equivalence::Equivalent),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn shared_repeat_keeps_closures_small() {
+        use crate::nfa::epsilon_elimination::epsilon_closure_of_state;
+
+        // Total ε-closure size is the work ε-elimination does
+        let closures = |nfa: &NFA| -> usize {
+            nfa.transitions
+                .keys()
+                .map(|&s| epsilon_closure_of_state(nfa, s).len())
+                .sum()
+        };
+        let ast = parse_language("a{0,200}").unwrap();
+        let unrolled = Thompson::new().from_ast(&ast).unwrap();
+        let shared = Thompson::new()
+            .with_repeat(RepeatStrategy::Shared)
+            .from_ast(&ast)
+            .unwrap();
+
+        assert!(closures(&shared) < 4 * shared.transitions.len());
+        assert!(closures(&unrolled) > 50 * unrolled.transitions.len());
+    }
+
+    #[test]
+    fn shared_repeat_uses_fewer_states() {
+        for (pattern, copies) in [("a{0,200}", 200), ("(ab){50,100}", 200)] {
+            let ast = parse_language(pattern).unwrap();
+            let unrolled = Thompson::new().from_ast(&ast).unwrap().transitions.len();
+            let shared = Thompson::new()
+                .with_repeat(RepeatStrategy::Shared)
+                .from_ast(&ast)
+                .unwrap()
+                .transitions
+                .len();
+            assert!(shared < unrolled);
+            assert!(shared <= 4 * copies);
+        }
+    }
+
+    #[test]
+    fn splice_enters_every_start() {
+        let multi = build("ab")
+            .union_multi_start(build("cd"))
+            .union_multi_start(build("ef"));
+        assert_eq!(multi.starts().len(), 3);
+        let refs = HashMap::from([("multi".to_string(), multi)]);
+        let ast = parse_language("x{multi}").unwrap();
+        let nfa = Thompson::with_refs(&refs).from_ast(&ast).unwrap();
+        assert_eq!(nfa.validate_thompson(), Ok(()));
+
+        let dfa = nfa_to_dfa(&remove_epsilon(&nfa));
+        for (word, accepted) in [("xab", true), ("xcd", true), ("xef", true), ("xba", false)] {
+            assert_eq!(dfa.accepts(word), accepted, "{}", word);
+        }
+    }
+
+    #[test]
+    fn unknown_reference_is_an_error() {
+        let ast = parse_language("a{digit}").unwrap();
+        assert_eq!(
+            Thompson::new().from_ast(&ast).unwrap_err(),
+            "Unknown language: {digit}"
+        );
+
+        let refs = HashMap::from([("digit".to_string(), build("0+1"))]);
+        let nfa = Thompson::with_refs(&refs).from_ast(&ast).unwrap();
+        assert_eq!(nfa.accept.len(), 1);
+    }
+
+    #[test]
+    fn splice_states_without_entries() {
+        // No states at all: the reference matches nothing
+        let refs = HashMap::from([("none".to_string(), NFA::new())]);
+        let ast = parse_language("a+{none}").unwrap();
+        let nfa = Thompson::with_refs(&refs).from_ast(&ast).unwrap();
+        let dfa = nfa_to_dfa(&remove_epsilon(&nfa));
+        assert!(dfa.accepts("a") && !dfa.accepts(""));
+
+        // The accept state only appears as an edge target
+        let mut transitions = HashMap::new();
+        transitions.insert(StateId(0), vec![(TransitionLabel::Char('b'), StateId(1))]);
+        let sub = NFA::from_parts(StateId(0), vec![StateId(1)], transitions);
+        let refs = HashMap::from([("b".to_string(), sub)]);
+        let ast = parse_language("a{b}*").unwrap();
+        let nfa = Thompson::with_refs(&refs).from_ast(&ast).unwrap();
+        let dfa = nfa_to_dfa(&remove_epsilon(&nfa));
+        for (word, accepted) in [("a", true), ("abb", true), ("b", false)] {
+            assert_eq!(dfa.accepts(word), accepted, "{}", word);
+        }
+    }
 }
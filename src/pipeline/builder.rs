@@ -0,0 +1,186 @@
+// pipeline/builder.rs
+//
+// Pipeline settings chosen one call at a time: a preset for the stage
+// strategies, then whatever the language itself needs.
+use super::preset::{Preset, PresetOutput};
+use super::tests::{MatchMode, PipelineOptions};
+use crate::dfa::dfa::{DeterminizeOptions, FrontierOrder};
+use crate::dfa::minimize::MinimizeAlgorithm;
+use crate::regex::parser::SyntaxConfig;
+use std::collections::BTreeSet;
+
+/// Builds a pipeline run. Starts from `Preset::default()`; `with_preset`
+/// swaps in another preset's stage strategies without touching the
+/// settings made so far, so calls can come in any order.
+#[derive(Debug, Clone)]
+pub struct PipelineBuilder {
+    preset: Preset,
+    opts: PipelineOptions,
+}
+
+impl Default for PipelineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipelineBuilder {
+    pub fn new() -> Self {
+        let preset = Preset::default();
+        Self {
+            preset,
+            opts: preset.options(),
+        }
+    }
+
+    pub fn with_preset(mut self, preset: Preset) -> Self {
+        preset.apply(&mut self.opts);
+        self.preset = preset;
+        self
+    }
+
+    pub fn with_syntax(mut self, syntax: SyntaxConfig) -> Self {
+        self.opts.syntax = syntax;
+        self
+    }
+
+    /// Prefix and Substring modes pad the language with Σ*; give the
+    /// input's alphabet with `with_alphabet` too, or input with chars the
+    /// pattern doesn't write is rejected. See `MatchMode`.
+    pub fn with_mode(mut self, mode: MatchMode) -> Self {
+        self.opts.mode = mode;
+        self
+    }
+
+    pub fn with_alphabet(mut self, alphabet: BTreeSet<char>) -> Self {
+        self.opts.alphabet = Some(alphabet);
+        self
+    }
+
+    pub fn with_max_alphabet(mut self, max: usize) -> Self {
+        self.opts.max_alphabet = max;
+        self
+    }
+
+    /// Subset construction settings. Only Teaching determinizes; the
+    /// other presets reject anything but the default at `compile`.
+    pub fn with_determinize(mut self, determinize: DeterminizeOptions) -> Self {
+        self.opts.determinize = determinize;
+        self
+    }
+
+    pub fn with_frontier_order(mut self, order: FrontierOrder) -> Self {
+        self.opts.determinize.order = order;
+        self
+    }
+
+    pub fn with_minimize(mut self, algorithm: MinimizeAlgorithm) -> Self {
+        self.opts.minimize = algorithm;
+        self
+    }
+
+    pub fn with_minimize_budget(mut self, budget: usize) -> Self {
+        self.opts.minimize_budget = Some(budget);
+        self
+    }
+
+    pub fn with_case_insensitive(mut self, on: bool) -> Self {
+        self.opts.case_insensitive = on;
+        self
+    }
+
+    pub fn preset(&self) -> Preset {
+        self.preset
+    }
+
+    pub fn options(&self) -> &PipelineOptions {
+        &self.opts
+    }
+
+    /// Compile `pattern` with the chosen preset and settings.
+    pub fn compile(&self, pattern: &str) -> Result<PresetOutput, String> {
+        self.preset.compile_with(pattern, &self.opts)
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfa::thompson::RepeatStrategy;
+
+    #[test]
+    fn preset_and_settings_in_any_order() {
+        let a = PipelineBuilder::new()
+            .with_case_insensitive(true)
+            .with_mode(MatchMode::Prefix)
+            .with_preset(Preset::Embedded);
+        let b = PipelineBuilder::new()
+            .with_preset(Preset::Embedded)
+            .with_mode(MatchMode::Prefix)
+            .with_case_insensitive(true);
+        for builder in [a, b] {
+            assert_eq!(builder.preset(), Preset::Embedded);
+            assert_eq!(builder.options().repeat, RepeatStrategy::Shared);
+            let out = builder.compile("ab").unwrap();
+            assert!(out.mindfa.accepts("ABba"));
+            assert!(out.bytes.unwrap().accepts_bytes(b"aBba"));
+        }
+    }
+
+    #[test]
+    fn defaults_to_teaching() {
+        let out = PipelineBuilder::default().compile("a*b").unwrap();
+        assert!(out.stages.is_some() && out.subsets.is_some());
+        assert!(PipelineBuilder::new()
+            .with_max_alphabet(1)
+            .compile("ab")
+            .is_err());
+    }
+
+    #[test]
+    fn teaching_takes_the_frontier_order() {
+        let bfs = PipelineBuilder::new().compile("abc+de").unwrap();
+        let dfs = PipelineBuilder::new()
+            .with_frontier_order(FrontierOrder::Dfs)
+            .compile("abc+de")
+            .unwrap();
+        let (bfs, dfs) = (bfs.stages.unwrap().dfa, dfs.stages.unwrap().dfa);
+        assert_ne!(bfs.transitions, dfs.transitions);
+        assert_eq!(bfs.states().len(), dfs.states().len());
+    }
+
+    #[test]
+    fn derivative_presets_reject_stage_options() {
+        for preset in [Preset::Fast, Preset::Embedded] {
+            let builder = PipelineBuilder::new().with_preset(preset);
+            let budget = builder
+                .clone()
+                .with_minimize(MinimizeAlgorithm::Moore)
+                .with_minimize_budget(10);
+            let order = builder.clone().with_frontier_order(FrontierOrder::Dfs);
+            let determinize = builder.clone().with_determinize(DeterminizeOptions {
+                order: FrontierOrder::SmallestSubset,
+            });
+            for b in [budget, order, determinize] {
+                assert!(b.compile("(a+b)*abb").is_err(), "{}", preset);
+            }
+
+            // The minimization algorithm is honored
+            let moore = builder
+                .with_minimize(MinimizeAlgorithm::Moore)
+                .compile("(a+b)*abb")
+                .unwrap();
+            assert_eq!(moore.mindfa.states().len(), 4);
+        }
+
+        let mut opts = Preset::Fast.options();
+        opts.repeat = RepeatStrategy::Unroll;
+        assert!(Preset::Fast.compile_with("a{2}", &opts).is_err());
+    }
+}
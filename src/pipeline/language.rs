@@ -0,0 +1,221 @@
+// pipeline/language.rs
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::preprocess::{Matcher, Preprocessor};
+use crate::dfa::canonical::fingerprint;
+use crate::dfa::compiled::CompiledDFA;
+use crate::dfa::dfa::nfa_to_dfa;
+use crate::dfa::minimize::minimize_dfa;
+use crate::dfa::to_regex::dfa_to_regex_bounded;
+use crate::nfa::epsilon_elimination::remove_epsilon;
+use crate::nfa::nfa::NFA;
+use crate::nfa::thompson::Thompson;
+use crate::regex::ast::RegexAST;
+use crate::regex::parser::parse_language;
+
+/// A named, compiled regex that other regexes can reference as `{name}`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Language {
+    pub name: String,
+    pub source: String,
+    pub ast: RegexAST,
+    pub enfa: NFA,
+    // Applied to input before matching; references don't inherit it
+    pub preprocessor: Preprocessor,
+}
+
+impl Language {
+    /// Fingerprint of the recognized language, independent of how the
+    /// regex was spelled. See `dfa::canonical::fingerprint`.
+    pub fn fingerprint(&self) -> u128 {
+        fingerprint(&nfa_to_dfa(&remove_epsilon(&self.enfa)))
+    }
+
+    /// Compile to a matcher that normalizes input with this language's
+    /// preprocessor. While `testing::corpus` is recording, the matcher
+    /// records its verdicts under the source, or under the normalized
+    /// pattern when the preprocessor rewrote it, or under a regex read off
+    /// the DFA when the source references other languages.
+    pub fn matcher(&self) -> Matcher {
+        let dfa = minimize_dfa(&nfa_to_dfa(&remove_epsilon(&self.enfa)));
+        let matcher = Matcher::new(CompiledDFA::from_dfa(&dfa), self.preprocessor.clone());
+        if !self.ast.references().is_empty() {
+            matcher
+        } else if self.preprocessor.is_identity() {
+            matcher.with_pattern(&self.source)
+        } else {
+            matcher.with_pattern(self.ast.to_pattern())
+        }
+    }
+
+    /// A simplified expression for this language, rebuilt from its minimal
+    /// DFA, with references expanded. Often more readable than the source.
+    pub fn to_regex(&self) -> String {
+        self.to_regex_with(usize::MAX).expect("no size limit")
+    }
+
+    /// Like `to_regex`, but fails instead of producing an expression of
+    /// more than `max_nodes` AST nodes.
+    pub fn to_regex_with(&self, max_nodes: usize) -> Result<String, String> {
+        let dfa = minimize_dfa(&nfa_to_dfa(&remove_epsilon(&self.enfa)));
+        match dfa_to_regex_bounded(&dfa, max_nodes) {
+            Some(ast) => Ok(ast.to_pattern()),
+            None => Err(format!(
+                "Regex for '{}' too large: over {} nodes",
+                self.name, max_nodes
+            )),
+        }
+    }
+}
+
+/// A set of compiled languages. Later definitions may reference earlier
+/// ones; their ε-NFAs are spliced in instead of re-parsing the source.
+#[derive(Debug, Default)]
+pub struct LanguageSet {
+    languages: HashMap<String, Language>,
+    enfas: HashMap<String, NFA>,
+}
+
+impl LanguageSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compile `source` and register it under `name`.
+    pub fn define(&mut self, name: &str, source: &str) -> Result<&Language, String> {
+        self.define_with(name, source, Preprocessor::default())
+    }
+
+    /// Like `define`, with input normalized by `preprocessor` when matching.
+    /// The pattern is normalized the same way (see
+    /// `Preprocessor::normalize_pattern`), so it can be written against
+    /// the raw input.
+    pub fn define_with(
+        &mut self,
+        name: &str,
+        source: &str,
+        preprocessor: Preprocessor,
+    ) -> Result<&Language, String> {
+        let ast = parse_language(source).map_err(|e| format!("Parse error: {}", e))?;
+        let ast = preprocessor.normalize_pattern(&ast);
+        let enfa = self.compile_ast(&ast)?;
+
+        self.enfas.insert(name.to_string(), enfa.clone());
+        self.languages.insert(
+            name.to_string(),
+            Language {
+                name: name.to_string(),
+                source: source.to_string(),
+                ast,
+                enfa,
+                preprocessor,
+            },
+        );
+
+        Ok(&self.languages[name])
+    }
+
+    /// Build the ε-NFA of an expression, resolving references in this set.
+    pub fn compile_ast(&self, ast: &RegexAST) -> Result<NFA, String> {
+        Thompson::with_refs(&self.enfas).from_ast(ast)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Language> {
+        self.languages.get(name)
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_are_spliced() {
+        let mut langs = LanguageSet::new();
+        langs.define("digit", "0+1").unwrap();
+        langs.define("number", "{digit}{digit}*").unwrap();
+        let top = langs.define("pair", "{number}a{number}").unwrap();
+
+        assert_eq!(top.enfa.validate_thompson(), Ok(()));
+
+        let dfa = nfa_to_dfa(&remove_epsilon(&top.enfa));
+        assert!(dfa.accepts("0a1"));
+        assert!(dfa.accepts("101a0011"));
+        assert!(!dfa.accepts("a1"));
+        assert!(!dfa.accepts("10a"));
+    }
+
+    #[test]
+    fn preprocessor_applies_when_matching() {
+        let mut langs = LanguageSet::new();
+        let p = Preprocessor::new().case_fold().map('_', None);
+        let m = langs.define_with("kw", "select", p).unwrap().matcher();
+
+        assert!(m.accepts("SELECT"));
+        assert!(m.accepts("Se_lect"));
+        assert!(!m.accepts("selects"));
+
+        // The pattern is normalized like the input
+        let p = Preprocessor::new().case_fold().map('-', None);
+        let m = langs
+            .define_with("kw", "SEL\\-ECT", p.clone())
+            .unwrap()
+            .matcher();
+        assert!(m.accepts("SELECT") && m.accepts("select"));
+        let m = langs
+            .define_with("id", "[A-C\\-][^X]", p)
+            .unwrap()
+            .matcher();
+        assert!(m.accepts("Ab") && m.accepts("-b") && m.accepts("c"));
+        assert!(!m.accepts("ax") && !m.accepts("AX"));
+    }
+
+    #[test]
+    fn empty_source_is_epsilon() {
+        let mut langs = LanguageSet::new();
+        langs.define("nothing", "").unwrap();
+        let lang = langs.define("opt", "a{nothing}+{nothing}").unwrap();
+        assert!(lang.matcher().accepts("") && lang.matcher().accepts("a"));
+        assert_eq!(langs.get("nothing").unwrap().to_regex(), "ε");
+    }
+
+    #[test]
+    fn unknown_reference_is_an_error() {
+        let mut langs = LanguageSet::new();
+        assert!(langs.define("top", "a{missing}").is_err());
+        assert!(langs.get("top").is_none());
+    }
+
+    #[test]
+    fn fingerprint_follows_language() {
+        let mut langs = LanguageSet::new();
+        langs.define("bit", "0+1").unwrap();
+        let a = langs.define("a", "{bit}*").unwrap().fingerprint();
+        let b = langs.define("b", "(0*1*)*").unwrap().fingerprint();
+        let c = langs.define("c", "{bit}{bit}*").unwrap().fingerprint();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn readable_regex_from_the_machine() {
+        let mut langs = LanguageSet::new();
+        langs.define("bit", "0+1").unwrap();
+        let lang = langs.define("bits", "({bit}*)*1(0*)*").unwrap();
+
+        // Any word with a 1 in it
+        assert_eq!(lang.to_regex(), "0*1[01]*");
+        let err = lang.to_regex_with(2).unwrap_err();
+        assert!(err.contains("too large"), "{}", err);
+    }
+}
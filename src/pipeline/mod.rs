@@ -1 +1,7 @@
+pub mod builder;
+pub mod language;
+pub mod preprocess;
+pub mod preset;
+pub mod stats;
+#[allow(clippy::module_inception)]
 pub mod tests;
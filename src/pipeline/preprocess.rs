@@ -0,0 +1,270 @@
+// pipeline/preprocess.rs
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::RangeInclusive;
+
+use crate::dfa::compiled::{Acceptance, CompiledDFA};
+use crate::regex::ast::RegexAST;
+
+/// One normalization applied to each input char.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Step {
+    // Lowercase chars whose lowercase form is a single char
+    CaseFold,
+    // Drop whitespace entirely
+    StripWhitespace,
+    // Replace chars by others; `None` drops the char
+    Map(BTreeMap<char, Option<char>>),
+}
+
+/// Char-by-char normalization of input before a DFA consumes it. Steps
+/// run in order, and a dropped char skips the remaining steps.
+///
+/// Stored as data rather than closures so it can be saved next to the
+/// machine it belongs to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Preprocessor {
+    pub steps: Vec<Step>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn case_fold(mut self) -> Self {
+        self.steps.push(Step::CaseFold);
+        self
+    }
+
+    pub fn strip_whitespace(mut self) -> Self {
+        self.steps.push(Step::StripWhitespace);
+        self
+    }
+
+    /// Map `from` to `to`, or drop it when `to` is `None`. Consecutive
+    /// calls extend the same mapping step.
+    pub fn map(mut self, from: char, to: Option<char>) -> Self {
+        match self.steps.last_mut() {
+            Some(Step::Map(map)) => {
+                map.insert(from, to);
+            }
+            _ => self.steps.push(Step::Map(BTreeMap::from([(from, to)]))),
+        }
+        self
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The char the DFA sees in place of `c`, if any.
+    pub fn apply(&self, c: char) -> Option<char> {
+        self.steps.iter().try_fold(c, |c, step| match step {
+            Step::CaseFold => {
+                let mut lower = c.to_lowercase();
+                match (lower.next(), lower.next()) {
+                    (Some(l), None) => Some(l),
+                    _ => Some(c),
+                }
+            }
+            Step::StripWhitespace => (!c.is_whitespace()).then_some(c),
+            Step::Map(map) => map.get(&c).copied().unwrap_or(Some(c)),
+        })
+    }
+
+    pub fn run<'a>(&'a self, input: &'a str) -> impl Iterator<Item = char> + 'a {
+        input.chars().filter_map(|c| self.apply(c))
+    }
+
+    /// The whole input, normalized.
+    pub fn process(&self, input: &str) -> String {
+        self.run(input).collect()
+    }
+
+    /// A pattern written against raw input, rewritten to match what the
+    /// DFA sees instead: each literal and class member becomes its
+    /// normalized char, and one that is dropped matches nothing (ε).
+    /// Negated classes exclude the normalized forms. Without this, a
+    /// case-folding preprocessor would keep `SELECT` from ever matching.
+    pub fn normalize_pattern(&self, ast: &RegexAST) -> RegexAST {
+        let norm = |e: &RegexAST| Box::new(self.normalize_pattern(e));
+        match ast {
+            RegexAST::Char(c) => match self.apply(*c) {
+                Some(c) => RegexAST::Char(c),
+                None => RegexAST::Epsilon,
+            },
+            RegexAST::Class(ranges) => {
+                let (members, dropped) = self.apply_ranges(ranges);
+                let class = RegexAST::Class(members);
+                if dropped {
+                    RegexAST::Optional(Box::new(class))
+                } else {
+                    class
+                }
+            }
+            RegexAST::NegClass(ranges) => RegexAST::NegClass(self.apply_ranges(ranges).0),
+            RegexAST::Concat(a, b) => RegexAST::Concat(norm(a), norm(b)),
+            RegexAST::Union(a, b) => RegexAST::Union(norm(a), norm(b)),
+            RegexAST::Intersect(a, b) => RegexAST::Intersect(norm(a), norm(b)),
+            RegexAST::Complement(e) => RegexAST::Complement(norm(e)),
+            RegexAST::Star(e) => RegexAST::Star(norm(e)),
+            RegexAST::Optional(e) => RegexAST::Optional(norm(e)),
+            RegexAST::Repeat(e, min, max) => RegexAST::Repeat(norm(e), *min, *max),
+            RegexAST::Epsilon | RegexAST::Empty | RegexAST::Any | RegexAST::Ref(_) => ast.clone(),
+        }
+    }
+
+    // The normalized members of `ranges` as ranges, and whether any member
+    // is dropped. Ranges the steps leave alone are kept as they are.
+    fn apply_ranges(&self, ranges: &[RangeInclusive<char>]) -> (Vec<RangeInclusive<char>>, bool) {
+        if ranges
+            .iter()
+            .flat_map(|r| r.clone())
+            .all(|c| self.apply(c) == Some(c))
+        {
+            return (ranges.to_vec(), false);
+        }
+        let mut members = BTreeSet::new();
+        let mut dropped = false;
+        for c in ranges.iter().flat_map(|r| r.clone()) {
+            match self.apply(c) {
+                Some(c) => {
+                    members.insert(c);
+                }
+                None => dropped = true,
+            }
+        }
+
+        let mut out: Vec<RangeInclusive<char>> = Vec::new();
+        for c in members {
+            match out.last_mut() {
+                Some(r) if *r.end() as u32 + 1 == c as u32 => *r = *r.start()..=c,
+                _ => out.push(c..=c),
+            }
+        }
+        (out, dropped)
+    }
+}
+
+/// A compiled DFA together with the preprocessing its language expects.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    pub dfa: CompiledDFA,
+    pub preprocessor: Preprocessor,
+    pub acceptance: Acceptance,
+    // A self-contained regex for the DFA, for `testing::corpus` to record
+    // verdicts under; without one, a regex is read off the DFA
+    pub pattern: Option<String>,
+}
+
+impl Matcher {
+    /// A whole-input matcher; see `with_acceptance` for prefix matching.
+    pub fn new(dfa: CompiledDFA, preprocessor: Preprocessor) -> Self {
+        Self {
+            dfa,
+            preprocessor,
+            acceptance: Acceptance::default(),
+            pattern: None,
+        }
+    }
+
+    pub fn with_acceptance(mut self, acceptance: Acceptance) -> Self {
+        self.acceptance = acceptance;
+        self
+    }
+
+    /// Name the regex the DFA was compiled from, so whole-input verdicts
+    /// can be recorded (see `testing::corpus`).
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    /// Whether `input` matches under this matcher's acceptance mode; for
+    /// the prefix modes, whether some prefix does.
+    pub fn accepts(&self, input: &str) -> bool {
+        let accepted = self.find(input).is_some();
+        #[cfg(feature = "testing")]
+        if self.acceptance == Acceptance::WholeInput && crate::testing::corpus::is_recording() {
+            // The preprocessor isn't part of the regex, so record what the
+            // DFA saw
+            let input = self.preprocessor.process(input);
+            crate::testing::corpus::record_compiled(
+                &self.dfa,
+                self.pattern.as_deref(),
+                &input,
+                accepted,
+            );
+        }
+        accepted
+    }
+
+    /// Byte offset in `input` just past the match, or None. Chars dropped
+    /// by the preprocessor still count towards the offset.
+    pub fn find(&self, input: &str) -> Option<usize> {
+        let steps = input.char_indices().filter_map(|(i, c)| {
+            let end = i + c.len_utf8();
+            self.preprocessor.apply(c).map(|c| (end, c))
+        });
+        self.dfa.find_steps(steps, input.len(), self.acceptance)
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn steps_run_in_order() {
+        let p = Preprocessor::new()
+            .strip_whitespace()
+            .case_fold()
+            .map('-', None)
+            .map('0', Some('o'));
+
+        assert_eq!(p.process("Foo- B0x\n"), "foobox");
+        assert_eq!(p.apply(' '), None);
+        // Multi-char lowercase forms are left alone
+        assert_eq!(p.apply('İ'), Some('İ'));
+        assert_eq!(p.steps.len(), 3);
+        assert!(Preprocessor::new().is_identity());
+    }
+
+    #[test]
+    fn matcher_normalizes_input() {
+        let dfa = full_pipeline("hello(world)?").unwrap().mindfa;
+        let m = Matcher::new(
+            CompiledDFA::from_dfa(&dfa),
+            Preprocessor::new().case_fold().strip_whitespace(),
+        );
+
+        assert!(m.accepts("Hello World"));
+        assert!(m.accepts(" HELLO "));
+        assert!(!m.accepts("Hello there"));
+    }
+
+    #[test]
+    fn prefix_offsets_are_in_the_original_input() {
+        let dfa = full_pipeline("ab(ab)*").unwrap().mindfa;
+        let m = Matcher::new(
+            CompiledDFA::from_dfa(&dfa),
+            Preprocessor::new().case_fold().strip_whitespace(),
+        )
+        .with_acceptance(Acceptance::LongestPrefix);
+
+        assert_eq!(m.find("A b ab!"), Some(6));
+        assert!(m.accepts("abx"));
+        assert_eq!(m.find("x"), None);
+    }
+}
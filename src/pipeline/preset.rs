@@ -0,0 +1,291 @@
+// pipeline/preset.rs
+//
+// Named bundles of pipeline settings, for callers who want a sensible
+// combination rather than picking every option themselves.
+use super::tests::{
+    case_folded, check_alphabet, full_pipeline_traced, with_mode, FullOutput, PipelineOptions,
+};
+use crate::dfa::bytes::Encoding;
+use crate::dfa::canonical::canonicalize;
+use crate::dfa::compiled::CompiledDFA;
+use crate::dfa::dense::DenseDFA;
+use crate::dfa::dfa::{DeterminizeOptions, DFA};
+use crate::dfa::minimize::{minimize_dfa, minimize_with};
+use crate::nfa::epsilon_elimination::EpsilonRemoval;
+use crate::nfa::thompson::RepeatStrategy;
+use crate::regex::derivatives::derivative_dfa;
+use crate::regex::parser::parse_language_with;
+use crate::types::StateId;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Preset {
+    // Every stage of Thompson → ε-removal → subsets → minimization, with
+    // counters unrolled, the textbook ε-removal and the subset behind each
+    // DFA state
+    #[default]
+    Teaching,
+    // Straight to the canonical minimal DFA by derivatives, no NFA stages
+    Fast,
+    // As Fast, plus the dense transition table for `to_bytes`/`save` and
+    // the UTF-8 byte DFA as a flat table, ready for `to_rust`
+    Embedded,
+}
+
+/// What a preset produces. The minimal DFA is always there and
+/// canonically numbered, so presets agree on it.
+pub struct PresetOutput {
+    pub mindfa: DFA,
+    // Teaching only
+    pub stages: Option<FullOutput>,
+    // Teaching only: the NFA states behind each state of `stages.dfa`
+    pub subsets: Option<Vec<BTreeSet<StateId>>>,
+    // Embedded only
+    pub table: Option<CompiledDFA>,
+    // Embedded only: the minimal DFA over the UTF-8 bytes of its words
+    pub bytes: Option<DenseDFA<u8>>,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Teaching, Preset::Fast, Preset::Embedded];
+
+    /// The stage options this preset uses.
+    pub fn options(self) -> PipelineOptions {
+        let mut opts = PipelineOptions::default();
+        self.apply(&mut opts);
+        opts
+    }
+
+    /// Set the stage strategies this preset picks in `opts`, leaving the
+    /// language settings (mode, alphabet, case, syntax, limits) alone.
+    pub fn apply(self, opts: &mut PipelineOptions) {
+        let (repeat, epsilon) = match self {
+            Preset::Teaching => (RepeatStrategy::Unroll, EpsilonRemoval::Classical),
+            Preset::Fast | Preset::Embedded => (RepeatStrategy::Shared, EpsilonRemoval::default()),
+        };
+        opts.repeat = repeat;
+        opts.epsilon = epsilon;
+    }
+
+    /// Compile `pattern` the way this preset does.
+    pub fn compile(self, pattern: &str) -> Result<PresetOutput, String> {
+        self.compile_with(pattern, &self.options())
+    }
+
+    /// Like `compile`, with `opts` in place of `options()`. Fast and
+    /// Embedded have no NFA stages, so they honor `minimize` but reject
+    /// a budget, a frontier order, or repeat and ε-removal strategies
+    /// other than their own, rather than silently dropping them.
+    pub fn compile_with(
+        self,
+        pattern: &str,
+        opts: &PipelineOptions,
+    ) -> Result<PresetOutput, String> {
+        match self {
+            Preset::Teaching => {
+                let (stages, subsets) = full_pipeline_traced(pattern, opts)?;
+                Ok(PresetOutput {
+                    mindfa: canonicalize(&stages.mindfa),
+                    stages: Some(stages),
+                    subsets: Some(subsets),
+                    table: None,
+                    bytes: None,
+                })
+            }
+            Preset::Fast | Preset::Embedded => {
+                self.check_derivative_options(opts)?;
+                let ast = parse_language_with(pattern, &opts.syntax)
+                    .map_err(|e| format!("Parse error: {}", e))?;
+                let (ast, alphabet) = case_folded(with_mode(ast, opts.mode), opts);
+                check_alphabet(&ast, alphabet.as_ref(), opts.max_alphabet)?;
+                let alphabet = alphabet.unwrap_or_else(|| ast.literals());
+
+                let dfa = derivative_dfa(&ast, &alphabet)?;
+                let mindfa = canonicalize(&minimize_with(&dfa, opts.minimize));
+                let embedded = self == Preset::Embedded;
+                let table = embedded.then(|| {
+                    if opts.case_insensitive {
+                        CompiledDFA::from_dfa_ignore_ascii_case(&mindfa)
+                    } else {
+                        CompiledDFA::from_dfa(&mindfa)
+                    }
+                });
+                // Case folding is already in `mindfa`, so both cases get bytes
+                let bytes = embedded
+                    .then(|| DenseDFA::from(&minimize_dfa(&mindfa.to_byte_dfa(Encoding::Utf8))));
+                Ok(PresetOutput {
+                    mindfa,
+                    stages: None,
+                    subsets: None,
+                    table,
+                    bytes,
+                })
+            }
+        }
+    }
+
+    // Settings the derivative path has no stage for
+    fn check_derivative_options(self, opts: &PipelineOptions) -> Result<(), String> {
+        let own = self.options();
+        let unused = if opts.minimize_budget.is_some() {
+            Some("`minimize_budget`")
+        } else if opts.determinize != DeterminizeOptions::default() {
+            Some("a determinization frontier order")
+        } else if opts.repeat != own.repeat {
+            Some("a repeat strategy")
+        } else if opts.epsilon != own.epsilon {
+            Some("an ε-removal strategy")
+        } else {
+            None
+        };
+        match unused {
+            Some(what) => Err(format!(
+                "The {} preset builds its DFA from derivatives and cannot honor {}; use the teaching preset",
+                self, what
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Preset::Teaching => write!(f, "teaching"),
+            Preset::Fast => write!(f, "fast"),
+            Preset::Embedded => write!(f, "embedded"),
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Preset::ALL
+            .into_iter()
+            .find(|p| p.to_string() == s)
+            .ok_or_else(|| format!("Unknown preset '{}': use teaching, fast or embedded", s))
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline_with;
+
+    fn same(a: &DFA, b: &DFA) -> bool {
+        a.start == b.start && a.accepts == b.accepts && a.transitions == b.transitions
+    }
+
+    #[test]
+    fn presets_agree_on_the_minimal_dfa() {
+        for pattern in ["(a+b)*abb", "a{2,4}b?", "!(ab)&[ab]*", "ε"] {
+            let outputs: Vec<PresetOutput> = Preset::ALL
+                .into_iter()
+                .map(|p| p.compile(pattern).unwrap())
+                .collect();
+            for out in &outputs[1..] {
+                assert!(same(&out.mindfa, &outputs[0].mindfa), "{}", pattern);
+            }
+        }
+    }
+
+    #[test]
+    fn what_each_preset_keeps() {
+        let teaching = Preset::Teaching.compile("(a+b)*abb").unwrap();
+        let stages = teaching.stages.unwrap();
+        let subsets = teaching.subsets.unwrap();
+        assert_eq!(subsets.len(), stages.dfa.states().len());
+        assert!(teaching.table.is_none());
+
+        let fast = Preset::Fast.compile("(a+b)*abb").unwrap();
+        assert!(fast.stages.is_none() && fast.subsets.is_none() && fast.table.is_none());
+
+        let embedded = Preset::Embedded.compile("(a+b)*abb").unwrap();
+        let table = embedded.table.unwrap();
+        assert!(table.accepts("babb") && !table.accepts("bab"));
+        let bytes = embedded.bytes.unwrap();
+        assert!(bytes.accepts_bytes(b"babb") && !bytes.accepts_bytes(b"bab"));
+        #[cfg(feature = "codegen")]
+        assert!(bytes.to_rust("matches").is_ok());
+    }
+
+    #[test]
+    fn teaching_keeps_the_pipeline_dfa() {
+        let opts = Preset::Teaching.options();
+        let teaching = Preset::Teaching.compile("(a+b)*abb").unwrap();
+        let pipeline = full_pipeline_with("(a+b)*abb", &opts).unwrap();
+        assert!(same(&teaching.stages.unwrap().dfa, &pipeline.dfa));
+    }
+
+    #[test]
+    fn case_insensitive_flag() {
+        for preset in Preset::ALL {
+            let opts = PipelineOptions {
+                case_insensitive: true,
+                ..preset.options()
+            };
+            let out = preset.compile_with("(a+b)*abb", &opts).unwrap();
+            assert!(out.mindfa.accepts("aBaBB") && !out.mindfa.accepts("ab"));
+            assert_eq!(out.mindfa.states().len(), 4);
+            if let Some(table) = out.table {
+                assert!(table.accepts("BABB"));
+                assert_eq!(table.alphabet(), ['a', 'b']);
+            }
+            if let Some(bytes) = out.bytes {
+                assert!(bytes.accepts_bytes(b"aBaBB") && bytes.accepts_bytes(b"BABB"));
+            }
+        }
+    }
+
+    #[test]
+    fn case_folding_before_negation() {
+        for preset in Preset::ALL {
+            let opts = PipelineOptions {
+                case_insensitive: true,
+                alphabet: Some(BTreeSet::from(['a', 'A', 'b'])),
+                ..preset.options()
+            };
+            for pattern in ["[^a]", "!a"] {
+                let dfa = preset.compile_with(pattern, &opts).unwrap().mindfa;
+                assert!(
+                    !dfa.accepts("a") && !dfa.accepts("A"),
+                    "{} {}",
+                    preset,
+                    pattern
+                );
+                assert!(
+                    dfa.accepts("b") && dfa.accepts("B"),
+                    "{} {}",
+                    preset,
+                    pattern
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for p in Preset::ALL {
+            assert_eq!(p.to_string().parse::<Preset>(), Ok(p));
+        }
+        assert!("turbo".parse::<Preset>().is_err());
+        assert!(Preset::Fast.compile("{x}").is_err());
+
+        let opts = PipelineOptions {
+            mode: crate::pipeline::tests::MatchMode::Substring,
+            ..Preset::Fast.options()
+        };
+        let fast = Preset::Fast.compile_with("ab", &opts).unwrap();
+        assert!(fast.mindfa.accepts("babba"));
+    }
+}
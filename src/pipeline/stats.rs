@@ -0,0 +1,172 @@
+// pipeline/stats.rs
+//
+// Size and timing of every pipeline stage, one CSV row per regex, for
+// studying how construction choices scale over a corpus.
+use super::tests::{full_pipeline_timed, PipelineOptions};
+use crate::dfa::dfa::DFA;
+use crate::nfa::nfa::NFA;
+use crate::types::AutomatonStats;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Size of one stage's automaton and the time spent building it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StageStats {
+    pub states: usize,
+    pub edges: usize,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineStats {
+    pub pattern: String,
+    // Distinct symbols of the ε-NFA
+    pub alphabet: usize,
+    pub parse_time: Duration,
+    pub enfa: StageStats,
+    pub nfa: StageStats,
+    pub dfa: StageStats,
+    pub mindfa: StageStats,
+}
+
+impl PipelineStats {
+    /// Column names matching `to_csv_row`. Times are in microseconds.
+    pub const CSV_HEADER: &'static str = "pattern,alphabet,parse_us,\
+        enfa_states,enfa_edges,enfa_us,nfa_states,nfa_edges,nfa_us,\
+        dfa_states,dfa_edges,dfa_us,mindfa_states,mindfa_edges,mindfa_us,\
+        minimality_delta,error";
+
+    /// Run the default pipeline on `pattern` and measure it.
+    pub fn measure(pattern: &str) -> Result<Self, String> {
+        Self::measure_with(pattern, &PipelineOptions::default())
+    }
+
+    pub fn measure_with(pattern: &str, opts: &PipelineOptions) -> Result<Self, String> {
+        let (out, times) = full_pipeline_timed(pattern, opts)?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            alphabet: out.enfa.alphabet().len(),
+            parse_time: times[0],
+            enfa: nfa_stats(&out.enfa, times[1]),
+            nfa: nfa_stats(&out.nfa, times[2]),
+            dfa: dfa_stats(&out.dfa, times[3]),
+            mindfa: dfa_stats(&out.mindfa, times[4]),
+        })
+    }
+
+    /// States minimization removed.
+    pub fn minimality_delta(&self) -> usize {
+        self.dfa.states - self.mindfa.states
+    }
+
+    /// One line (without newline) under `CSV_HEADER`; `error` is empty.
+    pub fn to_csv_row(&self) -> String {
+        let mut fields = vec![
+            csv_field(&self.pattern),
+            self.alphabet.to_string(),
+            self.parse_time.as_micros().to_string(),
+        ];
+        for stage in [&self.enfa, &self.nfa, &self.dfa, &self.mindfa] {
+            fields.push(stage.states.to_string());
+            fields.push(stage.edges.to_string());
+            fields.push(stage.time.as_micros().to_string());
+        }
+        fields.push(self.minimality_delta().to_string());
+        fields.push(String::new());
+        fields.join(",")
+    }
+}
+
+/// Measure every pattern and write `CSV_HEADER` and one row each to `out`.
+/// Patterns that fail to compile get a row with only the pattern and the
+/// error, so rows line up with the corpus. Returns the number of failures.
+pub fn write_stats_csv<'a, W: Write>(
+    patterns: impl IntoIterator<Item = &'a str>,
+    opts: &PipelineOptions,
+    mut out: W,
+) -> io::Result<usize> {
+    writeln!(out, "{}", PipelineStats::CSV_HEADER)?;
+    let columns = PipelineStats::CSV_HEADER.split(',').count();
+    let mut failed = 0;
+
+    for pattern in patterns {
+        match PipelineStats::measure_with(pattern, opts) {
+            Ok(stats) => writeln!(out, "{}", stats.to_csv_row())?,
+            Err(e) => {
+                failed += 1;
+                let blanks = ",".repeat(columns - 2);
+                writeln!(out, "{}{},{}", csv_field(pattern), blanks, csv_field(&e))?;
+            }
+        }
+    }
+    Ok(failed)
+}
+
+fn nfa_stats(nfa: &NFA, time: Duration) -> StageStats {
+    stage(nfa.stats(), time)
+}
+
+fn dfa_stats(dfa: &DFA, time: Duration) -> StageStats {
+    stage(dfa.stats(), time)
+}
+
+fn stage(stats: AutomatonStats, time: Duration) -> StageStats {
+    StageStats {
+        states: stats.states,
+        edges: stats.transitions,
+        time,
+    }
+}
+
+// Quote fields with separators, quotes or line breaks, doubling quotes.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_sizes() {
+        let stats = PipelineStats::measure("(a+b)*abb").unwrap();
+        assert_eq!(stats.alphabet, 2);
+        assert_eq!(stats.mindfa.states, 4);
+        assert_eq!(stats.mindfa.edges, 8);
+        assert!(stats.enfa.states >= stats.nfa.states);
+        assert_eq!(stats.minimality_delta(), stats.dfa.states - 4);
+    }
+
+    #[test]
+    fn rows_line_up_with_the_header() {
+        let row = PipelineStats::measure("a*").unwrap().to_csv_row();
+        assert_eq!(
+            row.split(',').count(),
+            PipelineStats::CSV_HEADER.split(',').count()
+        );
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+
+        let mut out = Vec::new();
+        let failed =
+            write_stats_csv(["ab*", "(a", "a+b"], &PipelineOptions::default(), &mut out).unwrap();
+        assert_eq!(failed, 1);
+
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], PipelineStats::CSV_HEADER);
+        assert!(lines[2].starts_with("(a,,,"));
+        assert!(lines[2].contains("Parse error"));
+        assert!(lines[1].ends_with(','));
+    }
+}
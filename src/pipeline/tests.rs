@@ -1,12 +1,18 @@
 // pipeline/tests.rs
-use crate::dfa::dfa::nfa_to_dfa;
-use crate::dfa::minimize::minimize_dfa;
-use crate::nfa::epsilon_elimination::remove_epsilon;
-use crate::nfa::thompson::enfa_from_ast;
-use crate::regex::parser::parse_language;
+use crate::dfa::dfa::{nfa_to_dfa_traced, DeterminizeOptions};
+use crate::dfa::minimize::{
+    brzozowski, minimize_dfa, minimize_dfa_within, minimize_moore, MinimizeAlgorithm,
+};
+use crate::nfa::epsilon_elimination::{remove_epsilon_with, EpsilonRemoval};
+use crate::nfa::thompson::{RepeatStrategy, Thompson};
+use crate::regex::ast::{ascii_case_closure, RegexAST};
+use crate::regex::parser::{parse_language_with, SyntaxConfig};
 
 use crate::dfa::dfa::DFA;
 use crate::nfa::nfa::NFA;
+use crate::types::StateId;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
 
 /// The unified output of the entire pipeline.
 pub struct FullOutput {
@@ -14,31 +20,253 @@ pub struct FullOutput {
     pub nfa: NFA,
     pub dfa: DFA,
     pub mindfa: DFA,
+    // False when `minimize_budget` ran out and `mindfa` is only reduced
+    pub minimal: bool,
+}
+
+/// Default limit on the number of distinct symbols a language may use.
+pub const DEFAULT_MAX_ALPHABET: usize = 128;
+
+/// What it means for an input to match the compiled language `L`. Σ is
+/// the `alphabet` option, and without one only the chars written in the
+/// pattern: a Prefix or Substring DFA of `ab` then rejects `abz`, since
+/// `z` is in no Σ*. Set `alphabet` to every char the input may contain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    // The whole input is in L
+    #[default]
+    Full,
+    // Some prefix of the input is in L: L Σ*
+    Prefix,
+    // Some substring of the input is in L: Σ* L Σ*
+    Substring,
+}
+
+/// Options for the individual pipeline stages.
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    pub syntax: SyntaxConfig,
+    // Σ is `alphabet` below, or the characters written in the regex
+    pub mode: MatchMode,
+    pub determinize: DeterminizeOptions,
+    // Languages with a larger alphabet are rejected before any automaton is
+    // built, counting the explicit alphabet and the chars the regex writes
+    pub max_alphabet: usize,
+    // Alphabet for `.` and negated classes; inferred from the regex when None
+    pub alphabet: Option<BTreeSet<char>>,
+    pub repeat: RepeatStrategy,
+    pub epsilon: EpsilonRemoval,
+    pub minimize: MinimizeAlgorithm,
+    // Work limit for Hopcroft minimization (see `minimize_dfa_within`);
+    // unlimited when None. Other algorithms have no budget, and setting
+    // one with them is an error
+    pub minimize_budget: Option<usize>,
+    // ASCII letters match in either case; other chars exactly
+    pub case_insensitive: bool,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            syntax: SyntaxConfig::default(),
+            mode: MatchMode::default(),
+            determinize: DeterminizeOptions::default(),
+            max_alphabet: DEFAULT_MAX_ALPHABET,
+            alphabet: None,
+            repeat: RepeatStrategy::default(),
+            epsilon: EpsilonRemoval::default(),
+            minimize: MinimizeAlgorithm::default(),
+            minimize_budget: None,
+            case_insensitive: false,
+        }
+    }
 }
 
 /// Convert a language string into a minimized DFA.
 pub fn full_pipeline(lang: &str) -> Result<FullOutput, String> {
+    let out = full_pipeline_with(lang, &PipelineOptions::default())?;
+    // Verdicts of this language are recorded under `lang`
+    #[cfg(feature = "testing")]
+    crate::testing::corpus::name(&out.mindfa, lang);
+    Ok(out)
+}
+
+/// Same as `full_pipeline`, with explicit stage options.
+pub fn full_pipeline_with(lang: &str, opts: &PipelineOptions) -> Result<FullOutput, String> {
+    full_pipeline_timed(lang, opts).map(|(out, _)| out)
+}
+
+/// Wall-clock time of each stage: parsing, Thompson, ε-removal, subset
+/// construction and minimization.
+pub(crate) type StageTimes = [Duration; 5];
+
+// `full_pipeline_with`, timing each stage.
+pub(crate) fn full_pipeline_timed(
+    lang: &str,
+    opts: &PipelineOptions,
+) -> Result<(FullOutput, StageTimes), String> {
+    run_pipeline(lang, opts).map(|(out, _, times)| (out, times))
+}
+
+// `full_pipeline_with`, also returning the NFA states behind each state of
+// the output's `dfa`.
+pub(crate) fn full_pipeline_traced(
+    lang: &str,
+    opts: &PipelineOptions,
+) -> Result<(FullOutput, Vec<BTreeSet<StateId>>), String> {
+    run_pipeline(lang, opts).map(|(out, subsets, _)| (out, subsets))
+}
+
+fn run_pipeline(
+    lang: &str,
+    opts: &PipelineOptions,
+) -> Result<(FullOutput, Vec<BTreeSet<StateId>>, StageTimes), String> {
+    let mut times = StageTimes::default();
+    let mut clock = Instant::now();
+    let mut lap = |i: usize| {
+        times[i] = clock.elapsed();
+        clock = Instant::now();
+    };
+
+    if opts.minimize_budget.is_some() && opts.minimize != MinimizeAlgorithm::Hopcroft {
+        return Err(format!(
+            "`minimize_budget` needs Hopcroft minimization, not {:?}",
+            opts.minimize
+        ));
+    }
+
     // 1. Parse the input language into an AST
-    let ast = parse_language(lang).map_err(|e| format!("Parse error: {:?}", e))?;
+    let ast =
+        parse_language_with(lang, &opts.syntax).map_err(|e| format!("Parse error: {}", e))?;
+    if let Some(name) = ast.references().first() {
+        return Err(format!("Unknown language: {{{}}}", name));
+    }
+
+    // Bake the match mode into the language itself
+    let ast = with_mode(ast, opts.mode);
+    let (ast, alphabet) = case_folded(ast, opts);
+    check_alphabet(&ast, alphabet.as_ref(), opts.max_alphabet)?;
+    lap(0);
 
     // 2. Thompson construction: AST → ε-NFA
-    let enfa = enfa_from_ast(&ast);
+    let mut thompson = Thompson::new().with_repeat(opts.repeat);
+    if let Some(alphabet) = alphabet {
+        thompson = thompson.with_alphabet(alphabet);
+    }
+    let enfa = thompson.from_ast(&ast)?;
+    lap(1);
 
     // 3. Eliminate ε-transitions: ENFA → NFA
-    let nfa = remove_epsilon(&enfa);
+    let nfa = remove_epsilon_with(&enfa, opts.epsilon);
+    lap(2);
 
     // 4. Subset construction: NFA → DFA
-    let dfa = nfa_to_dfa(&nfa);
+    let (dfa, subsets) = nfa_to_dfa_traced(&nfa, &opts.determinize);
+    lap(3);
 
-    // 5. Hopcroft (or equivalent): DFA → Minimized DFA
-    let mindfa = minimize_dfa(&dfa);
+    // 5. Hopcroft (or equivalent): DFA → Minimized DFA. Brzozowski starts
+    // over from the NFA rather than the DFA above; that DFA is still built,
+    // as part of the output, so the backend saves no time here
+    let (mindfa, minimal) = match (opts.minimize, opts.minimize_budget) {
+        (MinimizeAlgorithm::Brzozowski, _) => (brzozowski(&nfa), true),
+        (MinimizeAlgorithm::Moore, _) => (minimize_moore(&dfa), true),
+        (MinimizeAlgorithm::Hopcroft, None) => (minimize_dfa(&dfa), true),
+        (MinimizeAlgorithm::Hopcroft, Some(budget)) => {
+            let out = minimize_dfa_within(&dfa, budget);
+            (out.dfa, out.minimal)
+        }
+    };
+    lap(4);
 
-    Ok(FullOutput {
-        enfa,
-        nfa,
-        dfa,
-        mindfa,
-    })
+    Ok((
+        FullOutput {
+            enfa,
+            nfa,
+            dfa,
+            mindfa,
+            minimal,
+        },
+        subsets,
+        times,
+    ))
+}
+
+// The expression and alphabet to compile, folded when `case_insensitive`
+// asks. Folding the pattern rather than the automaton keeps complements
+// and negated classes from letting the other case back in.
+pub(crate) fn case_folded(
+    ast: RegexAST,
+    opts: &PipelineOptions,
+) -> (RegexAST, Option<BTreeSet<char>>) {
+    if opts.case_insensitive {
+        let alphabet = opts.alphabet.as_ref().map(ascii_case_closure);
+        (ast.ignore_ascii_case(), alphabet)
+    } else {
+        (ast, opts.alphabet.clone())
+    }
+}
+
+// Reject a language over more than `max` symbols before any automaton is
+// built. Σ is `alphabet` (or nothing when inferred) plus every char the
+// expression writes; classes count by their ranges, so checking a huge
+// class costs no more than a small one.
+pub(crate) fn check_alphabet(
+    ast: &RegexAST,
+    alphabet: Option<&BTreeSet<char>>,
+    max: usize,
+) -> Result<(), String> {
+    fn collect(ast: &RegexAST, out: &mut Vec<(u32, u32)>) {
+        match ast {
+            RegexAST::Char(c) => out.push((*c as u32, *c as u32)),
+            RegexAST::Class(ranges) | RegexAST::NegClass(ranges) => {
+                out.extend(ranges.iter().map(|r| (*r.start() as u32, *r.end() as u32)))
+            }
+            _ => {}
+        }
+        for child in ast.children() {
+            collect(child, out);
+        }
+    }
+
+    let mut ranges: Vec<(u32, u32)> = alphabet
+        .into_iter()
+        .flatten()
+        .map(|&c| (c as u32, c as u32))
+        .collect();
+    collect(ast, &mut ranges);
+    ranges.sort_unstable();
+
+    // Count the union of the ranges, overlaps once
+    let (mut size, mut covered) = (0u64, None::<u32>);
+    for (lo, hi) in ranges {
+        let lo = covered.map_or(lo, |c| lo.max(c + 1));
+        if lo <= hi {
+            size += (hi - lo + 1) as u64;
+        }
+        covered = Some(covered.map_or(hi, |c| c.max(hi)));
+    }
+
+    if size > max as u64 {
+        return Err(format!(
+            "Alphabet has {} symbols, exceeding the limit of {}; \
+             every later stage scales with the alphabet, raise `max_alphabet` to compile anyway",
+            size, max
+        ));
+    }
+    Ok(())
+}
+
+// L, L Σ* or Σ* L Σ*, as `mode` asks.
+pub(crate) fn with_mode(ast: RegexAST, mode: MatchMode) -> RegexAST {
+    let sigma_star = || Box::new(RegexAST::Star(Box::new(RegexAST::Any)));
+    match mode {
+        MatchMode::Full => ast,
+        MatchMode::Prefix => RegexAST::Concat(Box::new(ast), sigma_star()),
+        MatchMode::Substring => RegexAST::Concat(
+            Box::new(RegexAST::Concat(sigma_star(), Box::new(ast))),
+            sigma_star(),
+        ),
+    }
 }
 
 #[cfg(test)]
@@ -109,4 +337,353 @@ mod tests {
             reject: ["b", "ab"]
         );
     }
+
+    #[test]
+    fn pipeline_optional() {
+        let out = full_pipeline("ab?c").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ac", "abc"],
+            reject: ["", "a", "abbc", "bc"]
+        );
+    }
+
+    #[test]
+    fn pipeline_bounded_repeat() {
+        let out = full_pipeline("a{2,4}b").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["aab", "aaab", "aaaab"],
+            reject: ["ab", "aaaaab", "aa"]
+        );
+
+        let out = full_pipeline("(ab){2,}").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["abab", "ababab"],
+            reject: ["", "ab", "aba"]
+        );
+    }
+
+    #[test]
+    fn pipeline_char_class() {
+        let out = full_pipeline("[a-c][0-9]*").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["a", "b7", "c019"],
+            reject: ["", "d", "1", "a0b"]
+        );
+    }
+
+    #[test]
+    fn pipeline_negated_class() {
+        let opts = PipelineOptions {
+            alphabet: Some("abcd".chars().collect()),
+            ..Default::default()
+        };
+        let out = full_pipeline_with("[^ab]*a", &opts).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["a", "cda", "dddca"],
+            reject: ["", "ba", "aa", "cd"]
+        );
+
+        // Inferred alphabet: only the characters written in the regex
+        let out = full_pipeline("[^a]b+a").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["bb", "a"],
+            reject: ["ab", "cb"]
+        );
+    }
+
+    #[test]
+    fn pipeline_wildcard() {
+        let opts = PipelineOptions {
+            alphabet: Some("abc".chars().collect()),
+            ..Default::default()
+        };
+        let out = full_pipeline_with(".*ab", &opts).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ab", "cab", "abcab"],
+            reject: ["", "a", "abc", "dab"]
+        );
+    }
+
+    #[test]
+    fn pipeline_escaped_metachars() {
+        let out = full_pipeline(r"a\+(b\*)*").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["a+", "a+b*", "a+b*b*"],
+            reject: ["a", "aa", "a+b"]
+        );
+    }
+
+    #[test]
+    fn pipeline_pcre_style_syntax() {
+        let opts = PipelineOptions {
+            syntax: SyntaxConfig {
+                pipe_union: true,
+                plus_one_or_more: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let out = full_pipeline_with("a+|b", &opts).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["a", "aaa", "b"],
+            reject: ["", "ab", "bb"]
+        );
+    }
+
+    #[test]
+    fn pipeline_match_modes() {
+        let prefix = PipelineOptions {
+            mode: MatchMode::Prefix,
+            alphabet: Some("abc".chars().collect()),
+            ..Default::default()
+        };
+        let out = full_pipeline_with("ab", &prefix).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ab", "abc", "abab"],
+            reject: ["", "a", "cab"]
+        );
+
+        let substring = PipelineOptions {
+            mode: MatchMode::Substring,
+            alphabet: Some("abc".chars().collect()),
+            ..Default::default()
+        };
+        let out = full_pipeline_with("ab", &substring).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ab", "cab", "cabc"],
+            reject: ["", "ba", "acb"]
+        );
+
+        // Σ is only what the pattern writes when no alphabet is given
+        let inferred = PipelineOptions {
+            mode: MatchMode::Substring,
+            ..Default::default()
+        };
+        let m = full_pipeline_with("ab", &inferred).unwrap().mindfa;
+        assert_language!(
+            m,
+            accept: ["bab", "abba"],
+            reject: ["abz", "zab"]
+        );
+    }
+
+    #[test]
+    fn pipeline_epsilon_and_empty() {
+        let out = full_pipeline("a+ε").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["", "a"],
+            reject: ["aa", "b"]
+        );
+
+        let out = full_pipeline("∅").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: [],
+            reject: ["", "a"]
+        );
+
+        // An empty pattern is ε: one accepting state, no edges
+        let out = full_pipeline("").unwrap();
+        let m = out.mindfa;
+        assert_eq!(m.states().len(), 1);
+        assert!(m.transitions.values().all(|t| t.is_empty()));
+
+        assert_language!(
+            m,
+            accept: [""],
+            reject: ["a"]
+        );
+
+        let out = full_pipeline("a@0+b@0*").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["b"],
+            reject: ["", "a"]
+        );
+    }
+
+    #[test]
+    fn pipeline_epsilon_removal_methods() {
+        let classical = PipelineOptions {
+            epsilon: EpsilonRemoval::Classical,
+            ..Default::default()
+        };
+        for lang in ["(a+b)*abb", "a{2,3}(b+ε)*", "!(ab)&[ab]*", ""] {
+            let closures = full_pipeline(lang).unwrap();
+            let out = full_pipeline_with(lang, &classical).unwrap();
+            assert_eq!(out.nfa.transitions.len(), out.enfa.transitions.len());
+            assert_eq!(
+                out.mindfa.states().len(),
+                closures.mindfa.states().len(),
+                "{}",
+                lang
+            );
+        }
+    }
+
+    #[test]
+    fn pipeline_unicode() {
+        let out = full_pipeline(r"(é+\u{1F600})*ß[α-γ]").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ßα", "é😀éßγ", "😀ßβ"],
+            reject: ["ß", "eßα", "ßδ", "😀"]
+        );
+
+        // Negated classes and `.` work over non-ASCII alphabets too
+        let out = full_pipeline("[^α]ω").unwrap();
+        assert_language!(out.mindfa, accept: ["ωω"], reject: ["αω", "ω"]);
+    }
+
+    #[test]
+    fn pipeline_intersection() {
+        // Contains "ab", and has even length
+        let out = full_pipeline("(a+b)*ab(a+b)*&((a+b)(a+b))*").unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ab", "aabb", "baba"],
+            reject: ["", "aab", "bbaa", "ba"]
+        );
+    }
+
+    #[test]
+    fn pipeline_complement() {
+        let opts = PipelineOptions {
+            alphabet: Some("ab".chars().collect()),
+            ..Default::default()
+        };
+        // Words over {a, b} not containing "aa"
+        let out = full_pipeline_with("!((a+b)*aa(a+b)*)", &opts).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["", "a", "abab", "bab"],
+            reject: ["aa", "baab", "aaa"]
+        );
+
+        // Complement of a complement
+        let out = full_pipeline_with("!!(ab)", &opts).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["ab"],
+            reject: ["", "a", "abab"]
+        );
+    }
+
+    #[test]
+    fn pipeline_frontier_order() {
+        use crate::dfa::dfa::FrontierOrder;
+
+        let opts = PipelineOptions {
+            determinize: DeterminizeOptions {
+                order: FrontierOrder::Dfs,
+            },
+            ..Default::default()
+        };
+        let out = full_pipeline_with("(a+b)*c", &opts).unwrap();
+        let m = out.mindfa;
+
+        assert_language!(
+            m,
+            accept: ["c", "abc", "bbac"],
+            reject: ["", "ab", "cc"]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn pipeline_json_round_trip() {
+        let out = full_pipeline("(a+b)*abb+é").unwrap();
+
+        let ast = parse_language_with("(a+b)*abb+é", SyntaxConfig::default()).unwrap();
+        assert_eq!(RegexAST::from_json(&ast.to_json().unwrap()).unwrap(), ast);
+
+        for nfa in [&out.enfa, &out.nfa] {
+            let back = NFA::from_json(&nfa.to_json().unwrap()).unwrap();
+            assert_eq!(back.start, nfa.start);
+            assert_eq!(back.accept, nfa.accept);
+            assert_eq!(back.transitions, nfa.transitions);
+        }
+
+        for dfa in [&out.dfa, &out.mindfa] {
+            let back = DFA::from_json(&dfa.to_json().unwrap()).unwrap();
+            assert_eq!(back.start, dfa.start);
+            assert_eq!(back.accepts, dfa.accepts);
+            assert_eq!(back.transitions, dfa.transitions);
+        }
+
+        assert!(DFA::from_json("{\"start\": 0}").is_err());
+    }
+
+    #[test]
+    fn pipeline_alphabet_limit() {
+        let opts = PipelineOptions {
+            max_alphabet: 2,
+            ..Default::default()
+        };
+
+        assert!(full_pipeline_with("(a+b)*", &opts).is_ok());
+
+        let err = full_pipeline_with("(a+b)*c", &opts).err().unwrap();
+        assert!(err.contains("3 symbols"), "{}", err);
+
+        // Counted from the expression, before any edge exists
+        let err = full_pipeline("[\\u{0}-\\u{10FFFF}]").err().unwrap();
+        assert!(err.contains("1114112 symbols"), "{}", err);
+        let err = full_pipeline_with("[a-c][b-d]a", &opts).err().unwrap();
+        assert!(err.contains("4 symbols"), "{}", err);
+        let explicit = PipelineOptions {
+            alphabet: Some("abx".chars().collect()),
+            ..opts
+        };
+        let err = full_pipeline_with("a", &explicit).err().unwrap();
+        assert!(err.contains("3 symbols"), "{}", err);
+    }
 }
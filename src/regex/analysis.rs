@@ -0,0 +1,379 @@
+// regex/analysis.rs
+//
+// Static checks for expressions whose automata get expensive. None of
+// these change the language; they point at the spots where Thompson,
+// subset construction or desugaring of counters does a lot of work.
+use super::ast::{RegexAST, SpanTree};
+use super::derivatives::nullable;
+use super::parser::{parse_language_spanned, ParseError, SyntaxConfig};
+use super::tokenizer::Span;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Counted repetitions larger than this after multiplying out are flagged.
+pub const MAX_REPEAT_PRODUCT: u64 = 256;
+
+/// `Σ*` followed by a counter of at least this much needs on the order of
+/// 2^n DFA states.
+pub const MIN_EXPONENTIAL_COUNT: u32 = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskKind {
+    // A star over something that already repeats: (a*)*, (a*b*)*
+    NestedStar,
+    // A star over alternatives that can start with the same symbol: (a+ab)*
+    OverlappingAlternatives { symbol: char },
+    // Counters inside counters: (a{1,20}){1,20} unrolls to `copies` copies
+    MultipliedRepetition { copies: u64 },
+    // A star followed by a counter over overlapping symbols: (a+b)*a(a+b){n}
+    ExponentialDeterminization { count: u32 },
+}
+
+/// One flagged subexpression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Risk {
+    pub kind: RiskKind,
+    pub span: Span,
+    pub suggestion: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiskReport {
+    pub risks: Vec<Risk>,
+}
+
+impl RiskReport {
+    pub fn is_clean(&self) -> bool {
+        self.risks.is_empty()
+    }
+}
+
+impl fmt::Display for RiskKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RiskKind::NestedStar => write!(f, "nested star"),
+            RiskKind::OverlappingAlternatives { symbol } => {
+                write!(f, "starred alternatives overlap on {:?}", symbol)
+            }
+            RiskKind::MultipliedRepetition { copies } => {
+                write!(f, "nested counters unroll to {} copies", copies)
+            }
+            RiskKind::ExponentialDeterminization { count } => {
+                write!(
+                    f,
+                    "star before a counter of {} needs ~2^{} DFA states",
+                    count, count
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for RiskReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for r in &self.risks {
+            writeln!(
+                f,
+                "{}..{}: {}; {}",
+                r.span.start, r.span.end, r.kind, r.suggestion
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Find subexpressions likely to blow up determinization or unrolling.
+/// `spans` must come from parsing `ast` (see `parse_language_spanned`).
+pub fn blowup_risk(ast: &RegexAST, spans: &SpanTree) -> RiskReport {
+    let mut report = RiskReport::default();
+    visit(ast, spans, &mut report.risks);
+    report
+}
+
+/// Parse `pattern` in the default syntax and check it.
+pub fn blowup_risk_of(pattern: &str) -> Result<RiskReport, ParseError> {
+    let (ast, spans) = parse_language_spanned(pattern, SyntaxConfig::default())?;
+    Ok(blowup_risk(&ast, &spans))
+}
+
+fn visit(ast: &RegexAST, spans: &SpanTree, out: &mut Vec<Risk>) {
+    let span = spans.span.clone();
+
+    match ast {
+        RegexAST::Star(body) => {
+            if repeats(body) {
+                out.push(Risk {
+                    kind: RiskKind::NestedStar,
+                    span: span.clone(),
+                    suggestion: match flatten_star(body) {
+                        Some(flat) => format!("write it as {}", flat.to_pattern()),
+                        None => "drop the inner repetition".into(),
+                    },
+                });
+            }
+            if let Some(symbol) = overlapping_alternatives(body) {
+                out.push(Risk {
+                    kind: RiskKind::OverlappingAlternatives { symbol },
+                    span,
+                    suggestion: "factor the shared prefix out of the alternatives".into(),
+                });
+            }
+        }
+        RegexAST::Repeat(body, min, max) => {
+            let outer = max.unwrap_or(*min).max(1) as u64;
+            let inner = largest_count(body);
+            if inner > 1 && outer * inner > MAX_REPEAT_PRODUCT {
+                out.push(Risk {
+                    kind: RiskKind::MultipliedRepetition {
+                        copies: outer * inner,
+                    },
+                    span,
+                    suggestion: "use a single counter, or a star if the bound doesn't matter"
+                        .into(),
+                });
+            }
+        }
+        RegexAST::Concat(a, b) => {
+            if let Some(count) = star_then_counter(a, b) {
+                out.push(Risk {
+                    kind: RiskKind::ExponentialDeterminization { count },
+                    span,
+                    suggestion: "anchor the counted part or reduce the count".into(),
+                });
+            }
+        }
+        _ => {}
+    }
+
+    for (child, child_spans) in ast.children().into_iter().zip(&spans.children) {
+        visit(child, child_spans, out);
+    }
+}
+
+// Whether the body of a star itself repeats without bound.
+fn repeats(ast: &RegexAST) -> bool {
+    match ast {
+        RegexAST::Star(_) | RegexAST::Repeat(_, _, None) => true,
+        RegexAST::Optional(e) => repeats(e),
+        RegexAST::Concat(a, b) => {
+            (repeats(a) && nullable(b)) || (repeats(b) && nullable(a)) || (repeats(a) && repeats(b))
+        }
+        RegexAST::Union(a, b) => repeats(a) || repeats(b),
+        _ => false,
+    }
+}
+
+// (X*)* → X*, (X*Y*)* → (X+Y)*, when the body is nothing but starred parts.
+// Parts must also match ε: (X{1,}Y{1,})* is not (X+Y)*.
+fn flatten_star(body: &RegexAST) -> Option<RegexAST> {
+    fn parts(ast: &RegexAST, out: &mut Vec<RegexAST>) -> bool {
+        match ast {
+            RegexAST::Star(e) | RegexAST::Optional(e) | RegexAST::Repeat(e, 0, None) => {
+                out.push((**e).clone());
+                true
+            }
+            RegexAST::Concat(a, b) | RegexAST::Union(a, b) => parts(a, out) && parts(b, out),
+            _ => false,
+        }
+    }
+
+    let mut inner = Vec::new();
+    if !parts(body, &mut inner) {
+        return None;
+    }
+    let union = inner
+        .into_iter()
+        .reduce(|a, b| RegexAST::Union(Box::new(a), Box::new(b)))?;
+    Some(RegexAST::Star(Box::new(union)))
+}
+
+// A symbol two alternatives of a top-level union can both start with.
+fn overlapping_alternatives(ast: &RegexAST) -> Option<char> {
+    let mut alternatives = Vec::new();
+    collect_union(ast, &mut alternatives);
+
+    let firsts: Vec<Vec<RangeInclusive<char>>> = alternatives.iter().map(|a| first(a)).collect();
+    for (i, x) in firsts.iter().enumerate() {
+        for y in &firsts[i + 1..] {
+            if let Some(c) = overlap(x, y) {
+                return Some(c);
+            }
+        }
+    }
+    None
+}
+
+fn collect_union<'a>(ast: &'a RegexAST, out: &mut Vec<&'a RegexAST>) {
+    match ast {
+        RegexAST::Union(a, b) => {
+            collect_union(a, out);
+            collect_union(b, out);
+        }
+        _ => out.push(ast),
+    }
+}
+
+// Largest number of copies a counter inside `ast` unrolls to.
+pub(crate) fn largest_count(ast: &RegexAST) -> u64 {
+    let own = match ast {
+        RegexAST::Repeat(e, min, max) => max.unwrap_or(*min).max(1) as u64 * largest_count(e),
+        _ => 1,
+    };
+    ast.children()
+        .into_iter()
+        .map(largest_count)
+        .fold(own, u64::max)
+}
+
+// `…X*…Y{n}…` where X and Y share a symbol and n is large.
+fn star_then_counter(left: &RegexAST, right: &RegexAST) -> Option<u32> {
+    let star = trailing_star(left)?;
+    let star_first = first(star);
+
+    let mut counters = Vec::new();
+    collect_counters(right, &mut counters);
+    counters
+        .into_iter()
+        .filter(|(body, n)| {
+            *n >= MIN_EXPONENTIAL_COUNT && overlap(&star_first, &first(body)).is_some()
+        })
+        .map(|(_, n)| n)
+        .max()
+}
+
+// The body of the last star on the concatenation spine of `ast`. Anything
+// after it only delays the counter by a fixed amount.
+fn trailing_star(ast: &RegexAST) -> Option<&RegexAST> {
+    match ast {
+        RegexAST::Star(e) => Some(e),
+        RegexAST::Concat(a, b) => trailing_star(b).or_else(|| trailing_star(a)),
+        _ => None,
+    }
+}
+
+fn collect_counters<'a>(ast: &'a RegexAST, out: &mut Vec<(&'a RegexAST, u32)>) {
+    if let RegexAST::Repeat(e, min, max) = ast {
+        out.push((e, max.unwrap_or(*min)));
+    }
+    for child in ast.children() {
+        collect_counters(child, out);
+    }
+}
+
+// Symbols a nonempty word of `ast` can start with, as char ranges. Anything
+// relative to the compilation alphabet counts as every char.
+fn first(ast: &RegexAST) -> Vec<RangeInclusive<char>> {
+    let every = || vec!['\0'..=char::MAX];
+    match ast {
+        RegexAST::Epsilon | RegexAST::Empty => Vec::new(),
+        RegexAST::Char(c) => vec![*c..=*c],
+        RegexAST::Class(ranges) => ranges.clone(),
+        RegexAST::NegClass(_) | RegexAST::Any | RegexAST::Complement(_) | RegexAST::Ref(_) => {
+            every()
+        }
+        RegexAST::Concat(a, b) => {
+            let mut out = first(a);
+            if nullable(a) {
+                out.extend(first(b));
+            }
+            out
+        }
+        RegexAST::Union(a, b) | RegexAST::Intersect(a, b) => {
+            let mut out = first(a);
+            out.extend(first(b));
+            out
+        }
+        RegexAST::Star(e) | RegexAST::Optional(e) | RegexAST::Repeat(e, _, _) => first(e),
+    }
+}
+
+// Smallest char in both range lists.
+fn overlap(x: &[RangeInclusive<char>], y: &[RangeInclusive<char>]) -> Option<char> {
+    x.iter()
+        .flat_map(|a| {
+            y.iter().filter_map(move |b| {
+                let lo = *a.start().max(b.start());
+                (lo <= *a.end().min(b.end())).then_some(lo)
+            })
+        })
+        .min()
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(pattern: &str) -> Vec<(RiskKind, Span)> {
+        blowup_risk_of(pattern)
+            .unwrap()
+            .risks
+            .into_iter()
+            .map(|r| (r.kind, r.span))
+            .collect()
+    }
+
+    #[test]
+    fn nested_stars() {
+        let report = blowup_risk_of("x(a*b*)*").unwrap();
+        assert_eq!(report.risks.len(), 1);
+        assert_eq!(report.risks[0].kind, RiskKind::NestedStar);
+        assert_eq!(report.risks[0].span, 1..8);
+        assert_eq!(report.risks[0].suggestion, "write it as (a+b)*");
+
+        assert_eq!(kinds("(a{2,}c?)*"), vec![(RiskKind::NestedStar, 0..10)]);
+        assert!(kinds("(ab*c)*").is_empty());
+
+        // Each part must be passed at least once, so no (a+b)*
+        let report = blowup_risk_of("(a{1,}b{1,})*").unwrap();
+        assert_eq!(report.risks.len(), 1);
+        assert_eq!(report.risks[0].suggestion, "drop the inner repetition");
+        let report = blowup_risk_of("(a{0,}b?)*").unwrap();
+        assert_eq!(report.risks[0].suggestion, "write it as (a+b)*");
+    }
+
+    #[test]
+    fn overlapping_alternatives() {
+        assert_eq!(
+            kinds("(a+ab)*"),
+            vec![(RiskKind::OverlappingAlternatives { symbol: 'a' }, 0..7)]
+        );
+        assert_eq!(
+            kinds("([a-f]x+[d-z]y)*"),
+            vec![(RiskKind::OverlappingAlternatives { symbol: 'd' }, 0..16)]
+        );
+        assert!(kinds("(a+bc)*").is_empty());
+    }
+
+    #[test]
+    fn multiplied_counters() {
+        assert_eq!(
+            kinds("(a{1,20}){1,20}"),
+            vec![(RiskKind::MultipliedRepetition { copies: 400 }, 0..15)]
+        );
+        assert!(kinds("(a{1,10}){1,10}").is_empty());
+    }
+
+    #[test]
+    fn exponential_determinization() {
+        let report = blowup_risk_of("(a+b)*a(a+b){10}").unwrap();
+        assert_eq!(
+            report.risks.iter().map(|r| &r.kind).collect::<Vec<_>>(),
+            vec![&RiskKind::ExponentialDeterminization { count: 10 }]
+        );
+        assert_eq!(
+            report.to_string(),
+            "0..16: star before a counter of 10 needs ~2^10 DFA states; \
+             anchor the counted part or reduce the count\n"
+        );
+
+        // Disjoint symbols don't interact
+        assert!(kinds("a*b{10}").is_empty());
+        assert!(kinds("(a+b)*a(a+b){3}").is_empty());
+    }
+}
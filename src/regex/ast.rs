@@ -1,15 +1,371 @@
 // regest/ast.rs
-use serde::{Serialize, Deserialize};
+use super::tokenizer::Span;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum RegexAST {
+    // The empty string: ε
+    Epsilon,
+    // The empty language: ∅
+    Empty,
     // A single char literal
     Char(char),
+    // Character class: [a-z0-9]
+    Class(Vec<RangeInclusive<char>>),
+    // Negated class: [^a-z], relative to the compilation alphabet
+    NegClass(Vec<RangeInclusive<char>>),
+    // Wildcard: any symbol of the compilation alphabet
+    Any,
     // Concatenation: AB
     Concat(Box<RegexAST>, Box<RegexAST>),
     // Union: (A + B)
     Union(Box<RegexAST>, Box<RegexAST>),
+    // Intersection: (A & B)
+    Intersect(Box<RegexAST>, Box<RegexAST>),
+    // Complement relative to the compilation alphabet: !A
+    Complement(Box<RegexAST>),
     // Kleene star: (A*)
     Star(Box<RegexAST>),
+    // Optional: (A?)
+    Optional(Box<RegexAST>),
+    // Bounded repetition: A{min,max}, unbounded when `max` is None
+    Repeat(Box<RegexAST>, u32, Option<u32>),
+    // Reference to a compiled language: {name}
+    Ref(String),
 }
 
+/// Source ranges of an AST, shaped like the tree itself: `children` line
+/// up with the node's operands, left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanTree {
+    pub span: Span,
+    pub children: Vec<SpanTree>,
+}
+
+impl SpanTree {
+    pub fn leaf(span: Span) -> Self {
+        Self {
+            span,
+            children: Vec::new(),
+        }
+    }
+
+    /// Every node of `ast` with its span, in pre-order. `ast` must be the
+    /// tree these spans were parsed with.
+    pub fn nodes<'a>(&'a self, ast: &'a RegexAST) -> Vec<(&'a RegexAST, &'a Span)> {
+        let mut out = Vec::new();
+        let mut stack = vec![(ast, self)];
+        while let Some((node, spans)) = stack.pop() {
+            out.push((node, &spans.span));
+            let children = node.children().into_iter().zip(&spans.children);
+            stack.extend(children.rev());
+        }
+        out
+    }
+}
+
+impl RegexAST {
+    /// Direct operands, left to right.
+    pub fn children(&self) -> Vec<&RegexAST> {
+        match self {
+            RegexAST::Concat(a, b) | RegexAST::Union(a, b) | RegexAST::Intersect(a, b) => {
+                vec![a, b]
+            }
+            RegexAST::Star(e)
+            | RegexAST::Optional(e)
+            | RegexAST::Complement(e)
+            | RegexAST::Repeat(e, _, _) => vec![e],
+            RegexAST::Epsilon
+            | RegexAST::Empty
+            | RegexAST::Char(_)
+            | RegexAST::Class(_)
+            | RegexAST::NegClass(_)
+            | RegexAST::Any
+            | RegexAST::Ref(_) => Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("JSON error: {}", e))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("JSON error: {}", e))
+    }
+
+    /// Names of all languages referenced by this expression.
+    pub fn references(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_refs(&mut out);
+        out
+    }
+
+    /// Every character written in the expression, including class members.
+    pub fn literals(&self) -> BTreeSet<char> {
+        let mut out = BTreeSet::new();
+        self.collect_literals(&mut out);
+        out
+    }
+
+    /// The same expression with ASCII letters matching in either case:
+    /// literals and class members gain their other case, negated classes
+    /// exclude both. Folding here, before complement and negated classes
+    /// are compiled against an alphabet, keeps `!a` from matching "A".
+    /// Other chars are left alone, so the result doesn't depend on the
+    /// locale.
+    pub fn ignore_ascii_case(&self) -> RegexAST {
+        let fold = |e: &RegexAST| Box::new(e.ignore_ascii_case());
+        match self {
+            RegexAST::Char(c) if c.is_ascii_alphabetic() => {
+                RegexAST::Class(fold_ranges(&[*c..=*c]))
+            }
+            RegexAST::Class(ranges) => RegexAST::Class(fold_ranges(ranges)),
+            RegexAST::NegClass(ranges) => RegexAST::NegClass(fold_ranges(ranges)),
+            RegexAST::Concat(a, b) => RegexAST::Concat(fold(a), fold(b)),
+            RegexAST::Union(a, b) => RegexAST::Union(fold(a), fold(b)),
+            RegexAST::Intersect(a, b) => RegexAST::Intersect(fold(a), fold(b)),
+            RegexAST::Complement(e) => RegexAST::Complement(fold(e)),
+            RegexAST::Star(e) => RegexAST::Star(fold(e)),
+            RegexAST::Optional(e) => RegexAST::Optional(fold(e)),
+            RegexAST::Repeat(e, min, max) => RegexAST::Repeat(fold(e), *min, *max),
+            RegexAST::Epsilon
+            | RegexAST::Empty
+            | RegexAST::Char(_)
+            | RegexAST::Any
+            | RegexAST::Ref(_) => self.clone(),
+        }
+    }
+
+    /// Render back into the crate's default syntax, with only the
+    /// parentheses needed to parse to this exact tree again.
+    pub fn to_pattern(&self) -> String {
+        let mut out = String::new();
+        self.write_pattern(&mut out, 0);
+        out
+    }
+
+    // Binding strength, loosest first: union, intersection, concatenation,
+    // complement, postfix operators, atoms.
+    fn precedence(&self) -> u8 {
+        match self {
+            RegexAST::Union(..) => 0,
+            RegexAST::Intersect(..) => 1,
+            RegexAST::Concat(..) => 2,
+            RegexAST::Complement(_) => 3,
+            RegexAST::Star(_) | RegexAST::Optional(_) | RegexAST::Repeat(..) => 4,
+            _ => 5,
+        }
+    }
+
+    // Write `self` where an operand of at least `min` precedence is expected.
+    // Binary operators parse left-associatively, so their right operand
+    // needs one level more.
+    fn write_pattern(&self, out: &mut String, min: u8) {
+        let parens = self.precedence() < min;
+        if parens {
+            out.push('(');
+        }
+
+        match self {
+            RegexAST::Epsilon => out.push('ε'),
+            RegexAST::Empty => out.push('∅'),
+            RegexAST::Char(c) => push_literal(out, *c),
+            RegexAST::Class(ranges) | RegexAST::NegClass(ranges) => {
+                out.push('[');
+                if matches!(self, RegexAST::NegClass(_)) {
+                    out.push('^');
+                }
+                for r in ranges {
+                    push_literal(out, *r.start());
+                    if r.start() != r.end() {
+                        out.push('-');
+                        push_literal(out, *r.end());
+                    }
+                }
+                out.push(']');
+            }
+            RegexAST::Any => out.push('.'),
+            RegexAST::Union(a, b) => {
+                a.write_pattern(out, 0);
+                out.push('+');
+                b.write_pattern(out, 1);
+            }
+            RegexAST::Intersect(a, b) => {
+                a.write_pattern(out, 1);
+                out.push('&');
+                b.write_pattern(out, 2);
+            }
+            RegexAST::Concat(a, b) => {
+                a.write_pattern(out, 2);
+                b.write_pattern(out, 3);
+            }
+            RegexAST::Complement(e) => {
+                out.push('!');
+                e.write_pattern(out, 3);
+            }
+            RegexAST::Star(e) => {
+                e.write_pattern(out, 4);
+                out.push('*');
+            }
+            RegexAST::Optional(e) => {
+                e.write_pattern(out, 4);
+                out.push('?');
+            }
+            RegexAST::Repeat(e, min, max) => {
+                e.write_pattern(out, 4);
+                match max {
+                    Some(max) if max == min => out.push_str(&format!("{{{}}}", min)),
+                    Some(max) => out.push_str(&format!("{{{},{}}}", min, max)),
+                    None => out.push_str(&format!("{{{},}}", min)),
+                }
+            }
+            RegexAST::Ref(name) => out.push_str(&format!("{{{}}}", name)),
+        }
+
+        if parens {
+            out.push(')');
+        }
+    }
+
+    fn collect_literals(&self, out: &mut BTreeSet<char>) {
+        match self {
+            RegexAST::Char(c) => {
+                out.insert(*c);
+            }
+            RegexAST::Class(ranges) | RegexAST::NegClass(ranges) => {
+                out.extend(ranges.iter().cloned().flatten());
+            }
+            RegexAST::Concat(a, b) | RegexAST::Union(a, b) | RegexAST::Intersect(a, b) => {
+                a.collect_literals(out);
+                b.collect_literals(out);
+            }
+            RegexAST::Star(e)
+            | RegexAST::Optional(e)
+            | RegexAST::Complement(e)
+            | RegexAST::Repeat(e, _, _) => e.collect_literals(out),
+            RegexAST::Epsilon | RegexAST::Empty | RegexAST::Any | RegexAST::Ref(_) => {}
+        }
+    }
+
+    fn collect_refs<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            RegexAST::Epsilon
+            | RegexAST::Empty
+            | RegexAST::Char(_)
+            | RegexAST::Class(_)
+            | RegexAST::NegClass(_)
+            | RegexAST::Any => {}
+            RegexAST::Concat(a, b) | RegexAST::Union(a, b) | RegexAST::Intersect(a, b) => {
+                a.collect_refs(out);
+                b.collect_refs(out);
+            }
+            RegexAST::Star(e)
+            | RegexAST::Optional(e)
+            | RegexAST::Complement(e)
+            | RegexAST::Repeat(e, _, _) => e.collect_refs(out),
+            RegexAST::Ref(name) => out.push(name),
+        }
+    }
+}
+
+// Write a literal char the way the tokenizer reads it back.
+fn push_literal(out: &mut String, c: char) {
+    match c {
+        '\n' => out.push_str("\\n"),
+        '\t' => out.push_str("\\t"),
+        c if c.is_alphanumeric() => out.push(c),
+        c if "\\+|&!*?.·∪∩()[]{}^-@ε∅".contains(c) => {
+            out.push('\\');
+            out.push(c);
+        }
+        c => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+    }
+}
+
+/// `alphabet` with the other case of each ASCII letter in it, the
+/// alphabet to compile an `ignore_ascii_case` expression against.
+pub fn ascii_case_closure(alphabet: &BTreeSet<char>) -> BTreeSet<char> {
+    let swapped = alphabet
+        .iter()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|&c| swap_ascii_case(c));
+    alphabet.iter().copied().chain(swapped).collect()
+}
+
+// `ranges` followed by the other case of the ASCII letters they cover.
+fn fold_ranges(ranges: &[RangeInclusive<char>]) -> Vec<RangeInclusive<char>> {
+    let mut out = ranges.to_vec();
+    for r in ranges {
+        for (lo, hi) in [('a', 'z'), ('A', 'Z')] {
+            let (start, end) = ((*r.start()).max(lo), (*r.end()).min(hi));
+            if start <= end {
+                let range = swap_ascii_case(start)..=swap_ascii_case(end);
+                if !out.contains(&range) {
+                    out.push(range);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn swap_ascii_case(c: char) -> char {
+    if c.is_ascii_lowercase() {
+        c.to_ascii_uppercase()
+    } else {
+        c.to_ascii_lowercase()
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use crate::regex::parser::parse_language;
+
+    fn round_trip(pattern: &str) -> String {
+        let ast = parse_language(pattern).unwrap();
+        let printed = ast.to_pattern();
+        assert_eq!(parse_language(&printed).unwrap(), ast, "{}", printed);
+        printed
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        assert_eq!(round_trip("((a)(b))+((c)*)"), "ab+c*");
+        assert_eq!(round_trip("(a+b)c"), "(a+b)c");
+        assert_eq!(round_trip("a+b&c"), "a+b&c");
+        assert_eq!(round_trip("(a+b)&c"), "(a+b)&c");
+        assert_eq!(round_trip("!(ab)*"), "!(ab)*");
+        assert_eq!(round_trip("(!a)*b"), "(!a)*b");
+        assert_eq!(round_trip("(a|b)?{2,}"), "(a+b)?{2,}");
+    }
+
+    #[test]
+    fn right_nesting_is_kept() {
+        // The parser is left-associative, so a right-nested tree needs parens
+        assert_eq!(round_trip("a(bc)"), "a(bc)");
+        assert_eq!(round_trip("a+(b+c)"), "a+(b+c)");
+        assert_eq!(round_trip("(a+b)+c"), "a+b+c");
+    }
+
+    #[test]
+    fn literals_and_atoms() {
+        assert_eq!(round_trip(r"\+\n[a-c\-x]"), r"\+\n[a-c\-x]");
+        assert_eq!(
+            round_trip(r"[^0-9].ε∅{lang}a{3}b{1,2}"),
+            r"[^0-9].ε∅{lang}a{3}b{1,2}"
+        );
+        assert_eq!(round_trip("\\u{1F600} é"), r"\u{1f600}é");
+    }
+}
@@ -0,0 +1,71 @@
+// regex/complement.rs
+use super::parser::parse_language;
+use crate::dfa::dfa::DFA;
+use crate::dfa::minimize::minimize_dfa;
+use crate::dfa::ops::{self, trim};
+use crate::pipeline::tests::{full_pipeline_with, PipelineOptions};
+use std::collections::BTreeSet;
+
+/// The minimal DFA for `alphabet* \ L(pattern)`: every word over
+/// `alphabet` that the pattern doesn't match. `.` and negated classes in
+/// the pattern range over `alphabet`; symbols the pattern mentions outside
+/// it are ignored, since no word of the result can contain them.
+pub fn complement(pattern: &str, alphabet: &BTreeSet<char>) -> Result<DFA, String> {
+    let ast = parse_language(pattern).map_err(|e| format!("Parse error: {}", e))?;
+    let opts = PipelineOptions {
+        alphabet: Some(alphabet | &ast.literals()),
+        ..Default::default()
+    };
+    let mut dfa = full_pipeline_with(pattern, &opts)?.mindfa;
+
+    for map in dfa.transitions.values_mut() {
+        map.retain(|c, _| alphabet.contains(c));
+    }
+    Ok(minimize_dfa(&trim(&ops::complement(&dfa, alphabet))))
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn everything_except() {
+        let sigma: BTreeSet<char> = "ab".chars().collect();
+        let dfa = complement("(a+b)*abb", &sigma).unwrap();
+
+        for (w, expected) in [("", true), ("abb", false), ("babb", false), ("abba", true)] {
+            assert_eq!(dfa.accepts(w), expected, "{:?}", w);
+        }
+        assert!(!dfa.accepts("c"));
+    }
+
+    #[test]
+    fn alphabet_bounds_the_result() {
+        // `c` is outside Σ, so only the ε word is excluded
+        let sigma: BTreeSet<char> = "ab".chars().collect();
+        let dfa = complement("c*", &sigma).unwrap();
+        assert!(!dfa.accepts(""));
+        assert!(dfa.accepts("ab"));
+        assert!(!dfa.accepts("c"));
+
+        // `.` ranges over the given alphabet, not just the pattern's symbols
+        let dfa = complement("a.", &sigma).unwrap();
+        assert!(!dfa.accepts("ab"));
+        assert!(dfa.accepts("ba"));
+
+        assert!(complement("(a", &sigma).is_err());
+    }
+
+    #[test]
+    fn complement_of_universal_language_is_empty() {
+        let sigma: BTreeSet<char> = "ab".chars().collect();
+        let dfa = complement("(a+b)*", &sigma).unwrap();
+        assert!(dfa.accepts.is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+// regex/derivatives.rs
+//
+// Membership straight on the AST: the derivative of L by c is
+// { w | cw ∈ L }, so w ∈ L iff the derivative by each char of w in turn
+// contains ε. No automaton is built, which makes this cheap for one-off
+// queries and independent of the Thompson/subset/Hopcroft pipeline.
+// `derivative_dfa` goes the other way and builds the DFA whose states are
+// the distinct derivatives, skipping the NFA stages altogether.
+use super::ast::RegexAST;
+use super::normalize::normalize;
+use crate::dfa::dfa::DFA;
+use crate::types::StateId;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+/// Whether `ε ∈ L(ast)`. References are treated as the empty language.
+pub fn nullable(ast: &RegexAST) -> bool {
+    match ast {
+        RegexAST::Epsilon | RegexAST::Star(_) | RegexAST::Optional(_) => true,
+        RegexAST::Empty
+        | RegexAST::Char(_)
+        | RegexAST::Class(_)
+        | RegexAST::NegClass(_)
+        | RegexAST::Any
+        | RegexAST::Ref(_) => false,
+        RegexAST::Concat(a, b) | RegexAST::Intersect(a, b) => nullable(a) && nullable(b),
+        RegexAST::Union(a, b) => nullable(a) || nullable(b),
+        RegexAST::Complement(e) => !nullable(e),
+        RegexAST::Repeat(e, min, _) => *min == 0 || nullable(e),
+    }
+}
+
+/// The Brzozowski derivative of `ast` by `c`. `.` and negated classes
+/// range over `alphabet`. The result is lightly simplified (∅ and ε
+/// absorbed, identical operands merged) to keep it from growing.
+pub fn derivative(ast: &RegexAST, c: char, alphabet: &BTreeSet<char>) -> RegexAST {
+    let d = |e: &RegexAST| derivative(e, c, alphabet);
+
+    match ast {
+        RegexAST::Epsilon | RegexAST::Empty | RegexAST::Ref(_) => RegexAST::Empty,
+        RegexAST::Char(x) => symbol(*x == c),
+        RegexAST::Class(ranges) => symbol(ranges.iter().any(|r| r.contains(&c))),
+        RegexAST::NegClass(ranges) => {
+            symbol(alphabet.contains(&c) && !ranges.iter().any(|r| r.contains(&c)))
+        }
+        RegexAST::Any => symbol(alphabet.contains(&c)),
+        RegexAST::Concat(a, b) => {
+            let left = concat(d(a), (**b).clone());
+            if nullable(a) {
+                union(left, d(b))
+            } else {
+                left
+            }
+        }
+        RegexAST::Union(a, b) => union(d(a), d(b)),
+        RegexAST::Intersect(a, b) => intersect(d(a), d(b)),
+        RegexAST::Complement(e) => complement(d(e)),
+        RegexAST::Star(e) => concat(d(e), ast.clone()),
+        RegexAST::Optional(e) => d(e),
+        // d(e{m,n}) = d(e)·e{m-1,n-1}, saturating at 0
+        RegexAST::Repeat(_, _, Some(0)) => RegexAST::Empty,
+        RegexAST::Repeat(e, min, max) => {
+            let rest = RegexAST::Repeat(e.clone(), min.saturating_sub(1), max.map(|m| m - 1));
+            concat(d(e), rest)
+        }
+    }
+}
+
+/// Whether `input` is in the language of `ast`, with the alphabet inferred
+/// from the characters written in the expression, as the pipeline does.
+pub fn matches(ast: &RegexAST, input: &str) -> Result<bool, String> {
+    matches_with(ast, input, &ast.literals())
+}
+
+/// Like `matches`, with `.`, negated classes and complement relative to
+/// `alphabet`. Chars outside the alphabet and the expression never match.
+pub fn matches_with(
+    ast: &RegexAST,
+    input: &str,
+    alphabet: &BTreeSet<char>,
+) -> Result<bool, String> {
+    if let Some(name) = ast.references().first() {
+        return Err(format!("Unknown language: {{{}}}", name));
+    }
+
+    let literals = ast.literals();
+    let mut current = ast.clone();
+    for c in input.chars() {
+        if !alphabet.contains(&c) && !literals.contains(&c) {
+            return Ok(false);
+        }
+        current = derivative(&current, c, alphabet);
+        if current == RegexAST::Empty {
+            return Ok(false);
+        }
+    }
+    Ok(nullable(&current))
+}
+
+/// A DFA for `ast` whose states are its derivatives by each word. As in
+/// `Thompson::with_alphabet`, `.` and negated classes range over
+/// `alphabet`, and symbols the expression writes get transitions too.
+/// Derivatives are
+/// compared in normal form, which keeps them finitely many (Brzozowski);
+/// the ∅ derivative becomes the missing transition.
+pub fn derivative_dfa(ast: &RegexAST, alphabet: &BTreeSet<char>) -> Result<DFA, String> {
+    if let Some(name) = ast.references().first() {
+        return Err(format!("Unknown language: {{{}}}", name));
+    }
+
+    let symbols = alphabet | &ast.literals();
+    let start = normalize(ast);
+    let mut ids: HashMap<RegexAST, StateId> = HashMap::from([(start.clone(), StateId(0))]);
+    let mut queue = VecDeque::from([start]);
+    let mut accepts = HashSet::new();
+    let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+
+    while let Some(state) = queue.pop_front() {
+        let id = ids[&state];
+        if nullable(&state) {
+            accepts.insert(id);
+        }
+        let edges = transitions.entry(id).or_default();
+        for &c in &symbols {
+            let next = normalize(&derivative(&state, c, alphabet));
+            if next == RegexAST::Empty {
+                continue;
+            }
+            let fresh = StateId::new(ids.len());
+            let to = *ids.entry(next.clone()).or_insert_with(|| {
+                queue.push_back(next);
+                fresh
+            });
+            edges.insert(c, to);
+        }
+    }
+
+    Ok(DFA {
+        start: StateId(0),
+        accepts,
+        transitions,
+    })
+}
+
+// ε if the symbol matched, ∅ otherwise.
+fn symbol(matched: bool) -> RegexAST {
+    if matched {
+        RegexAST::Epsilon
+    } else {
+        RegexAST::Empty
+    }
+}
+
+fn concat(a: RegexAST, b: RegexAST) -> RegexAST {
+    match (a, b) {
+        (RegexAST::Empty, _) | (_, RegexAST::Empty) => RegexAST::Empty,
+        (RegexAST::Epsilon, e) | (e, RegexAST::Epsilon) => e,
+        (a, b) => RegexAST::Concat(Box::new(a), Box::new(b)),
+    }
+}
+
+fn union(a: RegexAST, b: RegexAST) -> RegexAST {
+    match (a, b) {
+        (RegexAST::Empty, e) | (e, RegexAST::Empty) => e,
+        (a, b) if a == b => a,
+        (a, b) => RegexAST::Union(Box::new(a), Box::new(b)),
+    }
+}
+
+fn intersect(a: RegexAST, b: RegexAST) -> RegexAST {
+    match (a, b) {
+        (RegexAST::Empty, _) | (_, RegexAST::Empty) => RegexAST::Empty,
+        (a, b) if a == b => a,
+        (a, b) => RegexAST::Intersect(Box::new(a), Box::new(b)),
+    }
+}
+
+fn complement(e: RegexAST) -> RegexAST {
+    match e {
+        RegexAST::Complement(inner) => *inner,
+        e => RegexAST::Complement(Box::new(e)),
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+    use crate::regex::parser::parse_language;
+
+    // Every word over the expression's alphabet up to length `len`.
+    fn words(alphabet: &BTreeSet<char>, len: usize) -> Vec<String> {
+        let mut out = vec![String::new()];
+        let mut layer = vec![String::new()];
+        for _ in 0..len {
+            layer = layer
+                .iter()
+                .flat_map(|w| alphabet.iter().map(move |&c| format!("{}{}", w, c)))
+                .collect();
+            out.extend(layer.iter().cloned());
+        }
+        out
+    }
+
+    #[test]
+    fn agrees_with_pipeline() {
+        for lang in [
+            "(a+b)*abb",
+            "a{2,3}b?",
+            "(ab){1,}",
+            "[^a]b*",
+            "a.c*",
+            "!(a*b)",
+            "(a+b)*&!(a*)",
+            "(ab*)*&a{0,2}b*",
+            "ε+∅a",
+            "(a?b?){2}",
+        ] {
+            let ast = parse_language(lang).unwrap();
+            let dfa = full_pipeline(lang).unwrap().mindfa;
+            for w in words(&ast.literals(), 5) {
+                assert_eq!(
+                    matches(&ast, &w).unwrap(),
+                    dfa.accepts(&w),
+                    "{} on {:?}",
+                    lang,
+                    w
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn foreign_chars_and_refs() {
+        let ast = parse_language("!a").unwrap();
+        assert!(matches(&ast, "").unwrap());
+        assert!(!matches(&ast, "z").unwrap());
+
+        let sigma: BTreeSet<char> = "az".chars().collect();
+        assert!(matches_with(&ast, "z", &sigma).unwrap());
+        assert!(matches_with(&parse_language("a.").unwrap(), "az", &sigma).unwrap());
+
+        assert!(matches(&parse_language("{x}").unwrap(), "a").is_err());
+    }
+
+    #[test]
+    fn derivative_dfa_matches_pipeline() {
+        use crate::decisions::{equivalence, Equivalence};
+
+        for lang in [
+            "(a+b)*abb",
+            "a{2,3}b?",
+            "((a*)*b*)*",
+            "!(a*b)&(a+b)*",
+            "[^a]b*",
+            "∅",
+            "ε",
+        ] {
+            let ast = parse_language(lang).unwrap();
+            let dfa = derivative_dfa(&ast, &ast.literals()).unwrap();
+            let expected = full_pipeline(lang).unwrap().mindfa;
+            assert_eq!(
+                equivalence(&dfa, &expected),
+                Ok(Equivalence::Equivalent),
+                "{}",
+                lang
+            );
+        }
+
+        // (a+b)*abb has four derivatives, as many as its minimal DFA states
+        let ast = parse_language("(a+b)*abb").unwrap();
+        assert_eq!(
+            derivative_dfa(&ast, &BTreeSet::new())
+                .unwrap()
+                .states()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn derivatives_simplify() {
+        let sigma = BTreeSet::new();
+        let ast = parse_language("abc").unwrap();
+        assert_eq!(derivative(&ast, 'a', &sigma), parse_language("bc").unwrap());
+        assert_eq!(derivative(&ast, 'b', &sigma), RegexAST::Empty);
+
+        let star = parse_language("(ab)*").unwrap();
+        let back = derivative(&derivative(&star, 'a', &sigma), 'b', &sigma);
+        assert_eq!(back, star);
+    }
+}
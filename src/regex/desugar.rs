@@ -0,0 +1,99 @@
+// regex/desugar.rs
+use super::ast::RegexAST;
+
+/// Rewrite derived operators into the core Char/Concat/Union/Star/Optional
+/// forms understood by Thompson construction.
+///
+/// - `A{0}`   → `ε`
+/// - `A{n}`   → `A…A` (n times)
+/// - `A{n,}`  → `A…A A*`
+/// - `A{n,m}` → `A…A A?…A?` (m - n optional copies)
+pub fn desugar(ast: &RegexAST) -> RegexAST {
+    match ast {
+        RegexAST::Epsilon
+        | RegexAST::Empty
+        | RegexAST::Char(_)
+        | RegexAST::Class(_)
+        | RegexAST::NegClass(_)
+        | RegexAST::Any
+        | RegexAST::Ref(_) => ast.clone(),
+        RegexAST::Concat(a, b) => RegexAST::Concat(Box::new(desugar(a)), Box::new(desugar(b))),
+        RegexAST::Union(a, b) => RegexAST::Union(Box::new(desugar(a)), Box::new(desugar(b))),
+        RegexAST::Intersect(a, b) => {
+            RegexAST::Intersect(Box::new(desugar(a)), Box::new(desugar(b)))
+        }
+        RegexAST::Complement(e) => RegexAST::Complement(Box::new(desugar(e))),
+        RegexAST::Star(e) => RegexAST::Star(Box::new(desugar(e))),
+        RegexAST::Optional(e) => RegexAST::Optional(Box::new(desugar(e))),
+        RegexAST::Repeat(e, min, max) => expand_repeat(&desugar(e), *min, *max),
+    }
+}
+
+fn expand_repeat(e: &RegexAST, min: u32, max: Option<u32>) -> RegexAST {
+    let mut parts: Vec<RegexAST> = (0..min).map(|_| e.clone()).collect();
+
+    match max {
+        None => parts.push(RegexAST::Star(Box::new(e.clone()))),
+        Some(max) => {
+            for _ in min..max {
+                parts.push(RegexAST::Optional(Box::new(e.clone())));
+            }
+        }
+    }
+
+    parts
+        .into_iter()
+        .reduce(|acc, p| RegexAST::Concat(Box::new(acc), Box::new(p)))
+        .unwrap_or(RegexAST::Epsilon)
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::parser::parse_language;
+
+    fn count_chars(ast: &RegexAST) -> usize {
+        match ast {
+            RegexAST::Char(_) | RegexAST::Class(_) | RegexAST::NegClass(_) | RegexAST::Any => 1,
+            RegexAST::Concat(a, b) | RegexAST::Union(a, b) | RegexAST::Intersect(a, b) => {
+                count_chars(a) + count_chars(b)
+            }
+            RegexAST::Star(e) | RegexAST::Optional(e) | RegexAST::Complement(e) => count_chars(e),
+            RegexAST::Repeat(..) => panic!("Repeat left after desugaring"),
+            RegexAST::Epsilon | RegexAST::Empty | RegexAST::Ref(_) => 0,
+        }
+    }
+
+    #[test]
+    fn desugar_exact() {
+        let ast = desugar(&parse_language("a{3}").unwrap());
+        assert_eq!(count_chars(&ast), 3);
+    }
+
+    #[test]
+    fn desugar_at_least() {
+        let ast = desugar(&parse_language("a{2,}").unwrap());
+        match ast {
+            RegexAST::Concat(_, star) => assert!(matches!(*star, RegexAST::Star(_))),
+            _ => panic!("expected a a a*"),
+        }
+    }
+
+    #[test]
+    fn desugar_zero() {
+        let ast = desugar(&parse_language("a{0}").unwrap());
+        assert!(matches!(ast, RegexAST::Epsilon));
+    }
+
+    #[test]
+    fn desugar_range_nested() {
+        let ast = desugar(&parse_language("(ab{1,2}){2}").unwrap());
+        assert_eq!(count_chars(&ast), 6);
+    }
+}
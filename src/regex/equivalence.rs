@@ -0,0 +1,71 @@
+// regex/equivalence.rs
+use super::parser::parse_language;
+use crate::decisions::{equivalence, Equivalence};
+use crate::pipeline::tests::{full_pipeline_with, PipelineOptions};
+use std::collections::BTreeSet;
+
+/// Do the two expressions denote the same language? Both are compiled to
+/// minimal DFAs and compared; `.`, negated classes and complement range
+/// over the symbols written in either expression, so e.g. `[^a]` and `b`
+/// agree. Use `decisions::equivalence` for a distinguishing word.
+pub fn equivalent(a: &str, b: &str) -> Result<bool, String> {
+    Ok(equivalence_witness(a, b)?.is_none())
+}
+
+/// A shortest word in exactly one of the two languages, and whether it is
+/// in `a`, or `None` when they are equivalent.
+pub fn equivalence_witness(a: &str, b: &str) -> Result<Option<(String, bool)>, String> {
+    let mut alphabet = BTreeSet::new();
+    for expr in [a, b] {
+        let ast = parse_language(expr).map_err(|e| format!("Parse error: {}", e))?;
+        alphabet.extend(ast.literals());
+    }
+
+    let opts = PipelineOptions {
+        alphabet: Some(alphabet),
+        ..Default::default()
+    };
+    let left = full_pipeline_with(a, &opts)?.mindfa;
+    let right = full_pipeline_with(b, &opts)?.mindfa;
+
+    Ok(match equivalence(&left, &right)? {
+        Equivalence::Equivalent => None,
+        Equivalence::Distinguished { witness, in_left } => Some((witness, in_left)),
+    })
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equivalent_spellings() {
+        assert_eq!(equivalent("(a+b)*", "(a*b*)*"), Ok(true));
+        assert_eq!(equivalent("a(ba)*", "(ab)*a"), Ok(true));
+        assert_eq!(equivalent("a{2,3}", "aaa?"), Ok(true));
+        assert_eq!(equivalent("[^a]", "b"), Ok(true));
+        assert_eq!(equivalent("!(a*)&b*", "b{1,}"), Ok(true));
+        assert_eq!(equivalent("(a+b)*", "a*b*"), Ok(false));
+    }
+
+    #[test]
+    fn witness_and_errors() {
+        assert_eq!(
+            equivalence_witness("(a+b)*", "a*b*"),
+            Ok(Some(("ba".to_string(), true)))
+        );
+        assert_eq!(
+            equivalence_witness("ab", "a"),
+            Ok(Some(("a".to_string(), false)))
+        );
+        assert!(equivalent("a(", "a")
+            .unwrap_err()
+            .starts_with("Parse error"));
+    }
+}
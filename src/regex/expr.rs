@@ -0,0 +1,169 @@
+// regex/expr.rs
+//
+// Building expressions in code: `(Expr::from('a') | 'b').star() * 'c'`
+// is the AST of `(a+b)*c`, without formatting and re-parsing a string.
+use super::ast::RegexAST;
+use std::ops::{BitAnd, BitOr, Mul, Not, RangeInclusive};
+
+/// A `RegexAST` with operators: `|` union, `*` concatenation,
+/// `&` intersection and `!` complement. Operands may be anything that
+/// converts into an `Expr`, such as a `char` or a `&str` literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr(pub RegexAST);
+
+impl Expr {
+    /// The literal word `s`; ε when empty.
+    pub fn literal(s: &str) -> Self {
+        s.chars()
+            .map(|c| Expr(RegexAST::Char(c)))
+            .reduce(|a, b| a * b)
+            .unwrap_or_else(Self::epsilon)
+    }
+
+    pub fn class(ranges: impl IntoIterator<Item = RangeInclusive<char>>) -> Self {
+        Expr(RegexAST::Class(ranges.into_iter().collect()))
+    }
+
+    pub fn neg_class(ranges: impl IntoIterator<Item = RangeInclusive<char>>) -> Self {
+        Expr(RegexAST::NegClass(ranges.into_iter().collect()))
+    }
+
+    pub fn any() -> Self {
+        Expr(RegexAST::Any)
+    }
+
+    pub fn epsilon() -> Self {
+        Expr(RegexAST::Epsilon)
+    }
+
+    pub fn empty() -> Self {
+        Expr(RegexAST::Empty)
+    }
+
+    /// A reference `{name}` to a language in a `LanguageSet`.
+    pub fn reference(name: &str) -> Self {
+        Expr(RegexAST::Ref(name.to_string()))
+    }
+
+    pub fn star(self) -> Self {
+        Expr(RegexAST::Star(Box::new(self.0)))
+    }
+
+    pub fn optional(self) -> Self {
+        Expr(RegexAST::Optional(Box::new(self.0)))
+    }
+
+    /// `self{min,max}`, unbounded when `max` is None.
+    pub fn repeat(self, min: u32, max: Option<u32>) -> Self {
+        Expr(RegexAST::Repeat(Box::new(self.0), min, max))
+    }
+
+    pub fn ast(&self) -> &RegexAST {
+        &self.0
+    }
+
+    pub fn into_ast(self) -> RegexAST {
+        self.0
+    }
+
+    /// The pattern in the default syntax; see `RegexAST::to_pattern`.
+    pub fn to_pattern(&self) -> String {
+        self.0.to_pattern()
+    }
+}
+
+impl From<RegexAST> for Expr {
+    fn from(ast: RegexAST) -> Self {
+        Expr(ast)
+    }
+}
+
+impl From<Expr> for RegexAST {
+    fn from(expr: Expr) -> Self {
+        expr.0
+    }
+}
+
+impl From<char> for Expr {
+    fn from(c: char) -> Self {
+        Expr(RegexAST::Char(c))
+    }
+}
+
+impl From<&str> for Expr {
+    fn from(s: &str) -> Self {
+        Expr::literal(s)
+    }
+}
+
+impl<T: Into<Expr>> BitOr<T> for Expr {
+    type Output = Expr;
+
+    fn bitor(self, rhs: T) -> Expr {
+        Expr(RegexAST::Union(Box::new(self.0), Box::new(rhs.into().0)))
+    }
+}
+
+impl<T: Into<Expr>> Mul<T> for Expr {
+    type Output = Expr;
+
+    fn mul(self, rhs: T) -> Expr {
+        Expr(RegexAST::Concat(Box::new(self.0), Box::new(rhs.into().0)))
+    }
+}
+
+impl<T: Into<Expr>> BitAnd<T> for Expr {
+    type Output = Expr;
+
+    fn bitand(self, rhs: T) -> Expr {
+        Expr(RegexAST::Intersect(
+            Box::new(self.0),
+            Box::new(rhs.into().0),
+        ))
+    }
+}
+
+impl Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr(RegexAST::Complement(Box::new(self.0)))
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::parser::parse_language;
+
+    fn parsed(pattern: &str) -> Expr {
+        Expr(parse_language(pattern).unwrap())
+    }
+
+    #[test]
+    fn operators_build_the_parsed_tree() {
+        let a = Expr::from('a');
+        assert_eq!((a.clone() | 'b').star() * 'c', parsed("(a+b)*c"));
+        assert_eq!(Expr::literal("abc"), parsed("abc"));
+        assert_eq!(!(a.clone() * "b") & Expr::any().star(), parsed("!(ab)&.*"));
+        assert_eq!(
+            Expr::class(['0'..='9']).repeat(2, Some(4)) * a.optional(),
+            parsed("[0-9]{2,4}a?")
+        );
+        assert_eq!(Expr::reference("digit").star(), parsed("{digit}*"));
+    }
+
+    #[test]
+    fn patterns_round_trip() {
+        let e = (Expr::literal("ab") | Expr::neg_class(['x'..='z'])) * Expr::epsilon();
+        assert_eq!(e.to_pattern(), "(ab+[^x-z])ε");
+        assert_eq!(parsed(&e.to_pattern()), e);
+        assert_eq!(Expr::literal(""), Expr::epsilon());
+    }
+}
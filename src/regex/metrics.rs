@@ -0,0 +1,83 @@
+// regex/metrics.rs
+//
+// Cheap size measures of an expression as written, for gating compilation
+// of large generated patterns before any automaton is built.
+use super::ast::RegexAST;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    // Deepest nesting of unbounded repetition: a* is 1, (a*b)* is 2
+    pub star_height: u32,
+    // Symbol occurrences (chars, classes, `.`); bounds the Glushkov NFA size
+    pub alphabetic_width: usize,
+    pub node_count: usize,
+    // Longest root-to-leaf path; a single symbol has depth 1
+    pub depth: usize,
+}
+
+/// Measure `ast` without desugaring, so `a{100}` counts as one symbol.
+pub fn metrics(ast: &RegexAST) -> Metrics {
+    let children: Vec<Metrics> = ast.children().into_iter().map(metrics).collect();
+
+    let inner_height = children.iter().map(|m| m.star_height).max().unwrap_or(0);
+    let star_height = match ast {
+        RegexAST::Star(_) | RegexAST::Repeat(_, _, None) => inner_height + 1,
+        _ => inner_height,
+    };
+    let alphabetic_width = match ast {
+        RegexAST::Char(_) | RegexAST::Class(_) | RegexAST::NegClass(_) | RegexAST::Any => 1,
+        _ => children.iter().map(|m| m.alphabetic_width).sum(),
+    };
+
+    Metrics {
+        star_height,
+        alphabetic_width,
+        node_count: 1 + children.iter().map(|m| m.node_count).sum::<usize>(),
+        depth: 1 + children.iter().map(|m| m.depth).max().unwrap_or(0),
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::parser::parse_language;
+
+    fn of(lang: &str) -> Metrics {
+        metrics(&parse_language(lang).unwrap())
+    }
+
+    #[test]
+    fn metrics_of_examples() {
+        assert_eq!(
+            of("a"),
+            Metrics {
+                star_height: 0,
+                alphabetic_width: 1,
+                node_count: 1,
+                depth: 1
+            }
+        );
+        let m = of("(a+b)*abb");
+        assert_eq!(m.star_height, 1);
+        assert_eq!(m.alphabetic_width, 5);
+
+        assert_eq!(of("((a*b)*c)*").star_height, 3);
+        assert_eq!(of("(ab){2,}").star_height, 1);
+        assert_eq!(of("(ab){2,5}").star_height, 0);
+        assert_eq!(of("[a-z].ε").alphabetic_width, 2);
+    }
+
+    #[test]
+    fn size_and_depth() {
+        // Star(Union(a, b))
+        let m = of("(a+b)*");
+        assert_eq!(m.node_count, 4);
+        assert_eq!(m.depth, 3);
+    }
+}
@@ -1,3 +1,20 @@
+pub mod analysis;
 pub mod ast;
+pub mod complement;
+pub mod derivatives;
+pub mod desugar;
+pub mod equivalence;
+pub mod expr;
+pub mod metrics;
+pub mod normalize;
 pub mod parser;
+pub mod positions;
+pub mod prune;
+pub mod simplify;
 pub mod tokenizer;
+
+pub use complement::complement;
+pub use equivalence::equivalent;
+pub use expr::Expr;
+pub use metrics::metrics;
+pub use prune::prune;
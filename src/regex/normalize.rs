@@ -0,0 +1,167 @@
+// regex/normalize.rs
+//
+// A canonical form up to structure: expressions that differ only in the
+// order or grouping of `+`/`&` operands, the grouping of concatenations,
+// the order of class ranges, or stacked stars normalize to the same tree.
+// Unlike `simplify`, nothing else is rewritten, so the result still has
+// the shape the author wrote.
+use super::ast::RegexAST;
+use std::ops::RangeInclusive;
+
+/// Rewrite `ast` into its canonical form:
+/// - unions and intersections flattened, deduplicated, sorted and
+///   right-associated
+/// - concatenations right-associated
+/// - classes sorted with overlapping or adjacent ranges merged
+/// - stacked `*` and `?` fused: `(e*)*`, `(e?)*` and `(e*)?` are `e*`,
+///   `(e?)?` is `e?`
+///
+/// Two expressions with equal normal forms denote the same language, so
+/// the normal form works as a cache key.
+pub fn normalize(ast: &RegexAST) -> RegexAST {
+    match ast {
+        RegexAST::Class(ranges) => RegexAST::Class(merge(ranges)),
+        RegexAST::NegClass(ranges) => RegexAST::NegClass(merge(ranges)),
+        RegexAST::Concat(..) => {
+            let mut parts = Vec::new();
+            flatten(ast, &mut parts, &|e| match e {
+                RegexAST::Concat(a, b) => Some((a, b)),
+                _ => None,
+            });
+            right_nested(parts.into_iter().map(normalize).collect(), RegexAST::Concat)
+        }
+        RegexAST::Union(..) => operands(ast, RegexAST::Union, &|e| match e {
+            RegexAST::Union(a, b) => Some((a, b)),
+            _ => None,
+        }),
+        RegexAST::Intersect(..) => operands(ast, RegexAST::Intersect, &|e| match e {
+            RegexAST::Intersect(a, b) => Some((a, b)),
+            _ => None,
+        }),
+        RegexAST::Complement(e) => RegexAST::Complement(Box::new(normalize(e))),
+        RegexAST::Star(e) => match normalize(e) {
+            RegexAST::Star(inner) | RegexAST::Optional(inner) => RegexAST::Star(inner),
+            e => RegexAST::Star(Box::new(e)),
+        },
+        RegexAST::Optional(e) => match normalize(e) {
+            e @ (RegexAST::Star(_) | RegexAST::Optional(_)) => e,
+            e => RegexAST::Optional(Box::new(e)),
+        },
+        RegexAST::Repeat(e, min, max) => RegexAST::Repeat(Box::new(normalize(e)), *min, *max),
+        _ => ast.clone(),
+    }
+}
+
+type Split = dyn Fn(&RegexAST) -> Option<(&RegexAST, &RegexAST)>;
+type Node = fn(Box<RegexAST>, Box<RegexAST>) -> RegexAST;
+
+// The normalized operands of a commutative, idempotent operator, sorted by
+// their patterns (which are distinct for distinct trees).
+fn operands(ast: &RegexAST, node: Node, split: &Split) -> RegexAST {
+    let mut parts = Vec::new();
+    flatten(ast, &mut parts, split);
+
+    let mut parts: Vec<RegexAST> = parts.into_iter().map(normalize).collect();
+    parts.sort_by_cached_key(|p| p.to_pattern());
+    parts.dedup();
+    right_nested(parts, node)
+}
+
+fn flatten<'a>(ast: &'a RegexAST, out: &mut Vec<&'a RegexAST>, split: &Split) {
+    match split(ast) {
+        Some((a, b)) => {
+            flatten(a, out, split);
+            flatten(b, out, split);
+        }
+        None => out.push(ast),
+    }
+}
+
+fn right_nested(parts: Vec<RegexAST>, node: Node) -> RegexAST {
+    parts
+        .into_iter()
+        .rev()
+        .reduce(|acc, p| node(Box::new(p), Box::new(acc)))
+        .expect("operators have operands")
+}
+
+fn merge(ranges: &[RangeInclusive<char>]) -> Vec<RangeInclusive<char>> {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_by_key(|r| (*r.start(), *r.end()));
+
+    let mut out: Vec<RangeInclusive<char>> = Vec::new();
+    for r in sorted {
+        match out.last_mut() {
+            Some(last) if *r.start() as u32 <= *last.end() as u32 + 1 => {
+                if r.end() > last.end() {
+                    *last = *last.start()..=*r.end();
+                }
+            }
+            _ => out.push(r),
+        }
+    }
+    out
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::parser::parse_language;
+
+    fn normal(pattern: &str) -> RegexAST {
+        normalize(&parse_language(pattern).unwrap())
+    }
+
+    #[test]
+    fn structurally_identical_expressions_agree() {
+        for (a, b) in [
+            ("a+b+c", "c+(b+a)"),
+            ("(ab)c", "a(bc)"),
+            ("(a*)*", "a*"),
+            ("((a?)*)?", "a*"),
+            ("(a?)?", "a?"),
+            ("[b-dac]", "[a-d]"),
+            ("x&y&x", "y&x"),
+            ("(b+a)*c", "(a+b)*c"),
+            ("a+b+a", "b+a"),
+        ] {
+            assert_eq!(normal(a), normal(b), "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn other_differences_are_kept() {
+        assert_ne!(normal("a"), normal("aε"));
+        assert_ne!(normal("ab"), normal("ba"));
+        assert_ne!(normal("a{2}"), normal("aa"));
+    }
+
+    #[test]
+    fn usable_as_a_cache_key() {
+        let mut cache = std::collections::HashMap::new();
+        cache.insert(normal("(a+b)*c"), 1);
+        assert_eq!(cache.get(&normal("(b+a)*c")), Some(&1));
+    }
+
+    #[test]
+    fn concatenation_is_right_nested() {
+        let ast = normal("(ab)c");
+        assert_eq!(
+            ast,
+            RegexAST::Concat(
+                Box::new(RegexAST::Char('a')),
+                Box::new(RegexAST::Concat(
+                    Box::new(RegexAST::Char('b')),
+                    Box::new(RegexAST::Char('c'))
+                ))
+            )
+        );
+        assert_eq!(normalize(&ast), ast);
+    }
+}
@@ -1,103 +1,777 @@
 // regex/parser.rs
-use super::ast::RegexAST;
-use super::tokenizer::{tokenize, Token};
+use super::analysis::largest_count;
+use super::ast::{RegexAST, SpanTree};
+use super::tokenizer::{tokenize_spanned, Span, Token, TokenizeError, Tokenizer};
+use std::fmt;
+use std::io::Read;
 
+/// Most copies a counted repetition may unroll to, nested counters
+/// multiplied out: `a{1001}` and `(a{50}){30}` are parse errors. Every
+/// copy becomes NFA states, so larger counts are better written as a star.
+pub const MAX_REPEAT: u64 = 1000;
+
+/// A parse failure. `pos` is the index of the offending token (the token
+/// count for `UnexpectedEnd`); `span` is its byte range in the source, when
+/// the parser was given spans.
 #[derive(Debug)]
 pub enum ParseError {
-    UnexpectedEnd,
-    UnexpectedToken(Token),
+    Tokenize(TokenizeError),
+    UnexpectedEnd {
+        pos: usize,
+        span: Option<Span>,
+        expected: Vec<&'static str>,
+    },
+    UnexpectedToken {
+        token: Token,
+        pos: usize,
+        span: Option<Span>,
+        expected: Vec<&'static str>,
+    },
+    // {min,max} with max < min
+    InvalidRepeat {
+        min: u32,
+        max: u32,
+        pos: usize,
+        span: Option<Span>,
+    },
+    // A counter unrolling to more than `MAX_REPEAT` copies
+    RepeatTooLarge {
+        copies: u64,
+        pos: usize,
+        span: Option<Span>,
+    },
+}
+
+impl ParseError {
+    /// Byte range of the offending token, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::Tokenize(e) => Some(e.byte..e.byte + e.ch.map_or(0, char::len_utf8)),
+            ParseError::UnexpectedEnd { span, .. }
+            | ParseError::UnexpectedToken { span, .. }
+            | ParseError::InvalidRepeat { span, .. }
+            | ParseError::RepeatTooLarge { span, .. } => span.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let at = |f: &mut fmt::Formatter, pos: usize, span: &Option<Span>| match span {
+            Some(span) => write!(f, " at {}..{}", span.start, span.end),
+            None => write!(f, " at token {}", pos),
+        };
+        let expected = |f: &mut fmt::Formatter, expected: &[&str]| match expected {
+            [] => Ok(()),
+            [one] => write!(f, ", expected {}", one),
+            many => write!(f, ", expected one of {}", many.join(", ")),
+        };
+
+        match self {
+            ParseError::Tokenize(e) => write!(f, "{}", e),
+            ParseError::UnexpectedEnd {
+                pos,
+                span,
+                expected: exp,
+            } => {
+                write!(f, "unexpected end of input")?;
+                at(f, *pos, span)?;
+                expected(f, exp)
+            }
+            ParseError::UnexpectedToken {
+                token,
+                pos,
+                span,
+                expected: exp,
+            } => {
+                write!(f, "unexpected {}", token)?;
+                at(f, *pos, span)?;
+                expected(f, exp)
+            }
+            ParseError::InvalidRepeat {
+                min,
+                max,
+                pos,
+                span,
+            } => {
+                write!(f, "invalid repetition {{{},{}}}", min, max)?;
+                at(f, *pos, span)?;
+                write!(f, ": maximum is below minimum")
+            }
+            ParseError::RepeatTooLarge { copies, pos, span } => {
+                write!(f, "repetition of {} copies", copies)?;
+                at(f, *pos, span)?;
+                write!(f, ": the limit is {}", MAX_REPEAT)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Tokenize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TokenizeError> for ParseError {
+    fn from(e: TokenizeError) -> Self {
+        ParseError::Tokenize(e)
+    }
+}
+
+/// How the recovering parser got past a `ParseError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    // A missing token was assumed, e.g. "')'"
+    Inserted(&'static str),
+    // The offending token was ignored
+    Skipped,
+    // A missing operand was read as ε
+    Epsilon,
+    // A binary operator with nothing after it was ignored
+    DroppedOperator,
+    // The tokenizer failed; only the input before the error was parsed
+    Truncated,
+}
+
+/// An error the recovering parser worked around, and how.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub error: ParseError,
+    pub recovery: Recovery,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let recovery = match &self.recovery {
+            Recovery::Inserted(token) => format!("assumed {}", token),
+            Recovery::Skipped => "skipped it".to_string(),
+            Recovery::Epsilon => "read it as ε".to_string(),
+            Recovery::DroppedOperator => "dropped the operator".to_string(),
+            Recovery::Truncated => "ignored the rest".to_string(),
+        };
+        write!(f, "{} ({})", self.error, recovery)
+    }
+}
+
+// What may start a primary, for "expected" lists.
+const PRIMARY: &[&str] = &["literal", "'('", "class", "'.'", "'ε'", "'∅'", "reference"];
+
+/// A binary operator, as an entry of the precedence table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    // `+`, `|` or `∪`
+    Union,
+    // `&` or `∩`
+    Intersect,
+    // `·` or juxtaposition
+    Concat,
+}
+
+impl Operator {
+    /// Union, then intersection, then concatenation.
+    pub const DEFAULT_PRECEDENCE: [Operator; 3] =
+        [Operator::Union, Operator::Intersect, Operator::Concat];
+
+    fn node(self) -> BinaryOp {
+        match self {
+            Operator::Union => RegexAST::Union,
+            Operator::Intersect => RegexAST::Intersect,
+            Operator::Concat => RegexAST::Concat,
+        }
+    }
+}
+
+/// Surface syntax options.
+#[derive(Debug, Clone)]
+pub struct SyntaxConfig {
+    // Accept `|` as union (PCRE style)
+    pub pipe_union: bool,
+    // Read `+` as postfix one-or-more instead of union
+    pub plus_one_or_more: bool,
+    // Accept postfix `?` as optional
+    pub optional: bool,
+    // Juxtaposition concatenates; `·` is accepted either way
+    pub implicit_concat: bool,
+    // Accept the `&` intersection and `!` complement operators
+    pub language_ops: bool,
+    // Accept `∪` as union and, with `language_ops`, `∩` as intersection
+    pub set_symbols: bool,
+    // Accept postfix `^*` as star
+    pub caret_star: bool,
+    // Binary operators from loosest to tightest binding, all left
+    // associative. One left out can't be written at all
+    pub precedence: Vec<Operator>,
 }
 
-pub struct Parser {
-    tokens: Vec<Token>,
+impl Default for SyntaxConfig {
+    fn default() -> Self {
+        Self {
+            pipe_union: true,
+            plus_one_or_more: false,
+            optional: true,
+            implicit_concat: true,
+            language_ops: true,
+            set_symbols: false,
+            caret_star: false,
+            precedence: Operator::DEFAULT_PRECEDENCE.to_vec(),
+        }
+    }
+}
+
+/// Ready-made syntax conventions of different communities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    // Textbook notation: `+` is union, no `?`, concatenation written `·`
+    Academic,
+    // POSIX ERE: `|` union, postfix `+` and `?`
+    Posix,
+    // POSIX operators plus the `&` and `!` language operators
+    PcreLite,
+    // Formal language notation: `∪`, `∩` and `·` written out, `^*` for
+    // star; `+` stays union
+    Formal,
+}
+
+impl From<Dialect> for SyntaxConfig {
+    fn from(dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::Academic => SyntaxConfig {
+                pipe_union: false,
+                plus_one_or_more: false,
+                optional: false,
+                implicit_concat: false,
+                language_ops: true,
+                ..Default::default()
+            },
+            Dialect::Posix => SyntaxConfig {
+                pipe_union: true,
+                plus_one_or_more: true,
+                optional: true,
+                implicit_concat: true,
+                language_ops: false,
+                ..Default::default()
+            },
+            Dialect::PcreLite => SyntaxConfig {
+                language_ops: true,
+                ..Dialect::Posix.into()
+            },
+            Dialect::Formal => SyntaxConfig {
+                set_symbols: true,
+                caret_star: true,
+                ..Dialect::Academic.into()
+            },
+        }
+    }
+}
+
+impl From<&SyntaxConfig> for SyntaxConfig {
+    fn from(config: &SyntaxConfig) -> Self {
+        config.clone()
+    }
+}
+
+// A parsed node and the source ranges of its subtree.
+type Node = (RegexAST, SpanTree);
+type BinaryOp = fn(Box<RegexAST>, Box<RegexAST>) -> RegexAST;
+
+/// Token results as `Tokenizer::spanned` yields them.
+pub type SpannedToken = Result<(Token, Span), TokenizeError>;
+
+/// Recursive-descent parser over a stream of tokens, read one at a time.
+pub struct Parser<I = std::vec::IntoIter<SpannedToken>> {
+    tokens: I,
+    // The token after the last consumed one, read ahead
+    next: Option<(Token, Span)>,
+    // The tokenizer error that ended `tokens` early; reported in place of
+    // whatever the parser makes of the truncated input
+    failed: Option<TokenizeError>,
+    // Whether `tokens` carries byte spans, rather than placeholders
+    spanned: bool,
+    // Byte spans of the consumed tokens, when `spanned`
+    spans: Vec<Span>,
     pos: usize,
+    // Open parentheses around the current position
+    depth: usize,
+    config: SyntaxConfig,
+    // Errors worked around so far, or None when any error is fatal
+    diagnostics: Option<Vec<Diagnostic>>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self::with_config(tokens, SyntaxConfig::default())
+    }
+
+    pub fn with_config(tokens: Vec<Token>, config: SyntaxConfig) -> Self {
+        let tokens: Vec<SpannedToken> = tokens.into_iter().map(|t| Ok((t, 0..0))).collect();
+        Self::build(tokens.into_iter(), false, config)
+    }
+
+    /// Parser over tokens from `tokenize_spanned`, so errors carry spans.
+    pub fn with_spans(tokens: Vec<(Token, Span)>, config: SyntaxConfig) -> Self {
+        let tokens: Vec<SpannedToken> = tokens.into_iter().map(Ok).collect();
+        Self::build(tokens.into_iter(), true, config)
+    }
+}
+
+impl<I: Iterator<Item = SpannedToken>> Parser<I> {
+    /// Parser pulling tokens as it needs them, e.g. from
+    /// `Tokenizer::spanned`, so the pattern is never tokenized ahead of
+    /// the parse. A tokenizer error fails the parse.
+    pub fn streaming(tokens: I, config: SyntaxConfig) -> Self {
+        Self::build(tokens, true, config)
+    }
+
+    fn build(tokens: I, spanned: bool, config: SyntaxConfig) -> Self {
+        let mut parser = Self {
+            tokens,
+            next: None,
+            failed: None,
+            spanned,
+            spans: Vec::new(),
+            pos: 0,
+            depth: 0,
+            config,
+            diagnostics: None,
+        };
+        parser.advance();
+        parser
+    }
+
+    // Read the next token ahead. The stream ends at its first error.
+    fn advance(&mut self) {
+        self.next = match self.failed.is_none().then(|| self.tokens.next()).flatten() {
+            Some(Ok(next)) => Some(next),
+            Some(Err(e)) => {
+                self.failed = Some(e);
+                None
+            }
+            None => None,
+        };
+    }
+
+    // Byte span of the next token, if known.
+    fn next_span(&self) -> Option<Span> {
+        self.next
+            .as_ref()
+            .filter(|_| self.spanned)
+            .map(|(_, span)| span.clone())
+    }
+
+    /// Work around errors instead of failing: skip unexpected tokens,
+    /// assume missing `)`, read missing operands as ε. See `diagnostics`.
+    pub fn recovering(mut self) -> Self {
+        self.diagnostics = Some(Vec::new());
+        self
+    }
+
+    /// The errors a recovering parser worked around so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.as_deref().unwrap_or_default()
+    }
+
+    // Note `error` as worked around by `recovery`, or fail with it when
+    // not recovering.
+    fn recover(&mut self, error: ParseError, recovery: Recovery) -> Result<(), ParseError> {
+        match &mut self.diagnostics {
+            Some(diagnostics) => {
+                diagnostics.push(Diagnostic { error, recovery });
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+
+    // Whether an operand should start here but the input or group ended.
+    fn operand_missing(&self) -> bool {
+        match self.peek() {
+            None => true,
+            Some(Token::RParen) => self.depth > 0,
+            Some(_) => false,
+        }
+    }
+
+    // Error for the token at `pos` (or the end of input). Past a tokenizer
+    // error, that error.
+    fn unexpected(&self, expected: Vec<&'static str>) -> ParseError {
+        if let Some(e) = &self.failed {
+            return ParseError::Tokenize(e.clone());
+        }
+        match self.peek() {
+            Some(token) => ParseError::UnexpectedToken {
+                token: token.clone(),
+                pos: self.pos,
+                span: self.next_span(),
+                expected,
+            },
+            None => ParseError::UnexpectedEnd {
+                pos: self.pos,
+                span: self.spans.last().map(|s| s.end..s.end),
+                expected,
+            },
+        }
+    }
+
+    // Tokens that may follow a complete expression here.
+    fn continuations(&self) -> Vec<&'static str> {
+        let mut out = Vec::new();
+        let has = |op| self.config.precedence.contains(&op);
+        if has(Operator::Union) {
+            if !self.config.plus_one_or_more {
+                out.push("'+'");
+            }
+            if self.config.pipe_union {
+                out.push("'|'");
+            }
+            if self.config.set_symbols {
+                out.push("'∪'");
+            }
+        }
+        if has(Operator::Concat) && !self.config.implicit_concat {
+            out.push("'·'");
+        }
+        if has(Operator::Intersect) && self.config.language_ops {
+            out.push("'&'");
+            if self.config.set_symbols {
+                out.push("'∩'");
+            }
+        }
+        out.push("'*'");
+        if self.config.caret_star {
+            out.push("'^*'");
+        }
+        if self.config.optional {
+            out.push("'?'");
+        }
+        out.push("repetition");
+        if self.config.plus_one_or_more {
+            out.push("'+'");
+        }
+        out.push(if self.depth > 0 {
+            "')'"
+        } else {
+            "end of input"
+        });
+        out
+    }
+
+    // Whether `op` comes next, consuming its token if it has one (a
+    // juxtaposition has none).
+    fn operator(&mut self, op: Operator) -> bool {
+        let config = &self.config;
+        let explicit = match (op, self.peek()) {
+            (Operator::Union, Some(Token::Plus)) => !config.plus_one_or_more,
+            (Operator::Union, Some(Token::Pipe)) => config.pipe_union,
+            (Operator::Union, Some(Token::Cup)) => config.set_symbols,
+            (Operator::Intersect, Some(Token::Amp)) => config.language_ops,
+            (Operator::Intersect, Some(Token::Cap)) => config.language_ops && config.set_symbols,
+            (Operator::Concat, Some(Token::Concat)) => true,
+            (
+                Operator::Concat,
+                Some(
+                    Token::Char(_)
+                    | Token::Class(_)
+                    | Token::NegClass(_)
+                    | Token::Dot
+                    | Token::Epsilon
+                    | Token::Empty
+                    | Token::LParen
+                    | Token::Ref(_),
+                ),
+            ) => return config.implicit_concat,
+            (Operator::Concat, Some(Token::Bang)) => {
+                return config.implicit_concat && config.language_ops
+            }
+            _ => false,
+        };
+        if explicit {
+            self.consume();
+        }
+        explicit
+    }
+
+    // Tokens that may start a factor here.
+    fn starters(&self) -> Vec<&'static str> {
+        let mut out = PRIMARY.to_vec();
+        if self.config.language_ops {
+            out.push("'!'");
+        }
+        out
     }
 
     fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.pos)
+        self.next.as_ref().map(|(token, _)| token)
     }
 
     fn consume(&mut self) -> Option<Token> {
-        if self.pos < self.tokens.len() {
-            let t = self.tokens[self.pos].clone();
-            self.pos += 1;
-            Some(t)
-        } else {
-            None
+        let (token, span) = self.next.take()?;
+        if self.spanned {
+            self.spans.push(span);
         }
+        self.pos += 1;
+        self.advance();
+        Some(token)
     }
 
-    fn expect(&mut self, token: &Token) -> Result<(), ParseError> {
+    fn expect_one(&mut self, token: &Token, name: &'static str) -> Result<(), ParseError> {
         match self.peek() {
             Some(t) if t == token => {
                 self.consume();
                 Ok(())
             }
-            Some(t) => Err(ParseError::UnexpectedToken(t.clone())),
-            None => Err(ParseError::UnexpectedEnd),
+            _ => self.recover(self.unexpected(vec![name]), Recovery::Inserted(name)),
+        }
+    }
+
+    /// Parse an expression, along with the source range of every node.
+    /// Ranges are byte spans when the parser was built `with_spans`, and
+    /// token index ranges otherwise.
+    pub fn parse_expr_spanned(&mut self) -> Result<(RegexAST, SpanTree), ParseError> {
+        let parsed = self.expr();
+        match &self.failed {
+            Some(e) => Err(e.clone().into()),
+            None => parsed,
         }
     }
 
-    // Grammar:
-    // Union: '+'
     pub fn parse_expr(&mut self) -> Result<RegexAST, ParseError> {
-        let mut node = self.parse_term()?;
+        Ok(self.expr()?.0)
+    }
 
-        while let Some(Token::Plus) = self.peek() {
-            self.consume(); // consume '+'
-            let rhs = self.parse_term()?;
-            node = RegexAST::Union(Box::new(node), Box::new(rhs));
+    // Entry points at one level of the grammar, stopping before the first
+    // token that level doesn't take. With `op` left out of the precedence
+    // table, the level is a single factor.
+    fn parse_level(&mut self, op: Operator) -> Result<RegexAST, ParseError> {
+        let node = match self.config.precedence.iter().position(|&o| o == op) {
+            Some(i) => self.level(i)?,
+            None => self.factor()?,
+        };
+        Ok(node.0)
+    }
+
+    pub fn parse_intersection(&mut self) -> Result<RegexAST, ParseError> {
+        self.parse_level(Operator::Intersect)
+    }
+
+    pub fn parse_term(&mut self) -> Result<RegexAST, ParseError> {
+        self.parse_level(Operator::Concat)
+    }
+
+    pub fn parse_factor(&mut self) -> Result<RegexAST, ParseError> {
+        Ok(self.factor()?.0)
+    }
+
+    pub fn parse_primary(&mut self) -> Result<RegexAST, ParseError> {
+        Ok(self.primary()?.0)
+    }
+
+    // Source range from the token at `start` through the last consumed one.
+    fn span_since(&self, start: usize) -> Span {
+        if !self.spanned {
+            return start..self.pos;
         }
+        self.spans[start].start..self.spans[self.pos - 1].end
+    }
 
+    // Empty range at the next token, for nodes made up by recovery.
+    fn here(&self) -> Span {
+        if !self.spanned {
+            return self.pos..self.pos;
+        }
+        let at = match self.next_span() {
+            Some(span) => span.start,
+            None => self.spans.last().map_or(0, |s| s.end),
+        };
+        at..at
+    }
+
+    // Tokens left over after the expression from `start`, up to the end
+    // of the input or group, are errors. When recovering, each is skipped
+    // and whatever follows is concatenated on.
+    fn skip_stray(&mut self, start: usize, mut node: Node) -> Result<Node, ParseError> {
+        while !self.operand_missing() {
+            self.recover(self.unexpected(self.continuations()), Recovery::Skipped)?;
+            self.consume();
+            if !self.operand_missing() {
+                let rhs = self.expr()?;
+                node = self.binary(start, node, rhs, RegexAST::Concat);
+            }
+        }
         Ok(node)
     }
 
-    // Term: 'char'
-    pub fn parse_term(&mut self) -> Result<RegexAST, ParseError> {
-        let mut node = self.parse_factor()?;
+    fn binary(&self, start: usize, lhs: Node, rhs: Node, op: BinaryOp) -> Node {
+        let span = SpanTree {
+            span: self.span_since(start),
+            children: vec![lhs.1, rhs.1],
+        };
+        (op(Box::new(lhs.0), Box::new(rhs.0)), span)
+    }
 
-        loop {
-            match self.peek() {
-                Some(Token::Char(_)) | Some(Token::LParen) => {
-                    let rhs = self.parse_factor()?;
-                    node = RegexAST::Concat(Box::new(node), Box::new(rhs));
-                }
-                _ => break,
+    fn unary(&self, start: usize, inner: Node, op: impl FnOnce(Box<RegexAST>) -> RegexAST) -> Node {
+        let span = SpanTree {
+            span: self.span_since(start),
+            children: vec![inner.1],
+        };
+        (op(Box::new(inner.0)), span)
+    }
+
+    // Grammar: one level per entry of `precedence`, loosest first, over
+    // factors. By default:
+    // Union: '+' or '|'
+    // Intersection: '&', binds tighter than union
+    // Term: 'char', concatenated by juxtaposition (if configured) or '·'
+    fn expr(&mut self) -> Result<Node, ParseError> {
+        self.level(0)
+    }
+
+    // The operator at `precedence[i]` over operands of the levels below.
+    fn level(&mut self, i: usize) -> Result<Node, ParseError> {
+        let Some(&op) = self.config.precedence.get(i) else {
+            return self.factor();
+        };
+        let start = self.pos;
+        let mut node = self.level(i + 1)?;
+
+        while self.operator(op) {
+            if self.diagnostics.is_some() && self.operand_missing() {
+                self.recover(self.unexpected(self.starters()), Recovery::DroppedOperator)?;
+                break;
             }
+            let rhs = self.level(i + 1)?;
+            node = self.binary(start, node, rhs, op.node());
         }
 
         Ok(node)
     }
 
-    // Star: '*'
-    pub fn parse_factor(&mut self) -> Result<RegexAST, ParseError> {
-        let mut node = self.parse_primary()?;
+    // Complement: '!' prefix, applies to the whole postfixed factor
+    // Star: '*', Optional: '?', Repeat: '{n,m}', One-or-more: '+' (if configured)
+    fn factor(&mut self) -> Result<Node, ParseError> {
+        let start = self.pos;
+        if self.config.language_ops && self.peek() == Some(&Token::Bang) {
+            self.consume();
+            let inner = self.factor()?;
+            return Ok(self.unary(start, inner, RegexAST::Complement));
+        }
+
+        let mut node = self.primary()?;
 
-        while let Some(Token::Star) = self.peek() {
+        loop {
+            let (min, max) = match self.peek() {
+                Some(Token::Star) => {
+                    self.consume();
+                    node = self.unary(start, node, RegexAST::Star);
+                    continue;
+                }
+                Some(Token::Caret) if self.config.caret_star => {
+                    self.consume();
+                    self.expect_one(&Token::Star, "'*'")?;
+                    node = self.unary(start, node, RegexAST::Star);
+                    continue;
+                }
+                Some(Token::Question) if self.config.optional => {
+                    self.consume();
+                    node = self.unary(start, node, RegexAST::Optional);
+                    continue;
+                }
+                Some(Token::Plus) if self.config.plus_one_or_more => (1, None),
+                Some(&Token::Repeat(min, max)) => {
+                    if let Some(max) = max.filter(|&max| max < min) {
+                        let error = ParseError::InvalidRepeat {
+                            min,
+                            max,
+                            pos: self.pos,
+                            span: self.next_span(),
+                        };
+                        self.recover(error, Recovery::Skipped)?;
+                        self.consume();
+                        continue;
+                    }
+                    let copies = max.unwrap_or(min).max(1) as u64 * largest_count(&node.0);
+                    if copies > MAX_REPEAT {
+                        let error = ParseError::RepeatTooLarge {
+                            copies,
+                            pos: self.pos,
+                            span: self.next_span(),
+                        };
+                        self.recover(error, Recovery::Skipped)?;
+                        self.consume();
+                        continue;
+                    }
+                    (min, max)
+                }
+                _ => break,
+            };
             self.consume();
-            node = RegexAST::Star(Box::new(node));
+            node = self.unary(start, node, |e| RegexAST::Repeat(e, min, max));
         }
 
         Ok(node)
     }
 
     // Paren: '(', ')' [Recursively]
-    pub fn parse_primary(&mut self) -> Result<RegexAST, ParseError> {
-        match self.consume() {
-            Some(Token::Char(c)) => Ok(RegexAST::Char(c)),
-            Some(Token::LParen) => {
-                let node = self.parse_expr()?;
-                self.expect(&Token::RParen)?;
-                Ok(node)
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        loop {
+            if self.operand_missing() {
+                self.recover(self.unexpected(self.starters()), Recovery::Epsilon)?;
+                return Ok((RegexAST::Epsilon, SpanTree::leaf(self.here())));
+            }
+            if matches!(
+                self.peek(),
+                Some(
+                    Token::Char(_)
+                        | Token::Class(_)
+                        | Token::NegClass(_)
+                        | Token::Dot
+                        | Token::Epsilon
+                        | Token::Empty
+                        | Token::Ref(_)
+                        | Token::LParen
+                )
+            ) {
+                break;
             }
-            Some(t) => Err(ParseError::UnexpectedToken(t)),
-            None => Err(ParseError::UnexpectedEnd),
+            self.recover(self.unexpected(self.starters()), Recovery::Skipped)?;
+            self.consume();
         }
+        let start = self.pos;
+
+        let ast = match self.consume() {
+            Some(Token::Char(c)) => RegexAST::Char(c),
+            Some(Token::Class(ranges)) => RegexAST::Class(ranges),
+            Some(Token::NegClass(ranges)) => RegexAST::NegClass(ranges),
+            Some(Token::Dot) => RegexAST::Any,
+            Some(Token::Epsilon) => RegexAST::Epsilon,
+            Some(Token::Empty) => RegexAST::Empty,
+            Some(Token::Ref(name)) => RegexAST::Ref(name),
+            Some(Token::LParen) => {
+                self.depth += 1;
+                let node = self.expr()?;
+                let (ast, inner) = self.skip_stray(start + 1, node)?;
+                if self.peek().is_some() {
+                    self.consume();
+                } else {
+                    self.recover(
+                        self.unexpected(self.continuations()),
+                        Recovery::Inserted("')'"),
+                    )?;
+                }
+                self.depth -= 1;
+                // A group's node covers its parentheses
+                let span = SpanTree {
+                    span: self.span_since(start),
+                    ..inner
+                };
+                return Ok((ast, span));
+            }
+            _ => unreachable!("checked above"),
+        };
+        Ok((ast, SpanTree::leaf(self.span_since(start))))
     }
 }
 
@@ -108,17 +782,96 @@ impl Parser {
 */
 
 pub fn parse_language(input: &str) -> Result<RegexAST, ParseError> {
-    let tokens = tokenize(input);
-    let mut parser = Parser::new(tokens);
+    parse_language_with(input, SyntaxConfig::default())
+}
+
+/// Parse with a `Dialect` or a custom `&SyntaxConfig`.
+pub fn parse_language_with(
+    input: &str,
+    syntax: impl Into<SyntaxConfig>,
+) -> Result<RegexAST, ParseError> {
+    Ok(parse_language_spanned(input, syntax)?.0)
+}
 
-    let ast = parser.parse_expr()?;
+/// Parse, keeping the byte span of every AST node. An empty (or blank)
+/// pattern denotes the language of the empty word, as if it were `ε`.
+pub fn parse_language_spanned(
+    input: &str,
+    syntax: impl Into<SyntaxConfig>,
+) -> Result<(RegexAST, SpanTree), ParseError> {
+    parse_tokens(Tokenizer::new(input).spanned(), syntax.into())
+}
 
-    // Optional: ensure entire input was consumed
-    if parser.peek().is_some() {
-        return Err(ParseError::UnexpectedToken(parser.peek().unwrap().clone()));
+/// Parse a pattern read from `reader` as UTF-8, tokenizing it as the
+/// parser goes, so a long machine-generated pattern is never held as a
+/// string or a token list.
+pub fn parse_reader(
+    reader: impl Read,
+    syntax: impl Into<SyntaxConfig>,
+) -> Result<(RegexAST, SpanTree), ParseError> {
+    parse_tokens(Tokenizer::from_reader(reader).spanned(), syntax.into())
+}
+
+fn parse_tokens(
+    tokens: impl Iterator<Item = SpannedToken>,
+    syntax: SyntaxConfig,
+) -> Result<(RegexAST, SpanTree), ParseError> {
+    let mut parser = Parser::streaming(tokens, syntax);
+    if let Some(e) = parser.failed.take() {
+        return Err(e.into());
+    }
+    if parser.peek().is_none() {
+        return Ok((RegexAST::Epsilon, SpanTree::leaf(0..0)));
     }
 
-    Ok(ast)
+    let parsed = parser.parse_expr_spanned()?;
+
+    // Ensure entire input was consumed
+    parser.skip_stray(0, parsed)
+}
+
+/// Parse as much as possible, for previews of a pattern still being typed.
+/// Unexpected tokens are skipped, a missing `)` is assumed and a missing
+/// operand reads as ε; a tokenizer error drops the input from there on.
+/// The AST is `None` only when not a single token could be read.
+pub fn parse_language_recovering(
+    input: &str,
+    syntax: impl Into<SyntaxConfig>,
+) -> (Option<RegexAST>, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let mut end = input.len();
+    let tokens = loop {
+        match tokenize_spanned(&input[..end]) {
+            Ok(tokens) => break tokens,
+            Err(e) => {
+                // Back off to before the error, or by a char if it is
+                // reported at the end of the prefix
+                end = if e.byte < end {
+                    e.byte
+                } else {
+                    input[..end].char_indices().last().map_or(0, |(i, _)| i)
+                };
+                if diagnostics.is_empty() {
+                    diagnostics.push(Diagnostic {
+                        error: e.into(),
+                        recovery: Recovery::Truncated,
+                    });
+                }
+            }
+        }
+    };
+    if tokens.is_empty() {
+        let ast = diagnostics.is_empty().then_some(RegexAST::Epsilon);
+        return (ast, diagnostics);
+    }
+
+    let mut parser = Parser::with_spans(tokens, syntax.into()).recovering();
+    let ast = parser
+        .parse_expr_spanned()
+        .and_then(|parsed| parser.skip_stray(0, parsed))
+        .expect("a recovering parser doesn't fail");
+    diagnostics.extend(parser.diagnostics.take().unwrap_or_default());
+    (Some(ast.0), diagnostics)
 }
 
 /*
@@ -134,11 +887,32 @@ mod tests {
     use crate::regex::tokenizer::tokenize;
 
     fn parse(expr: &str) -> RegexAST {
-        let tokens = tokenize(expr);
+        let tokens = tokenize(expr).unwrap();
         let mut p = Parser::new(tokens);
         p.parse_expr().unwrap()
     }
 
+    #[test]
+    fn test_parse_reader() {
+        let pattern = "(a+b)*abb";
+        let (ast, spans) = parse_reader(pattern.as_bytes(), SyntaxConfig::default()).unwrap();
+        assert_eq!(
+            (ast, spans),
+            parse_language_spanned(pattern, SyntaxConfig::default()).unwrap()
+        );
+
+        // A tokenizer error wins over what the parser makes of the prefix
+        let bytes: &[u8] = &[b'a', b'|', 0xff];
+        assert!(matches!(
+            parse_reader(bytes, SyntaxConfig::default()),
+            Err(ParseError::Tokenize(_))
+        ));
+        assert!(matches!(
+            parse_language("ab$"),
+            Err(ParseError::Tokenize(e)) if e.byte == 2
+        ));
+    }
+
     #[test]
     fn test_single_literal() {
         let ast = parse("a");
@@ -228,6 +1002,421 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_optional() {
+        // ab? parses as Concat(a, b?)
+        let ast = parse("ab?");
+
+        match ast {
+            RegexAST::Concat(left, right) => {
+                assert!(matches!(*left, RegexAST::Char('a')));
+
+                match *right {
+                    RegexAST::Optional(inner) => {
+                        assert!(matches!(*inner, RegexAST::Char('b')))
+                    }
+                    _ => panic!("expected b?"),
+                }
+            }
+            _ => panic!("wrong precedence"),
+        }
+    }
+
+    #[test]
+    fn test_repeat() {
+        let ast = parse("ab{2,4}");
+
+        match ast {
+            RegexAST::Concat(_, right) => match *right {
+                RegexAST::Repeat(inner, 2, Some(4)) => {
+                    assert!(matches!(*inner, RegexAST::Char('b')))
+                }
+                _ => panic!("expected b{{2,4}}"),
+            },
+            _ => panic!("wrong precedence"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_repeat() {
+        assert!(matches!(
+            parse_language("a{3,2}"),
+            Err(ParseError::InvalidRepeat {
+                min: 3,
+                max: 2,
+                pos: 1,
+                span: Some(_)
+            })
+        ));
+        assert!(parse_language("a{0}").is_ok());
+    }
+
+    #[test]
+    fn test_repeat_cap() {
+        assert!(parse_language("a{1000}").is_ok());
+        assert!(parse_language("a{2,}").is_ok());
+        assert!(matches!(
+            parse_language("ba{1001}"),
+            Err(ParseError::RepeatTooLarge {
+                copies: 1001,
+                pos: 2,
+                span: Some(_)
+            })
+        ));
+        assert!(matches!(
+            parse_language("(a{50}b){30}"),
+            Err(ParseError::RepeatTooLarge { copies: 1500, .. })
+        ));
+        assert!(parse_language("a{4294967295,}").is_err());
+        let e = parse_language("a{5000}").unwrap_err().to_string();
+        assert_eq!(e, "repetition of 5000 copies at 1..7: the limit is 1000");
+    }
+
+    #[test]
+    fn test_class() {
+        let ast = parse("a[0-9]");
+
+        match ast {
+            RegexAST::Concat(_, right) => match *right {
+                RegexAST::Class(ranges) => assert_eq!(ranges, vec!['0'..='9']),
+                _ => panic!("expected [0-9]"),
+            },
+            _ => panic!("expected Concat"),
+        }
+    }
+
+    #[test]
+    fn test_pipe_union() {
+        let ast = parse("a|b+c");
+        match ast {
+            RegexAST::Union(left, _) => {
+                assert!(matches!(*left, RegexAST::Union(_, _)));
+            }
+            _ => panic!("expected Union"),
+        }
+    }
+
+    #[test]
+    fn test_plus_one_or_more() {
+        let config = SyntaxConfig {
+            pipe_union: true,
+            plus_one_or_more: true,
+            ..Default::default()
+        };
+        let ast = parse_language_with("a+|b", &config).unwrap();
+
+        match ast {
+            RegexAST::Union(left, _) => {
+                assert!(matches!(*left, RegexAST::Repeat(_, 1, None)));
+            }
+            _ => panic!("expected Union"),
+        }
+
+        let config = SyntaxConfig {
+            pipe_union: false,
+            plus_one_or_more: false,
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_language_with("a|b", &config),
+            Err(ParseError::UnexpectedToken {
+                token: Token::Pipe,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let err = parse_language("ab+(c*d))e").unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::UnexpectedToken {
+                token: Token::RParen,
+                pos: 8,
+                ..
+            }
+        ));
+        assert_eq!(err.span(), Some(8..9));
+
+        // Missing ')' points at the end of the input
+        let err = parse_language("(ab").unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedEnd { pos: 3, .. }));
+        assert_eq!(err.span(), Some(3..3));
+
+        // Without spans, the token index is still reported
+        let mut p = Parser::new(tokenize("a+*").unwrap());
+        let err = p.parse_expr().unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::UnexpectedToken {
+                pos: 2,
+                span: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_recovery() {
+        let recover = |s: &str| {
+            let (ast, diagnostics) = parse_language_recovering(s, SyntaxConfig::default());
+            let recoveries: Vec<Recovery> = diagnostics.into_iter().map(|d| d.recovery).collect();
+            (ast, recoveries)
+        };
+
+        // Mid-edit patterns parse as their obvious completion
+        assert_eq!(
+            recover("(a+b"),
+            (Some(parse("(a+b)")), vec![Recovery::Inserted("')'")])
+        );
+        assert_eq!(
+            recover("ab+"),
+            (Some(parse("ab")), vec![Recovery::DroppedOperator])
+        );
+        assert_eq!(
+            recover("a(b|"),
+            (
+                Some(parse("a(b)")),
+                vec![Recovery::DroppedOperator, Recovery::Inserted("')'")]
+            )
+        );
+        assert_eq!(recover("a()"), (Some(parse("aε")), vec![Recovery::Epsilon]));
+
+        // Stray tokens are skipped
+        assert_eq!(recover("a)b"), (Some(parse("ab")), vec![Recovery::Skipped]));
+        assert_eq!(
+            recover("*a+*b"),
+            (Some(parse("a+b")), vec![Recovery::Skipped; 2])
+        );
+        assert_eq!(
+            recover("a{3,2}b"),
+            (Some(parse("ab")), vec![Recovery::Skipped])
+        );
+
+        // Tokenizer errors keep the prefix
+        assert_eq!(
+            recover("ab[c"),
+            (Some(parse("ab")), vec![Recovery::Truncated])
+        );
+        assert_eq!(recover("#"), (None, vec![Recovery::Truncated]));
+        assert_eq!(recover("(a)*"), (Some(parse("(a)*")), vec![]));
+
+        let academic = parse_language_recovering("(a|b)", Dialect::Academic);
+        assert!(academic.0.is_some());
+        assert_eq!(
+            academic.1[0].to_string(),
+            "unexpected '|' at 2..3, expected one of '+', '·', '&', '*', repetition, ')' (skipped it)"
+        );
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let msg = |s: &str| parse_language(s).unwrap_err().to_string();
+
+        assert_eq!(
+            msg("(a+b"),
+            "unexpected end of input at 4..4, expected one of \
+             '+', '|', '&', '*', '?', repetition, ')'"
+        );
+        assert_eq!(
+            msg("a+*"),
+            "unexpected '*' at 2..3, expected one of \
+             literal, '(', class, '.', 'ε', '∅', reference, '!'"
+        );
+        assert_eq!(
+            msg("a{3,2}"),
+            "invalid repetition {3,2} at 1..6: maximum is below minimum"
+        );
+        assert_eq!(msg("a#"), "unexpected character '#' at index 1");
+    }
+
+    #[test]
+    fn test_node_spans() {
+        let (ast, spans) = parse_language_spanned("ab+(c|d)*", SyntaxConfig::default()).unwrap();
+        let nodes: Vec<(String, Span)> = spans
+            .nodes(&ast)
+            .into_iter()
+            .map(|(node, span)| (node.to_pattern(), span.clone()))
+            .collect();
+
+        let expected = [
+            ("ab+(c+d)*", 0..9),
+            ("ab", 0..2),
+            ("a", 0..1),
+            ("b", 1..2),
+            // Groups cover their parentheses
+            ("(c+d)*", 3..9),
+            ("c+d", 3..8),
+            ("c", 4..5),
+            ("d", 6..7),
+        ];
+        let expected: Vec<(String, Span)> = expected
+            .into_iter()
+            .map(|(p, span)| (p.to_string(), span))
+            .collect();
+        assert_eq!(nodes, expected);
+    }
+
+    #[test]
+    fn test_node_spans_multibyte() {
+        let (ast, spans) = parse_language_spanned("!é{2}·ε", Dialect::Academic).unwrap();
+        assert!(matches!(ast, RegexAST::Concat(..)));
+        assert_eq!(spans.span, 0..10);
+        assert_eq!(spans.children[0].span, 0..6);
+        assert_eq!(spans.children[0].children[0].span, 1..6);
+        assert_eq!(spans.children[1].span, 8..10);
+
+        // Without byte spans, nodes cover token index ranges
+        let mut p = Parser::new(tokenize("a(b)*").unwrap());
+        let (_, spans) = p.parse_expr_spanned().unwrap();
+        assert_eq!(spans.children[1].span, 1..5);
+    }
+
+    #[test]
+    fn test_dialects() {
+        let same = |a: &str, dialect: Dialect, b: &str| {
+            let left = parse_language_with(a, dialect).unwrap();
+            let right = parse_language(b).unwrap();
+            assert_eq!(format!("{:?}", left), format!("{:?}", right), "{}", a);
+        };
+
+        same("a·(b+c)*·c", Dialect::Academic, "a(b+c)*c");
+        same("a+|b?c", Dialect::Posix, "a{1,}|b?c");
+        same("a·b|c", Dialect::PcreLite, "ab|c");
+        same("!a&b", Dialect::PcreLite, "!a&b");
+
+        // Academic has no juxtaposition, `|` or `?`
+        assert!(parse_language_with("ab", Dialect::Academic).is_err());
+        assert!(parse_language_with("a|b", Dialect::Academic).is_err());
+        assert!(parse_language_with("a?", Dialect::Academic).is_err());
+        // POSIX has no language operators
+        assert!(parse_language_with("a&b", Dialect::Posix).is_err());
+        assert!(parse_language_with("!a", Dialect::Posix).is_err());
+    }
+
+    #[test]
+    fn test_formal_notation() {
+        let same = |a: &str, b: &str| {
+            let left = parse_language_with(a, Dialect::Formal).unwrap();
+            let right = parse_language(b).unwrap();
+            assert_eq!(format!("{:?}", left), format!("{:?}", right), "{}", a);
+        };
+        same("a·(b∪c)^*·c", "a(b+c)*c");
+        same("(a∪ε)∩!b^*", "(a+ε)&!b*");
+        same("a+b·c", "a+bc");
+
+        assert!(parse_language_with("a^", Dialect::Formal).is_err());
+        assert!(parse_language_with("ab", Dialect::Formal).is_err());
+        // Off by default
+        assert!(parse_language("a∪b").is_err());
+        assert!(parse_language("a^*").is_err());
+    }
+
+    #[test]
+    fn test_precedence_table() {
+        let with = |precedence: Vec<Operator>| SyntaxConfig {
+            precedence,
+            ..Default::default()
+        };
+
+        // Intersection looser than union: (a+b)&c
+        let config = with(vec![Operator::Intersect, Operator::Union, Operator::Concat]);
+        let ast = parse_language_with("a+b&c", &config).unwrap();
+        assert_eq!(ast.to_pattern(), parse("(a+b)&c").to_pattern());
+
+        // Concatenation loosest: a(b+c)
+        let config = with(vec![Operator::Concat, Operator::Union, Operator::Intersect]);
+        let ast = parse_language_with("ab+c", &config).unwrap();
+        assert_eq!(ast.to_pattern(), parse("a(b+c)").to_pattern());
+
+        // An operator left out of the table is not recognized
+        let config = with(vec![Operator::Union, Operator::Concat]);
+        let err = parse_language_with("a&b", &config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected '&' at 1..2, expected one of \
+             '+', '|', '*', '?', repetition, end of input"
+        );
+    }
+
+    #[test]
+    fn test_dialect_messages() {
+        let err = parse_language_with("ab", Dialect::Academic).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected 'b' at 1..2, expected one of \
+             '+', '·', '&', '*', repetition, end of input"
+        );
+
+        let err = parse_language_with("a|!b", Dialect::Posix).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unexpected '!' at 2..3, expected one of \
+             literal, '(', class, '.', 'ε', '∅', reference"
+        );
+    }
+
+    #[test]
+    fn test_intersection_precedence() {
+        // a+b&c parses as Union(a, Intersect(b, c))
+        let ast = parse("a+b&cd");
+
+        match ast {
+            RegexAST::Union(_, right) => match *right {
+                RegexAST::Intersect(b, cd) => {
+                    assert!(matches!(*b, RegexAST::Char('b')));
+                    assert!(matches!(*cd, RegexAST::Concat(_, _)));
+                }
+                _ => panic!("expected b&cd"),
+            },
+            _ => panic!("wrong precedence"),
+        }
+    }
+
+    #[test]
+    fn test_level_entry_points() {
+        // Each level stops at the first operator looser than itself
+        let mut p = Parser::new(tokenize("a&bc+d").unwrap());
+        assert!(matches!(
+            p.parse_intersection(),
+            Ok(RegexAST::Intersect(_, _))
+        ));
+        assert_eq!(p.peek(), Some(&Token::Plus));
+
+        let mut p = Parser::new(tokenize("ab*&c").unwrap());
+        assert!(matches!(p.parse_term(), Ok(RegexAST::Concat(_, _))));
+        assert_eq!(p.peek(), Some(&Token::Amp));
+
+        let mut p = Parser::new(tokenize("a*b").unwrap());
+        assert!(matches!(p.parse_factor(), Ok(RegexAST::Star(_))));
+        let mut p = Parser::new(tokenize("(a+b)*").unwrap());
+        assert!(matches!(p.parse_primary(), Ok(RegexAST::Union(_, _))));
+        assert_eq!(p.peek(), Some(&Token::Star));
+
+        // Left out of the table, a level is one factor
+        let config = SyntaxConfig {
+            precedence: vec![Operator::Union, Operator::Concat],
+            ..Default::default()
+        };
+        let mut p = Parser::with_config(tokenize("a*b").unwrap(), config);
+        assert!(matches!(p.parse_intersection(), Ok(RegexAST::Star(_))));
+    }
+
+    #[test]
+    fn test_complement() {
+        // !a*b parses as Concat(Complement(a*), b)
+        let ast = parse("!a*b");
+
+        match ast {
+            RegexAST::Concat(left, _) => match *left {
+                RegexAST::Complement(inner) => assert!(matches!(*inner, RegexAST::Star(_))),
+                _ => panic!("expected !a*"),
+            },
+            _ => panic!("wrong precedence"),
+        }
+    }
+
     #[test]
     fn test_complex_expression() {
         let ast = parse("(a+b)*c");
@@ -245,4 +1434,28 @@ mod tests {
             _ => panic!("incorrect full expression parse"),
         }
     }
+
+    #[test]
+    fn test_language_ref() {
+        let ast = parse("{ident}{num}*");
+        match ast {
+            RegexAST::Concat(left, right) => {
+                assert!(matches!(*left, RegexAST::Ref(ref n) if n == "ident"));
+                assert!(matches!(*right, RegexAST::Star(_)));
+            }
+            _ => panic!("expected Concat of references"),
+        }
+    }
+
+    #[test]
+    fn test_empty_input() {
+        for input in ["", "  "] {
+            let (ast, spans) = parse_language_spanned(input, SyntaxConfig::default()).unwrap();
+            assert!(matches!(ast, RegexAST::Epsilon));
+            assert_eq!(spans.span, 0..0);
+        }
+        // Only the whole pattern may be empty
+        assert!(parse_language("a+").is_err());
+        assert!(parse_language("()").is_err());
+    }
 }
@@ -0,0 +1,179 @@
+// regex/positions.rs
+//
+// The position sets behind the Glushkov and direct-DFA constructions:
+// every symbol occurrence in the expression is a position, numbered left
+// to right, and the sets below say which positions can start a word, end
+// it, or come right after another one.
+use super::ast::RegexAST;
+use super::desugar::desugar;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Positions {
+    // The symbol at each position: Char, Class, NegClass or Any
+    pub symbols: Vec<RegexAST>,
+    // ε is in the language
+    pub nullable: bool,
+    pub firstpos: BTreeSet<usize>,
+    pub lastpos: BTreeSet<usize>,
+    // followpos[p]: positions that can come right after p
+    pub followpos: Vec<BTreeSet<usize>>,
+}
+
+/// Compute nullable, firstpos, lastpos and followpos. Counted repetitions
+/// are unrolled first, so each copy gets its own positions. Intersection,
+/// complement and references have no position-based reading and are
+/// rejected.
+pub fn positions(ast: &RegexAST) -> Result<Positions, String> {
+    let mut out = Positions {
+        symbols: Vec::new(),
+        nullable: false,
+        firstpos: BTreeSet::new(),
+        lastpos: BTreeSet::new(),
+        followpos: Vec::new(),
+    };
+    let node = visit(&desugar(ast), &mut out)?;
+    out.nullable = node.nullable;
+    out.firstpos = node.first;
+    out.lastpos = node.last;
+    Ok(out)
+}
+
+// The three per-node sets; followpos is filled in globally.
+struct Node {
+    nullable: bool,
+    first: BTreeSet<usize>,
+    last: BTreeSet<usize>,
+}
+
+fn visit(ast: &RegexAST, out: &mut Positions) -> Result<Node, String> {
+    Ok(match ast {
+        RegexAST::Epsilon | RegexAST::Empty => Node {
+            nullable: matches!(ast, RegexAST::Epsilon),
+            first: BTreeSet::new(),
+            last: BTreeSet::new(),
+        },
+        RegexAST::Char(_) | RegexAST::Class(_) | RegexAST::NegClass(_) | RegexAST::Any => {
+            let p = out.symbols.len();
+            out.symbols.push(ast.clone());
+            out.followpos.push(BTreeSet::new());
+            Node {
+                nullable: false,
+                first: BTreeSet::from([p]),
+                last: BTreeSet::from([p]),
+            }
+        }
+        RegexAST::Concat(a, b) => {
+            let a = visit(a, out)?;
+            let b = visit(b, out)?;
+            for &p in &a.last {
+                out.followpos[p].extend(&b.first);
+            }
+
+            let mut first = a.first;
+            if a.nullable {
+                first.extend(&b.first);
+            }
+            let mut last = b.last;
+            if b.nullable {
+                last.extend(&a.last);
+            }
+            Node {
+                nullable: a.nullable && b.nullable,
+                first,
+                last,
+            }
+        }
+        RegexAST::Union(a, b) => {
+            let a = visit(a, out)?;
+            let b = visit(b, out)?;
+            Node {
+                nullable: a.nullable || b.nullable,
+                first: &a.first | &b.first,
+                last: &a.last | &b.last,
+            }
+        }
+        RegexAST::Star(e) => {
+            let e = visit(e, out)?;
+            for &p in &e.last {
+                out.followpos[p].extend(&e.first);
+            }
+            Node {
+                nullable: true,
+                ..e
+            }
+        }
+        RegexAST::Optional(e) => Node {
+            nullable: true,
+            ..visit(e, out)?
+        },
+        RegexAST::Repeat(..) => unreachable!("desugared above"),
+        RegexAST::Intersect(..) => return Err("Positions are undefined for '&'".into()),
+        RegexAST::Complement(_) => return Err("Positions are undefined for '!'".into()),
+        RegexAST::Ref(name) => return Err(format!("Unknown language: {{{}}}", name)),
+    })
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::parser::parse_language;
+
+    fn set(ps: &[usize]) -> BTreeSet<usize> {
+        ps.iter().copied().collect()
+    }
+
+    #[test]
+    fn dragon_book_example() {
+        // Positions: a0 b1 a2 b3 b4
+        let p = positions(&parse_language("(a+b)*abb").unwrap()).unwrap();
+
+        assert_eq!(p.symbols.len(), 5);
+        assert!(!p.nullable);
+        assert_eq!(p.firstpos, set(&[0, 1, 2]));
+        assert_eq!(p.lastpos, set(&[4]));
+        assert_eq!(
+            p.followpos,
+            vec![
+                set(&[0, 1, 2]),
+                set(&[0, 1, 2]),
+                set(&[3]),
+                set(&[4]),
+                set(&[])
+            ]
+        );
+    }
+
+    #[test]
+    fn optional_and_repeat() {
+        // a{2} unrolls to a0 a1; b? is position 2
+        let p = positions(&parse_language("a{2}b?").unwrap()).unwrap();
+
+        assert_eq!(
+            p.symbols,
+            vec![
+                RegexAST::Char('a'),
+                RegexAST::Char('a'),
+                RegexAST::Char('b')
+            ]
+        );
+        assert_eq!(p.firstpos, set(&[0]));
+        assert_eq!(p.lastpos, set(&[1, 2]));
+        assert_eq!(p.followpos[1], set(&[2]));
+
+        let p = positions(&parse_language("a?ε").unwrap()).unwrap();
+        assert!(p.nullable);
+    }
+
+    #[test]
+    fn language_operators_are_rejected() {
+        assert!(positions(&parse_language("a&b").unwrap()).is_err());
+        assert!(positions(&parse_language("!a").unwrap()).is_err());
+    }
+}
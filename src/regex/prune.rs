@@ -0,0 +1,315 @@
+// regex/prune.rs
+//
+// Dead alternatives: operands of `+` that can be deleted without changing
+// the language, either because they match nothing over the alphabet or
+// because everything they match is matched anyway. Each candidate is
+// replaced by ∅ and the result compared with the original automaton, so
+// context counts: in `(a+b)c&ac` the `b` is dead.
+use super::ast::{RegexAST, SpanTree};
+use super::parser::{parse_language_spanned, SyntaxConfig};
+use super::tokenizer::Span;
+use crate::decisions::{equivalence, Equivalence};
+use crate::dfa::dfa::{nfa_to_dfa, DFA};
+use crate::dfa::minimize::minimize_dfa;
+use crate::dfa::ops::trim;
+use crate::nfa::epsilon_elimination::remove_epsilon;
+use crate::nfa::thompson::Thompson;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneKind {
+    // Matches no word over the alphabet: a∅, [^a] over {a}, a&b
+    Empty,
+    // Matches only words the rest of the expression already accounts for
+    Redundant,
+}
+
+/// One deleted alternative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pruned {
+    pub kind: PruneKind,
+    pub span: Span,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PruneReport {
+    // The expression with every pruned alternative removed
+    pub ast: RegexAST,
+    // In source order
+    pub pruned: Vec<Pruned>,
+}
+
+impl fmt::Display for PruneKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PruneKind::Empty => write!(f, "matches nothing"),
+            PruneKind::Redundant => write!(f, "already matched elsewhere"),
+        }
+    }
+}
+
+impl fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for p in &self.pruned {
+            writeln!(
+                f,
+                "{}..{}: {} {}; remove it",
+                p.span.start, p.span.end, p.pattern, p.kind
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Delete the alternatives of `ast` that contribute nothing to its
+/// language over `alphabet`, which is also what `.`, negated classes and
+/// complement range over. Later duplicates go before earlier ones, so of
+/// `a+b+a` the last `a` is reported. `spans` must come from parsing `ast`.
+pub fn prune(
+    ast: &RegexAST,
+    spans: &SpanTree,
+    alphabet: &BTreeSet<char>,
+) -> Result<PruneReport, String> {
+    if let Some(name) = ast.references().first() {
+        return Err(format!("Unknown language: {{{}}}", name));
+    }
+
+    let mut candidates = Vec::new();
+    alternatives(ast, spans, &mut Vec::new(), false, &mut candidates);
+
+    let compile = |ast: &RegexAST| language(ast, alphabet);
+    let target = compile(ast);
+    let mut current = ast.clone();
+    let mut removed = HashSet::new();
+    let mut pruned = Vec::new();
+
+    for (path, span) in candidates.into_iter().rev() {
+        let alt = std::mem::replace(at(&mut current, &path), RegexAST::Empty);
+        if equivalence(&compile(&current), &target)? == Equivalence::Equivalent {
+            let kind = if compile(&alt).accepts.is_empty() {
+                PruneKind::Empty
+            } else {
+                PruneKind::Redundant
+            };
+            pruned.push(Pruned {
+                kind,
+                span,
+                pattern: alt.to_pattern(),
+            });
+            removed.insert(path);
+        } else {
+            *at(&mut current, &path) = alt;
+        }
+    }
+
+    pruned.reverse();
+    Ok(PruneReport {
+        ast: without(&current, &removed, &mut Vec::new()).unwrap_or(RegexAST::Empty),
+        pruned,
+    })
+}
+
+/// Parse `pattern` in the default syntax and prune it.
+pub fn prune_of(pattern: &str, alphabet: &BTreeSet<char>) -> Result<PruneReport, String> {
+    let (ast, spans) = parse_language_spanned(pattern, SyntaxConfig::default())
+        .map_err(|e| format!("Parse error: {}", e))?;
+    prune(&ast, &spans, alphabet)
+}
+
+// The minimal DFA of `ast` over the words of `alphabet*`. Symbols written
+// outside the alphabet are compiled, then cut off.
+fn language(ast: &RegexAST, alphabet: &BTreeSet<char>) -> DFA {
+    let enfa = Thompson::new()
+        .with_alphabet(alphabet | &ast.literals())
+        .from_ast(ast)
+        .expect("references are rejected by prune");
+    let mut dfa = nfa_to_dfa(&remove_epsilon(&enfa));
+    for map in dfa.transitions.values_mut() {
+        map.retain(|c, _| alphabet.contains(c));
+    }
+    minimize_dfa(&trim(&dfa))
+}
+
+// Paths (child indices from the root) and spans of union operands that
+// are not unions themselves, in pre-order.
+fn alternatives(
+    ast: &RegexAST,
+    spans: &SpanTree,
+    path: &mut Vec<usize>,
+    in_union: bool,
+    out: &mut Vec<(Vec<usize>, Span)>,
+) {
+    let is_union = matches!(ast, RegexAST::Union(..));
+    if in_union && !is_union {
+        out.push((path.clone(), spans.span.clone()));
+    }
+    for (i, (child, child_spans)) in ast.children().into_iter().zip(&spans.children).enumerate() {
+        path.push(i);
+        alternatives(child, child_spans, path, is_union, out);
+        path.pop();
+    }
+}
+
+fn at<'a>(ast: &'a mut RegexAST, path: &[usize]) -> &'a mut RegexAST {
+    let Some((&i, rest)) = path.split_first() else {
+        return ast;
+    };
+    let child = match ast {
+        RegexAST::Concat(a, b) | RegexAST::Union(a, b) | RegexAST::Intersect(a, b) => {
+            if i == 0 {
+                a
+            } else {
+                b
+            }
+        }
+        RegexAST::Star(e)
+        | RegexAST::Optional(e)
+        | RegexAST::Complement(e)
+        | RegexAST::Repeat(e, _, _) => e,
+        _ => unreachable!("paths follow the tree"),
+    };
+    at(child, rest)
+}
+
+// `ast` with the nodes at `removed` deleted from their unions; None when
+// nothing is left.
+fn without(
+    ast: &RegexAST,
+    removed: &HashSet<Vec<usize>>,
+    path: &mut Vec<usize>,
+) -> Option<RegexAST> {
+    if removed.contains(path) {
+        return None;
+    }
+
+    let mut child = |i: usize, e: &RegexAST| {
+        path.push(i);
+        let out = without(e, removed, path);
+        path.pop();
+        out
+    };
+    Some(match ast {
+        RegexAST::Union(a, b) => match (child(0, a), child(1, b)) {
+            (Some(a), Some(b)) => RegexAST::Union(Box::new(a), Box::new(b)),
+            (Some(e), None) | (None, Some(e)) => e,
+            (None, None) => return None,
+        },
+        RegexAST::Concat(a, b) => RegexAST::Concat(
+            Box::new(child(0, a).unwrap_or(RegexAST::Empty)),
+            Box::new(child(1, b).unwrap_or(RegexAST::Empty)),
+        ),
+        RegexAST::Intersect(a, b) => RegexAST::Intersect(
+            Box::new(child(0, a).unwrap_or(RegexAST::Empty)),
+            Box::new(child(1, b).unwrap_or(RegexAST::Empty)),
+        ),
+        RegexAST::Star(e) => RegexAST::Star(Box::new(child(0, e).unwrap_or(RegexAST::Empty))),
+        RegexAST::Optional(e) => {
+            RegexAST::Optional(Box::new(child(0, e).unwrap_or(RegexAST::Empty)))
+        }
+        RegexAST::Complement(e) => {
+            RegexAST::Complement(Box::new(child(0, e).unwrap_or(RegexAST::Empty)))
+        }
+        RegexAST::Repeat(e, min, max) => {
+            RegexAST::Repeat(Box::new(child(0, e).unwrap_or(RegexAST::Empty)), *min, *max)
+        }
+        _ => ast.clone(),
+    })
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sigma(chars: &str) -> BTreeSet<char> {
+        chars.chars().collect()
+    }
+
+    fn pruned<'a>(pattern: &'a str, alphabet: &str) -> (String, Vec<(PruneKind, &'a str)>) {
+        let report = prune_of(pattern, &sigma(alphabet)).unwrap();
+        let found = report
+            .pruned
+            .iter()
+            .map(|p| (p.kind, &pattern[p.span.clone()]))
+            .collect();
+        (report.ast.to_pattern(), found)
+    }
+
+    #[test]
+    fn empty_alternatives() {
+        assert_eq!(
+            pruned("a+b∅", "ab"),
+            ("a".into(), vec![(PruneKind::Empty, "b∅")])
+        );
+        assert_eq!(
+            pruned("a+[^ab]+c", "ab"),
+            (
+                "a".into(),
+                vec![(PruneKind::Empty, "[^ab]"), (PruneKind::Empty, "c")]
+            )
+        );
+        assert_eq!(
+            pruned("(a&b)+ab", "ab"),
+            ("ab".into(), vec![(PruneKind::Empty, "(a&b)")])
+        );
+    }
+
+    #[test]
+    fn redundant_alternatives() {
+        assert_eq!(
+            pruned("a*+aa", "a"),
+            ("a*".into(), vec![(PruneKind::Redundant, "aa")])
+        );
+        assert_eq!(
+            pruned("a+b+a", "ab"),
+            ("a+b".into(), vec![(PruneKind::Redundant, "a")])
+        );
+        // Dead only in context
+        assert_eq!(
+            pruned("(a+b)c&ac", "abc"),
+            ("ac&ac".into(), vec![(PruneKind::Redundant, "b")])
+        );
+    }
+
+    #[test]
+    fn spans_point_at_the_source() {
+        let report = prune_of("x(ab+a(b))y", &sigma("abxy")).unwrap();
+        assert_eq!(report.pruned.len(), 1);
+        assert_eq!(report.pruned[0].span, 5..9);
+        assert_eq!(report.pruned[0].pattern, "ab");
+        assert_eq!(
+            report.to_string(),
+            "5..9: ab already matched elsewhere; remove it\n"
+        );
+    }
+
+    #[test]
+    fn nothing_to_prune() {
+        let report = prune_of("(a+b)*abb", &sigma("ab")).unwrap();
+        assert!(report.pruned.is_empty());
+        assert_eq!(report.ast.to_pattern(), "(a+b)*abb");
+    }
+
+    #[test]
+    fn language_is_kept() {
+        for pattern in ["a+b+a+∅", "(a+ab)*+b*", "(a+b)(a+ε+a)", "!(a+a)"] {
+            let report = prune_of(pattern, &sigma("ab")).unwrap();
+            let out = report.ast.to_pattern();
+            assert_eq!(
+                super::super::equivalent(pattern, &out),
+                Ok(true),
+                "{} -> {}",
+                pattern,
+                out
+            );
+        }
+        assert!(prune_of("{digit}+a", &sigma("a")).is_err());
+    }
+}
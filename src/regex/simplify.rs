@@ -0,0 +1,327 @@
+// regex/simplify.rs
+//
+// Language-preserving rewrites that make machine-generated expressions
+// readable: ∅ and ε absorbed, duplicate and subsumed alternatives dropped,
+// single symbols merged into classes, common prefixes and suffixes
+// factored out, and redundant stars collapsed. Chains of `+` and
+// concatenation are rebuilt left-nested so `to_pattern` needs no extra
+// parentheses.
+use super::ast::RegexAST;
+use super::derivatives::nullable;
+use std::ops::RangeInclusive;
+
+/// Rewrite `ast` bottom-up into an equivalent, usually smaller expression.
+pub fn simplify(ast: &RegexAST) -> RegexAST {
+    match ast {
+        RegexAST::Class(ranges) => class(ranges.clone()),
+        RegexAST::Concat(a, b) => concat(simplify(a), simplify(b)),
+        RegexAST::Union(a, b) => union(simplify(a), simplify(b)),
+        RegexAST::Intersect(a, b) => match (simplify(a), simplify(b)) {
+            (RegexAST::Empty, _) | (_, RegexAST::Empty) => RegexAST::Empty,
+            (a, b) if a == b => a,
+            (a, b) => RegexAST::Intersect(Box::new(a), Box::new(b)),
+        },
+        RegexAST::Complement(e) => match simplify(e) {
+            RegexAST::Complement(inner) => *inner,
+            e => RegexAST::Complement(Box::new(e)),
+        },
+        RegexAST::Star(e) => star(simplify(e)),
+        RegexAST::Optional(e) => optional(simplify(e)),
+        RegexAST::Repeat(e, min, max) => repeat(simplify(e), *min, *max),
+        _ => ast.clone(),
+    }
+}
+
+/// `a·b`, simplified. Operands are assumed simplified already.
+pub(crate) fn concat(a: RegexAST, b: RegexAST) -> RegexAST {
+    if a == RegexAST::Empty || b == RegexAST::Empty {
+        return RegexAST::Empty;
+    }
+
+    let mut out: Vec<RegexAST> = Vec::new();
+    for f in factors(a).into_iter().chain(factors(b)) {
+        // x*x* = x*
+        if matches!(f, RegexAST::Star(_)) && out.last() == Some(&f) {
+            continue;
+        }
+        out.push(f);
+    }
+    concat_all(out)
+}
+
+/// `a+b`, simplified. Operands are assumed simplified already.
+pub(crate) fn union(a: RegexAST, b: RegexAST) -> RegexAST {
+    union_all(vec![a, b])
+}
+
+/// `e*`, simplified. The operand is assumed simplified already.
+pub(crate) fn star(e: RegexAST) -> RegexAST {
+    match e {
+        RegexAST::Empty | RegexAST::Epsilon => RegexAST::Epsilon,
+        RegexAST::Star(_) => e,
+        RegexAST::Optional(inner) => star(*inner),
+        // (x*+y)* = (x+y)*
+        RegexAST::Union(..) => {
+            let alts = alternatives(e).into_iter().map(unstar).collect();
+            RegexAST::Star(Box::new(union_all(alts)))
+        }
+        // (x*y*)* = (x+y)* when every factor is nullable
+        RegexAST::Concat(..) if nullable(&e) => {
+            let alts = factors(e).into_iter().map(unstar).collect();
+            star(union_all(alts))
+        }
+        e => RegexAST::Star(Box::new(e)),
+    }
+}
+
+fn optional(e: RegexAST) -> RegexAST {
+    match e {
+        RegexAST::Empty => RegexAST::Epsilon,
+        e if nullable(&e) => e,
+        // (xx*)? = (x*x)? = x*
+        RegexAST::Concat(a, b) => match (*a, *b) {
+            (x, RegexAST::Star(y)) | (RegexAST::Star(y), x) if x == *y => RegexAST::Star(y),
+            (a, b) => RegexAST::Optional(Box::new(RegexAST::Concat(Box::new(a), Box::new(b)))),
+        },
+        e => RegexAST::Optional(Box::new(e)),
+    }
+}
+
+fn repeat(e: RegexAST, min: u32, max: Option<u32>) -> RegexAST {
+    match (e, min, max) {
+        (RegexAST::Epsilon, _, _) | (_, 0, Some(0)) => RegexAST::Epsilon,
+        (RegexAST::Empty, 0, _) => RegexAST::Epsilon,
+        (RegexAST::Empty, _, _) => RegexAST::Empty,
+        (e, 0, None) => star(e),
+        (e, 0, Some(1)) => optional(e),
+        (e, 1, Some(1)) => e,
+        (e, min, max) => RegexAST::Repeat(Box::new(e), min, max),
+    }
+}
+
+fn union_all(alts: Vec<RegexAST>) -> RegexAST {
+    let mut out: Vec<RegexAST> = Vec::new();
+    let mut has_epsilon = false;
+    for alt in alts.into_iter().flat_map(alternatives) {
+        match alt {
+            RegexAST::Empty => {}
+            RegexAST::Epsilon => has_epsilon = true,
+            alt if !out.contains(&alt) => out.push(alt),
+            _ => {}
+        }
+    }
+
+    // x ⊆ x* and x ⊆ x?
+    let subsumed: Vec<RegexAST> = out
+        .iter()
+        .filter_map(|alt| match alt {
+            RegexAST::Star(x) | RegexAST::Optional(x) => Some((**x).clone()),
+            _ => None,
+        })
+        .collect();
+    out.retain(|alt| !subsumed.contains(alt));
+
+    merge_symbols(&mut out);
+
+    let body = match factor(&out) {
+        Some(factored) => factored,
+        None => match out
+            .into_iter()
+            .reduce(|a, b| RegexAST::Union(Box::new(a), Box::new(b)))
+        {
+            Some(u) => u,
+            None if has_epsilon => return RegexAST::Epsilon,
+            None => return RegexAST::Empty,
+        },
+    };
+    if has_epsilon {
+        optional(body)
+    } else {
+        body
+    }
+}
+
+// Merge all Char/Class alternatives into one class at the first one's place.
+fn merge_symbols(alts: &mut Vec<RegexAST>) {
+    let is_symbol = |a: &RegexAST| matches!(a, RegexAST::Char(_) | RegexAST::Class(_));
+    if alts.iter().filter(|a| is_symbol(a)).count() < 2 {
+        return;
+    }
+
+    let first = alts.iter().position(is_symbol).unwrap();
+    let mut ranges = Vec::new();
+    for alt in alts.iter().filter(|a| is_symbol(a)) {
+        match alt {
+            RegexAST::Char(c) => ranges.push(*c..=*c),
+            RegexAST::Class(rs) => ranges.extend(rs.iter().cloned()),
+            _ => unreachable!(),
+        }
+    }
+    let merged = class(ranges);
+
+    let mut i = 0;
+    alts.retain(|a| {
+        i += 1;
+        i - 1 == first || !is_symbol(a)
+    });
+    alts[first] = merged;
+}
+
+// ab+ac = a(b+c) and ac+bc = (a+b)c, when every alternative shares it.
+fn factor(alts: &[RegexAST]) -> Option<RegexAST> {
+    if alts.len() < 2 {
+        return None;
+    }
+    let split: Vec<Vec<RegexAST>> = alts.iter().map(|a| factors(a.clone())).collect();
+
+    let head = &split[0][0];
+    if split.iter().all(|fs| &fs[0] == head) {
+        let rests = split
+            .iter()
+            .map(|fs| concat_all(fs[1..].to_vec()))
+            .collect();
+        return Some(concat(head.clone(), union_all(rests)));
+    }
+
+    let tail = split[0].last().unwrap();
+    if split.iter().all(|fs| fs.last() == Some(tail)) {
+        let rests = split
+            .iter()
+            .map(|fs| concat_all(fs[..fs.len() - 1].to_vec()))
+            .collect();
+        return Some(concat(union_all(rests), tail.clone()));
+    }
+    None
+}
+
+// A class over `ranges`, sorted and merged; a single char stays a Char.
+// Runs of two are kept as two chars, `[bc]` reading better than `[b-c]`.
+fn class(mut ranges: Vec<RangeInclusive<char>>) -> RegexAST {
+    ranges.sort_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<char>> = Vec::new();
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if *r.start() as u32 <= *last.end() as u32 + 1 => {
+                if r.end() > last.end() {
+                    *last = *last.start()..=*r.end();
+                }
+            }
+            _ => merged.push(r),
+        }
+    }
+    let merged: Vec<RangeInclusive<char>> = merged
+        .into_iter()
+        .flat_map(|r| {
+            if *r.end() as u32 == *r.start() as u32 + 1 {
+                vec![*r.start()..=*r.start(), *r.end()..=*r.end()]
+            } else {
+                vec![r]
+            }
+        })
+        .collect();
+
+    if merged.len() == 1 && merged[0].start() == merged[0].end() {
+        RegexAST::Char(*merged[0].start())
+    } else {
+        RegexAST::Class(merged)
+    }
+}
+
+fn unstar(e: RegexAST) -> RegexAST {
+    match e {
+        RegexAST::Star(x) | RegexAST::Optional(x) => *x,
+        e => e,
+    }
+}
+
+fn alternatives(e: RegexAST) -> Vec<RegexAST> {
+    match e {
+        RegexAST::Union(a, b) => {
+            let mut out = alternatives(*a);
+            out.extend(alternatives(*b));
+            out
+        }
+        e => vec![e],
+    }
+}
+
+fn factors(e: RegexAST) -> Vec<RegexAST> {
+    match e {
+        RegexAST::Concat(a, b) => {
+            let mut out = factors(*a);
+            out.extend(factors(*b));
+            out
+        }
+        RegexAST::Epsilon => Vec::new(),
+        e => vec![e],
+    }
+}
+
+fn concat_all(fs: Vec<RegexAST>) -> RegexAST {
+    fs.into_iter()
+        .reduce(|a, b| RegexAST::Concat(Box::new(a), Box::new(b)))
+        .unwrap_or(RegexAST::Epsilon)
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::equivalent;
+    use crate::regex::parser::parse_language;
+
+    fn simplified(pattern: &str) -> String {
+        simplify(&parse_language(pattern).unwrap()).to_pattern()
+    }
+
+    #[test]
+    fn rewrites() {
+        for (input, expected) in [
+            ("a+a", "a"),
+            ("(a*)*", "a*"),
+            ("ε+a", "a?"),
+            ("ε+a*", "a*"),
+            ("a+b+c", "[a-c]"),
+            ("ab+ac", "a[bc]"),
+            ("ac+bc", "[ab]c"),
+            ("(a*b*)*", "[ab]*"),
+            ("(a*+b)*", "[ab]*"),
+            ("∅a+b", "b"),
+            ("a*a*b", "a*b"),
+            ("a+a*", "a*"),
+            ("(ab){0,}", "(ab)*"),
+            ("!!a", "a"),
+            ("a(bc)", "abc"),
+            ("(aa*)?b", "a*b"),
+            ("ε+b*b", "b*"),
+        ] {
+            assert_eq!(simplified(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn simplification_preserves_the_language() {
+        for pattern in [
+            "(a+b)*abb",
+            "ab+ac+ε",
+            "(a?b?)*c",
+            "(ab+ab*)(ε+∅)",
+            "[a-c]+b+d",
+            "a{0,1}+b{1,1}",
+        ] {
+            let out = simplified(pattern);
+            assert_eq!(
+                equivalent(pattern, &out),
+                Ok(true),
+                "{} -> {}",
+                pattern,
+                out
+            );
+        }
+    }
+}
@@ -1,34 +1,531 @@
 // regex/tokenizer.rs
+use std::fmt;
+use std::io::{self, BufReader, Read};
+use std::iter::Peekable;
+use std::ops::{Range, RangeInclusive};
+use std::str::Chars;
+
+/// Byte range of a token in the source pattern.
+pub type Span = Range<usize>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Char(char),
-    Plus,   // +
-    Star,   // *
-    LParen, // (
-    RParen, // )
+    Plus,        // +
+    Pipe,        // |
+    Amp,         // &
+    Bang,        // !
+    Star,        // *
+    Question,    // ?
+    Dot,         // .
+    Concat,      // ·
+    Cup,         // ∪
+    Cap,         // ∩
+    Caret,       // ^
+    Epsilon,     // ε or @e
+    Empty,       // ∅ or @0
+    LParen,      // (
+    RParen,      // )
+    Ref(String), // {name}
+    // {n}, {n,}, {n,m}
+    Repeat(u32, Option<u32>),
+    // [a-z0-9]
+    Class(Vec<RangeInclusive<char>>),
+    // [^a-z]
+    NegClass(Vec<RangeInclusive<char>>),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let class = |f: &mut fmt::Formatter, ranges: &[RangeInclusive<char>]| {
+            for r in ranges {
+                match r.start() == r.end() {
+                    true => write!(f, "{}", r.start())?,
+                    false => write!(f, "{}-{}", r.start(), r.end())?,
+                }
+            }
+            write!(f, "]")
+        };
+
+        match self {
+            Token::Char(c) => write!(f, "{:?}", c),
+            Token::Plus => write!(f, "'+'"),
+            Token::Pipe => write!(f, "'|'"),
+            Token::Amp => write!(f, "'&'"),
+            Token::Bang => write!(f, "'!'"),
+            Token::Star => write!(f, "'*'"),
+            Token::Question => write!(f, "'?'"),
+            Token::Dot => write!(f, "'.'"),
+            Token::Concat => write!(f, "'·'"),
+            Token::Cup => write!(f, "'∪'"),
+            Token::Cap => write!(f, "'∩'"),
+            Token::Caret => write!(f, "'^'"),
+            Token::Epsilon => write!(f, "'ε'"),
+            Token::Empty => write!(f, "'∅'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Ref(name) => write!(f, "{{{}}}", name),
+            Token::Repeat(min, None) => write!(f, "{{{},}}", min),
+            Token::Repeat(min, Some(max)) if min == max => write!(f, "{{{}}}", min),
+            Token::Repeat(min, Some(max)) => write!(f, "{{{},{}}}", min, max),
+            Token::Class(ranges) => {
+                write!(f, "[")?;
+                class(f, ranges)
+            }
+            Token::NegClass(ranges) => {
+                write!(f, "[^")?;
+                class(f, ranges)
+            }
+        }
+    }
+}
+
+/// What went wrong while tokenizing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenizeErrorKind {
+    UnexpectedChar,
+    // `@` followed by something other than `e` or `0`
+    UnknownLiteral,
+    UnknownEscape,
+    DanglingEscape,
+    UnterminatedBraces,
+    UnterminatedClass,
+    EmptyClass,
+    InvalidRange(char, char),
+    InvalidRef(String),
+    InvalidRepeat(String),
+    // `\\u{...}` that isn't 1-6 hex digits naming a valid char
+    InvalidUnicode(String),
+    // The reader failed or produced invalid UTF-8
+    Io(String),
+}
+
+/// A tokenizer error and where it happened. `ch` is the offending
+/// character, or `None` when the input ended too early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenizeError {
+    pub kind: TokenizeErrorKind,
+    pub ch: Option<char>,
+    // Byte offset and char index into the input
+    pub byte: usize,
+    pub index: usize,
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            TokenizeErrorKind::UnexpectedChar => write!(f, "unexpected character")?,
+            TokenizeErrorKind::UnknownLiteral => write!(f, "unknown @-literal")?,
+            TokenizeErrorKind::UnknownEscape => write!(f, "unknown escape sequence")?,
+            TokenizeErrorKind::DanglingEscape => write!(f, "dangling escape")?,
+            TokenizeErrorKind::UnterminatedBraces => write!(f, "unterminated brace group")?,
+            TokenizeErrorKind::UnterminatedClass => write!(f, "unterminated character class")?,
+            TokenizeErrorKind::EmptyClass => write!(f, "empty character class")?,
+            TokenizeErrorKind::InvalidRange(lo, hi) => {
+                write!(f, "invalid class range {}-{}", lo, hi)?
+            }
+            TokenizeErrorKind::InvalidRef(body) => {
+                write!(f, "invalid language reference {{{}}}", body)?
+            }
+            TokenizeErrorKind::InvalidRepeat(body) => {
+                write!(f, "invalid repetition count {{{}}}", body)?
+            }
+            TokenizeErrorKind::InvalidUnicode(digits) => {
+                write!(f, "invalid unicode escape \\u{{{}}}", digits)?
+            }
+            TokenizeErrorKind::Io(msg) => write!(f, "read error ({})", msg)?,
+        }
+        match self.ch {
+            Some(c) => write!(f, " {:?} at index {}", c, self.index),
+            None => write!(f, " at end of input"),
+        }
+    }
+}
+
+impl std::error::Error for TokenizeError {}
+
+// Char iterator that remembers the position of the last char it returned.
+struct Cursor<I: Iterator<Item = io::Result<char>>> {
+    chars: Peekable<I>,
+    byte: usize,
+    index: usize,
+    // Byte offset and count of the chars returned so far
+    end: usize,
+    count: usize,
+    // First read or decoding error; the input is treated as ending there
+    failed: Option<String>,
+}
+
+impl<I: Iterator<Item = io::Result<char>>> Cursor<I> {
+    fn new(chars: I) -> Self {
+        Self {
+            chars: chars.peekable(),
+            byte: 0,
+            index: 0,
+            end: 0,
+            count: 0,
+            failed: None,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        // At the end, point one past the last char
+        self.byte = self.end;
+        self.index = self.count;
+        // Nothing is read past an error: a failing reader may never end
+        if self.failed.is_some() {
+            return None;
+        }
+
+        match self.chars.next()? {
+            Ok(c) => {
+                self.end += c.len_utf8();
+                self.count += 1;
+                Some(c)
+            }
+            Err(e) => {
+                self.failed = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        if self.failed.is_some() {
+            return None;
+        }
+        match self.chars.peek() {
+            Some(Ok(c)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    // Error at the last char returned (or at the end, if it was `None`).
+    // A pending read error takes precedence.
+    fn error(&self, kind: TokenizeErrorKind, ch: Option<char>) -> TokenizeError {
+        let (kind, ch) = match &self.failed {
+            Some(msg) => (TokenizeErrorKind::Io(msg.clone()), None),
+            None => (kind, ch),
+        };
+        TokenizeError {
+            kind,
+            ch,
+            byte: self.byte,
+            index: self.index,
+        }
+    }
+}
+
+/// Streaming tokenizer: yields tokens one at a time from a `&str` or any
+/// `Read`, without collecting them first. Stops after the first error.
+pub struct Tokenizer<I: Iterator<Item = io::Result<char>>> {
+    chars: Cursor<I>,
+    done: bool,
+}
+
+type StrChars<'a> = std::iter::Map<Chars<'a>, fn(char) -> io::Result<char>>;
+
+impl<'a> Tokenizer<StrChars<'a>> {
+    pub fn new(input: &'a str) -> Self {
+        Self::from_chars(input.chars().map(Ok as fn(char) -> io::Result<char>))
+    }
+}
+
+impl<R: Read> Tokenizer<Utf8Chars<R>> {
+    /// Tokenize UTF-8 text from a reader (buffered internally).
+    pub fn from_reader(reader: R) -> Self {
+        Self::from_chars(Utf8Chars {
+            bytes: BufReader::new(reader).bytes(),
+        })
+    }
 }
 
-pub fn tokenize(input: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
+impl<I: Iterator<Item = io::Result<char>>> Tokenizer<I> {
+    pub fn from_chars(chars: I) -> Self {
+        Self {
+            chars: Cursor::new(chars),
+            done: false,
+        }
+    }
+
+    /// The tokens together with their byte spans, as `Parser::streaming`
+    /// reads them.
+    pub fn spanned(mut self) -> impl Iterator<Item = Result<(Token, Span), TokenizeError>> {
+        std::iter::from_fn(move || self.next_spanned())
+    }
+
+    /// Next token together with its byte span in the input.
+    pub fn next_spanned(&mut self) -> Option<Result<(Token, Span), TokenizeError>> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_token();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+
+    fn read_token(&mut self) -> Option<Result<(Token, Span), TokenizeError>> {
+        let chars = &mut self.chars;
 
-    for ch in input.chars() {
-        match ch {
-            '+' => tokens.push(Token::Plus),
-            '*' => tokens.push(Token::Star),
-            '(' => tokens.push(Token::LParen),
-            ')' => tokens.push(Token::RParen),
+        loop {
+            let Some(ch) = chars.next() else {
+                // A read error ends the input early
+                return chars
+                    .failed
+                    .is_some()
+                    .then(|| Err(chars.error(TokenizeErrorKind::UnexpectedChar, None)));
+            };
+            let start = chars.byte;
 
-            // treat anything alphanumeric as a literal
-            c if c.is_alphanumeric() => tokens.push(Token::Char(c)),
+            let token = match ch {
+                '+' => Token::Plus,
+                '|' => Token::Pipe,
+                '&' => Token::Amp,
+                '!' => Token::Bang,
+                '*' => Token::Star,
+                '?' => Token::Question,
+                '.' => Token::Dot,
+                '·' => Token::Concat,
+                '∪' => Token::Cup,
+                '∩' => Token::Cap,
+                '^' => Token::Caret,
+                'ε' => Token::Epsilon,
+                '∅' => Token::Empty,
+                '@' => match chars.next() {
+                    Some('e') => Token::Epsilon,
+                    Some('0') => Token::Empty,
+                    other => {
+                        return Some(Err(chars.error(TokenizeErrorKind::UnknownLiteral, other)))
+                    }
+                },
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '{' => match read_braces(chars) {
+                    Ok(token) => token,
+                    Err(e) => return Some(Err(e)),
+                },
+                '[' => {
+                    let negated = chars.peek() == Some('^');
+                    if negated {
+                        chars.next();
+                    }
+                    match read_class(chars) {
+                        Ok(ranges) if negated => Token::NegClass(ranges),
+                        Ok(ranges) => Token::Class(ranges),
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                '\\' => match read_escape(chars) {
+                    Ok(c) => Token::Char(c),
+                    Err(e) => return Some(Err(e)),
+                },
 
-            // tolerate whitespace
-            c if c.is_whitespace() => continue,
+                // treat anything alphanumeric as a literal
+                c if c.is_alphanumeric() => Token::Char(c),
 
-            _ => panic!("Unexpected character in regex: {}", ch),
+                // tolerate whitespace
+                c if c.is_whitespace() => continue,
+
+                _ => return Some(Err(chars.error(TokenizeErrorKind::UnexpectedChar, Some(ch)))),
+            };
+
+            return Some(Ok((token, start..chars.end)));
         }
     }
+}
+
+impl<I: Iterator<Item = io::Result<char>>> Iterator for Tokenizer<I> {
+    type Item = Result<Token, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned().map(|r| r.map(|(token, _)| token))
+    }
+}
 
-    tokens
+/// Decodes UTF-8 from a byte reader, one char at a time. Invalid UTF-8 is
+/// reported as an `InvalidData` error.
+pub struct Utf8Chars<R> {
+    bytes: io::Bytes<BufReader<R>>,
+}
+
+impl<R: Read> Iterator for Utf8Chars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self.bytes.next()? {
+            Ok(b) => b,
+            Err(e) => return Some(Err(e)),
+        };
+        let width = match first.leading_ones() {
+            0 => 1,
+            n @ 2..=4 => n as usize,
+            _ => return Some(Err(invalid_utf8())),
+        };
+
+        let mut buf = [first, 0, 0, 0];
+        for slot in &mut buf[1..width] {
+            match self.bytes.next() {
+                Some(Ok(b)) => *slot = b,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return Some(Err(invalid_utf8())),
+            }
+        }
+
+        Some(
+            std::str::from_utf8(&buf[..width])
+                .map(|s| s.chars().next().unwrap())
+                .map_err(|_| invalid_utf8()),
+        )
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8")
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, TokenizeError> {
+    Tokenizer::new(input).collect()
+}
+
+/// Like `tokenize`, but pairs every token with its byte span in `input`.
+pub fn tokenize_spanned(input: &str) -> Result<Vec<(Token, Span)>, TokenizeError> {
+    let mut tokenizer = Tokenizer::new(input);
+    std::iter::from_fn(|| tokenizer.next_spanned()).collect()
+}
+
+// Read a `{...}` group: a repetition count if it starts with a digit,
+// otherwise a language reference name.
+fn read_braces<I: Iterator<Item = io::Result<char>>>(
+    chars: &mut Cursor<I>,
+) -> Result<Token, TokenizeError> {
+    let open = chars.error(TokenizeErrorKind::UnterminatedBraces, Some('{'));
+    let mut body = String::new();
+
+    while let Some(ch) = chars.next() {
+        if ch == '}' {
+            let token = if body.starts_with(|c: char| c.is_ascii_digit()) {
+                parse_repeat(&body)
+            } else {
+                parse_ref(&body)
+            };
+            // Report bad contents at the opening brace
+            return token.map_err(|kind| TokenizeError { kind, ..open });
+        }
+        body.push(ch);
+    }
+
+    Err(chars.error(TokenizeErrorKind::UnterminatedBraces, None))
+}
+
+// Read the ranges of a `[...]` class up to the closing ']'. A '-' that
+// can't be a range, first in the class, after a range or right before the
+// ']', is a literal, as in `[-a]`, `[a-]` and `[a-c-]`.
+fn read_class<I: Iterator<Item = io::Result<char>>>(
+    chars: &mut Cursor<I>,
+) -> Result<Vec<RangeInclusive<char>>, TokenizeError> {
+    let mut ranges = Vec::new();
+    let mut pending: Option<char> = None;
+    let mut in_range = false;
+
+    while let Some(ch) = chars.next() {
+        let c = match ch {
+            ']' => {
+                ranges.extend(pending.map(|c| c..=c));
+                if in_range {
+                    ranges.push('-'..='-');
+                }
+                if ranges.is_empty() {
+                    return Err(chars.error(TokenizeErrorKind::EmptyClass, Some(ch)));
+                }
+                return Ok(ranges);
+            }
+            '-' if pending.is_some() && !in_range => {
+                in_range = true;
+                continue;
+            }
+            // No char to start a range from
+            '-' if pending.is_none() => '-',
+            '\\' => read_escape(chars)?,
+            c if c.is_alphanumeric() => c,
+            _ => return Err(chars.error(TokenizeErrorKind::UnexpectedChar, Some(ch))),
+        };
+
+        if in_range {
+            let lo = pending.take().unwrap();
+            if lo > c {
+                return Err(chars.error(TokenizeErrorKind::InvalidRange(lo, c), Some(c)));
+            }
+            ranges.push(lo..=c);
+            in_range = false;
+        } else {
+            ranges.extend(pending.replace(c).map(|p| p..=p));
+        }
+    }
+
+    Err(chars.error(TokenizeErrorKind::UnterminatedClass, None))
+}
+
+// Resolve the character after a '\\' to the literal it stands for.
+fn read_escape<I: Iterator<Item = io::Result<char>>>(
+    chars: &mut Cursor<I>,
+) -> Result<char, TokenizeError> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('u') => read_unicode(chars),
+        Some(c) if "\\+|&!*?.·∪∩()[]{}^-@ε∅".contains(c) => Ok(c),
+        Some(c) => Err(chars.error(TokenizeErrorKind::UnknownEscape, Some(c))),
+        None => Err(chars.error(TokenizeErrorKind::DanglingEscape, None)),
+    }
+}
+
+// Read the `{XXXX}` part of a `\\u{XXXX}` escape: 1 to 6 hex digits naming
+// a Unicode scalar value.
+fn read_unicode<I: Iterator<Item = io::Result<char>>>(
+    chars: &mut Cursor<I>,
+) -> Result<char, TokenizeError> {
+    let mut digits = String::new();
+    if chars.next() != Some('{') {
+        return Err(chars.error(TokenizeErrorKind::InvalidUnicode(digits), None));
+    }
+
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+            other => {
+                digits.extend(other);
+                return Err(chars.error(TokenizeErrorKind::InvalidUnicode(digits), other));
+            }
+        }
+    }
+
+    u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| chars.error(TokenizeErrorKind::InvalidUnicode(digits), Some('}')))
+}
+
+fn parse_ref(body: &str) -> Result<Token, TokenizeErrorKind> {
+    if body.is_empty() || !body.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(TokenizeErrorKind::InvalidRef(body.to_string()));
+    }
+
+    Ok(Token::Ref(body.to_string()))
+}
+
+fn parse_repeat(body: &str) -> Result<Token, TokenizeErrorKind> {
+    let count = |s: &str| -> Result<u32, TokenizeErrorKind> {
+        s.trim()
+            .parse()
+            .map_err(|_| TokenizeErrorKind::InvalidRepeat(body.to_string()))
+    };
+
+    Ok(match body.split_once(',') {
+        None => Token::Repeat(count(body)?, Some(count(body)?)),
+        Some((min, max)) if max.trim().is_empty() => Token::Repeat(count(min)?, None),
+        Some((min, max)) => Token::Repeat(count(min)?, Some(count(max)?)),
+    })
 }
 
 /*
@@ -43,7 +540,7 @@ mod tests {
 
     #[test]
     fn test_simple_literals() {
-        let t = tokenize("abc");
+        let t = tokenize("abc").unwrap();
         assert_eq!(
             t,
             vec![Token::Char('a'), Token::Char('b'), Token::Char('c'),]
@@ -52,16 +549,22 @@ mod tests {
 
     #[test]
     fn test_operators() {
-        let t = tokenize("a+b*");
+        let t = tokenize("a+b*").unwrap();
         assert_eq!(
             t,
             vec![Token::Char('a'), Token::Plus, Token::Char('b'), Token::Star,]
         );
     }
 
+    #[test]
+    fn test_optional() {
+        let t = tokenize("ab?").unwrap();
+        assert_eq!(t, vec![Token::Char('a'), Token::Char('b'), Token::Question]);
+    }
+
     #[test]
     fn test_parens() {
-        let t = tokenize("(a+b)*c");
+        let t = tokenize("(a+b)*c").unwrap();
         assert_eq!(
             t,
             vec![
@@ -75,4 +578,320 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_language_ref() {
+        let t = tokenize("{ident}(a+{num_2})").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Ref("ident".into()),
+                Token::LParen,
+                Token::Char('a'),
+                Token::Plus,
+                Token::Ref("num_2".into()),
+                Token::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repeat() {
+        let t = tokenize("a{2}b{1,}c{0, 3}").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Char('a'),
+                Token::Repeat(2, Some(2)),
+                Token::Char('b'),
+                Token::Repeat(1, None),
+                Token::Char('c'),
+                Token::Repeat(0, Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class() {
+        let t = tokenize("[a-cx0-9]b").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Class(vec!['a'..='c', 'x'..='x', '0'..='9']),
+                Token::Char('b'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dash_at_class_edges() {
+        let t = tokenize("[a-][-a][^-]").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Class(vec!['a'..='a', '-'..='-']),
+                Token::Class(vec!['-'..='-', 'a'..='a']),
+                Token::NegClass(vec!['-'..='-']),
+            ]
+        );
+        // After a finished range
+        assert_eq!(
+            tokenize("[a-c-][0-9a-z-]").unwrap(),
+            [
+                Token::Class(vec!['a'..='c', '-'..='-']),
+                Token::Class(vec!['0'..='9', 'a'..='z', '-'..='-']),
+            ]
+        );
+        assert_eq!(
+            tokenize("[a-c-e]").unwrap(),
+            [Token::Class(vec!['a'..='c', '-'..='-', 'e'..='e'])]
+        );
+        // A leading '-' can start a range itself
+        assert_eq!(tokenize("[--a]").unwrap(), [Token::Class(vec!['-'..='a'])]);
+    }
+
+    #[test]
+    fn test_negated_class() {
+        let t = tokenize("[^a-c]").unwrap();
+        assert_eq!(t, vec![Token::NegClass(vec!['a'..='c'])]);
+    }
+
+    #[test]
+    fn test_dot() {
+        let t = tokenize(".*ab").unwrap();
+        assert_eq!(
+            t,
+            vec![Token::Dot, Token::Star, Token::Char('a'), Token::Char('b')]
+        );
+    }
+
+    #[test]
+    fn test_escapes() {
+        let t = tokenize(r"\+\*\(\)\\a\n\t").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Char('+'),
+                Token::Char('*'),
+                Token::Char('('),
+                Token::Char(')'),
+                Token::Char('\\'),
+                Token::Char('a'),
+                Token::Char('\n'),
+                Token::Char('\t'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escapes_in_class() {
+        let t = tokenize(r"[\-\]a\+]").unwrap();
+        assert_eq!(
+            t,
+            vec![Token::Class(vec![
+                '-'..='-',
+                ']'..=']',
+                'a'..='a',
+                '+'..='+'
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_unknown_escape() {
+        let err = tokenize(r"ab\q").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::UnknownEscape);
+        assert_eq!(err.ch, Some('q'));
+        assert_eq!((err.byte, err.index), (3, 3));
+    }
+
+    #[test]
+    fn test_error_positions() {
+        // Byte and char offsets differ after a multi-byte char
+        let err = tokenize("ε#").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::UnexpectedChar);
+        assert_eq!(err.ch, Some('#'));
+        assert_eq!((err.byte, err.index), (2, 1));
+
+        let err = tokenize("a[b-").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::UnterminatedClass);
+        assert_eq!((err.ch, err.byte, err.index), (None, 4, 4));
+
+        let err = tokenize("a[z-b]").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidRange('z', 'b'));
+        assert_eq!(err.index, 4);
+
+        let err = tokenize("ab{x y}").unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::InvalidRef("x y".into()));
+        assert_eq!((err.ch, err.index), (Some('{'), 2));
+    }
+
+    #[test]
+    fn test_other_errors() {
+        let kind = |s: &str| tokenize(s).unwrap_err().kind;
+        assert_eq!(kind("@x"), TokenizeErrorKind::UnknownLiteral);
+        assert_eq!(kind("a\\"), TokenizeErrorKind::DanglingEscape);
+        assert_eq!(kind("a{2"), TokenizeErrorKind::UnterminatedBraces);
+        assert_eq!(kind("[]"), TokenizeErrorKind::EmptyClass);
+        assert_eq!(
+            kind("a{1,x}"),
+            TokenizeErrorKind::InvalidRepeat("1,x".into())
+        );
+    }
+
+    #[test]
+    fn test_epsilon_and_empty() {
+        let t = tokenize("ε+@e∅@0\\ε").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Epsilon,
+                Token::Plus,
+                Token::Epsilon,
+                Token::Empty,
+                Token::Empty,
+                Token::Char('ε'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let t = tokenize_spanned("ε[a-c]* {x}").unwrap();
+        let spans: Vec<Span> = t.into_iter().map(|(_, span)| span).collect();
+        assert_eq!(spans, vec![0..2, 2..7, 7..8, 9..12]);
+    }
+
+    #[test]
+    fn test_display() {
+        let t = tokenize("[^a-cx]{2,}{3}{lang}@e").unwrap();
+        let shown: Vec<String> = t.iter().map(|t| t.to_string()).collect();
+        assert_eq!(shown, vec!["[^a-cx]", "{2,}", "{3}", "{lang}", "'ε'"]);
+
+        let err = tokenize("ab#").unwrap_err();
+        assert_eq!(err.to_string(), "unexpected character '#' at index 2");
+    }
+
+    #[test]
+    fn test_unicode_escapes() {
+        let t = tokenize(r"\u{1F600}é[\u{41}-\u{5a}]\u{3b5}").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Char('😀'),
+                Token::Char('é'),
+                Token::Class(vec!['A'..='Z']),
+                Token::Char('ε'),
+            ]
+        );
+
+        let kind = |s: &str| tokenize(s).unwrap_err().kind;
+        assert_eq!(kind(r"\u{}"), TokenizeErrorKind::InvalidUnicode("".into()));
+        assert_eq!(
+            kind(r"\u{D800}"),
+            TokenizeErrorKind::InvalidUnicode("D800".into())
+        );
+        assert_eq!(
+            kind(r"\u{1234567}"),
+            TokenizeErrorKind::InvalidUnicode("1234567".into())
+        );
+        assert_eq!(
+            kind(r"\u{12x}"),
+            TokenizeErrorKind::InvalidUnicode("12x".into())
+        );
+        assert_eq!(kind(r"\u41"), TokenizeErrorKind::InvalidUnicode("".into()));
+
+        let err = tokenize(r"ab\u{zz}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r"invalid unicode escape \u{z} 'z' at index 5"
+        );
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut t = Tokenizer::new("a*[b-c]");
+        assert_eq!(t.next(), Some(Ok(Token::Char('a'))));
+        assert_eq!(t.next(), Some(Ok(Token::Star)));
+        assert_eq!(t.next(), Some(Ok(Token::Class(vec!['b'..='c']))));
+        assert_eq!(t.next(), None);
+
+        // Stops after the first error
+        let mut t = Tokenizer::new("a#b");
+        assert_eq!(t.next(), Some(Ok(Token::Char('a'))));
+        assert!(matches!(t.next(), Some(Err(_))));
+        assert_eq!(t.next(), None);
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = "ε(a+b)*{2,}[^x]";
+        let from_reader: Vec<Token> = Tokenizer::from_reader(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(from_reader, tokenize(input).unwrap());
+
+        // Invalid UTF-8 after "ab"
+        let bytes: &[u8] = &[b'a', b'b', 0xff, b'c'];
+        let err = Tokenizer::from_reader(bytes)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert!(matches!(err.kind, TokenizeErrorKind::Io(_)));
+        assert_eq!(err.index, 2);
+    }
+
+    #[test]
+    fn test_reader_that_keeps_failing() {
+        // Fails on every read, forever, after "a*"
+        struct Broken(usize);
+        impl Read for Broken {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.0 += 1;
+                match self.0 {
+                    1 => {
+                        buf[..2].copy_from_slice(b"a*");
+                        Ok(2)
+                    }
+                    _ => Err(io::Error::other("connection reset")),
+                }
+            }
+        }
+
+        let mut tokens = Tokenizer::from_reader(Broken(0));
+        assert_eq!(tokens.next(), Some(Ok(Token::Char('a'))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Star)));
+        let err = tokens.next().unwrap().unwrap_err();
+        assert_eq!(err.kind, TokenizeErrorKind::Io("connection reset".into()));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn test_language_operators() {
+        let t = tokenize("!a&b").unwrap();
+        assert_eq!(
+            t,
+            vec![Token::Bang, Token::Char('a'), Token::Amp, Token::Char('b')]
+        );
+    }
+
+    #[test]
+    fn test_set_notation() {
+        let t = tokenize("a∪b∩c^*").unwrap();
+        assert_eq!(
+            t,
+            vec![
+                Token::Char('a'),
+                Token::Cup,
+                Token::Char('b'),
+                Token::Cap,
+                Token::Char('c'),
+                Token::Caret,
+                Token::Star
+            ]
+        );
+        assert_eq!(
+            tokenize(r"\∪\^").unwrap(),
+            [Token::Char('∪'), Token::Char('^')]
+        );
+    }
 }
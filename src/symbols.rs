@@ -0,0 +1,297 @@
+// symbols.rs
+//
+// The regex front end reads chars. To write regexes over tokens or other
+// interned strings, each name is assigned a char from the supplementary
+// private use planes (U+F0000 onwards), so the whole pipeline (parser,
+// determinization, matchers, exporters) works on token streams unchanged.
+// Those chars are rare in text but not impossible: an input char that
+// happens to be a symbol of the table is read as that symbol, so keep
+// token streams and raw text apart. Automata built by hand can use
+// `TokenId` letters directly instead. A table is shared between threads;
+// the global one lets independent front-ends agree on ids.
+use crate::utils::edges::display_char;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+// Planes 15 and 16, minus their last two code points (noncharacters)
+const PLANE_SIZE: u32 = 0xFFFE;
+const PLANES: [u32; 2] = [0xF0000, 0x100000];
+
+/// The most names a table can hold.
+pub const CAPACITY: usize = 2 * PLANE_SIZE as usize;
+
+#[derive(Debug, Default)]
+struct Names {
+    ids: HashMap<Arc<str>, char>,
+    names: Vec<Arc<str>>,
+}
+
+/// A thread-safe, append-only map between names and symbol chars. Ids are
+/// handed out in order, so tables filled in the same order agree.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    inner: RwLock<Names>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide table.
+    pub fn global() -> &'static SymbolTable {
+        static GLOBAL: OnceLock<SymbolTable> = OnceLock::new();
+        GLOBAL.get_or_init(SymbolTable::new)
+    }
+
+    /// The symbol for `name`, assigning the next free one on first use.
+    pub fn intern(&self, name: &str) -> Result<char, String> {
+        if let Some(c) = self.get(name) {
+            return Ok(c);
+        }
+
+        let mut inner = self.inner.write().expect("symbol table poisoned");
+        // Another thread may have got there between the two locks
+        if let Some(&c) = inner.ids.get(name) {
+            return Ok(c);
+        }
+        let c = symbol(inner.names.len())
+            .ok_or_else(|| format!("Symbol error: table full ({} names)", CAPACITY))?;
+        let name: Arc<str> = name.into();
+        inner.ids.insert(name.clone(), c);
+        inner.names.push(name);
+        Ok(c)
+    }
+
+    /// The symbol for `name`, if interned.
+    pub fn get(&self, name: &str) -> Option<char> {
+        let inner = self.inner.read().expect("symbol table poisoned");
+        inner.ids.get(name).copied()
+    }
+
+    /// The name behind symbol `c`, if it is one of this table's.
+    pub fn name(&self, c: char) -> Option<Arc<str>> {
+        let inner = self.inner.read().expect("symbol table poisoned");
+        index(c).and_then(|i| inner.names.get(i).cloned())
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner
+            .read()
+            .expect("symbol table poisoned")
+            .names
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A word of symbols, ready for `accepts` or `find` on a matcher,
+    /// interning names not seen before.
+    pub fn encode<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Result<String, String> {
+        names.into_iter().map(|n| self.intern(n)).collect()
+    }
+
+    /// The names spelled by `word`; chars that are not symbols of this
+    /// table stand for themselves.
+    pub fn decode(&self, word: &str) -> Vec<String> {
+        word.chars()
+            .map(|c| match self.name(c) {
+                Some(name) => name.to_string(),
+                None => c.to_string(),
+            })
+            .collect()
+    }
+
+    /// Rewrite a pattern over names into one over symbols: every `<name>`
+    /// (alphanumerics and `_`) becomes the `\u{..}` escape of its symbol,
+    /// interning as needed. So `<if><ident>(<comma><ident>)*` is a regex
+    /// over three tokens, and `[<plus><minus>]` a class of two. Other
+    /// escapes are copied as they are; `\<` and `\>` stand for the
+    /// brackets themselves.
+    pub fn translate(&self, pattern: &str) -> Result<String, String> {
+        let mut out = String::with_capacity(pattern.len());
+        let mut pos = 0;
+        while let Some(c) = pattern[pos..].chars().next() {
+            pos += c.len_utf8();
+            match c {
+                '\\' => match pattern[pos..].chars().next() {
+                    Some(b @ ('<' | '>')) => {
+                        out.push_str(&format!("\\u{{{:x}}}", b as u32));
+                        pos += 1;
+                    }
+                    Some(e) => {
+                        out.push('\\');
+                        out.push(e);
+                        pos += e.len_utf8();
+                    }
+                    None => out.push('\\'),
+                },
+                '<' => {
+                    let close = pattern[pos..].find('>').ok_or_else(|| {
+                        format!("Symbol error: unterminated '<' at index {}", pos - 1)
+                    })?;
+                    let name = &pattern[pos..pos + close];
+                    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                        return Err(format!("Symbol error: invalid name '<{}>'", name));
+                    }
+                    out.push_str(&format!("\\u{{{:x}}}", self.intern(name)? as u32));
+                    pos += close + 1;
+                }
+                c => out.push(c),
+            }
+        }
+        Ok(out)
+    }
+
+    /// An edge label for a set of symbols: names for this table's symbols,
+    /// as in `edges::display_char` for anything else, comma separated.
+    pub fn label(&self, chars: &[char]) -> String {
+        let mut chars = chars.to_vec();
+        chars.sort_unstable();
+        chars.dedup();
+        let parts: Vec<String> = chars
+            .into_iter()
+            .map(|c| match self.name(c) {
+                Some(name) => name.to_string(),
+                None => display_char(c),
+            })
+            .collect();
+        parts.join(",")
+    }
+}
+
+fn symbol(i: usize) -> Option<char> {
+    let plane = PLANES.get(i / PLANE_SIZE as usize)?;
+    char::from_u32(plane + (i % PLANE_SIZE as usize) as u32)
+}
+
+fn index(c: char) -> Option<usize> {
+    let c = c as u32;
+    PLANES.iter().enumerate().find_map(|(p, &base)| {
+        (base..base + PLANE_SIZE)
+            .contains(&c)
+            .then(|| p * PLANE_SIZE as usize + (c - base) as usize)
+    })
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::compiled::CompiledDFA;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn interning_is_stable() {
+        let table = SymbolTable::new();
+        let a = table.intern("ident").unwrap();
+        let b = table.intern("comma").unwrap();
+        assert_eq!(a, '\u{F0000}');
+        assert_eq!(table.intern("ident"), Ok(a));
+        assert_eq!(table.get("comma"), Some(b));
+        assert_eq!(table.get("number"), None);
+        assert_eq!(table.name(b).as_deref(), Some("comma"));
+        assert_eq!(table.name('x'), None);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn ids_span_both_planes() {
+        for i in [
+            0,
+            PLANE_SIZE as usize - 1,
+            PLANE_SIZE as usize,
+            CAPACITY - 1,
+        ] {
+            assert_eq!(index(symbol(i).unwrap()), Some(i));
+        }
+        assert_eq!(symbol(PLANE_SIZE as usize), Some('\u{100000}'));
+        assert_eq!(symbol(CAPACITY), None);
+        assert_eq!(index('\u{FFFFE}'), None);
+    }
+
+    #[test]
+    fn token_level_matching() {
+        let table = SymbolTable::new();
+        let pattern = table.translate("<ident>(<comma><ident>)*").unwrap();
+        let dfa = CompiledDFA::from_dfa(&full_pipeline(&pattern).unwrap().mindfa);
+
+        let word = table.encode(["ident", "comma", "ident"]).unwrap();
+        assert!(dfa.accepts(&word));
+        assert!(!dfa.accepts(&table.encode(["ident", "comma"]).unwrap()));
+        assert_eq!(table.decode(&word), ["ident", "comma", "ident"]);
+    }
+
+    #[test]
+    fn translate_errors() {
+        let table = SymbolTable::new();
+        assert!(table.translate("<a").is_err());
+        assert!(table.translate("<>").is_err());
+        assert!(table.translate("<a b>").is_err());
+        assert_eq!(table.translate("x<a>*").unwrap(), "x\\u{f0000}*");
+    }
+
+    #[test]
+    fn translate_escapes_and_classes() {
+        let table = SymbolTable::new();
+        let (a, b) = ("\\u{f0000}", "\\u{f0001}");
+        assert_eq!(table.translate("\\<a\\>").unwrap(), "\\u{3c}a\\u{3e}");
+        assert_eq!(table.translate("\\\\<a>").unwrap(), format!("\\\\{}", a));
+        assert_eq!(
+            table.translate("[<a><b>]").unwrap(),
+            format!("[{}{}]", a, b)
+        );
+        assert_eq!(table.translate("\\u{3c}\\(").unwrap(), "\\u{3c}\\(");
+
+        let pattern = table.translate("[<a><b>]\\<").unwrap();
+        let dfa = CompiledDFA::from_dfa(&full_pipeline(&pattern).unwrap().mindfa);
+        assert!(dfa.accepts(&format!("{}<", table.encode(["b"]).unwrap())));
+        assert!(!dfa.accepts(&table.encode(["a", "b"]).unwrap()));
+    }
+
+    #[test]
+    fn labels_use_names() {
+        let table = SymbolTable::new();
+        let word = table.encode(["lparen", "rparen"]).unwrap();
+        let mut chars: Vec<char> = word.chars().collect();
+        chars.push(',');
+        assert_eq!(table.label(&chars), "\\,,lparen,rparen");
+    }
+
+    #[test]
+    fn concurrent_interning_agrees() {
+        let table = Arc::new(SymbolTable::new());
+        let names: Vec<String> = (0..200).map(|i| format!("t{}", i)).collect();
+        let handles: Vec<_> = [1, 3, 7, 9]
+            .into_iter()
+            .map(|k| {
+                let (table, names) = (table.clone(), names.clone());
+                std::thread::spawn(move || {
+                    // Each thread interns in a different order
+                    let mut out = HashMap::new();
+                    for i in 0..names.len() {
+                        let n = &names[(i * k) % names.len()];
+                        out.insert(n.clone(), table.intern(n).unwrap());
+                    }
+                    out
+                })
+            })
+            .collect();
+
+        let results: Vec<HashMap<String, char>> =
+            handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(table.len(), 200);
+        for name in &names {
+            let c = table.get(name).unwrap();
+            assert!(results.iter().all(|r| r.get(name).is_none_or(|&d| d == c)));
+            assert_eq!(table.name(c).as_deref(), Some(name.as_str()));
+        }
+    }
+}
@@ -0,0 +1,342 @@
+// testing/corpus.rs
+//
+// Behavioural regression protection. While recording is on, every
+// whole-input match decision made through `DFA::accepts`,
+// `CompiledDFA::accepts` or `Matcher::accepts` is appended to a JSON-lines
+// corpus; `replay` recompiles each regex with the current algorithms and
+// reports the decisions that changed. Recording is opt-in, through
+// `record_to`.
+use crate::dfa::canonical::isomorphic;
+use crate::dfa::compiled::CompiledDFA;
+use crate::dfa::dfa::DFA;
+use crate::dfa::ops::OpCache;
+use crate::dfa::to_regex::dfa_to_regex_bounded;
+use crate::pipeline::tests::full_pipeline;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Largest regex, in AST nodes, written for a machine no regex was given
+/// for. Verdicts of bigger machines are not recorded.
+pub const MAX_DERIVED_NODES: usize = 10_000;
+
+/// One recorded decision: whether `regex` matched all of `input`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub regex: String,
+    pub input: String,
+    pub accepted: bool,
+}
+
+/// Appends verdicts to a corpus file, one JSON object per line. Lines are
+/// written whole and flushed, so a crash loses at most the last one.
+#[derive(Debug)]
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Write error: {}", e))?;
+        Ok(Self { file })
+    }
+
+    pub fn record(&mut self, verdict: &Verdict) -> Result<(), String> {
+        let mut line = serde_json::to_string(verdict).map_err(|e| format!("JSON error: {}", e))?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("Write error: {}", e))
+    }
+}
+
+// A thread's recorder, and the regex to record each language under
+struct Recording {
+    recorder: Recorder,
+    languages: OpCache,
+    // Language fingerprint → canonical minimal DFAs with that fingerprint,
+    // each with its regex (None when too big to write down)
+    names: HashMap<u128, Vec<(DFA, Option<String>)>>,
+}
+
+impl Recording {
+    // The regex to record `dfa`'s verdicts under: the one its language was
+    // compiled from by `full_pipeline`, or else one read off the DFA.
+    fn name(&mut self, dfa: &DFA) -> Option<String> {
+        let (f, canonical) = self.languages.language(dfa);
+        let known = self.names.entry(f).or_default();
+        if let Some((_, name)) = known.iter().find(|(d, _)| isomorphic(d, &canonical)) {
+            return name.clone();
+        }
+        let name = dfa_to_regex_bounded(&canonical, MAX_DERIVED_NODES).map(|ast| ast.to_pattern());
+        known.push((canonical, name.clone()));
+        name
+    }
+}
+
+thread_local! {
+    static RECORDING: RefCell<Option<Recording>> = const { RefCell::new(None) };
+}
+
+/// Record every later verdict made on this thread to `path`, replacing any
+/// earlier target. Recording is per thread, so tests running in parallel
+/// each keep their own corpus; a program matching on several threads
+/// calls this on each.
+pub fn record_to(path: impl AsRef<Path>) -> Result<(), String> {
+    let recording = Recording {
+        recorder: Recorder::open(path)?,
+        languages: OpCache::new(),
+        names: HashMap::new(),
+    };
+    RECORDING.with(|r| *r.borrow_mut() = Some(recording));
+    Ok(())
+}
+
+pub fn stop_recording() {
+    RECORDING.with(|r| *r.borrow_mut() = None);
+}
+
+pub fn is_recording() -> bool {
+    RECORDING.with(|r| r.try_borrow().is_ok_and(|r| r.is_some()))
+}
+
+// Run `f` on this thread's recording, if any. Matching done while a
+// verdict is being named isn't recorded.
+fn with_recording(f: impl FnOnce(&mut Recording)) {
+    RECORDING.with(|r| {
+        if let Ok(mut r) = r.try_borrow_mut() {
+            if let Some(recording) = r.as_mut() {
+                f(recording);
+            }
+        }
+    });
+}
+
+// Record later verdicts of `dfa`'s language under `regex`.
+pub(crate) fn name(dfa: &DFA, regex: &str) {
+    with_recording(|r| {
+        let (f, canonical) = r.languages.language(dfa);
+        let known = r.names.entry(f).or_default();
+        known.retain(|(d, _)| !isomorphic(d, &canonical));
+        known.push((canonical, Some(regex.to_string())));
+    });
+}
+
+// Append a verdict of `dfa` if recording is on, under `regex` or else
+// the name of its language. Failures to write are dropped: recording must
+// never change what the caller sees.
+pub(crate) fn record(dfa: &DFA, regex: Option<&str>, input: &str, accepted: bool) {
+    with_recording(|r| {
+        let Some(regex) = regex.map(str::to_string).or_else(|| r.name(dfa)) else {
+            return;
+        };
+        let _ = r.recorder.record(&Verdict {
+            regex,
+            input: input.to_string(),
+            accepted,
+        });
+    });
+}
+
+// `record` for a compiled table, converted back only while recording.
+pub(crate) fn record_compiled(dfa: &CompiledDFA, regex: Option<&str>, input: &str, accepted: bool) {
+    if is_recording() {
+        record(&dfa.to_dfa(), regex, input, accepted);
+    }
+}
+
+/// A recorded verdict the current code disagrees with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    // 1-based line in the corpus
+    pub line: usize,
+    pub verdict: Verdict,
+    // What the regex does now, or why it no longer compiles
+    pub now: Result<bool, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    pub checked: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl ReplayReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for m in &self.mismatches {
+            let now = match &m.now {
+                Ok(accepted) => accepted.to_string(),
+                Err(e) => e.clone(),
+            };
+            writeln!(
+                f,
+                "line {}: {:?} on {:?}: recorded {}, now {}",
+                m.line, m.verdict.regex, m.verdict.input, m.verdict.accepted, now
+            )?;
+        }
+        write!(
+            f,
+            "{} verdicts checked, {} changed",
+            self.checked,
+            self.mismatches.len()
+        )
+    }
+}
+
+/// Check every verdict of the corpus at `path` against the current
+/// pipeline. Blank lines are skipped; a malformed line is an error.
+pub fn replay(path: impl AsRef<Path>) -> Result<ReplayReport, String> {
+    let file = File::open(path).map_err(|e| format!("Read error: {}", e))?;
+    let mut verdicts = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Read error: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let verdict = serde_json::from_str(&line)
+            .map_err(|e| format!("JSON error on line {}: {}", i + 1, e))?;
+        verdicts.push((i + 1, verdict));
+    }
+    Ok(replay_verdicts(verdicts))
+}
+
+/// `replay` over verdicts already in memory, paired with their lines.
+/// Each regex is compiled once.
+pub fn replay_verdicts(verdicts: impl IntoIterator<Item = (usize, Verdict)>) -> ReplayReport {
+    let mut compiled: HashMap<String, Result<CompiledDFA, String>> = HashMap::new();
+    let mut report = ReplayReport::default();
+
+    for (line, verdict) in verdicts {
+        let dfa = compiled.entry(verdict.regex.clone()).or_insert_with(|| {
+            full_pipeline(&verdict.regex).map(|out| CompiledDFA::from_dfa(&out.mindfa))
+        });
+        let now = dfa
+            .as_ref()
+            .map(|dfa| dfa.accepts(&verdict.input))
+            .map_err(Clone::clone);
+
+        report.checked += 1;
+        if now != Ok(verdict.accepted) {
+            report.mismatches.push(Mismatch { line, verdict, now });
+        }
+    }
+    report
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::language::LanguageSet;
+
+    fn temp(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("kleeners-{}-{}.jsonl", name, std::process::id()))
+    }
+
+    fn verdict(regex: &str, input: &str, accepted: bool) -> Verdict {
+        Verdict {
+            regex: regex.into(),
+            input: input.into(),
+            accepted,
+        }
+    }
+
+    #[test]
+    fn replay_flags_changed_verdicts() {
+        let report = replay_verdicts([
+            (1, verdict("(a+b)*abb", "babb", true)),
+            (2, verdict("(a+b)*abb", "ab", true)),
+            (3, verdict("(a", "a", false)),
+        ]);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.mismatches.len(), 2);
+        assert_eq!(report.mismatches[0].now, Ok(false));
+        assert!(report.mismatches[1].now.is_err());
+        assert!(report
+            .to_string()
+            .starts_with("line 2: \"(a+b)*abb\" on \"ab\": recorded true, now false\n"));
+        assert!(report
+            .to_string()
+            .ends_with("3 verdicts checked, 2 changed"));
+    }
+
+    #[test]
+    fn recorded_corpus_replays_clean() {
+        let path = temp("corpus");
+        let _ = std::fs::remove_file(&path);
+        let mut recorder = Recorder::open(&path).unwrap();
+        for (input, accepted) in [("ab", true), ("", true), ("ba", false)] {
+            recorder.record(&verdict("(ab)*", input, accepted)).unwrap();
+        }
+        drop(recorder);
+
+        let report = replay(&path).unwrap();
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checked, 3);
+
+        std::fs::write(&path, "{\"regex\":\"a\"}\n").unwrap();
+        assert!(replay(&path).unwrap_err().contains("line 1"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn public_api_records_on_this_thread() {
+        let path = temp("thread");
+        let _ = std::fs::remove_file(&path);
+        record_to(&path).unwrap();
+
+        let out = full_pipeline("(a+b)*abb").unwrap();
+        assert!(out.dfa.accepts("babb"));
+        assert!(!CompiledDFA::from_dfa(&out.mindfa).accepts("ab"));
+
+        let mut langs = LanguageSet::new();
+        langs.define("digit", "0+1").unwrap();
+        let number = langs.define("number", "{digit}{digit}*").unwrap();
+        let m = number.matcher();
+        assert!(m.accepts("0110") && !m.accepts("012"));
+
+        // Other threads record only if they asked to
+        std::thread::spawn(move || assert!(out.mindfa.accepts("abb")))
+            .join()
+            .unwrap();
+        stop_recording();
+        assert!(!is_recording());
+
+        let corpus = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = corpus.lines().collect();
+        assert_eq!(lines.len(), 4, "{}", corpus);
+        // Named by the pipeline, whichever machine of the language matched
+        assert_eq!(
+            lines[0],
+            r#"{"regex":"(a+b)*abb","input":"babb","accepted":true}"#
+        );
+        assert!(lines[1].starts_with(r#"{"regex":"(a+b)*abb","input":"ab""#));
+        // The references, expanded into a regex read off the DFA
+        assert!(lines[3].ends_with(r#""input":"012","accepted":false}"#));
+
+        let report = replay(&path).unwrap();
+        assert!(report.passed(), "{}", report);
+        assert_eq!(report.checked, 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+// testing/golden.rs
+use std::path::Path;
+
+/// Set to a non-empty value other than `0` to (re)write snapshots instead
+/// of comparing against them.
+pub const BLESS_VAR: &str = "KLEENERS_BLESS";
+
+/// Compare `content` with the blessed snapshot `name` (a file name such as
+/// `dfa_abb.dot`) in `dir`, after normalizing both for the format given by
+/// its extension. With `KLEENERS_BLESS=1` the snapshot is written instead.
+/// Pass a directory of the calling crate, e.g.
+/// `concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots")`.
+///
+/// Panics on a mismatch or a missing snapshot, so it can be used like
+/// `assert_eq!` inside tests.
+pub fn assert_matches_snapshot(dir: impl AsRef<Path>, name: &str, content: &str) {
+    let path = dir.as_ref().join(name);
+    let actual = normalize(name, content);
+
+    if blessing() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).expect("create snapshot dir");
+        }
+        std::fs::write(&path, &actual).expect("write snapshot");
+        return;
+    }
+
+    match std::fs::read_to_string(&path) {
+        Ok(s) => compare(name, &normalize(name, &s), &actual),
+        Err(_) => panic!(
+            "missing snapshot {}; run with {}=1 to create it",
+            path.display(),
+            BLESS_VAR
+        ),
+    }
+}
+
+fn compare(name: &str, expected: &str, actual: &str) {
+    if expected != actual {
+        panic!(
+            "snapshot {} differs{}\n--- expected\n{}\n--- actual\n{}\n\
+             run with {}=1 to accept the new output",
+            name,
+            first_difference(expected, actual),
+            expected,
+            actual,
+            BLESS_VAR
+        );
+    }
+}
+
+/// Canonical text for a snapshot: line endings unified, trailing spaces
+/// dropped and exactly one final newline. JSON is reformatted with sorted
+/// keys, so the order of hash maps doesn't matter; CSV keeps its header
+/// line and sorts the rows.
+pub fn normalize(name: &str, content: &str) -> String {
+    let content = match extension(name) {
+        #[cfg(feature = "serde")]
+        "json" => match serde_json::from_str::<serde_json::Value>(content) {
+            Ok(value) => serde_json::to_string_pretty(&value).expect("JSON values serialize"),
+            Err(_) => content.to_string(),
+        },
+        _ => content.to_string(),
+    };
+
+    let mut lines: Vec<&str> = content.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+
+    if extension(name) == "csv" && lines.len() > 1 {
+        lines[1..].sort_unstable();
+    }
+
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+fn extension(name: &str) -> &str {
+    name.rsplit_once('.').map_or("", |(_, ext)| ext)
+}
+
+fn blessing() -> bool {
+    std::env::var(BLESS_VAR).is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+// " at line N" for the first differing line, for long snapshots.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut lines = expected.lines().zip(actual.lines());
+    match lines.position(|(e, a)| e != a) {
+        Some(i) => format!(" at line {}", i + 1),
+        None => " in length".into(),
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+    #[test]
+    fn normalization_per_format() {
+        assert_eq!(normalize("x.dot", "a  \r\nb\n\n\n"), "a\nb\n");
+        assert_eq!(
+            normalize("x.csv", "input,accepted\nb,false\na,true"),
+            "input,accepted\na,true\nb,false\n"
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            normalize("x.json", r#"{"b":1,"a":[2,1]}"#),
+            normalize("x.json", "{\n  \"a\": [2, 1],\n  \"b\": 1\n}")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "differs at line 2")]
+    fn mismatch_panics() {
+        compare("x.txt", "first\nsecond\n", "first\nchanged\n");
+    }
+
+    #[test]
+    fn matching_snapshot_passes() {
+        assert_matches_snapshot(SNAPSHOTS, "golden_self.txt", "first\nsecond  \r\n");
+    }
+}
@@ -0,0 +1,3 @@
+#[cfg(feature = "testing")]
+pub mod corpus;
+pub mod golden;
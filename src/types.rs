@@ -1,28 +1,294 @@
 // types.rs
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-pub type StateID = u32;
+use crate::dfa::dfa::DFA;
+use crate::nfa::nfa::{TransitionLabel, NFA};
+use crate::pipeline::preprocess::Preprocessor;
+use std::fmt;
+use std::hash::Hash;
+
+/// State identifier shared by `Automaton`, `NFA` and `DFA`. States are
+/// mostly numbered 0..n and used as indices, so an id converts to and from
+/// `usize` (see `index`); it is stored in 32 bits, which halves the size
+/// of transition tables. Serialized as a plain number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+pub struct StateId(pub u32);
+
+impl StateId {
+    /// The id of index `i`. Panics past `u32::MAX` states.
+    #[inline]
+    pub fn new(i: usize) -> Self {
+        StateId(u32::try_from(i).expect("more than u32::MAX states"))
+    }
+
+    /// The id as an index into per-state tables.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The id after this one, for a fresh state past the largest.
+    #[inline]
+    pub fn next(self) -> Self {
+        StateId(self.0 + 1)
+    }
+}
+
+impl From<usize> for StateId {
+    fn from(i: usize) -> Self {
+        StateId::new(i)
+    }
+}
+
+impl From<u32> for StateId {
+    fn from(i: u32) -> Self {
+        StateId(i)
+    }
+}
+
+impl From<StateId> for usize {
+    fn from(s: StateId) -> Self {
+        s.index()
+    }
+}
+
+impl fmt::Display for StateId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// What an `NFA` or `DFA` reads. Automata built from regexes read `char`s,
+/// the default; the core algorithms (ε-removal, subset construction,
+/// minimization) work for any letter type, such as `u8` for matching raw
+/// bytes or `TokenId` for automata over a lexer's output.
+pub trait Letter: Copy + Ord + Hash + fmt::Debug {}
+
+impl<T: Copy + Ord + Hash + fmt::Debug> Letter for T {}
+
+/// A token kind, as a letter of automata over token streams.
+pub type TokenId = u32;
 
 /// A transition label (OR):
 /// - `None`     = epsilon
 /// - `Some(c)`  = char literal
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Symbol {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Symbol<S = char> {
     Epsilon,
-    Char(char),
+    Char(S),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transition {
-    pub from: StateID,
-    pub to: StateID,
+    pub from: StateId,
+    pub to: StateId,
     pub symbol: Symbol,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Automaton {
-    pub states: Vec<StateID>,
-    pub start: StateID,
-    pub accepts: Vec<StateID>,
+    pub states: Vec<StateId>,
+    pub start: StateId,
+    pub accepts: Vec<StateId>,
     pub transitions: Vec<Transition>,
+    // Input normalization the machine expects; missing means none
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub preprocessor: Preprocessor,
+}
+
+/// Size of an automaton, from `NFA::stats` or `DFA::stats`: enough to
+/// decide whether to minimize, or to give up, before doing the work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AutomatonStats {
+    pub states: usize,
+    // Every edge, ε-edges included
+    pub transitions: usize,
+    pub epsilons: usize,
+    // Distinct char symbols on some edge
+    pub alphabet: usize,
+    // Most edges leaving a single state
+    pub max_out_degree: usize,
+}
+
+impl fmt::Display for AutomatonStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} states, {} transitions ({} ε), {} symbols, max out-degree {}",
+            self.states, self.transitions, self.epsilons, self.alphabet, self.max_out_degree
+        )
+    }
+}
+
+impl<S: Letter> From<&TransitionLabel<S>> for Symbol<S> {
+    fn from(label: &TransitionLabel<S>) -> Self {
+        match label {
+            TransitionLabel::Epsilon => Symbol::Epsilon,
+            TransitionLabel::Char(c) => Symbol::Char(*c),
+        }
+    }
+}
+
+impl<S: Letter> From<&Symbol<S>> for TransitionLabel<S> {
+    fn from(symbol: &Symbol<S>) -> Self {
+        match symbol {
+            Symbol::Epsilon => TransitionLabel::Epsilon,
+            Symbol::Char(c) => TransitionLabel::Char(*c),
+        }
+    }
+}
+
+/// An `Automaton` has one start state, so several are joined by a fresh
+/// one (see `NFA::single_start`).
+impl From<&NFA> for Automaton {
+    fn from(nfa: &NFA) -> Self {
+        if !nfa.extra_starts.is_empty() {
+            return Automaton::from(&nfa.clone().single_start());
+        }
+        let mut states: Vec<StateId> = nfa.transitions.keys().copied().collect();
+        states.sort_unstable();
+
+        let mut transitions: Vec<Transition> = nfa
+            .transitions
+            .iter()
+            .flat_map(|(&from, edges)| {
+                edges.iter().map(move |(label, to)| Transition {
+                    from,
+                    to: *to,
+                    symbol: label.into(),
+                })
+            })
+            .collect();
+        // Stable: keeps each state's edge order
+        transitions.sort_by_key(|t| t.from);
+
+        Automaton {
+            states,
+            start: nfa.start,
+            accepts: nfa.accept.clone(),
+            transitions,
+            preprocessor: Preprocessor::default(),
+        }
+    }
+}
+
+impl From<&Automaton> for NFA {
+    fn from(a: &Automaton) -> Self {
+        let mut nfa = NFA::new();
+        for &s in &a.states {
+            nfa.transitions.entry(s).or_default();
+        }
+        for t in &a.transitions {
+            nfa.add_edge_auto(t.from, (&t.symbol).into(), t.to);
+        }
+        nfa.start = a.start;
+        nfa.accept = a.accepts.clone();
+        nfa
+    }
+}
+
+impl From<&DFA> for Automaton {
+    fn from(dfa: &DFA) -> Self {
+        let mut edges: Vec<(StateId, char, StateId)> = dfa
+            .transitions
+            .iter()
+            .flat_map(|(&from, map)| map.iter().map(move |(&c, &to)| (from, c, to)))
+            .collect();
+        edges.sort_unstable();
+
+        let transitions = edges
+            .into_iter()
+            .map(|(from, c, to)| Transition {
+                from,
+                to,
+                symbol: Symbol::Char(c),
+            })
+            .collect();
+
+        let mut accepts: Vec<StateId> = dfa.accepts.iter().copied().collect();
+        accepts.sort_unstable();
+
+        Automaton {
+            states: dfa.states().into_iter().collect(),
+            start: dfa.start,
+            accepts,
+            transitions,
+            preprocessor: Preprocessor::default(),
+        }
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn nfa_automaton_round_trip() {
+        let enfa = full_pipeline("(a+b)*c").unwrap().enfa;
+        let automaton = Automaton::from(&enfa);
+
+        assert_eq!(automaton.start, enfa.start);
+        assert_eq!(automaton.states.len(), enfa.transitions.len());
+
+        let back = NFA::from(&automaton);
+        assert_eq!(back.start, enfa.start);
+        assert_eq!(back.accept, enfa.accept);
+        for (s, edges) in &enfa.transitions {
+            assert_eq!(back.transitions[s], *edges);
+        }
+    }
+
+    #[test]
+    fn dfa_to_automaton() {
+        let dfa = full_pipeline("ab").unwrap().mindfa;
+        let automaton = Automaton::from(&dfa);
+
+        assert_eq!(automaton.states.len(), 3);
+        assert_eq!(automaton.transitions.len(), 2);
+        assert_eq!(automaton.accepts.len(), 1);
+    }
+
+    #[test]
+    fn stats_of_each_stage() {
+        let out = full_pipeline("(a+b)*abb").unwrap();
+
+        let enfa = out.enfa.stats();
+        assert_eq!(enfa.states, out.enfa.transitions.len());
+        assert!(enfa.epsilons > 0 && enfa.epsilons < enfa.transitions);
+        assert_eq!((enfa.alphabet, enfa.max_out_degree), (2, 2));
+
+        let nfa = out.nfa.stats();
+        assert_eq!(nfa.epsilons, 0);
+        assert!(nfa.states <= enfa.states);
+
+        let mindfa = out.mindfa.stats();
+        assert_eq!(
+            mindfa,
+            AutomatonStats {
+                states: 4,
+                transitions: 8,
+                epsilons: 0,
+                alphabet: 2,
+                max_out_degree: 2,
+            }
+        );
+        assert_eq!(
+            mindfa.to_string(),
+            "4 states, 8 transitions (0 ε), 2 symbols, max out-degree 2"
+        );
+        assert_eq!(NFA::new().stats().states, 1);
+    }
 }
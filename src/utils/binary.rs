@@ -0,0 +1,249 @@
+// utils/binary.rs
+//
+// Compact binary files for NFAs and DFAs, so determinization and
+// minimization can be done once and reused by later runs. A file is a
+// header (magic, format version, which automaton) followed by the bincode
+// encoding of the machine with its states in order, so the same machine
+// always gives the same bytes.
+use crate::dfa::dfa::DFA;
+use crate::nfa::nfa::{TransitionLabel, NFA};
+use crate::types::StateId;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const MAGIC: [u8; 4] = *b"KLAU";
+pub const FORMAT_VERSION: u32 = 1;
+
+// Magic, version and kind
+const HEADER_LEN: usize = 9;
+
+// Which automaton follows the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Nfa = 0,
+    Dfa = 1,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Nfa => "an NFA",
+            Kind::Dfa => "a DFA",
+        }
+    }
+}
+
+// The JSON form skips empty fields, which a non-self-describing format
+// can't do, so the binary form has its own layout
+#[derive(Serialize, Deserialize)]
+struct NfaRepr {
+    start: StateId,
+    extra_starts: Vec<StateId>,
+    accept: Vec<StateId>,
+    // By state; each state's edges in their stored order
+    transitions: Vec<(StateId, Vec<(TransitionLabel, StateId)>)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DfaRepr {
+    start: StateId,
+    accepts: Vec<StateId>,
+    // By state, then by symbol
+    transitions: Vec<(StateId, Vec<(char, StateId)>)>,
+}
+
+impl NFA {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut transitions: Vec<_> = self
+            .transitions
+            .iter()
+            .map(|(&s, edges)| (s, edges.clone()))
+            .collect();
+        transitions.sort_unstable_by_key(|&(s, _)| s);
+
+        encode(
+            Kind::Nfa,
+            &NfaRepr {
+                start: self.start,
+                extra_starts: self.extra_starts.clone(),
+                accept: self.accept.clone(),
+                transitions,
+            },
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let repr: NfaRepr = decode(Kind::Nfa, bytes)?;
+        NFA {
+            start: repr.start,
+            extra_starts: repr.extra_starts,
+            accept: repr.accept,
+            transitions: repr.transitions.into_iter().collect(),
+        }
+        .checked()
+        .map_err(|e| format!("Binary error: {}", e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Write error: {}", e))
+    }
+
+    /// Read a file written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Read error: {}", e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl DFA {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut transitions: Vec<(StateId, Vec<(char, StateId)>)> = self
+            .transitions
+            .iter()
+            .map(|(&s, map)| {
+                let mut edges: Vec<(char, StateId)> = map.iter().map(|(&c, &t)| (c, t)).collect();
+                edges.sort_unstable();
+                (s, edges)
+            })
+            .collect();
+        transitions.sort_unstable_by_key(|&(s, _)| s);
+        let mut accepts: Vec<StateId> = self.accepts.iter().copied().collect();
+        accepts.sort_unstable();
+
+        encode(
+            Kind::Dfa,
+            &DfaRepr {
+                start: self.start,
+                accepts,
+                transitions,
+            },
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let repr: DfaRepr = decode(Kind::Dfa, bytes)?;
+        Ok(DFA {
+            start: repr.start,
+            accepts: repr.accepts.into_iter().collect(),
+            transitions: repr
+                .transitions
+                .into_iter()
+                .map(|(s, edges)| (s, edges.into_iter().collect()))
+                .collect(),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| format!("Write error: {}", e))
+    }
+
+    /// Read a file written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Read error: {}", e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+// Little-endian varints, and no trailing bytes after the value
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn encode(kind: Kind, value: &impl Serialize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN);
+    out.extend(MAGIC);
+    out.extend(FORMAT_VERSION.to_le_bytes());
+    out.push(kind as u8);
+    options()
+        .serialize_into(&mut out, value)
+        .expect("encoding to memory doesn't fail");
+    out
+}
+
+fn decode<T: DeserializeOwned>(kind: Kind, bytes: &[u8]) -> Result<T, String> {
+    if bytes.len() < HEADER_LEN || bytes[..4] != MAGIC {
+        return Err("Not a kleeners automaton file".into());
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(format!("Unsupported format version {}", version));
+    }
+    let found = match bytes[8] {
+        0 => Kind::Nfa,
+        1 => Kind::Dfa,
+        other => return Err(format!("Unknown automaton kind {}", other)),
+    };
+    if found != kind {
+        return Err(format!("Expected {}, found {}", kind.name(), found.name()));
+    }
+    options()
+        .deserialize(&bytes[HEADER_LEN..])
+        .map_err(|e| format!("Binary error: {}", e))
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn round_trips() {
+        let out = full_pipeline("(a+b)*abb").unwrap();
+        let reversed = out.nfa.reverse();
+        for nfa in [&out.enfa, &reversed] {
+            let back = NFA::from_bytes(&nfa.to_bytes()).unwrap();
+            assert_eq!(back.starts(), nfa.starts());
+            assert_eq!(back.accept, nfa.accept);
+            assert_eq!(back.transitions, nfa.transitions);
+        }
+        assert!(NFA::from_bytes(&NFA::new().to_bytes()).is_ok());
+
+        let dfa = DFA::from_bytes(&out.mindfa.to_bytes()).unwrap();
+        assert_eq!(dfa.start, out.mindfa.start);
+        assert_eq!(dfa.accepts, out.mindfa.accepts);
+        assert_eq!(dfa.transitions, out.mindfa.transitions);
+        // Same machine, same bytes
+        assert_eq!(dfa.to_bytes(), out.mindfa.to_bytes());
+        assert!(out.mindfa.to_bytes().len() < out.mindfa.to_json().unwrap().len() / 2);
+
+        let path = std::env::temp_dir().join(format!("kleeners-{}.kfa", std::process::id()));
+        out.dfa.save(&path).unwrap();
+        assert_eq!(DFA::load(&path).unwrap().transitions, out.dfa.transitions);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bad_files() {
+        let dfa = full_pipeline("ab").unwrap().mindfa.to_bytes();
+
+        assert_eq!(
+            NFA::from_bytes(&dfa).unwrap_err(),
+            "Expected an NFA, found a DFA"
+        );
+        assert_eq!(
+            DFA::from_bytes(b"KLDF").unwrap_err(),
+            "Not a kleeners automaton file"
+        );
+
+        let mut newer = dfa.clone();
+        newer[4] = 2;
+        assert_eq!(
+            DFA::from_bytes(&newer).unwrap_err(),
+            "Unsupported format version 2"
+        );
+
+        for bytes in [&dfa[..dfa.len() - 1], &[dfa.as_slice(), &[0]].concat()] {
+            assert!(DFA::from_bytes(bytes)
+                .unwrap_err()
+                .starts_with("Binary error"));
+        }
+    }
+}
@@ -0,0 +1,363 @@
+// utils/doctor.rs
+use crate::dfa::canonical::canonicalize;
+use crate::dfa::compiled::CompiledDFA;
+use crate::dfa::dfa::DFA;
+use crate::dfa::minimize::minimize_dfa;
+use crate::dfa::ops::{reachable, trim};
+use crate::types::{Automaton, StateId, Symbol};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// Outcome of a single check. Warnings are legal machines that are not in
+/// the shape the pipeline produces; failures are broken invariants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    Warning(String),
+    Failed(String),
+    // The check needs a property an earlier check found missing
+    Skipped(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: &'static str,
+    pub health: Health,
+}
+
+/// Result of every check, in the order they ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn is_healthy(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|c| matches!(c.health, Health::Failed(_)))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Health> {
+        self.checks
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| &c.health)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            match &check.health {
+                Health::Ok => writeln!(f, "[ ok ] {}", check.name)?,
+                Health::Warning(msg) => writeln!(f, "[warn] {}: {}", check.name, msg)?,
+                Health::Failed(msg) => writeln!(f, "[FAIL] {}: {}", check.name, msg)?,
+                Health::Skipped(msg) => writeln!(f, "[skip] {}: {}", check.name, msg)?,
+            }
+        }
+        let verdict = if self.is_healthy() {
+            "healthy"
+        } else {
+            "broken"
+        };
+        write!(f, "{}", verdict)
+    }
+}
+
+type Validator = fn(&DFA) -> Health;
+
+/// Run every validator on a machine: well-formedness, determinism,
+/// completeness, reachability, minimality, canonical numbering and
+/// serialization round-trips. Checks that need a DFA are skipped when the
+/// machine is not deterministic.
+pub fn diagnose(machine: &Automaton) -> Report {
+    let mut checks = vec![
+        Check {
+            name: "well-formed",
+            health: well_formed(machine),
+        },
+        Check {
+            name: "determinism",
+            health: determinism(machine),
+        },
+    ];
+
+    let dfa = checks
+        .iter()
+        .all(|c| c.health == Health::Ok)
+        .then(|| to_dfa(machine));
+    let validators: [(&'static str, Validator); 5] = [
+        ("completeness", completeness),
+        ("reachability", reachability),
+        ("minimality", minimality),
+        ("canonical numbering", canonical_numbering),
+        ("compiled round-trip", compiled_round_trip),
+    ];
+    for (name, check) in validators {
+        let health = match &dfa {
+            Some(dfa) => check(dfa),
+            None => Health::Skipped("not a valid DFA".into()),
+        };
+        checks.push(Check { name, health });
+    }
+
+    checks.push(Check {
+        name: "json round-trip",
+        health: json_round_trip(machine),
+    });
+
+    Report { checks }
+}
+
+// Every referenced state is declared, and declared only once.
+fn well_formed(machine: &Automaton) -> Health {
+    let declared: HashSet<StateId> = machine.states.iter().copied().collect();
+    if declared.len() != machine.states.len() {
+        return Health::Failed("a state is declared twice".into());
+    }
+
+    let mut used = vec![machine.start];
+    used.extend(&machine.accepts);
+    used.extend(machine.transitions.iter().flat_map(|t| [t.from, t.to]));
+    match used.into_iter().find(|s| !declared.contains(s)) {
+        Some(s) => Health::Failed(format!("state {} is used but not declared", s)),
+        None => Health::Ok,
+    }
+}
+
+fn determinism(machine: &Automaton) -> Health {
+    let mut seen: HashMap<(StateId, char), StateId> = HashMap::new();
+    for t in &machine.transitions {
+        let c = match t.symbol {
+            Symbol::Epsilon => {
+                return Health::Failed(format!("ε-transition {} -> {}", t.from, t.to));
+            }
+            Symbol::Char(c) => c,
+        };
+        if let Some(&other) = seen.get(&(t.from, c)) {
+            if other != t.to {
+                return Health::Failed(format!(
+                    "state {} goes to both {} and {} on {:?}",
+                    t.from, other, t.to, c
+                ));
+            }
+        }
+        seen.insert((t.from, c), t.to);
+    }
+    Health::Ok
+}
+
+fn to_dfa(machine: &Automaton) -> DFA {
+    let mut transitions: HashMap<StateId, HashMap<char, StateId>> = HashMap::new();
+    for t in &machine.transitions {
+        if let Symbol::Char(c) = t.symbol {
+            transitions.entry(t.from).or_default().insert(c, t.to);
+        }
+    }
+    DFA {
+        start: machine.start,
+        accepts: machine.accepts.iter().copied().collect(),
+        transitions,
+    }
+}
+
+// Partial DFAs are normal (missing transitions reject), so this only warns.
+fn completeness(dfa: &DFA) -> Health {
+    let sigma = dfa.alphabet();
+    let missing = dfa
+        .states()
+        .into_iter()
+        .flat_map(|s| sigma.iter().map(move |&c| (s, c)))
+        .filter(|(s, c)| !dfa.transitions.get(s).is_some_and(|m| m.contains_key(c)))
+        .count();
+
+    match missing {
+        0 => Health::Ok,
+        n => Health::Warning(format!("{} missing transitions (partial DFA)", n)),
+    }
+}
+
+fn reachability(dfa: &DFA) -> Health {
+    let seen = reachable(dfa);
+    // Plain numbers, so the message reads [3, 4] rather than Debug ids
+    let unreachable: Vec<u32> = dfa
+        .states()
+        .into_iter()
+        .filter(|s| !seen.contains(s))
+        .map(|s| s.0)
+        .collect();
+
+    if unreachable.is_empty() {
+        Health::Ok
+    } else {
+        Health::Warning(format!("unreachable states {:?}", unreachable))
+    }
+}
+
+// Compared on the trimmed machine, so an explicit dead state doesn't count.
+fn minimality(dfa: &DFA) -> Health {
+    let trimmed = trim(dfa);
+    let have = trimmed.states().len();
+    let need = minimize_dfa(&trimmed).states().len();
+
+    if have == need {
+        Health::Ok
+    } else {
+        Health::Warning(format!(
+            "{} live states, the minimal DFA has {}",
+            have, need
+        ))
+    }
+}
+
+fn canonical_numbering(dfa: &DFA) -> Health {
+    let canon = canonicalize(dfa);
+    let same = canon.start == dfa.start
+        && canon.accepts == dfa.accepts
+        && canon.transitions == dfa.transitions;
+
+    if same {
+        Health::Ok
+    } else {
+        Health::Warning("states are not numbered in BFS order from 0".into())
+    }
+}
+
+fn compiled_round_trip(dfa: &DFA) -> Health {
+    let compiled = CompiledDFA::from_dfa(dfa);
+    let loaded = match CompiledDFA::from_bytes(&compiled.to_bytes()) {
+        Ok(loaded) => loaded,
+        Err(e) => return Health::Failed(e),
+    };
+    if loaded != compiled {
+        return Health::Failed("reloaded tables differ".into());
+    }
+
+    // Spot-check the tables against the machine on short words
+    let sigma: BTreeSet<char> = dfa.alphabet();
+    for word in sigma.iter().map(|c| c.to_string()).chain([String::new()]) {
+        if compiled.accepts(&word) != dfa.accepts(&word) {
+            return Health::Failed(format!("compiled tables disagree on {:?}", word));
+        }
+    }
+    Health::Ok
+}
+
+#[cfg(feature = "serde")]
+fn json_round_trip(machine: &Automaton) -> Health {
+    let json = match serde_json::to_string(machine) {
+        Ok(json) => json,
+        Err(e) => return Health::Failed(e.to_string()),
+    };
+    let back: Automaton = match serde_json::from_str(&json) {
+        Ok(back) => back,
+        Err(e) => return Health::Failed(e.to_string()),
+    };
+
+    if serde_json::to_string(&back).ok() == Some(json) {
+        Health::Ok
+    } else {
+        Health::Failed("reloaded machine differs".into())
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn json_round_trip(_: &Automaton) -> Health {
+    Health::Skipped("built without the serde feature".into())
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::canonical::canonicalize;
+    use crate::pipeline::preprocess::Preprocessor;
+    use crate::pipeline::tests::full_pipeline;
+    use crate::types::Transition;
+
+    fn edge(from: u32, c: char, to: u32) -> Transition {
+        Transition {
+            from: StateId(from),
+            to: StateId(to),
+            symbol: Symbol::Char(c),
+        }
+    }
+
+    #[test]
+    fn pipeline_output_is_healthy() {
+        let dfa = canonicalize(&full_pipeline("(a+b)*abb").unwrap().mindfa);
+        let report = diagnose(&Automaton::from(&dfa));
+
+        assert!(report.is_healthy(), "{}", report);
+        for check in &report.checks {
+            if check.name != "completeness" && check.name != "json round-trip" {
+                assert_eq!(check.health, Health::Ok, "{}", check.name);
+            }
+        }
+    }
+
+    #[test]
+    fn nondeterminism_fails_and_skips() {
+        let machine = Automaton {
+            states: vec![StateId(0), StateId(1), StateId(2)],
+            start: StateId(0),
+            accepts: vec![StateId(1)],
+            transitions: vec![edge(0, 'a', 1), edge(0, 'a', 2)],
+            preprocessor: Preprocessor::default(),
+        };
+        let report = diagnose(&machine);
+
+        assert!(!report.is_healthy());
+        assert!(matches!(report.get("determinism"), Some(Health::Failed(_))));
+        assert!(matches!(report.get("minimality"), Some(Health::Skipped(_))));
+    }
+
+    #[test]
+    fn legal_but_untidy_machine_warns() {
+        // State 2 duplicates 1, state 3 is unreachable, numbering starts at 5
+        let machine = Automaton {
+            states: vec![StateId(5), StateId(1), StateId(2), StateId(3)],
+            start: StateId(5),
+            accepts: vec![StateId(1), StateId(2)],
+            transitions: vec![edge(5, 'a', 1), edge(5, 'b', 2), edge(3, 'a', 5)],
+            preprocessor: Preprocessor::new().case_fold(),
+        };
+        let report = diagnose(&machine);
+
+        assert!(report.is_healthy(), "{}", report);
+        for name in [
+            "completeness",
+            "reachability",
+            "minimality",
+            "canonical numbering",
+        ] {
+            assert!(
+                matches!(report.get(name), Some(Health::Warning(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn undeclared_state_fails() {
+        let machine = Automaton {
+            states: vec![StateId(0)],
+            start: StateId(0),
+            accepts: vec![StateId(7)],
+            transitions: vec![],
+            preprocessor: Preprocessor::default(),
+        };
+        assert!(matches!(
+            diagnose(&machine).get("well-formed"),
+            Some(Health::Failed(_))
+        ));
+    }
+}
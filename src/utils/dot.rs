@@ -1,28 +1,57 @@
 // utils/dot.rs
+use super::edges::{
+    group_dfa_edges, group_dfa_edges_by, group_nfa_edges, group_nfa_edges_by, GroupedEdge,
+};
+use crate::dfa::compiled::CompiledDFA;
 use crate::dfa::dfa::DFA;
+use crate::dfa::profile::Profile;
 use crate::nfa::nfa::NFA;
+use crate::nfa::renumber::{renumber, renumber_dfa, StateOrder};
+use crate::symbols::SymbolTable;
+use crate::types::StateId;
+use std::collections::BTreeSet;
 
-fn escape(c: char) -> String {
-    match c {
-        '"' => "\\\"".into(),
-        _ => c.to_string()
-    }
+// Quote a label for a DOT string. Backslashes go first so that escapes
+// already in the label (like `\n`) are shown literally.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
+/// States are renumbered breadth-first from the start first, so the
+/// output doesn't depend on how the NFA was built or on hash order.
 pub fn nfa_to_dot(nfa: &NFA) -> String {
+    nfa_to_dot_ordered(nfa, StateOrder::default())
+}
+
+/// `nfa_to_dot` numbering states by `order`. `StateOrder::Original` keeps
+/// the ids, to draw next to `subsets_to_dot` of the same NFA.
+pub fn nfa_to_dot_ordered(nfa: &NFA, order: StateOrder) -> String {
+    let nfa = renumber(nfa, order);
+    nfa_dot(&nfa, group_nfa_edges(&nfa))
+}
+
+/// `nfa_to_dot` with interned symbols labelled by name.
+pub fn nfa_to_dot_with(nfa: &NFA, symbols: &SymbolTable) -> String {
+    let nfa = renumber(nfa, StateOrder::default());
+    nfa_dot(&nfa, group_nfa_edges_by(&nfa, |cs| symbols.label(&cs)))
+}
+
+fn nfa_dot(nfa: &NFA, edges: Vec<GroupedEdge>) -> String {
     let mut out = String::new();
     out.push_str("digraph NFA {\n  rankdir=LR;\n  node [shape=circle];\n");
 
-    out.push_str(&format!("  start [shape=point];\n  start -> {};\n", nfa.start));
+    out.push_str("  start [shape=point];\n");
+    for s in nfa.starts() {
+        out.push_str(&format!("  start -> {};\n", s));
+    }
 
-    for (src, edges) in &nfa.transitions {
-        for (label, dst) in edges {
-            let lbl = match label {
-                crate::nfa::nfa::TransitionLabel::Char(c) => escape(*c),
-                crate::nfa::nfa::TransitionLabel::Epsilon => "ε".into(),
-            };
-            out.push_str(&format!("  {} -> {} [label=\"{}\"];\n", src, dst, lbl));
-        }
+    for e in edges {
+        out.push_str(&format!(
+            "  {} -> {} [label=\"{}\"];\n",
+            e.from,
+            e.to,
+            escape(&e.label)
+        ));
     }
 
     for a in &nfa.accept {
@@ -33,22 +62,454 @@ pub fn nfa_to_dot(nfa: &NFA) -> String {
     out
 }
 
+/// States are renumbered breadth-first from the start, following symbols
+/// in order, so minimizations that only differ in their ids draw alike.
 pub fn dfa_to_dot(dfa: &DFA) -> String {
+    dfa_to_dot_ordered(dfa, StateOrder::default())
+}
+
+/// `dfa_to_dot` numbering states by `order`. `StateOrder::Original` keeps
+/// the ids, to draw next to `subsets_to_dot` or `minimization_to_dot`.
+pub fn dfa_to_dot_ordered(dfa: &DFA, order: StateOrder) -> String {
+    let dfa = renumber_dfa(dfa, order);
+    dfa_dot(&dfa, group_dfa_edges(&dfa))
+}
+
+/// `dfa_to_dot` with interned symbols labelled by name.
+pub fn dfa_to_dot_with(dfa: &DFA, symbols: &SymbolTable) -> String {
+    let dfa = renumber_dfa(dfa, StateOrder::default());
+    dfa_dot(&dfa, group_dfa_edges_by(&dfa, |cs| symbols.label(&cs)))
+}
+
+fn dfa_dot(dfa: &DFA, edges: Vec<GroupedEdge>) -> String {
     let mut out = String::new();
     out.push_str("digraph DFA {\n  rankdir=LR;\n  node [shape=circle];\n");
 
-    out.push_str(&format!("  start [shape=point];\n  start -> {};\n", dfa.start));
+    out.push_str(&format!(
+        "  start [shape=point];\n  start -> {};\n",
+        dfa.start
+    ));
 
-    for (src, map) in &dfa.transitions {
-        for (c, dst) in map {
-            out.push_str(&format!("  {} -> {} [label=\"{}\"];\n", src, dst, escape(*c)));
-        }
+    for e in edges {
+        out.push_str(&format!(
+            "  {} -> {} [label=\"{}\"];\n",
+            e.from,
+            e.to,
+            escape(&e.label)
+        ));
     }
 
-    for a in &dfa.accepts {
+    let mut accepts: Vec<_> = dfa.accepts.iter().collect();
+    accepts.sort_unstable();
+    for a in accepts {
         out.push_str(&format!("  {} [shape=doublecircle];\n", a));
     }
 
     out.push_str("}\n");
     out
 }
+
+/// Subset construction as a bipartite picture: NFA states on the left,
+/// DFA states on the right labelled with their subsets, and an edge from
+/// each NFA state to every DFA state containing it. `subsets` is the
+/// provenance from `nfa_to_dfa_traced`. Start states are bold.
+pub fn subsets_to_dot(nfa: &NFA, dfa: &DFA, subsets: &[BTreeSet<StateId>]) -> String {
+    let mut out = String::new();
+    out.push_str("digraph Subsets {\n  rankdir=LR;\n  node [shape=circle];\n");
+
+    let mut nfa_states: BTreeSet<StateId> = nfa.transitions.keys().copied().collect();
+    let starts = nfa.starts();
+    nfa_states.extend(starts.iter().copied());
+    nfa_states.extend(nfa.accept.iter().copied());
+    nfa_states.extend(nfa.transitions.values().flatten().map(|&(_, to)| to));
+
+    out.push_str("  subgraph cluster_nfa {\n    label=\"NFA\";\n");
+    for s in nfa_states {
+        let shape = if nfa.accept.contains(&s) {
+            "doublecircle"
+        } else {
+            "circle"
+        };
+        let style = if starts.contains(&s) {
+            ", style=bold"
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "    n{} [label=\"{}\", shape={}{}];\n",
+            s, s, shape, style
+        ));
+    }
+    out.push_str("  }\n");
+
+    out.push_str("  subgraph cluster_dfa {\n    label=\"DFA\";\n    node [shape=box];\n");
+    for (d, subset) in subsets.iter().enumerate() {
+        let d = StateId::new(d);
+        let members: Vec<String> = subset.iter().map(|s| s.to_string()).collect();
+        let peripheries = if dfa.accepts.contains(&d) { 2 } else { 1 };
+        let style = if d == dfa.start { ", style=bold" } else { "" };
+        out.push_str(&format!(
+            "    d{} [label=\"{} = {{{}}}\", peripheries={}{}];\n",
+            d,
+            d,
+            members.join(","),
+            peripheries,
+            style
+        ));
+    }
+    out.push_str("  }\n");
+
+    for (d, subset) in subsets.iter().enumerate() {
+        for s in subset {
+            out.push_str(&format!("  n{} -> d{} [arrowhead=none];\n", s, d));
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Minimization explained: the original DFA with every state filled in the
+/// colour of its partition, so merged states share a colour, next to the
+/// minimized DFA in the same colours. `partitions` is the map from
+/// `minimize_dfa_traced`, indexed by minimized state id; colours follow
+/// the smallest original state of each partition.
+pub fn minimization_to_dot(
+    original: &DFA,
+    minimized: &DFA,
+    partitions: &[BTreeSet<StateId>],
+) -> String {
+    let mut by_smallest: Vec<usize> = (0..partitions.len()).collect();
+    by_smallest.sort_by_key(|&p| partitions[p].first().copied());
+    let mut colour = vec![String::new(); partitions.len()];
+    for (rank, p) in by_smallest.into_iter().enumerate() {
+        // Golden-ratio steps around the hue circle keep neighbours apart
+        colour[p] = format!("{:.3} 0.35 1.0", (rank as f64 * 0.618_034) % 1.0);
+    }
+    let partition_of = |s: StateId| partitions.iter().position(|p| p.contains(&s));
+
+    let mut out = String::new();
+    out.push_str("digraph Minimization {\n  rankdir=LR;\n  node [shape=circle, style=filled];\n");
+
+    out.push_str("  subgraph cluster_original {\n    label=\"original\";\n");
+    for s in original.states() {
+        let shape = if original.accepts.contains(&s) {
+            "doublecircle"
+        } else {
+            "circle"
+        };
+        let fill = partition_of(s).map_or("white", |p| &colour[p]);
+        out.push_str(&format!(
+            "    o{} [label=\"{}\", shape={}, fillcolor=\"{}\"];\n",
+            s, s, shape, fill
+        ));
+    }
+    for e in group_dfa_edges(original) {
+        out.push_str(&format!(
+            "    o{} -> o{} [label=\"{}\"];\n",
+            e.from,
+            e.to,
+            escape(&e.label)
+        ));
+    }
+    out.push_str("  }\n");
+
+    out.push_str("  subgraph cluster_minimized {\n    label=\"minimized\";\n");
+    for (i, members) in partitions.iter().enumerate() {
+        let p = StateId::new(i);
+        let members: Vec<String> = members.iter().map(|s| s.to_string()).collect();
+        let shape = if minimized.accepts.contains(&p) {
+            "doublecircle"
+        } else {
+            "circle"
+        };
+        out.push_str(&format!(
+            "    m{} [label=\"{} = {{{}}}\", shape={}, fillcolor=\"{}\"];\n",
+            p,
+            p,
+            members.join(","),
+            shape,
+            colour[i]
+        ));
+    }
+    for e in group_dfa_edges(minimized) {
+        out.push_str(&format!(
+            "    m{} -> m{} [label=\"{}\"];\n",
+            e.from,
+            e.to,
+            escape(&e.label)
+        ));
+    }
+    out.push_str("  }\n");
+
+    out.push_str(&format!(
+        "  start [shape=point];\n  start -> o{};\n  start -> m{};\n",
+        original.start, minimized.start
+    ));
+    out.push_str("}\n");
+    out
+}
+
+/// A profiled compiled DFA as a heat map: states filled and transitions
+/// drawn thicker and redder the more the workload used them, with their
+/// counts in the labels. Transitions into the dead state are only drawn
+/// (dashed) when taken. States are the compiled DFA's rows.
+pub fn profile_to_dot(dfa: &CompiledDFA, profile: &Profile) -> String {
+    let hottest_state = (0..dfa.num_states() as u32)
+        .map(|s| profile.visits(s))
+        .max()
+        .unwrap_or(0);
+    let hottest_edge = profile.hottest(1).first().map_or(0, |&(_, _, n)| n);
+    // Blue when unused, through green, to red at the maximum
+    let heat = |n: u64, max: u64| {
+        let ratio = if max == 0 { 0.0 } else { n as f64 / max as f64 };
+        format!("{:.3} 0.8 0.9", 0.667 * (1.0 - ratio))
+    };
+
+    let mut out = String::new();
+    out.push_str("digraph Profile {\n  rankdir=LR;\n  node [shape=circle, style=filled];\n");
+    out.push_str(&format!(
+        "  start [shape=point];\n  start -> {};\n",
+        dfa.start()
+    ));
+
+    for s in 0..dfa.num_states() as u32 {
+        let visits = profile.visits(s);
+        if s == dfa.dead() && visits == 0 {
+            continue;
+        }
+        let shape = if dfa.is_accepting(s) {
+            "doublecircle"
+        } else {
+            "circle"
+        };
+        let name = if s == dfa.dead() {
+            "dead".to_string()
+        } else {
+            s.to_string()
+        };
+        out.push_str(&format!(
+            "  {} [label=\"{}\\n{}\", shape={}, fillcolor=\"{}\"];\n",
+            s,
+            name,
+            visits,
+            shape,
+            heat(visits, hottest_state)
+        ));
+    }
+
+    for from in 0..dfa.dead() {
+        // Columns grouped by target, in column order
+        let mut targets: Vec<(u32, Vec<String>, u64)> = Vec::new();
+        for column in 0..dfa.stride() {
+            let to = dfa.table()[(from * dfa.stride() + column) as usize];
+            let taken = profile.taken(from, column);
+            if to == dfa.dead() && taken == 0 {
+                continue;
+            }
+            let symbol = profile
+                .symbol(column)
+                .map_or("other".to_string(), |c| c.to_string());
+            match targets.iter_mut().find(|(t, _, _)| *t == to) {
+                Some((_, symbols, n)) => {
+                    symbols.push(symbol);
+                    *n += taken;
+                }
+                None => targets.push((to, vec![symbol], taken)),
+            }
+        }
+
+        for (to, symbols, taken) in targets {
+            let width = 1.0 + 4.0 * taken as f64 / hottest_edge.max(1) as f64;
+            let style = if to == dfa.dead() {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "  {} -> {} [label=\"{} ({})\", color=\"{}\", penwidth={:.1}{}];\n",
+                from,
+                to,
+                escape(&symbols.join(",")),
+                taken,
+                heat(taken, hottest_edge),
+                width,
+                style
+            ));
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::canonical::canonicalize;
+    use crate::dfa::dfa::nfa_to_dfa_traced;
+    use crate::dfa::minimize::minimize_dfa_traced;
+    use crate::nfa::nfa::TransitionLabel;
+    use crate::pipeline::tests::full_pipeline;
+    use crate::testing::golden::assert_matches_snapshot;
+
+    const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+    #[test]
+    fn dot_snapshots() {
+        let out = full_pipeline("(a+b)*abb").unwrap();
+
+        assert_matches_snapshot(SNAPSHOTS, "enfa_abb.dot", &nfa_to_dot(&out.enfa));
+        assert_matches_snapshot(
+            SNAPSHOTS,
+            "mindfa_abb.dot",
+            &dfa_to_dot(&canonicalize(&out.mindfa)),
+        );
+    }
+
+    #[test]
+    fn subset_mapping_snapshot() {
+        // (a+b)*ab, guessing where the final ab starts
+        let mut nfa = NFA::new();
+        let [s0, s1, s2] = [nfa.add_state(), nfa.add_state(), nfa.add_state()];
+        for (from, c, to) in [(s0, 'a', s0), (s0, 'b', s0), (s0, 'a', s1), (s1, 'b', s2)] {
+            nfa.add_edge(from, TransitionLabel::Char(c), to).unwrap();
+        }
+        nfa.accept.push(s2);
+        let (dfa, subsets) = nfa_to_dfa_traced(&nfa, &Default::default());
+
+        assert_eq!(subsets[1], BTreeSet::from([s0, s1]));
+        assert_matches_snapshot(
+            SNAPSHOTS,
+            "subsets_ab.dot",
+            &subsets_to_dot(&nfa, &dfa, &subsets),
+        );
+    }
+
+    #[test]
+    fn unicode_and_epsilon_labels() {
+        let out = full_pipeline(r"é\u{1F600}+\ε\\").unwrap();
+
+        let dfa = dfa_to_dot(&out.mindfa);
+        assert!(dfa.contains("[label=\"é\"]"));
+        assert!(dfa.contains("[label=\"😀\"]"));
+        // Literal ε and backslash, escaped for DOT
+        assert!(dfa.contains("[label=\"\\\\ε\"]"));
+        assert!(dfa.contains("[label=\"\\\\\\\\\"]"));
+
+        // Real epsilon edges are labelled with a bare ε
+        let enfa = nfa_to_dot(&out.enfa);
+        assert!(enfa.contains("[label=\"ε\"]"));
+    }
+
+    #[test]
+    fn symbol_names_as_labels() {
+        let symbols = SymbolTable::new();
+        let pattern = symbols.translate("<num>(<plus><num>)*").unwrap();
+        let out = full_pipeline(&pattern).unwrap();
+
+        let dfa = dfa_to_dot_with(&out.mindfa, &symbols);
+        assert!(dfa.contains("[label=\"num\"]"));
+        assert!(dfa.contains("[label=\"plus\"]"));
+        assert!(nfa_to_dot_with(&out.enfa, &symbols).contains("[label=\"ε\"]"));
+    }
+
+    #[test]
+    fn every_start_gets_an_arrow() {
+        let nfa = full_pipeline("ab+c").unwrap().nfa.reverse();
+        let dot = nfa_to_dot(&nfa);
+        assert!(dot.contains("  start -> 0;\n  start -> 1;\n"));
+        assert_eq!(dot.matches("start -> ").count(), 2);
+
+        let original = nfa_to_dot_ordered(&nfa, StateOrder::Original);
+        for s in nfa.starts() {
+            assert!(original.contains(&format!("  start -> {};\n", s)));
+        }
+    }
+
+    #[test]
+    fn numbering_is_deterministic() {
+        // ε-removal iterates hash maps; the picture must not care
+        let dots: Vec<String> = (0..8)
+            .map(|_| nfa_to_dot(&full_pipeline("(a+b)*a(b+c)").unwrap().nfa))
+            .collect();
+        assert!(dots.iter().all(|d| *d == dots[0]));
+        assert!(dots[0].contains("  start -> 0;\n"));
+    }
+
+    #[test]
+    fn dfa_numbering_ignores_ids() {
+        let dfa = full_pipeline("(a+b)*abb").unwrap().mindfa;
+        let id = |s: StateId| StateId(1000 - s.0);
+        let shuffled = DFA {
+            start: id(dfa.start),
+            accepts: dfa.accepts.iter().map(|&s| id(s)).collect(),
+            transitions: dfa
+                .transitions
+                .iter()
+                .map(|(&s, m)| (id(s), m.iter().map(|(&c, &t)| (c, id(t))).collect()))
+                .collect(),
+        };
+        assert_eq!(dfa_to_dot(&dfa), dfa_to_dot(&shuffled));
+        assert!(dfa_to_dot(&shuffled).contains("  start -> 0;\n"));
+
+        let original = dfa_to_dot_ordered(&shuffled, StateOrder::Original);
+        assert!(original.contains(&format!("  start -> {};\n", id(dfa.start))));
+    }
+
+    #[test]
+    fn merged_states_share_a_colour() {
+        // Subset construction on (a+b)*abb leaves two states that minimize
+        // into one
+        let out = full_pipeline("(a+b)*abb").unwrap();
+        let (minimized, partitions) = minimize_dfa_traced(&out.dfa);
+        assert_eq!(partitions.len(), out.mindfa.states().len());
+        let merged = partitions.iter().find(|p| p.len() > 1).unwrap();
+
+        let dot = minimization_to_dot(&out.dfa, &minimized, &partitions);
+        let fill = |node: String| {
+            let line = dot.lines().find(|l| l.starts_with(&node)).unwrap();
+            line.split("fillcolor=").nth(1).unwrap().to_string()
+        };
+        let colours: BTreeSet<String> = merged
+            .iter()
+            .map(|s| fill(format!("    o{} ", s)))
+            .collect();
+        assert_eq!(colours.len(), 1);
+
+        // One colour per partition, the same on both sides
+        let all: BTreeSet<String> = (0..partitions.len())
+            .map(|p| fill(format!("    m{} ", p)))
+            .collect();
+        assert_eq!(all.len(), partitions.len());
+        for (p, members) in partitions.iter().enumerate() {
+            for s in members {
+                assert_eq!(fill(format!("    o{} ", s)), fill(format!("    m{} ", p)));
+            }
+        }
+        assert_eq!(dot.matches("start -> ").count(), 2);
+    }
+
+    #[test]
+    fn profile_heat_map() {
+        let dfa = CompiledDFA::from_dfa(&full_pipeline("a*b").unwrap().mindfa);
+        let profile = dfa.profile(["aab", "ab", "ba"]);
+        let dot = profile_to_dot(&dfa, &profile);
+
+        let start = dfa.start();
+        // The hottest transition is red and widest; the dead state shows up
+        // because "ba" died
+        assert!(dot.contains(&format!(
+            "  {} -> {} [label=\"a (3)\", color=\"0.000 0.8 0.9\", penwidth=5.0];\n",
+            start, start
+        )));
+        assert!(dot.contains("[label=\"dead\\n1\""));
+        assert!(dot.contains("style=dashed"));
+    }
+}
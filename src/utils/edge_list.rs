@@ -0,0 +1,231 @@
+// utils/edge_list.rs
+//
+// A plain text format for typing in machines from textbooks and other
+// tools, one fact per line:
+//
+//   # (a+b)*abb
+//   start: 0
+//   accept: 3
+//   0 a 0
+//   0 b 0
+//   0 a 1
+//   1 b 2
+//   2 b 3
+//
+// States are numbers and need no declaration; `states:` lists ones that
+// appear nowhere else. A symbol is one char, `ε` for an ε-edge, or
+// `\u{hex}` (for whitespace, `#`, `\`, `:` or a literal `ε`). `#` starts
+// a comment. A line is a key line only when a word comes before its `:`,
+// so `0 : 1` is an edge on `:`.
+use crate::pipeline::preprocess::Preprocessor;
+use crate::types::{Automaton, StateId, Symbol, Transition};
+use std::collections::BTreeSet;
+
+/// Read a machine in the edge-list format. Errors name the line.
+pub fn parse_edge_list(text: &str) -> Result<Automaton, String> {
+    let mut start = None;
+    let mut accepts = Vec::new();
+    let mut states = BTreeSet::new();
+    let mut transitions = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let error = |msg: String| format!("Parse error: line {}: {}", i + 1, msg);
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':').filter(|(key, _)| is_key(key.trim())) {
+            let ids = value
+                .split_whitespace()
+                .map(state)
+                .collect::<Result<Vec<StateId>, String>>()
+                .map_err(error)?;
+            match key.trim() {
+                "start" => match (ids.as_slice(), start) {
+                    (_, Some(_)) => return Err(error("start state given twice".into())),
+                    (&[s], None) => start = Some(s),
+                    _ => return Err(error("expected exactly one start state".into())),
+                },
+                "accept" => accepts.extend(ids.iter().copied()),
+                "states" => {}
+                other => return Err(error(format!("unknown key '{}'", other))),
+            }
+            states.extend(ids);
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [from, symbol, to] = fields.as_slice() else {
+            return Err(error(format!(
+                "expected 'from symbol to', found {} fields",
+                fields.len()
+            )));
+        };
+        let (from, to) = (state(from).map_err(error)?, state(to).map_err(error)?);
+        let symbol = parse_symbol(symbol).map_err(error)?;
+        states.extend([from, to]);
+        transitions.push(Transition { from, to, symbol });
+    }
+
+    let start = start.ok_or("Parse error: no 'start:' line")?;
+    accepts.sort_unstable();
+    accepts.dedup();
+    Ok(Automaton {
+        states: states.into_iter().collect(),
+        start,
+        accepts,
+        transitions,
+        preprocessor: Preprocessor::default(),
+    })
+}
+
+/// Write `machine` in the edge-list format, transitions in their order.
+/// States no line mentions otherwise are listed under `states:`.
+pub fn to_edge_list(machine: &Automaton) -> String {
+    let mut out = format!("start: {}\n", machine.start);
+    if !machine.accepts.is_empty() {
+        let accepts: Vec<String> = machine.accepts.iter().map(|s| s.to_string()).collect();
+        out.push_str(&format!("accept: {}\n", accepts.join(" ")));
+    }
+
+    let mut mentioned: BTreeSet<StateId> = machine.accepts.iter().copied().collect();
+    mentioned.insert(machine.start);
+    mentioned.extend(machine.transitions.iter().flat_map(|t| [t.from, t.to]));
+    let isolated: Vec<String> = machine
+        .states
+        .iter()
+        .filter(|s| !mentioned.contains(s))
+        .map(|s| s.to_string())
+        .collect();
+    if !isolated.is_empty() {
+        out.push_str(&format!("states: {}\n", isolated.join(" ")));
+    }
+
+    for t in &machine.transitions {
+        let symbol = match t.symbol {
+            Symbol::Epsilon => "ε".to_string(),
+            Symbol::Char(c) if c.is_whitespace() || c.is_control() || "#\\:ε".contains(c) => {
+                format!("\\u{{{:x}}}", c as u32)
+            }
+            Symbol::Char(c) => c.to_string(),
+        };
+        out.push_str(&format!("{} {} {}\n", t.from, symbol, t.to));
+    }
+    out
+}
+
+// A word before a `:`, as opposed to the state number of an edge on `:`.
+fn is_key(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(char::is_alphabetic)
+}
+
+fn state(field: &str) -> Result<StateId, String> {
+    field
+        .parse()
+        .map(StateId)
+        .map_err(|_| format!("'{}' is not a state number", field))
+}
+
+fn parse_symbol(field: &str) -> Result<Symbol, String> {
+    if field == "ε" {
+        return Ok(Symbol::Epsilon);
+    }
+    if let Some(hex) = field
+        .strip_prefix("\\u{")
+        .and_then(|rest| rest.strip_suffix('}'))
+    {
+        return u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .map(Symbol::Char)
+            .ok_or_else(|| format!("invalid escape '{}'", field));
+    }
+    let mut chars = field.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != '\\' => Ok(Symbol::Char(c)),
+        _ => Err(format!("'{}' is not a single symbol", field)),
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decisions::{equivalence, Equivalence};
+    use crate::nfa::nfa::NFA;
+
+    const ABB: &str = "
+        # (a+b)*abb, the textbook NFA
+        start: 0
+        accept: 3
+        0 a 0
+        0 b 0
+        0 a 1   # guess where abb starts
+        1 b 2
+        2 b 3
+    ";
+
+    #[test]
+    fn textbook_machine() {
+        let machine = parse_edge_list(ABB).unwrap();
+        assert_eq!(
+            machine.states,
+            [StateId(0), StateId(1), StateId(2), StateId(3)]
+        );
+        assert_eq!(machine.transitions.len(), 5);
+
+        let nfa = NFA::from(&machine);
+        assert_eq!(equivalence(&nfa, "(a+b)*abb"), Ok(Equivalence::Equivalent));
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "start: 0\naccept: 1 2\nstates: 7\n0 ε 1\n1 \\u{20} 2\n2 \\u{3b5} 0\n2 é 1\n1 \\u{3a} 1\n";
+        let machine = parse_edge_list(text).unwrap();
+        assert!(matches!(machine.transitions[0].symbol, Symbol::Epsilon));
+        assert!(matches!(machine.transitions[1].symbol, Symbol::Char(' ')));
+        assert!(matches!(machine.transitions[2].symbol, Symbol::Char('ε')));
+        assert!(matches!(machine.transitions[4].symbol, Symbol::Char(':')));
+        assert_eq!(
+            machine.states,
+            [StateId(0), StateId(1), StateId(2), StateId(7)]
+        );
+        assert_eq!(to_edge_list(&machine), text);
+
+        // Written by hand, an edge on `:` is not a key line
+        let machine = parse_edge_list("start: 0\n0 : 1").unwrap();
+        assert!(matches!(machine.transitions[0].symbol, Symbol::Char(':')));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let err = |text: &str| parse_edge_list(text).unwrap_err();
+        assert_eq!(
+            err("start: 0\n0 ab 1"),
+            "Parse error: line 2: 'ab' is not a single symbol"
+        );
+        assert_eq!(
+            err("start: 0\nstart: 1"),
+            "Parse error: line 2: start state given twice"
+        );
+        assert_eq!(
+            err("start: x"),
+            "Parse error: line 1: 'x' is not a state number"
+        );
+        assert_eq!(
+            err("start: 0\n0 a"),
+            "Parse error: line 2: expected 'from symbol to', found 2 fields"
+        );
+        assert_eq!(
+            err("initial: 0"),
+            "Parse error: line 1: unknown key 'initial'"
+        );
+        assert_eq!(err("0 a 1"), "Parse error: no 'start:' line");
+    }
+}
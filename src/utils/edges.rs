@@ -0,0 +1,189 @@
+// utils/edges.rs
+use crate::dfa::dfa::DFA;
+use crate::nfa::nfa::{StateId, TransitionLabel, NFA};
+use std::collections::BTreeMap;
+
+/// One exported edge standing for all transitions between a pair of states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupedEdge {
+    pub from: StateId,
+    pub to: StateId,
+    pub label: String,
+}
+
+/// Group NFA transitions by (from, to), ordered by source then target.
+pub fn group_nfa_edges(nfa: &NFA) -> Vec<GroupedEdge> {
+    group_nfa_edges_by(nfa, compress_chars)
+}
+
+/// Like `group_nfa_edges`, rendering each group's symbols with `label`.
+pub fn group_nfa_edges_by(nfa: &NFA, label: impl Fn(Vec<char>) -> String) -> Vec<GroupedEdge> {
+    let mut groups: BTreeMap<(StateId, StateId), (bool, Vec<char>)> = BTreeMap::new();
+
+    for (&src, edges) in &nfa.transitions {
+        for (label, dst) in edges {
+            let group = groups.entry((src, *dst)).or_default();
+            match label {
+                TransitionLabel::Epsilon => group.0 = true,
+                TransitionLabel::Char(c) => group.1.push(*c),
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((from, to), (epsilon, chars))| {
+            let mut label = if chars.is_empty() {
+                String::new()
+            } else {
+                label(chars)
+            };
+            if epsilon {
+                label = if label.is_empty() {
+                    "ε".into()
+                } else {
+                    format!("ε,{}", label)
+                };
+            }
+            GroupedEdge { from, to, label }
+        })
+        .collect()
+}
+
+/// Group DFA transitions by (from, to), ordered by source then target.
+pub fn group_dfa_edges(dfa: &DFA) -> Vec<GroupedEdge> {
+    group_dfa_edges_by(dfa, compress_chars)
+}
+
+/// Like `group_dfa_edges`, rendering each group's symbols with `label`.
+pub fn group_dfa_edges_by(dfa: &DFA, label: impl Fn(Vec<char>) -> String) -> Vec<GroupedEdge> {
+    let mut groups: BTreeMap<(StateId, StateId), Vec<char>> = BTreeMap::new();
+
+    for (&src, map) in &dfa.transitions {
+        for (&c, &dst) in map {
+            groups.entry((src, dst)).or_default().push(c);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((from, to), chars)| GroupedEdge {
+            from,
+            to,
+            label: label(chars),
+        })
+        .collect()
+}
+
+/// Render a set of symbols as a short label: `a` or `a,b` for a few
+/// scattered symbols, `[a-dx]` once consecutive runs of three or more exist.
+pub fn compress_chars(mut chars: Vec<char>) -> String {
+    chars.sort_unstable();
+    chars.dedup();
+
+    // Split into runs of consecutive code points
+    let mut runs: Vec<(char, char)> = Vec::new();
+    for &c in &chars {
+        match runs.last_mut() {
+            Some((_, hi)) if (*hi as u32) + 1 == c as u32 => *hi = c,
+            _ => runs.push((c, c)),
+        }
+    }
+
+    if runs.iter().all(|&(lo, hi)| (hi as u32) - (lo as u32) < 2) {
+        let singles: Vec<String> = chars.iter().map(|&c| display_char(c)).collect();
+        return singles.join(",");
+    }
+
+    let mut out = String::from("[");
+    for (lo, hi) in runs {
+        out.push_str(&display_char(lo));
+        match (hi as u32) - (lo as u32) {
+            0 => {}
+            1 => out.push_str(&display_char(hi)),
+            _ => {
+                out.push('-');
+                out.push_str(&display_char(hi));
+            }
+        }
+    }
+    out.push(']');
+    out
+}
+
+/// Render one symbol for a label. Label syntax characters (and a literal
+/// `ε`, which would read as an epsilon edge) get a backslash; invisible
+/// characters are shown as escapes. Other Unicode is kept as is.
+pub fn display_char(c: char) -> String {
+    match c {
+        'ε' | ',' | '[' | ']' | '-' | '\\' => format!("\\{}", c),
+        '\n' | '\t' | '\r' => c.escape_default().to_string(),
+        c if c.is_control() || c.is_whitespace() => c.escape_unicode().to_string(),
+        c => c.to_string(),
+    }
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::full_pipeline;
+
+    #[test]
+    fn compress_labels() {
+        assert_eq!(compress_chars(vec!['a']), "a");
+        assert_eq!(compress_chars(vec!['b', 'a', 'x']), "a,b,x");
+        assert_eq!(compress_chars(vec!['c', 'a', 'b', 'x']), "[a-cx]");
+        assert_eq!(compress_chars(vec!['0', '1', '2', 'a', 'b']), "[0-2ab]");
+    }
+
+    #[test]
+    fn labels_are_unambiguous() {
+        assert_eq!(compress_chars(vec!['ε']), "\\ε");
+        assert_eq!(compress_chars(vec![',', '-']), "\\,,\\-");
+        assert_eq!(compress_chars(vec!['\n', ' ']), "\\n,\\u{20}");
+        assert_eq!(compress_chars(vec!['é', '😀']), "é,😀");
+        assert_eq!(compress_chars(vec!['α', 'β', 'γ']), "[α-γ]");
+    }
+
+    #[test]
+    fn dfa_edges_grouped() {
+        let dfa = full_pipeline("[a-z]").unwrap().mindfa;
+        let edges = group_dfa_edges(&dfa);
+
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].label, "[a-z]");
+    }
+
+    #[test]
+    fn nfa_edges_grouped() {
+        let mut nfa = NFA::new();
+        let s = nfa.add_state();
+        let t = nfa.add_state();
+        nfa.add_edge(s, TransitionLabel::Char('b'), t).unwrap();
+        nfa.add_edge(s, TransitionLabel::Epsilon, t).unwrap();
+        nfa.add_edge(s, TransitionLabel::Char('a'), t).unwrap();
+        nfa.add_edge(t, TransitionLabel::Epsilon, s).unwrap();
+
+        assert_eq!(
+            group_nfa_edges(&nfa),
+            vec![
+                GroupedEdge {
+                    from: s,
+                    to: t,
+                    label: "ε,a,b".into()
+                },
+                GroupedEdge {
+                    from: t,
+                    to: s,
+                    label: "ε".into()
+                },
+            ]
+        );
+    }
+}
@@ -1,16 +1,23 @@
 // utils/graph_builder.rs
-use crate::types::{Automaton, StateID, Symbol, Transition};
+use crate::pipeline::preprocess::Preprocessor;
+use crate::types::{Automaton, StateId, Symbol, Transition};
 
 pub struct GraphBuilder {
-    next_state: StateID,
+    next_state: StateId,
     transitions: Vec<Transition>,
-    accepts: Vec<StateID>,
+    accepts: Vec<StateId>,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GraphBuilder {
     pub fn new() -> Self {
         Self {
-            next_state: 0,
+            next_state: StateId(0),
             transitions: Vec::new(),
             accepts: Vec::new(),
         }
@@ -18,32 +25,33 @@ impl GraphBuilder {
 
     // Add new state.
     #[inline]
-    pub fn new_state(&mut self) -> StateID {
+    pub fn new_state(&mut self) -> StateId {
         let id = self.next_state;
-        self.next_state += 1;
+        self.next_state = id.next();
         id
     }
 
     // Add a transition between states.
     #[inline]
-    pub fn add_transition(&mut self, from: StateID, to: StateID, symbol: Symbol) {
+    pub fn add_transition(&mut self, from: StateId, to: StateId, symbol: Symbol) {
         self.transitions.push(Transition { from, to, symbol });
     }
 
     // Add an accepting state.
     #[inline]
-    pub fn add_accept(&mut self, state: StateID) {
+    pub fn add_accept(&mut self, state: StateId) {
         self.accepts.push(state);
     }
 
-    pub fn build(self, start: StateID) -> Automaton {
-        let states: Vec<StateID> = (0..self.next_state).collect();
+    pub fn build(self, start: StateId) -> Automaton {
+        let states: Vec<StateId> = (0..self.next_state.0).map(StateId).collect();
 
         Automaton {
             states,
             start,
             accepts: self.accepts,
             transitions: self.transitions,
+            preprocessor: Preprocessor::default(),
         }
     }
 }
@@ -1,2 +1,9 @@
+#[cfg(feature = "bincode")]
+pub mod binary;
+pub mod doctor;
+#[cfg(feature = "dot")]
 pub mod dot;
+pub mod edge_list;
+pub mod edges;
 pub mod graph_builder;
+pub mod morphism;
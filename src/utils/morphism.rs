@@ -0,0 +1,195 @@
+// utils/morphism.rs
+use crate::dfa::dfa::DFA;
+use crate::types::StateId;
+use std::collections::{BTreeSet, HashMap};
+
+/// The first way a proposed state mapping fails to be a DFA homomorphism.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    // A state of `a` has no image
+    Unmapped(StateId),
+    // f(a.start) != b.start
+    Start {
+        image: StateId,
+        expected: StateId,
+    },
+    // q is accepting in `a` but f(q) is not in `b`, or the other way round
+    Accept {
+        state: StateId,
+        image: StateId,
+    },
+    // f(δa(q, c)) != δb(f(q), c); `None` means the transition is missing
+    Transition {
+        state: StateId,
+        symbol: char,
+        mapped: Option<StateId>,
+        expected: Option<StateId>,
+    },
+}
+
+/// Check that `f` maps `a` onto `b` preserving the start state, acceptance
+/// (in both directions) and every transition. Missing transitions must map
+/// to missing transitions. States are checked in sorted order, so the
+/// reported violation is deterministic.
+pub fn is_homomorphism(f: &HashMap<StateId, StateId>, a: &DFA, b: &DFA) -> Result<(), Violation> {
+    let image = |q: StateId| f.get(&q).copied().ok_or(Violation::Unmapped(q));
+    let sigma: BTreeSet<char> = a.alphabet().union(&b.alphabet()).copied().collect();
+
+    let start = image(a.start)?;
+    if start != b.start {
+        return Err(Violation::Start {
+            image: start,
+            expected: b.start,
+        });
+    }
+
+    for q in a.states() {
+        let fq = image(q)?;
+        if a.accepts.contains(&q) != b.accepts.contains(&fq) {
+            return Err(Violation::Accept {
+                state: q,
+                image: fq,
+            });
+        }
+
+        for &c in &sigma {
+            let mapped = match a.transitions.get(&q).and_then(|m| m.get(&c)) {
+                Some(&t) => Some(image(t)?),
+                None => None,
+            };
+            let expected = b.transitions.get(&fq).and_then(|m| m.get(&c)).copied();
+            if mapped != expected {
+                return Err(Violation::Transition {
+                    state: q,
+                    symbol: c,
+                    mapped,
+                    expected,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/*
+* =====================
+*   CORRECTNESS TESTS
+* =====================
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // Parity of a's, but with the even state split in two copies (0 and 2)
+    fn unminimized() -> DFA {
+        DFA {
+            start: StateId(0),
+            accepts: HashSet::from([StateId(0), StateId(2)]),
+            transitions: HashMap::from([
+                (
+                    StateId(0),
+                    HashMap::from([('a', StateId(1)), ('b', StateId(2))]),
+                ),
+                (
+                    StateId(1),
+                    HashMap::from([('a', StateId(2)), ('b', StateId(1))]),
+                ),
+                (
+                    StateId(2),
+                    HashMap::from([('a', StateId(1)), ('b', StateId(0))]),
+                ),
+            ]),
+        }
+    }
+
+    fn parity() -> DFA {
+        DFA {
+            start: StateId(0),
+            accepts: HashSet::from([StateId(0)]),
+            transitions: HashMap::from([
+                (
+                    StateId(0),
+                    HashMap::from([('a', StateId(1)), ('b', StateId(0))]),
+                ),
+                (
+                    StateId(1),
+                    HashMap::from([('a', StateId(0)), ('b', StateId(1))]),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn valid_mapping() {
+        let f = HashMap::from([
+            (StateId(0), StateId(0)),
+            (StateId(1), StateId(1)),
+            (StateId(2), StateId(0)),
+        ]);
+        assert_eq!(is_homomorphism(&f, &unminimized(), &parity()), Ok(()));
+    }
+
+    #[test]
+    fn violations() {
+        let (a, b) = (unminimized(), parity());
+
+        let f = HashMap::from([(StateId(0), StateId(0)), (StateId(1), StateId(1))]);
+        assert_eq!(
+            is_homomorphism(&f, &a, &b),
+            Err(Violation::Unmapped(StateId(2)))
+        );
+
+        let f = HashMap::from([
+            (StateId(0), StateId(1)),
+            (StateId(1), StateId(0)),
+            (StateId(2), StateId(1)),
+        ]);
+        assert_eq!(
+            is_homomorphism(&f, &a, &b),
+            Err(Violation::Start {
+                image: StateId(1),
+                expected: StateId(0)
+            })
+        );
+
+        // Right shape, but `b` now accepts odd parity
+        let mut odd = parity();
+        odd.accepts = HashSet::from([StateId(1)]);
+        let f = HashMap::from([
+            (StateId(0), StateId(0)),
+            (StateId(1), StateId(1)),
+            (StateId(2), StateId(0)),
+        ]);
+        assert_eq!(
+            is_homomorphism(&f, &a, &odd),
+            Err(Violation::Accept {
+                state: StateId(0),
+                image: StateId(0)
+            })
+        );
+    }
+
+    #[test]
+    fn transition_violation() {
+        let mut b = parity();
+        b.transitions.get_mut(&StateId(1)).unwrap().remove(&'b');
+
+        let f = HashMap::from([
+            (StateId(0), StateId(0)),
+            (StateId(1), StateId(1)),
+            (StateId(2), StateId(0)),
+        ]);
+        assert_eq!(
+            is_homomorphism(&f, &unminimized(), &b),
+            Err(Violation::Transition {
+                state: StateId(1),
+                symbol: 'b',
+                mapped: Some(StateId(1)),
+                expected: None
+            })
+        );
+    }
+}